@@ -0,0 +1,177 @@
+//! Native asset metadata: ticker and decimals per policy + asset name, from the token registry
+//! or CIP-68 reference data where the provider serves them.
+
+use serde::{Deserialize, Serialize};
+
+/// CIP-67 label prefixes (4 bytes, hex) of CIP-68 user tokens: 222 (NFT), 333 (FT), 444 (RFT).
+const CIP68_USER_LABELS: [&str; 3] = ["000de140", "0014df10", "001bc280"];
+
+/// Display metadata for one asset.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetInfo {
+    pub policy_id: String,
+    /// Hex asset name.
+    pub asset_name: String,
+    /// Registry / CIP-68 ticker, else the printable ASCII asset name, else `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticker: Option<String>,
+    /// Decimal places of the on-chain integer quantity (0 when unknown).
+    #[serde(default)]
+    pub decimals: u8,
+}
+
+impl AssetInfo {
+    /// Metadata derived from the name alone: ASCII ticker (CIP-68 label stripped), no decimals.
+    pub fn from_name(policy_id: &str, asset_name: &str) -> Self {
+        Self {
+            policy_id: policy_id.to_string(),
+            asset_name: asset_name.to_string(),
+            ticker: ascii_name(asset_name),
+            decimals: 0,
+        }
+    }
+
+    /// [`AssetInfo::from_name`] for a `policy$asset_name` unit; `None` if it is malformed.
+    pub fn from_unit(unit: &str) -> Option<Self> {
+        let (policy_id, asset_name) = split_asset_unit(unit)?;
+        Some(Self::from_name(policy_id, asset_name))
+    }
+
+    /// `policy$asset_name`, the asset id used in events.
+    pub fn unit(&self) -> String {
+        asset_unit(&self.policy_id, &self.asset_name)
+    }
+
+    /// Ticker, or a shortened policy id when there is none.
+    pub fn display_name(&self) -> String {
+        self.ticker
+            .clone()
+            .unwrap_or_else(|| format!("{}…", &self.policy_id[..self.policy_id.len().min(8)]))
+    }
+
+    /// `"iUSD 1,234.56"` for a raw on-chain quantity.
+    pub fn format_quantity(&self, quantity: u128) -> String {
+        format!(
+            "{} {}",
+            self.display_name(),
+            format_decimal(quantity, self.decimals)
+        )
+    }
+}
+
+/// Asset id as written in events: `policy$asset_name`.
+pub fn asset_unit(policy_id: &str, asset_name: &str) -> String {
+    format!("{}${}", policy_id, asset_name)
+}
+
+/// Split an event asset id back into (policy, asset_name).
+pub fn split_asset_unit(unit: &str) -> Option<(&str, &str)> {
+    unit.split_once('$')
+}
+
+/// Printable ASCII of a hex asset name, without a CIP-68 label prefix.
+fn ascii_name(asset_name_hex: &str) -> Option<String> {
+    let lower = asset_name_hex.to_lowercase();
+    let body = CIP68_USER_LABELS
+        .iter()
+        .find_map(|label| lower.strip_prefix(label))
+        .unwrap_or(&lower);
+    let bytes = hex::decode(body).ok()?;
+    let printable = !bytes.is_empty() && bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ');
+    printable.then(|| String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Ticker and decimals from a CIP-68 reference datum in detailed-schema plutus JSON
+/// (`{"constructor":0,"fields":[{"map":[{"k":{"bytes":…},"v":…}]}, …]}`). Keys are hex UTF-8.
+pub(crate) fn cip68_ticker_decimals(datum: &serde_json::Value) -> (Option<String>, Option<u8>) {
+    let Some(entries) = datum
+        .get("fields")
+        .and_then(|f| f.get(0))
+        .and_then(|m| m.get("map"))
+        .and_then(|m| m.as_array())
+    else {
+        return (None, None);
+    };
+    let field = |name: &str| {
+        let key = hex::encode(name);
+        entries
+            .iter()
+            .find(|e| e["k"]["bytes"].as_str() == Some(key.as_str()))
+            .map(|e| &e["v"])
+    };
+    let ticker = field("ticker")
+        .and_then(|v| v["bytes"].as_str())
+        .and_then(|h| hex::decode(h).ok())
+        .and_then(|b| String::from_utf8(b).ok());
+    let decimals = field("decimals")
+        .and_then(|v| v["int"].as_u64())
+        .and_then(|d| u8::try_from(d).ok());
+    (ticker, decimals)
+}
+
+/// Integer quantity with `decimals` places and thousands separators: `1234560000, 6` →
+/// `"1,234.56"` (trailing zeros trimmed).
+pub fn format_decimal(quantity: u128, decimals: u8) -> String {
+    let scale = 10u128.pow(u32::from(decimals));
+    let (whole, frac) = (quantity / scale, quantity % scale);
+    let digits = whole.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    if decimals == 0 || frac == 0 {
+        return grouped;
+    }
+    let frac = format!("{:0width$}", frac, width = usize::from(decimals));
+    format!("{}.{}", grouped, frac.trim_end_matches('0'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_quantities_format_for_display() {
+        // "iUSD"
+        let plain = AssetInfo::from_name("f66d", "69555344");
+        assert_eq!(plain.ticker.as_deref(), Some("iUSD"));
+        assert_eq!(plain.unit(), "f66d$69555344");
+        // CIP-68 FT (333) label, then "INDY".
+        let cip68 = AssetInfo::from_name("aa", "0014df10494e4459");
+        assert_eq!(cip68.ticker.as_deref(), Some("INDY"));
+        assert_eq!(AssetInfo::from_name("abcdef0123", "00ff").ticker, None);
+        let iusd = AssetInfo {
+            decimals: 6,
+            ..plain
+        };
+        assert_eq!(iusd.format_quantity(1_234_560_000), "iUSD 1,234.56");
+        assert_eq!(format_decimal(1_000_000, 0), "1,000,000");
+        assert_eq!(format_decimal(5, 6), "0.000005");
+        assert_eq!(split_asset_unit("p$n"), Some(("p", "n")));
+    }
+
+    #[test]
+    fn cip68_reference_datum_fields() {
+        let datum = serde_json::json!({
+            "constructor": 0,
+            "fields": [
+                { "map": [
+                    { "k": { "bytes": "7469636b6572" }, "v": { "bytes": "494e4459" } },
+                    { "k": { "bytes": "646563696d616c73" }, "v": { "int": 6 } }
+                ] },
+                { "int": 1 }
+            ]
+        });
+        assert_eq!(
+            cip68_ticker_decimals(&datum),
+            (Some("INDY".to_string()), Some(6))
+        );
+        assert_eq!(
+            cip68_ticker_decimals(&serde_json::json!({ "int": 1 })),
+            (None, None)
+        );
+    }
+}
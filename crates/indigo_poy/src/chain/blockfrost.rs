@@ -0,0 +1,527 @@
+//! Blockfrost REST backend. Responses are normalized into the Koios-shaped types.
+
+use crate::chain::assets::AssetInfo;
+use crate::chain::cache::{Cache, CacheClass};
+use crate::chain::fetch::{
+    ChainTip, FetchConfig, FetchError, FetchStats, KoiosAccountTx, KoiosAsset, KoiosInlineDatum,
+    KoiosPaymentAddr, KoiosTxUtxos, KoiosUtxo, RawResponse, RewardWithdrawal, TxMetadata,
+};
+use crate::chain::http::HttpClient;
+use crate::chain::network::Network;
+use crate::chain::normalize::slot_from_unix_time;
+use crate::chain::provider::ChainProvider;
+use crate::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Env var consulted for the project id when the config omits it.
+pub const BLOCKFROST_PROJECT_ID_ENV: &str = "BLOCKFROST_PROJECT_ID";
+/// Blockfrost's maximum page size.
+const PAGE_SIZE: usize = 100;
+
+#[derive(Clone, Debug, Deserialize)]
+struct BfAmount {
+    unit: String,
+    quantity: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BfAccountAddress {
+    address: String,
+}
+
+/// Row of `/accounts/{stake}/withdrawals`.
+#[derive(Clone, Debug, Deserialize)]
+struct BfWithdrawal {
+    tx_hash: String,
+    amount: String,
+}
+
+/// Body of `/blocks/latest` (fields used).
+#[derive(Clone, Debug, Deserialize)]
+struct BfBlock {
+    height: Option<u64>,
+    slot: Option<u64>,
+}
+
+/// Body of `/assets/{unit}` (fields used). `metadata` is the token registry entry.
+#[derive(Clone, Debug, Deserialize)]
+struct BfAsset {
+    policy_id: String,
+    #[serde(default)]
+    asset_name: Option<String>,
+    #[serde(default)]
+    metadata: Option<BfAssetMetadata>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BfAssetMetadata {
+    #[serde(default)]
+    ticker: Option<String>,
+    #[serde(default)]
+    decimals: Option<u8>,
+}
+
+/// Body of `/scripts/datum/{hash}/cbor`.
+#[derive(Clone, Debug, Deserialize)]
+struct BfDatumCbor {
+    cbor: String,
+}
+
+/// Entry of `/txs/{h}/metadata`.
+#[derive(Clone, Debug, Deserialize)]
+struct BfMetadata {
+    label: String,
+    json_metadata: serde_json::Value,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BfAddressTx {
+    tx_hash: String,
+    block_height: Option<u64>,
+    block_time: Option<i64>,
+}
+
+/// UTxO entry as returned by `/addresses/{a}/utxos` and `/txs/{h}/utxos`.
+#[derive(Clone, Debug, Deserialize)]
+struct BfUtxo {
+    #[serde(default)]
+    address: Option<String>,
+    #[serde(default)]
+    tx_hash: Option<String>,
+    output_index: u32,
+    amount: Vec<BfAmount>,
+    #[serde(default)]
+    data_hash: Option<String>,
+    /// CBOR hex of the inline datum, if any.
+    #[serde(default)]
+    inline_datum: Option<String>,
+    /// Collateral inputs are not spent on success; skipped.
+    #[serde(default)]
+    collateral: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BfTxUtxos {
+    hash: String,
+    inputs: Vec<BfUtxo>,
+    outputs: Vec<BfUtxo>,
+}
+
+/// Blockfrost `unit` is `lovelace` or policy id (56 hex chars) followed by the hex asset name.
+fn normalize_utxo(u: BfUtxo, default_tx_hash: &str) -> KoiosUtxo {
+    let mut value = "0".to_string();
+    let mut assets = Vec::new();
+    for a in u.amount {
+        if a.unit == "lovelace" {
+            value = a.quantity;
+        } else if a.unit.len() >= 56 {
+            let (policy, name) = a.unit.split_at(56);
+            assets.push(KoiosAsset {
+                policy_id: policy.to_string(),
+                asset_name: name.to_string(),
+                quantity: a.quantity,
+            });
+        }
+    }
+    KoiosUtxo {
+        tx_hash: u.tx_hash.unwrap_or_else(|| default_tx_hash.to_string()),
+        tx_index: u.output_index,
+        value,
+        datum_hash: u.data_hash,
+        asset_list: (!assets.is_empty()).then_some(assets),
+        inline_datum: u
+            .inline_datum
+            .map(|bytes| KoiosInlineDatum { bytes, value: None }),
+        resolved_datum: None,
+        payment_addr: u.address.map(KoiosPaymentAddr::bech32),
+    }
+}
+
+fn normalize_tx_utxos(tx: BfTxUtxos) -> KoiosTxUtxos {
+    let hash = tx.hash;
+    KoiosTxUtxos {
+        inputs: Some(
+            tx.inputs
+                .into_iter()
+                .filter(|u| !u.collateral)
+                .map(|u| normalize_utxo(u, &hash))
+                .collect(),
+        ),
+        outputs: Some(
+            tx.outputs
+                .into_iter()
+                .map(|u| normalize_utxo(u, &hash))
+                .collect(),
+        ),
+        metadata: None,
+    }
+}
+
+/// Blockfrost API client (`project_id` header auth, page-based pagination).
+pub struct BlockfrostProvider {
+    http: HttpClient,
+    network: Network,
+}
+
+impl BlockfrostProvider {
+    /// The project id is required unless running offline from cache.
+    pub fn new(config: FetchConfig, cache: Option<Cache>) -> Result<Self, FetchError> {
+        let project_id = config
+            .blockfrost_project_id
+            .clone()
+            .or_else(|| std::env::var(BLOCKFROST_PROJECT_ID_ENV).ok());
+        let mut headers = HeaderMap::new();
+        match project_id {
+            Some(id) => {
+                let value = HeaderValue::from_str(id.trim())
+                    .map_err(|_| FetchError::Config("invalid blockfrost project id".into()))?;
+                headers.insert("project_id", value);
+            }
+            None if !config.offline => {
+                return Err(FetchError::Config(format!(
+                    "blockfrost: set {} or FetchConfig::blockfrost_project_id",
+                    BLOCKFROST_PROJECT_ID_ENV
+                )));
+            }
+            None => {}
+        }
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| config.network.blockfrost_url().to_string());
+        let network = config.network;
+        Ok(Self {
+            http: HttpClient::new(config, base_url, headers, cache)?,
+            network,
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        class: CacheClass,
+    ) -> Result<Option<T>, FetchError> {
+        let key = HttpClient::path_cache_key("blockfrost", path);
+        self.http.get_parsed(path, &key, class).await
+    }
+
+    /// Follow `?page=N` until a short page.
+    async fn get_all_pages<T: DeserializeOwned>(
+        &self,
+        path_with_query: &str,
+        class: CacheClass,
+    ) -> Result<Vec<T>, FetchError> {
+        let sep = if path_with_query.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let mut out = Vec::new();
+        for page in 1.. {
+            let path = format!(
+                "{}{}count={}&page={}",
+                path_with_query, sep, PAGE_SIZE, page
+            );
+            let items: Vec<T> = self.get(&path, class).await?.unwrap_or_default();
+            let len = items.len();
+            out.extend(items);
+            if len < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    async fn account_txs_impl(
+        &self,
+        address: &str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> Result<Vec<KoiosAccountTx>, FetchError> {
+        let path = format!(
+            "/addresses/{}/transactions?order=asc",
+            urlencoding::encode(address)
+        );
+        let raw: Vec<BfAddressTx> = self.get_all_pages(&path, CacheClass::TxHistory).await?;
+        let parsed: Vec<KoiosAccountTx> = raw
+            .into_iter()
+            .map(|t| KoiosAccountTx {
+                slot_no: t
+                    .block_time
+                    .and_then(|ts| slot_from_unix_time(self.network, ts)),
+                tx_hash: t.tx_hash,
+                block_height: t.block_height,
+                block_time: t.block_time,
+                epoch_no: None,
+            })
+            .filter(|t| match t.slot_no {
+                Some(slot) => {
+                    from_slot.is_none_or(|f| slot >= f) && to_slot.is_none_or(|to| slot <= to)
+                }
+                None => from_slot.is_none() && to_slot.is_none(),
+            })
+            .collect();
+        info!(count = parsed.len(), "account_txs");
+        Ok(parsed)
+    }
+
+    async fn address_utxos_impl(&self, address: &str) -> Result<Vec<KoiosUtxo>, FetchError> {
+        let path = format!("/addresses/{}/utxos", urlencoding::encode(address));
+        let raw: Vec<BfUtxo> = self.get_all_pages(&path, CacheClass::AddressUtxos).await?;
+        Ok(raw.into_iter().map(|u| normalize_utxo(u, "")).collect())
+    }
+
+    async fn stake_addresses_impl(&self, stake_address: &str) -> Result<Vec<String>, FetchError> {
+        let path = format!("/accounts/{}/addresses", urlencoding::encode(stake_address));
+        let raw: Vec<BfAccountAddress> = self
+            .get_all_pages(&path, CacheClass::AccountAddresses)
+            .await?;
+        let mut addresses: Vec<String> = raw.into_iter().map(|a| a.address).collect();
+        addresses.sort();
+        addresses.dedup();
+        Ok(addresses)
+    }
+
+    async fn reward_withdrawals_impl(
+        &self,
+        stake_address: &str,
+    ) -> Result<Vec<RewardWithdrawal>, FetchError> {
+        let path = format!(
+            "/accounts/{}/withdrawals",
+            urlencoding::encode(stake_address)
+        );
+        let raw: Vec<BfWithdrawal> = self
+            .get_all_pages(&path, CacheClass::AccountAddresses)
+            .await?;
+        raw.into_iter()
+            .map(|w| {
+                let amount_lovelace = w.amount.trim().parse().map_err(|_| {
+                    FetchError::Api(0, format!("withdrawals: bad amount {}", w.amount))
+                })?;
+                Ok(RewardWithdrawal {
+                    tx_hash: w.tx_hash,
+                    stake_address: stake_address.to_string(),
+                    amount_lovelace,
+                })
+            })
+            .collect()
+    }
+
+    async fn tx_utxos_impl(&self, tx_hash: &str) -> Result<KoiosTxUtxos, FetchError> {
+        let path = format!("/txs/{}/utxos", urlencoding::encode(tx_hash));
+        let raw: BfTxUtxos = self
+            .get(&path, CacheClass::TxUtxos)
+            .await?
+            .ok_or_else(|| FetchError::Api(404, format!("tx not found: {}", tx_hash)))?;
+        Ok(normalize_tx_utxos(raw))
+    }
+
+    async fn tip_impl(&self) -> Result<ChainTip, FetchError> {
+        let body = self.http.request_uncached("/blocks/latest", None).await?;
+        let block: BfBlock = serde_json::from_str(&body)
+            .map_err(|e| FetchError::Api(0, format!("parse blocks/latest: {}", e)))?;
+        let slot = block
+            .slot
+            .ok_or_else(|| FetchError::Api(0, "blocks/latest: no slot".into()))?;
+        Ok(ChainTip {
+            slot,
+            block_height: block.height,
+        })
+    }
+
+    async fn datum_info_impl(
+        &self,
+        datum_hashes: &[String],
+    ) -> Result<BTreeMap<String, KoiosInlineDatum>, FetchError> {
+        let mut out = BTreeMap::new();
+        for hash in datum_hashes {
+            let path = format!("/scripts/datum/{}/cbor", urlencoding::encode(hash));
+            let found: Option<BfDatumCbor> = self.get(&path, CacheClass::DatumInfo).await?;
+            if let Some(d) = found {
+                let datum = KoiosInlineDatum {
+                    bytes: d.cbor,
+                    value: None,
+                };
+                out.insert(hash.clone(), datum);
+            }
+        }
+        Ok(out)
+    }
+
+    async fn asset_info_impl(
+        &self,
+        policy_id: &str,
+        asset_name: &str,
+    ) -> Result<Option<AssetInfo>, FetchError> {
+        let path = format!(
+            "/assets/{}{}",
+            urlencoding::encode(policy_id),
+            urlencoding::encode(asset_name)
+        );
+        let found: Option<BfAsset> = self.get(&path, CacheClass::AssetInfo).await?;
+        Ok(found.map(|a| {
+            let mut info =
+                AssetInfo::from_name(&a.policy_id, a.asset_name.as_deref().unwrap_or(""));
+            if let Some(m) = a.metadata {
+                info.ticker = m.ticker.or(info.ticker);
+                info.decimals = m.decimals.unwrap_or(0);
+            }
+            info
+        }))
+    }
+
+    async fn policy_minted_impl(&self, policy_id: &str) -> Result<Option<bool>, FetchError> {
+        let path = format!("/assets/policy/{}?count=1", urlencoding::encode(policy_id));
+        let assets: Option<Vec<serde_json::Value>> = self.get(&path, CacheClass::AssetInfo).await?;
+        Ok(Some(assets.is_some_and(|a| !a.is_empty())))
+    }
+
+    async fn tx_metadata_impl(&self, tx_hash: &str) -> Result<Option<TxMetadata>, FetchError> {
+        let path = format!("/txs/{}/metadata", urlencoding::encode(tx_hash));
+        let raw: Vec<BfMetadata> = self
+            .get(&path, CacheClass::TxMetadata)
+            .await?
+            .unwrap_or_default();
+        Ok((!raw.is_empty()).then(|| {
+            raw.into_iter()
+                .map(|m| (m.label, m.json_metadata))
+                .collect()
+        }))
+    }
+}
+
+impl ChainProvider for BlockfrostProvider {
+    fn name(&self) -> &str {
+        "blockfrost"
+    }
+
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+        Box::pin(self.account_txs_impl(address, from_slot, to_slot))
+    }
+
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+        Box::pin(self.tx_utxos_impl(tx_hash))
+    }
+
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+        Box::pin(self.address_utxos_impl(address))
+    }
+
+    fn stake_addresses<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(self.stake_addresses_impl(stake_address))
+    }
+
+    fn reward_withdrawals<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RewardWithdrawal>, FetchError>> {
+        Box::pin(self.reward_withdrawals_impl(stake_address))
+    }
+
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(self.tip_impl())
+    }
+
+    fn datum_info<'a>(
+        &'a self,
+        datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(self.datum_info_impl(datum_hashes))
+    }
+
+    fn tx_metadata<'a>(
+        &'a self,
+        tx_hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+        Box::pin(self.tx_metadata_impl(tx_hash))
+    }
+
+    fn asset_info<'a>(
+        &'a self,
+        policy_id: &'a str,
+        asset_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<AssetInfo>, FetchError>> {
+        Box::pin(self.asset_info_impl(policy_id, asset_name))
+    }
+
+    fn policy_minted<'a>(
+        &'a self,
+        policy_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<bool>, FetchError>> {
+        Box::pin(self.policy_minted_impl(policy_id))
+    }
+
+    fn stats(&self) -> FetchStats {
+        self.http.stats()
+    }
+
+    fn take_responses(&self) -> Vec<RawResponse> {
+        self.http.take_responses()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tx_utxos_normalized_to_koios_shape() {
+        let policy = "f66d78b4a3cb3d37afa0ec36461e51ecbde00f26c8f0a68f94b69880";
+        let body = format!(
+            r#"{{"hash":"abc","inputs":[
+                {{"address":"addr1x","tx_hash":"prev","output_index":1,
+                  "amount":[{{"unit":"lovelace","quantity":"5000000"}}],"collateral":false}},
+                {{"address":"addr1x","tx_hash":"coll","output_index":0,
+                  "amount":[{{"unit":"lovelace","quantity":"1"}}],"collateral":true}}],
+              "outputs":[
+                {{"address":"addr1y","output_index":0,"data_hash":"dh","inline_datum":"d87980",
+                  "amount":[{{"unit":"lovelace","quantity":"2000000"}},
+                            {{"unit":"{}69555344","quantity":"42"}}]}}]}}"#,
+            policy
+        );
+        let tx: BfTxUtxos = serde_json::from_str(&body).unwrap();
+        let utxos = normalize_tx_utxos(tx);
+        let inputs = utxos.inputs.unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].tx_hash, "prev");
+        assert_eq!(inputs[0].value, "5000000");
+        let out = &utxos.outputs.unwrap()[0];
+        assert_eq!(out.tx_hash, "abc");
+        assert_eq!(out.datum_hash.as_deref(), Some("dh"));
+        assert_eq!(out.inline_datum.as_ref().unwrap().bytes, "d87980");
+        let asset = &out.asset_list.as_ref().unwrap()[0];
+        assert_eq!(asset.policy_id, policy);
+        assert_eq!(asset.asset_name, "69555344");
+        assert_eq!(asset.quantity, "42");
+    }
+
+    #[test]
+    fn online_without_project_id_is_config_error() {
+        if std::env::var(BLOCKFROST_PROJECT_ID_ENV).is_ok() {
+            return;
+        }
+        let config = FetchConfig {
+            provider: crate::chain::ProviderKind::Blockfrost,
+            ..Default::default()
+        };
+        assert!(matches!(
+            BlockfrostProvider::new(config, None),
+            Err(FetchError::Config(_))
+        ));
+    }
+}
@@ -1,9 +1,17 @@
 //! SQLite cache with content-hash keys for fetched API responses.
 
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
-use std::sync::Mutex;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,36 +20,276 @@ pub enum CacheError {
     Sqlite(#[from] rusqlite::Error),
     #[error("io: {0}")]
     Io(#[from] std::io::Error),
+    #[error("archive: {0}")]
+    Archive(String),
+    #[error("schema: {0}")]
+    Schema(String),
+}
+
+/// Schema migrations, applied in order by [`Cache::open`]. Entry `i` upgrades version `i` to
+/// `i + 1`. Append only; never edit a shipped entry. Version 1 is the original layout, written
+/// with `IF NOT EXISTS` so caches that predate versioning adopt it in place.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS cache (
+        key TEXT PRIMARY KEY,
+        value BLOB NOT NULL,
+        created_utc INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_cache_created ON cache(created_utc);
+    "#,
+    r#"
+    CREATE TABLE watermarks (
+        scope TEXT NOT NULL,
+        address TEXT NOT NULL,
+        block_height INTEGER NOT NULL,
+        slot INTEGER,
+        updated_utc INTEGER NOT NULL,
+        PRIMARY KEY (scope, address)
+    );
+    "#,
+];
+
+/// Archive header line; bump `version` on incompatible row changes.
+const ARCHIVE_FORMAT: &str = "indigo-poy-cache";
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+}
+
+/// One cache row in an archive; `value` is hex so arbitrary bytes survive the JSON encoding.
+#[derive(Serialize, Deserialize)]
+struct ArchiveRow {
+    key: String,
+    value: String,
+    created_utc: i64,
+}
+
+/// Endpoint class of a cached response; each class has its own TTL (see [`CacheTtl`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CacheClass {
+    /// Address tx lists (`account_txs`); grow as new txs land.
+    TxHistory,
+    /// Per-tx inputs/outputs; immutable once the tx is on chain.
+    TxUtxos,
+    /// Current UTxO set of an address; changes with every spend.
+    AddressUtxos,
+    /// Stake-key account data (payment addresses used, reward withdrawals); grows as the
+    /// wallet is used.
+    AccountAddresses,
+    /// Per-tx metadata; immutable like [`CacheClass::TxUtxos`].
+    TxMetadata,
+    /// Datum bodies by hash; immutable.
+    DatumInfo,
+    /// Asset ticker/decimals; registry entries are occasionally corrected.
+    AssetInfo,
+    /// Empty and not-found answers of any class (see [`NegativeEntry`]); short-lived so new
+    /// activity shows up on a later run.
+    Negative,
+}
+
+impl CacheClass {
+    pub const ALL: [CacheClass; 8] = [
+        CacheClass::TxHistory,
+        CacheClass::TxUtxos,
+        CacheClass::AddressUtxos,
+        CacheClass::AccountAddresses,
+        CacheClass::TxMetadata,
+        CacheClass::DatumInfo,
+        CacheClass::AssetInfo,
+        CacheClass::Negative,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheClass::TxHistory => "tx_history",
+            CacheClass::TxUtxos => "tx_utxos",
+            CacheClass::AddressUtxos => "address_utxos",
+            CacheClass::AccountAddresses => "account_addresses",
+            CacheClass::TxMetadata => "tx_metadata",
+            CacheClass::DatumInfo => "datum_info",
+            CacheClass::AssetInfo => "asset_info",
+            CacheClass::Negative => "negative",
+        }
+    }
+}
+
+impl fmt::Display for CacheClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for CacheClass {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CacheClass::ALL
+            .into_iter()
+            .find(|c| c.as_str() == s.trim())
+            .ok_or_else(|| {
+                let names: Vec<&str> = CacheClass::ALL.iter().map(CacheClass::as_str).collect();
+                format!(
+                    "unknown cache class: {} (expected one of {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Default TTL for current-UTxO responses.
+const ADDRESS_UTXOS_TTL_SECS: u64 = 600;
+/// Default TTL for stake-key address lists.
+const ACCOUNT_ADDRESSES_TTL_SECS: u64 = 3_600;
+/// Default TTL for asset metadata.
+const ASSET_INFO_TTL_SECS: u64 = 86_400;
+/// Default TTL for empty / not-found answers.
+const NEGATIVE_TTL_SECS: u64 = 300;
+/// Key namespace of negative entries, apart from the response they stand in for.
+const NEGATIVE_KEY_PREFIX: &str = "negative:";
+
+/// A cached "nothing there" answer: an empty body (`[]`, `{}`, `null`) or a 404. Kept under its
+/// own key with [`CacheClass::Negative`]'s TTL, so emptiness is not cached forever under the
+/// request's class while repeated runs in one session still skip the call.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NegativeEntry {
+    pub status: u16,
+    pub body: String,
+}
+
+/// True for response bodies that carry no rows.
+pub fn is_empty_body(body: &str) -> bool {
+    matches!(body.trim(), "" | "[]" | "{}" | "null")
+}
+
+/// Maximum age (seconds) at which a cached response is still served; `None` never expires.
+/// Offline runs ignore TTLs: a stale row beats no data when replaying.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheTtl {
+    pub tx_history: Option<u64>,
+    pub tx_utxos: Option<u64>,
+    pub address_utxos: Option<u64>,
+    pub account_addresses: Option<u64>,
+    pub tx_metadata: Option<u64>,
+    pub datum_info: Option<u64>,
+    pub asset_info: Option<u64>,
+    pub negative: Option<u64>,
+}
+
+impl Default for CacheTtl {
+    fn default() -> Self {
+        Self {
+            tx_history: None,
+            tx_utxos: None,
+            address_utxos: Some(ADDRESS_UTXOS_TTL_SECS),
+            account_addresses: Some(ACCOUNT_ADDRESSES_TTL_SECS),
+            tx_metadata: None,
+            datum_info: None,
+            asset_info: Some(ASSET_INFO_TTL_SECS),
+            negative: Some(NEGATIVE_TTL_SECS),
+        }
+    }
+}
+
+impl CacheTtl {
+    pub fn for_class(&self, class: CacheClass) -> Option<Duration> {
+        let secs = match class {
+            CacheClass::TxHistory => self.tx_history,
+            CacheClass::TxUtxos => self.tx_utxos,
+            CacheClass::AddressUtxos => self.address_utxos,
+            CacheClass::AccountAddresses => self.account_addresses,
+            CacheClass::TxMetadata => self.tx_metadata,
+            CacheClass::DatumInfo => self.datum_info,
+            CacheClass::AssetInfo => self.asset_info,
+            CacheClass::Negative => self.negative,
+        };
+        secs.map(Duration::from_secs)
+    }
+
+    fn secs_mut(&mut self, class: CacheClass) -> &mut Option<u64> {
+        match class {
+            CacheClass::TxHistory => &mut self.tx_history,
+            CacheClass::TxUtxos => &mut self.tx_utxos,
+            CacheClass::AddressUtxos => &mut self.address_utxos,
+            CacheClass::AccountAddresses => &mut self.account_addresses,
+            CacheClass::TxMetadata => &mut self.tx_metadata,
+            CacheClass::DatumInfo => &mut self.datum_info,
+            CacheClass::AssetInfo => &mut self.asset_info,
+            CacheClass::Negative => &mut self.negative,
+        }
+    }
+
+    /// Apply a `class=secs` override; `secs` may be `never`. `0` always refetches.
+    pub fn apply_override(&mut self, spec: &str) -> Result<(), String> {
+        let (class, secs) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected class=secs, got {}", spec))?;
+        let secs = match secs.trim() {
+            "never" => None,
+            s => Some(
+                s.parse::<u64>()
+                    .map_err(|_| format!("invalid TTL seconds: {}", s))?,
+            ),
+        };
+        *self.secs_mut(class.parse::<CacheClass>()?) = secs;
+        Ok(())
+    }
+}
+
+/// Highest block (and its slot) already fetched for an address; incremental fetches resume
+/// after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watermark {
+    pub block_height: u64,
+    pub slot: Option<u64>,
+}
+
+/// Size and age summary of a cache file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub rows: u64,
+    /// Sum of stored value sizes (excludes SQLite overhead).
+    pub bytes: u64,
+    /// Unix time of the oldest / newest row; `None` when empty.
+    pub oldest_utc: Option<i64>,
+    pub newest_utc: Option<i64>,
 }
 
 /// Content-addressed cache for API responses. Key = SHA-256 of request params (normalized).
+/// Clones share one connection.
+#[derive(Clone)]
 pub struct Cache {
-    conn: Mutex<Connection>,
+    conn: Arc<Mutex<Connection>>,
 }
 
 impl Cache {
-    /// Open or create cache at `path`. Creates parent dirs if needed.
+    /// Open or create cache at `path`, migrating it to the current schema. Creates parent dirs
+    /// if needed. Fails on a cache written by a newer version rather than guessing its layout.
     pub fn open(path: impl AsRef<Path>) -> Result<Self, CacheError> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let conn = Connection::open(path)?;
-        conn.execute_batch(
-            r#"
-            CREATE TABLE IF NOT EXISTS cache (
-                key TEXT PRIMARY KEY,
-                value BLOB NOT NULL,
-                created_utc INTEGER NOT NULL
-            );
-            CREATE INDEX IF NOT EXISTS idx_cache_created ON cache(created_utc);
-            "#,
-        )?;
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
         Ok(Self {
-            conn: Mutex::new(conn),
+            conn: Arc::new(Mutex::new(conn)),
         })
     }
 
+    /// Schema version of the open cache (equals the number of known migrations).
+    pub fn schema_version(&self) -> Result<u32, CacheError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        read_schema_version(&conn)
+    }
+
     /// Compute content-hash key from normalized request identifier (e.g. JSON string).
     pub fn key_for(content: &str) -> String {
         let mut hasher = Sha256::new();
@@ -62,9 +310,111 @@ impl Cache {
         Ok(row)
     }
 
+    /// Like [`Cache::get`], but treats rows older than `max_age` as missing.
+    pub fn get_fresh(
+        &self,
+        key: &str,
+        max_age: Option<Duration>,
+    ) -> Result<Option<Vec<u8>>, CacheError> {
+        let Some(max_age) = max_age else {
+            return self.get(key);
+        };
+        let cutoff = now_utc() - max_age.as_secs() as i64;
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut stmt =
+            conn.prepare("SELECT value FROM cache WHERE key = ?1 AND created_utc >= ?2")?;
+        let row = stmt
+            .query_row(rusqlite::params![key, cutoff], |r| r.get::<_, Vec<u8>>(0))
+            .optional()?;
+        Ok(row)
+    }
+
+    pub fn stats(&self) -> Result<CacheStats, CacheError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let stats = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(value)), 0), MIN(created_utc), MAX(created_utc)
+             FROM cache",
+            [],
+            |r| {
+                Ok(CacheStats {
+                    rows: r.get::<_, i64>(0)? as u64,
+                    bytes: r.get::<_, i64>(1)? as u64,
+                    oldest_utc: r.get(2)?,
+                    newest_utc: r.get(3)?,
+                })
+            },
+        )?;
+        Ok(stats)
+    }
+
+    /// Watermark for `address` under `scope` (usually the provider name).
+    pub fn watermark(&self, scope: &str, address: &str) -> Result<Option<Watermark>, CacheError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let row = conn
+            .query_row(
+                "SELECT block_height, slot FROM watermarks WHERE scope = ?1 AND address = ?2",
+                [scope, address],
+                |r| {
+                    Ok(Watermark {
+                        block_height: r.get::<_, i64>(0)? as u64,
+                        slot: r.get::<_, Option<i64>>(1)?.map(|s| s as u64),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(row)
+    }
+
+    pub fn set_watermark(
+        &self,
+        scope: &str,
+        address: &str,
+        watermark: Watermark,
+    ) -> Result<(), CacheError> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO watermarks (scope, address, block_height, slot, updated_utc)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                scope,
+                address,
+                watermark.block_height as i64,
+                watermark.slot.map(|s| s as i64),
+                now_utc()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete rows written more than `older_than` ago. Returns the number removed.
+    pub fn prune(&self, older_than: Duration) -> Result<usize, CacheError> {
+        let cutoff = now_utc() - older_than.as_secs() as i64;
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let removed = conn.execute("DELETE FROM cache WHERE created_utc < ?1", [cutoff])?;
+        if removed > 0 {
+            conn.execute_batch("VACUUM")?;
+        }
+        Ok(removed)
+    }
+
     /// Insert or replace value for key.
     pub fn set(&self, key: &str, value: &[u8]) -> Result<(), CacheError> {
-        let created = time::OffsetDateTime::now_utc().unix_timestamp();
+        let created = now_utc();
         let conn = self
             .conn
             .lock()
@@ -76,18 +426,171 @@ impl Cache {
         Ok(())
     }
 
+    /// Write every row to a gzip-compressed JSON-lines archive at `path` (header line, then
+    /// one row per line). Independent of the SQLite file layout, so it survives schema changes.
+    /// Returns the number of rows written.
+    pub fn export(&self, path: impl AsRef<Path>) -> Result<usize, CacheError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = std::fs::File::create(path)?;
+        let mut out = BufWriter::new(GzEncoder::new(file, Compression::default()));
+        let header = ArchiveHeader {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+        };
+        write_line(&mut out, &header)?;
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut stmt = conn.prepare("SELECT key, value, created_utc FROM cache ORDER BY key")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(ArchiveRow {
+                key: r.get(0)?,
+                value: hex::encode(r.get::<_, Vec<u8>>(1)?),
+                created_utc: r.get(2)?,
+            })
+        })?;
+        let mut count = 0;
+        for row in rows {
+            write_line(&mut out, &row?)?;
+            count += 1;
+        }
+        out.into_inner()
+            .map_err(|e| e.into_error())?
+            .finish()?
+            .sync_all()?;
+        Ok(count)
+    }
+
+    /// Load rows from an archive written by [`Cache::export`]. Existing keys are only replaced
+    /// by newer rows, so importing never rolls back fresher data. Returns rows written.
+    pub fn import(&self, path: impl AsRef<Path>) -> Result<usize, CacheError> {
+        let reader = BufReader::new(GzDecoder::new(std::fs::File::open(path)?));
+        let mut lines = reader.lines();
+        let header: ArchiveHeader = match lines.next() {
+            Some(line) => parse_line(&line?)?,
+            None => return Err(CacheError::Archive("empty archive".into())),
+        };
+        if header.format != ARCHIVE_FORMAT || header.version != ARCHIVE_VERSION {
+            return Err(CacheError::Archive(format!(
+                "unsupported archive {} v{} (expected {} v{})",
+                header.format, header.version, ARCHIVE_FORMAT, ARCHIVE_VERSION
+            )));
+        }
+        let mut conn = self
+            .conn
+            .lock()
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let tx = conn.transaction()?;
+        let mut count = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO cache (key, value, created_utc) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value,
+                     created_utc = excluded.created_utc
+                 WHERE excluded.created_utc > cache.created_utc",
+            )?;
+            for line in lines {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let row: ArchiveRow = parse_line(&line)?;
+                let value = hex::decode(&row.value)
+                    .map_err(|e| CacheError::Archive(format!("row {}: {}", row.key, e)))?;
+                count += stmt.execute(rusqlite::params![row.key, value, row.created_utc])?;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
     /// Get JSON string from cache; returns None if key missing or invalid UTF-8.
     pub fn get_json(&self, key: &str) -> Result<Option<String>, CacheError> {
         let raw = self.get(key)?;
         Ok(raw.and_then(|b| String::from_utf8(b).ok()))
     }
 
+    /// JSON variant of [`Cache::get_fresh`].
+    pub fn get_json_fresh(
+        &self,
+        key: &str,
+        max_age: Option<Duration>,
+    ) -> Result<Option<String>, CacheError> {
+        let raw = self.get_fresh(key, max_age)?;
+        Ok(raw.and_then(|b| String::from_utf8(b).ok()))
+    }
+
+    /// Negative entry for request `key` no older than `max_age`.
+    pub fn get_negative(
+        &self,
+        key: &str,
+        max_age: Option<Duration>,
+    ) -> Result<Option<NegativeEntry>, CacheError> {
+        let raw = self.get_fresh(&format!("{}{}", NEGATIVE_KEY_PREFIX, key), max_age)?;
+        Ok(raw.and_then(|b| serde_json::from_slice(&b).ok()))
+    }
+
+    pub fn set_negative(&self, key: &str, entry: &NegativeEntry) -> Result<(), CacheError> {
+        let json = serde_json::to_vec(entry)
+            .map_err(|e| CacheError::Archive(format!("negative entry: {}", e)))?;
+        self.set(&format!("{}{}", NEGATIVE_KEY_PREFIX, key), &json)
+    }
+
     /// Cache a JSON string. Key should be from `key_for(normalized_request)`.
     pub fn set_json(&self, key: &str, json: &str) -> Result<(), CacheError> {
         self.set(key, json.as_bytes())
     }
 }
 
+fn read_schema_version(conn: &Connection) -> Result<u32, CacheError> {
+    let version = conn
+        .query_row("SELECT version FROM schema_version", [], |r| r.get(0))
+        .optional()?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply pending [`MIGRATIONS`], each in its own transaction together with the version bump.
+fn migrate(conn: &mut Connection) -> Result<(), CacheError> {
+    conn.execute_batch("CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)")?;
+    let current = read_schema_version(conn)?;
+    let latest = MIGRATIONS.len() as u32;
+    if current > latest {
+        return Err(CacheError::Schema(format!(
+            "cache schema v{} is newer than supported v{}; upgrade indigo-poy or use another --cache-dir",
+            current, latest
+        )));
+    }
+    for (version, sql) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.execute("DELETE FROM schema_version", [])?;
+        tx.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            [version as u32 + 1],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn write_line<T: Serialize>(out: &mut impl Write, value: &T) -> Result<(), CacheError> {
+    serde_json::to_writer(&mut *out, value).map_err(|e| CacheError::Archive(e.to_string()))?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+fn parse_line<T: for<'de> Deserialize<'de>>(line: &str) -> Result<T, CacheError> {
+    serde_json::from_str(line).map_err(|e| CacheError::Archive(e.to_string()))
+}
+
+fn now_utc() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,4 +623,137 @@ mod tests {
         cache.set_json(&key, json).unwrap();
         assert_eq!(cache.get_json(&key).unwrap(), Some(json.to_string()));
     }
+
+    #[test]
+    fn open_migrates_legacy_cache_and_rejects_newer_schema() {
+        let tmp = NamedTempFile::new().unwrap();
+        {
+            // Pre-versioning layout with a row in it.
+            let conn = Connection::open(tmp.path()).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE cache (key TEXT PRIMARY KEY, value BLOB NOT NULL, created_utc INTEGER NOT NULL);
+                 INSERT INTO cache VALUES ('k', x'01', 0);",
+            )
+            .unwrap();
+        }
+        let cache = Cache::open(tmp.path()).unwrap();
+        assert_eq!(cache.schema_version().unwrap(), MIGRATIONS.len() as u32);
+        assert_eq!(cache.get("k").unwrap(), Some(vec![1]));
+        drop(cache);
+        assert!(Cache::open(tmp.path()).is_ok());
+
+        let conn = Connection::open(tmp.path()).unwrap();
+        conn.execute("UPDATE schema_version SET version = 99", [])
+            .unwrap();
+        drop(conn);
+        assert!(matches!(
+            Cache::open(tmp.path()),
+            Err(CacheError::Schema(_))
+        ));
+    }
+
+    #[test]
+    fn watermark_roundtrip() {
+        let tmp = NamedTempFile::new().unwrap();
+        let cache = Cache::open(tmp.path()).unwrap();
+        assert!(cache.watermark("koios", "addr1x").unwrap().is_none());
+        let wm = Watermark {
+            block_height: 10_000_000,
+            slot: Some(120_000_000),
+        };
+        cache.set_watermark("koios", "addr1x", wm).unwrap();
+        assert_eq!(cache.watermark("koios", "addr1x").unwrap(), Some(wm));
+        assert!(cache.watermark("blockfrost", "addr1x").unwrap().is_none());
+    }
+
+    /// Backdate a row so TTL and prune logic can be exercised without sleeping.
+    fn backdate(cache: &Cache, key: &str, secs: i64) {
+        let conn = cache.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE cache SET created_utc = created_utc - ?2 WHERE key = ?1",
+            rusqlite::params![key, secs],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn ttl_expires_and_prune_removes_old_rows() {
+        let tmp = NamedTempFile::new().unwrap();
+        let cache = Cache::open(tmp.path()).unwrap();
+        cache.set("old", b"a").unwrap();
+        cache.set("new", b"b").unwrap();
+        backdate(&cache, "old", 3_600);
+        let ttl = Some(Duration::from_secs(600));
+        assert!(cache.get_fresh("old", ttl).unwrap().is_none());
+        assert!(cache.get_fresh("old", None).unwrap().is_some());
+        assert!(cache.get_fresh("new", ttl).unwrap().is_some());
+        assert_eq!(cache.prune(Duration::from_secs(1_800)).unwrap(), 1);
+        assert!(cache.get("old").unwrap().is_none());
+        assert!(cache.get("new").unwrap().is_some());
+    }
+
+    #[test]
+    fn negative_entries_expire_apart_from_responses() {
+        let tmp = NamedTempFile::new().unwrap();
+        let cache = Cache::open(tmp.path()).unwrap();
+        let entry = NegativeEntry {
+            status: 404,
+            body: "not found".into(),
+        };
+        cache.set_negative("k", &entry).unwrap();
+        assert_eq!(cache.get_json("k").unwrap(), None);
+        let ttl = CacheTtl::default().for_class(CacheClass::Negative);
+        assert_eq!(cache.get_negative("k", ttl).unwrap(), Some(entry));
+        backdate(&cache, "negative:k", 3_600);
+        assert_eq!(cache.get_negative("k", ttl).unwrap(), None);
+        assert!(is_empty_body(" [] ") && is_empty_body("null") && !is_empty_body("[{}]"));
+    }
+
+    #[test]
+    fn export_import_roundtrip_keeps_newer_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = Cache::open(dir.path().join("src.sqlite")).unwrap();
+        src.set("a", b"\x00\xffbinary").unwrap();
+        src.set("b", b"old").unwrap();
+        backdate(&src, "b", 60);
+        let archive = dir.path().join("cache.jsonl.gz");
+        assert_eq!(src.export(&archive).unwrap(), 2);
+
+        let dst = Cache::open(dir.path().join("dst.sqlite")).unwrap();
+        dst.set("b", b"newer").unwrap();
+        assert_eq!(dst.import(&archive).unwrap(), 1);
+        assert_eq!(dst.get("a").unwrap(), Some(b"\x00\xffbinary".to_vec()));
+        assert_eq!(dst.get("b").unwrap(), Some(b"newer".to_vec()));
+
+        std::fs::write(&archive, b"not gzip").unwrap();
+        assert!(dst.import(&archive).is_err());
+    }
+
+    #[test]
+    fn stats_count_rows_bytes_and_age() {
+        let tmp = NamedTempFile::new().unwrap();
+        let cache = Cache::open(tmp.path()).unwrap();
+        assert_eq!(cache.stats().unwrap(), CacheStats::default());
+        cache.set("a", b"hello").unwrap();
+        cache.set("b", b"abc").unwrap();
+        backdate(&cache, "a", 100);
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.rows, stats.bytes), (2, 8));
+        assert_eq!(stats.newest_utc.unwrap() - stats.oldest_utc.unwrap(), 100);
+    }
+
+    #[test]
+    fn ttl_overrides_parse() {
+        let mut ttl = CacheTtl::default();
+        assert_eq!(
+            ttl.for_class(CacheClass::AddressUtxos),
+            Some(Duration::from_secs(600))
+        );
+        ttl.apply_override("tx_history=3600").unwrap();
+        ttl.apply_override("address_utxos=never").unwrap();
+        assert_eq!(ttl.tx_history, Some(3600));
+        assert_eq!(ttl.address_utxos, None);
+        assert!(ttl.apply_override("blocks=1").is_err());
+        assert!(ttl.apply_override("tx_utxos").is_err());
+    }
 }
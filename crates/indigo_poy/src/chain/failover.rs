@@ -0,0 +1,286 @@
+//! Ordered provider chain: every call goes to the first provider, and on to the next one when
+//! it fails in a way another provider might not (see [`fails_over`]).
+
+use crate::chain::assets::AssetInfo;
+use crate::chain::fetch::{
+    ChainTip, FetchError, FetchStats, KoiosAccountTx, KoiosInlineDatum, KoiosTxUtxos, KoiosUtxo,
+    RawResponse, RewardWithdrawal, TxMetadata,
+};
+use crate::chain::provider::ChainProvider;
+use crate::BoxFuture;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// Separator of provider names in a chain's name, e.g. `"koios,blockfrost"`.
+pub(crate) const CHAIN_SEPARATOR: char = ',';
+
+/// Transport failures, throttling and server errors, an open circuit breaker, unsupported
+/// lookups, and cache / replay misses. Real answers (a 404, a parse of a good response) are
+/// final: another provider's view of the chain should not silently replace them.
+pub(crate) fn fails_over(e: &FetchError) -> bool {
+    match e {
+        FetchError::Request(_)
+        | FetchError::CircuitOpen(_)
+        | FetchError::Config(_)
+        | FetchError::OfflineMiss => true,
+        FetchError::Api(status, _) => *status == 0 || *status == 429 || *status >= 500,
+        FetchError::Cache(_) | FetchError::Normalize(_) => false,
+    }
+}
+
+/// Providers tried in order for each call. Its name lists them all, so a bundle records the
+/// chain and a replay can rebuild it.
+pub struct FailoverProvider {
+    providers: Vec<Box<dyn ChainProvider>>,
+    name: String,
+}
+
+impl FailoverProvider {
+    /// `providers` must not be empty; the first is the primary.
+    pub fn new(providers: Vec<Box<dyn ChainProvider>>) -> Result<Self, FetchError> {
+        if providers.is_empty() {
+            return Err(FetchError::Config("failover: no providers".into()));
+        }
+        let name = providers
+            .iter()
+            .map(|p| p.name())
+            .collect::<Vec<_>>()
+            .join(&CHAIN_SEPARATOR.to_string());
+        Ok(Self { providers, name })
+    }
+
+    async fn first<'a, T>(
+        &'a self,
+        call: impl Fn(&'a dyn ChainProvider) -> BoxFuture<'a, Result<T, FetchError>> + Send,
+    ) -> Result<T, FetchError> {
+        let (last, rest) = self.providers.split_last().expect("non-empty chain");
+        for provider in rest {
+            match call(provider.as_ref()).await {
+                Err(e) if fails_over(&e) => {
+                    warn!(provider = provider.name(), error = %e, "provider failed; failing over")
+                }
+                result => return result,
+            }
+        }
+        call(last.as_ref()).await
+    }
+}
+
+impl ChainProvider for FailoverProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+        Box::pin(self.first(move |p| p.account_txs(address, from_slot, to_slot)))
+    }
+
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+        Box::pin(self.first(move |p| p.tx_utxos(tx_hash)))
+    }
+
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+        Box::pin(self.first(move |p| p.address_utxos(address)))
+    }
+
+    fn stake_addresses<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(self.first(move |p| p.stake_addresses(stake_address)))
+    }
+
+    fn reward_withdrawals<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RewardWithdrawal>, FetchError>> {
+        Box::pin(self.first(move |p| p.reward_withdrawals(stake_address)))
+    }
+
+    fn tx_metadata<'a>(
+        &'a self,
+        tx_hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+        Box::pin(self.first(move |p| p.tx_metadata(tx_hash)))
+    }
+
+    fn datum_info<'a>(
+        &'a self,
+        datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(self.first(move |p| p.datum_info(datum_hashes)))
+    }
+
+    fn asset_info<'a>(
+        &'a self,
+        policy_id: &'a str,
+        asset_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<AssetInfo>, FetchError>> {
+        Box::pin(self.first(move |p| p.asset_info(policy_id, asset_name)))
+    }
+
+    fn policy_minted<'a>(
+        &'a self,
+        policy_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<bool>, FetchError>> {
+        Box::pin(self.first(move |p| p.policy_minted(policy_id)))
+    }
+
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(self.first(|p| p.tip()))
+    }
+
+    /// Counters summed over the chain; the rate limit is the widest of any provider.
+    fn stats(&self) -> FetchStats {
+        self.providers
+            .iter()
+            .map(|p| p.stats())
+            .fold(FetchStats::default(), |a, b| FetchStats {
+                requests: a.requests + b.requests,
+                throttled: a.throttled + b.throttled,
+                rate_limit_ms: a.rate_limit_ms.max(b.rate_limit_ms),
+                cache_hits: a.cache_hits + b.cache_hits,
+                cache_misses: a.cache_misses + b.cache_misses,
+                coalesced: a.coalesced + b.coalesced,
+            })
+    }
+
+    /// Responses of every provider, each tagged with the providers that served it.
+    fn take_responses(&self) -> Vec<RawResponse> {
+        let mut merged: BTreeMap<String, RawResponse> = BTreeMap::new();
+        for provider in &self.providers {
+            for response in provider.take_responses() {
+                let entry = merged
+                    .entry(response.sha256.clone())
+                    .or_insert_with(|| RawResponse {
+                        requests: vec![],
+                        ..response.clone()
+                    });
+                entry.requests.extend(response.requests);
+                entry.requests.sort();
+                entry.requests.dedup();
+                entry.providers.push(provider.name().to_string());
+            }
+        }
+        merged.into_values().collect()
+    }
+}
+
+/// Which provider served which responses: provider name → sorted response hashes. Empty
+/// unless the responses came through a [`FailoverProvider`].
+pub fn served_by(responses: &[RawResponse]) -> BTreeMap<String, Vec<String>> {
+    let mut out: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for response in responses {
+        for provider in &response.providers {
+            out.entry(provider.clone())
+                .or_default()
+                .push(response.sha256.clone());
+        }
+    }
+    for hashes in out.values_mut() {
+        hashes.sort();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Fetcher;
+
+    /// Fails every tx lookup with `error`, or serves an empty tx when `error` is `None`.
+    struct Fixed {
+        name: &'static str,
+        error: Option<fn() -> FetchError>,
+        served: std::sync::Mutex<Vec<RawResponse>>,
+    }
+
+    impl Fixed {
+        fn boxed(name: &'static str, error: Option<fn() -> FetchError>) -> Box<dyn ChainProvider> {
+            Box::new(Self {
+                name,
+                error,
+                served: Default::default(),
+            })
+        }
+    }
+
+    impl ChainProvider for Fixed {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn account_txs<'a>(
+            &'a self,
+            _address: &'a str,
+            _from_slot: Option<u64>,
+            _to_slot: Option<u64>,
+        ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+            Box::pin(async { Err(FetchError::Api(404, "unknown address".into())) })
+        }
+
+        fn tx_utxos<'a>(
+            &'a self,
+            _tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+            Box::pin(async move {
+                if let Some(error) = self.error {
+                    return Err(error());
+                }
+                let mut response = RawResponse::new(format!("{{\"from\":\"{}\"}}", self.name));
+                response.requests = vec!["req".into()];
+                self.served.lock().unwrap().push(response);
+                Ok(KoiosTxUtxos {
+                    inputs: Some(vec![]),
+                    outputs: Some(vec![]),
+                    metadata: None,
+                })
+            })
+        }
+
+        fn address_utxos<'a>(
+            &'a self,
+            _address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn take_responses(&self) -> Vec<RawResponse> {
+            std::mem::take(&mut *self.served.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn fails_over_on_throttling_but_not_on_answers() {
+        let chain = FailoverProvider::new(vec![
+            Fixed::boxed("koios", Some(|| FetchError::Api(429, "slow down".into()))),
+            Fixed::boxed("breaker", Some(|| FetchError::CircuitOpen(1_000))),
+            Fixed::boxed("blockfrost", None),
+        ])
+        .unwrap();
+        assert_eq!(chain.name(), "koios,breaker,blockfrost");
+        let fetcher = Fetcher::with_provider(Box::new(chain));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert!(rt.block_on(fetcher.tx_utxos("aa")).is_ok());
+        // A 404 from the primary is an answer, not an outage.
+        assert!(matches!(
+            rt.block_on(fetcher.account_txs("addr1x", None, None)),
+            Err(FetchError::Api(404, _))
+        ));
+        let responses = fetcher.take_responses();
+        assert_eq!(responses[0].providers, vec!["blockfrost".to_string()]);
+        assert_eq!(
+            served_by(&responses),
+            BTreeMap::from([("blockfrost".to_string(), vec![responses[0].sha256.clone()])])
+        );
+        assert!(FailoverProvider::new(vec![]).is_err());
+    }
+}
@@ -0,0 +1,856 @@
+//! Shared HTTP layer for chain providers: rate limiting, retries, and the raw-response cache.
+
+use crate::chain::cache::{is_empty_body, Cache, CacheClass, NegativeEntry};
+use crate::chain::fetch::{FetchConfig, FetchError, FetchStats, RawResponse};
+use crate::chain::metrics::endpoint_label;
+use crate::chain::replay;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use time::format_description::well_known::Rfc2822;
+use time::OffsetDateTime;
+use tokio::sync::OnceCell;
+use tracing::{debug, warn};
+
+type SharedResult = Arc<OnceCell<Result<String, FetchError>>>;
+
+/// Deduplicates identical in-flight requests: callers with the same key while one is running
+/// await its result instead of issuing their own.
+#[derive(Default)]
+struct InFlight {
+    calls: std::sync::Mutex<HashMap<String, SharedResult>>,
+    coalesced: AtomicU64,
+}
+
+impl InFlight {
+    async fn run<F>(&self, key: &str, fetch: F) -> Result<String, FetchError>
+    where
+        F: Future<Output = Result<String, FetchError>>,
+    {
+        let (cell, leader) = {
+            let mut calls = self.calls.lock().unwrap();
+            match calls.get(key) {
+                Some(cell) => (Arc::clone(cell), false),
+                None => {
+                    let cell = SharedResult::default();
+                    calls.insert(key.to_string(), Arc::clone(&cell));
+                    (cell, true)
+                }
+            }
+        };
+        if !leader {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+            debug!(key, "coalesced with in-flight request");
+        }
+        // A follower only runs `fetch` if the leader was cancelled before finishing.
+        let result = cell.get_or_init(|| fetch).await;
+        if leader {
+            self.calls.lock().unwrap().remove(key);
+        }
+        match result {
+            Ok(body) => Ok(body.clone()),
+            Err(e) => Err(e.duplicate()),
+        }
+    }
+}
+
+/// Timeout / retry override for one endpoint; unset fields use the [`FetchConfig`] defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EndpointPolicy {
+    pub timeout_ms: Option<u64>,
+    pub max_retries: Option<u32>,
+}
+
+/// Per-endpoint overrides keyed by endpoint label ([`endpoint_label`], as in the metrics file:
+/// `/account_txs`, `/tx_utxos`, `/txs/:id/utxos`, ...).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EndpointPolicies(pub BTreeMap<String, EndpointPolicy>);
+
+impl EndpointPolicies {
+    pub fn get(&self, path: &str) -> Option<&EndpointPolicy> {
+        self.0.get(&endpoint_label(path))
+    }
+
+    /// Apply an `endpoint=secs` timeout override.
+    pub fn apply_timeout(&mut self, spec: &str) -> Result<(), String> {
+        let (endpoint, secs) = parse_policy_spec(spec)?;
+        self.entry(endpoint).timeout_ms = Some(secs.saturating_mul(1000));
+        Ok(())
+    }
+
+    /// Apply an `endpoint=n` retry-count override.
+    pub fn apply_retries(&mut self, spec: &str) -> Result<(), String> {
+        let (endpoint, n) = parse_policy_spec(spec)?;
+        self.entry(endpoint).max_retries =
+            Some(u32::try_from(n).map_err(|_| format!("retry count too large: {}", n))?);
+        Ok(())
+    }
+
+    fn entry(&mut self, endpoint: &str) -> &mut EndpointPolicy {
+        self.0.entry(endpoint_label(endpoint)).or_default()
+    }
+}
+
+fn parse_policy_spec(spec: &str) -> Result<(&str, u64), String> {
+    let (endpoint, value) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected endpoint=value, got {}", spec))?;
+    let endpoint = endpoint.trim();
+    if !endpoint.starts_with('/') {
+        return Err(format!("endpoint must start with '/': {}", endpoint));
+    }
+    let value = value
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("invalid number: {}", value))?;
+    Ok((endpoint, value))
+}
+
+/// Trips after `threshold` consecutive server errors and rejects requests until the cooldown
+/// has passed. The first request after that is a probe: a success closes the breaker, a failure
+/// re-opens it straight away (the failure count is only reset by a success).
+#[derive(Default)]
+struct CircuitBreaker {
+    state: std::sync::Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// `Err(ms until the probe)` while open.
+    fn check(&self, now: Instant) -> Result<(), u64> {
+        match self.state.lock().unwrap().open_until {
+            Some(until) if now < until => Err((until - now).as_millis() as u64),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    /// Count a server error; true if this opened the breaker. `threshold` 0 never opens.
+    fn record_failure(&self, now: Instant, threshold: u32, cooldown: Duration) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        if threshold == 0 || state.consecutive_failures < threshold {
+            return false;
+        }
+        state.open_until = Some(now + cooldown);
+        true
+    }
+}
+
+/// Uniform in `0..=max`, seeded per call from the std hasher's random keys (no RNG dependency
+/// needed for jitter).
+fn jitter(max: u64) -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(max);
+    hasher.finish() % max.saturating_add(1).max(1)
+}
+
+/// Rate-limited, retrying HTTP client with an optional content-addressed cache.
+pub struct HttpClient {
+    config: FetchConfig,
+    base_url: String,
+    client: Option<reqwest::Client>,
+    cache: Option<Cache>,
+    last_request: std::sync::Mutex<Option<OffsetDateTime>>,
+    request_count: AtomicU64,
+    throttled: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    in_flight: InFlight,
+    breaker: CircuitBreaker,
+    /// Responses served since the last `take_responses`, by SHA-256.
+    responses: std::sync::Mutex<BTreeMap<String, RawResponse>>,
+    /// Effective spacing between requests; starts at `config.rate_limit_ms`, widened on 429.
+    rate_limit_ms: AtomicU64,
+}
+
+/// 429 responses waited out per request before treating them as ordinary failures.
+const MAX_THROTTLED_WAITS: u32 = 8;
+const MIN_WIDENED_RATE_MS: u64 = 100;
+const MAX_RATE_LIMIT_MS: u64 = 10_000;
+const MAX_RETRY_AFTER_MS: u64 = 300_000;
+
+/// Client builder with the settings every provider connection shares: rustls, timeout, proxy,
+/// and extra trusted roots from `config`.
+pub fn client_builder(config: &FetchConfig) -> Result<reqwest::ClientBuilder, FetchError> {
+    let mut builder = reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(Duration::from_millis(config.timeout_ms));
+    if let Some(proxy) = &config.proxy_url {
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| FetchError::Config(format!("invalid proxy url: {}", e)))?;
+        builder = builder.proxy(proxy);
+    }
+    for path in &config.extra_root_certs {
+        let pem = std::fs::read(path)
+            .map_err(|e| FetchError::Config(format!("root cert {}: {}", path.display(), e)))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| FetchError::Config(format!("root cert {}: {}", path.display(), e)))?;
+        if certs.is_empty() {
+            return Err(FetchError::Config(format!(
+                "root cert {}: no PEM certificates found",
+                path.display()
+            )));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    Ok(builder)
+}
+
+impl HttpClient {
+    /// `headers` are sent with every request (e.g. provider auth). `config.auth_token` adds a
+    /// sensitive `Authorization: Bearer` header unless `headers` already has one. Headers are
+    /// never part of cache keys or cached bodies.
+    pub fn new(
+        config: FetchConfig,
+        base_url: String,
+        mut headers: HeaderMap,
+        cache: Option<Cache>,
+    ) -> Result<Self, FetchError> {
+        if let Some(token) = config.resolved_auth_token() {
+            if !headers.contains_key(AUTHORIZATION) {
+                let mut value = HeaderValue::from_str(&format!("Bearer {}", token.trim()))
+                    .map_err(|_| FetchError::Config("invalid auth token".into()))?;
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
+        }
+        let client =
+            if config.offline || config.replay_dir.is_some() || config.replay_responses.is_some() {
+                None
+            } else {
+                Some(client_builder(&config)?.default_headers(headers).build()?)
+            };
+        Ok(Self {
+            base_url,
+            client,
+            cache,
+            last_request: std::sync::Mutex::new(None),
+            request_count: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            in_flight: InFlight::default(),
+            breaker: CircuitBreaker::default(),
+            responses: std::sync::Mutex::new(BTreeMap::new()),
+            rate_limit_ms: AtomicU64::new(config.rate_limit_ms),
+            config,
+        })
+    }
+
+    /// Reserve the next request slot under the lock, then sleep until it. Concurrent callers get
+    /// successive slots, so the rate limit holds across parallel requests.
+    async fn rate_limit(&self) {
+        let sleep_ms = {
+            let mut last = self.last_request.lock().unwrap();
+            let now = OffsetDateTime::now_utc();
+            let slot = match *last {
+                Some(prev) => {
+                    let spacing = self.rate_limit_ms.load(Ordering::Relaxed);
+                    let next = prev + Duration::from_millis(spacing);
+                    if next > now {
+                        next
+                    } else {
+                        now
+                    }
+                }
+                None => now,
+            };
+            *last = Some(slot);
+            (slot - now).whole_milliseconds().max(0) as u64
+        };
+        if sleep_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(sleep_ms)).await;
+        }
+    }
+
+    /// Cache key for a provider whose responses are fully described by the request path.
+    pub fn path_cache_key(provider: &str, path: &str) -> String {
+        let req = serde_json::json!({ "provider": provider, "path": path });
+        Cache::key_for(&req.to_string())
+    }
+
+    /// GET and parse `path`; a 404 (unknown address / tx) yields `None`.
+    pub async fn get_parsed<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        cache_key: &str,
+        class: CacheClass,
+    ) -> Result<Option<T>, FetchError> {
+        match self.get_json(path, cache_key, class).await {
+            Ok(body) => serde_json::from_str(&body)
+                .map(Some)
+                .map_err(|e| FetchError::Api(0, format!("parse {}: {}", path, e))),
+            Err(FetchError::Api(404, _)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn get_json(
+        &self,
+        path: &str,
+        cache_key: &str,
+        class: CacheClass,
+    ) -> Result<String, FetchError> {
+        self.request_json(path, cache_key, class, None).await
+    }
+
+    /// GET (or POST when `post_body` is set) `base_url + path`, served from cache when present
+    /// and younger than the TTL for `class`. Empty and 404 answers live in a negative entry with
+    /// the shorter [`CacheClass::Negative`] TTL instead. Offline runs serve any cached row.
+    pub async fn request_json(
+        &self,
+        path: &str,
+        cache_key: &str,
+        class: CacheClass,
+        post_body: Option<serde_json::Value>,
+    ) -> Result<String, FetchError> {
+        if let Some(cache) = &self.cache {
+            let max_age = if self.config.offline {
+                None
+            } else {
+                self.config.cache_ttl.for_class(class)
+            };
+            // Empty rows cached before negative entries existed are refetched once online.
+            let cached = cache
+                .get_json_fresh(cache_key, max_age)?
+                .filter(|body| self.config.offline || !is_empty_body(body));
+            if let Some(cached) = cached {
+                debug!(key = %cache_key, "cache hit");
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                self.config.metrics.record_cache(path, true);
+                self.record_served(path, post_body.as_ref(), 200, &cached);
+                self.record_fixture(path, post_body.as_ref(), 200, &cached);
+                return Ok(cached);
+            }
+            let negative_age = if self.config.offline {
+                None
+            } else {
+                self.config.cache_ttl.for_class(CacheClass::Negative)
+            };
+            if let Some(entry) = cache.get_negative(cache_key, negative_age)? {
+                debug!(key = %cache_key, status = entry.status, "negative cache hit");
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                self.config.metrics.record_cache(path, true);
+                self.record_fixture(path, post_body.as_ref(), entry.status, &entry.body);
+                self.record_served(path, post_body.as_ref(), entry.status, &entry.body);
+                if !(200..300).contains(&entry.status) {
+                    return Err(FetchError::Api(entry.status, entry.body));
+                }
+                return Ok(entry.body);
+            }
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+            self.config.metrics.record_cache(path, false);
+            if self.config.offline {
+                return Err(FetchError::OfflineMiss);
+            }
+        }
+        self.in_flight
+            .run(
+                cache_key,
+                self.fetch_upstream(path, Some(cache_key), post_body.clone()),
+            )
+            .await
+    }
+
+    /// Like [`HttpClient::request_json`] but always goes upstream and caches nothing, for
+    /// requests whose answer must be current (e.g. the tail after a watermark).
+    pub async fn request_uncached(
+        &self,
+        path: &str,
+        post_body: Option<serde_json::Value>,
+    ) -> Result<String, FetchError> {
+        self.fetch_upstream(path, None, post_body).await
+    }
+
+    /// Shared cache for provider-level bookkeeping (e.g. watermarks, merged histories).
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    /// Note a body as used for the current bundle. Served responses are recorded
+    /// automatically; providers call this for data they read from the cache directly.
+    pub fn record_response(&self, body: &str) {
+        self.record_request(None, 200, body);
+    }
+
+    /// [`HttpClient::record_response`] for the answer to a request, keyed for replay. Error
+    /// answers the run went on with (a 404 read as "not found") are kept with their status.
+    fn record_served(
+        &self,
+        path: &str,
+        post_body: Option<&serde_json::Value>,
+        status: u16,
+        body: &str,
+    ) {
+        self.record_request(Some(replay::fixture_key(path, post_body)), status, body);
+    }
+
+    fn record_request(&self, request: Option<String>, status: u16, body: &str) {
+        let mut response = RawResponse::new(body.to_string());
+        if !(200..300).contains(&status) {
+            response.status = Some(status);
+        }
+        let mut responses = self.responses.lock().unwrap();
+        let entry = responses.entry(response.sha256.clone()).or_insert(response);
+        if let Some(key) = request {
+            if let Err(i) = entry.requests.binary_search(&key) {
+                entry.requests.insert(i, key);
+            }
+        }
+    }
+
+    pub fn take_responses(&self) -> Vec<RawResponse> {
+        std::mem::take(&mut *self.responses.lock().unwrap())
+            .into_values()
+            .collect()
+    }
+
+    /// Write a fixture when recording; failures are logged, never fatal to the fetch.
+    fn record_fixture(
+        &self,
+        path: &str,
+        post_body: Option<&serde_json::Value>,
+        status: u16,
+        body: &str,
+    ) {
+        if let Some(dir) = &self.config.record_dir {
+            if let Err(e) = replay::write_fixture(dir, path, post_body, status, body) {
+                warn!(error = %e, "failed to record fixture");
+            }
+        }
+    }
+
+    /// True when the run must be replayable from what went over the wire: responses are being
+    /// recorded as fixtures, or kept for a bundle's responses archive (`replayable`).
+    pub fn recording(&self) -> bool {
+        self.config.record_dir.is_some() || self.config.replayable
+    }
+
+    pub fn config(&self) -> &FetchConfig {
+        &self.config
+    }
+
+    pub fn offline(&self) -> bool {
+        self.config.offline
+    }
+
+    async fn fetch_upstream(
+        &self,
+        path: &str,
+        cache_key: Option<&str>,
+        post_body: Option<serde_json::Value>,
+    ) -> Result<String, FetchError> {
+        if let Some(dir) = &self.config.replay_dir {
+            let fixture = replay::read_fixture(dir, path, post_body.as_ref())?;
+            let body = fixture.body_text();
+            self.request_count.fetch_add(1, Ordering::Relaxed);
+            self.record_served(path, post_body.as_ref(), fixture.status, &body);
+            if !(200..300).contains(&fixture.status) {
+                return Err(FetchError::Api(fixture.status, body));
+            }
+            return Ok(body);
+        }
+        if let Some(recorded) = &self.config.replay_responses {
+            let (status, body) = recorded.get(path, post_body.as_ref()).ok_or_else(|| {
+                FetchError::Config(format!("bundle replay: no response for {}", path))
+            })?;
+            let body = body.to_string();
+            self.request_count.fetch_add(1, Ordering::Relaxed);
+            self.record_served(path, post_body.as_ref(), status, &body);
+            if !(200..300).contains(&status) {
+                return Err(FetchError::Api(status, body));
+            }
+            return Ok(body);
+        }
+        let client = self.client.as_ref().ok_or(FetchError::OfflineMiss)?;
+        self.rate_limit().await;
+
+        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
+        let mut attempt = 0;
+        let mut throttled_waits = 0;
+        let metrics = &self.config.metrics;
+        let policy = self.config.endpoint_policies.get(path).copied();
+        let timeout = policy.and_then(|p| p.timeout_ms).map(Duration::from_millis);
+        let max_retries = policy
+            .and_then(|p| p.max_retries)
+            .unwrap_or(self.config.max_retries);
+        let cooldown = Duration::from_millis(self.config.circuit_breaker_cooldown_ms);
+        loop {
+            if let Err(ms) = self.breaker.check(Instant::now()) {
+                return Err(FetchError::CircuitOpen(ms));
+            }
+            let started = Instant::now();
+            let mut request = match &post_body {
+                Some(body) => client.post(&url).json(body),
+                None => client.get(&url),
+            };
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            let res = request.send().await;
+            let status = res.as_ref().map_or(0, |r| r.status().as_u16());
+            metrics.record_response(path, status, started.elapsed());
+            let server_error = status == 0 || status >= 500;
+            let tripped = server_error
+                && self.breaker.record_failure(
+                    Instant::now(),
+                    self.config.circuit_breaker_threshold,
+                    cooldown,
+                );
+            if !server_error {
+                self.breaker.record_success();
+            }
+            let err = match res {
+                Ok(r)
+                    if r.status() == StatusCode::TOO_MANY_REQUESTS
+                        && throttled_waits < MAX_THROTTLED_WAITS =>
+                {
+                    // Throttling is not a failure: wait as told and slow down, without using a retry.
+                    let wait_ms = retry_after_ms(r.headers(), OffsetDateTime::now_utc())
+                        .unwrap_or_else(|| self.backoff_ms(throttled_waits));
+                    throttled_waits += 1;
+                    self.throttled.fetch_add(1, Ordering::Relaxed);
+                    metrics.record_throttled(path);
+                    let rate_limit_ms = self.widen_rate_limit();
+                    warn!(wait_ms, rate_limit_ms, "rate limited (429), backing off");
+                    tokio::time::sleep(Duration::from_millis(wait_ms)).await;
+                    self.rate_limit().await;
+                    continue;
+                }
+                Ok(r) => {
+                    let status = r.status();
+                    let body = r.text().await.unwrap_or_default();
+                    if status.is_success() {
+                        self.request_count.fetch_add(1, Ordering::Relaxed);
+                        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+                            let _ = if is_empty_body(&body) {
+                                cache.set_negative(
+                                    key,
+                                    &NegativeEntry {
+                                        status: status.as_u16(),
+                                        body: body.clone(),
+                                    },
+                                )
+                            } else {
+                                cache.set_json(key, &body)
+                            };
+                        }
+                        self.record_served(path, post_body.as_ref(), status.as_u16(), &body);
+                        self.record_fixture(path, post_body.as_ref(), status.as_u16(), &body);
+                        return Ok(body);
+                    }
+                    if attempt >= max_retries || status == StatusCode::NOT_FOUND {
+                        self.record_served(path, post_body.as_ref(), status.as_u16(), &body);
+                        self.record_fixture(path, post_body.as_ref(), status.as_u16(), &body);
+                    }
+                    if status == StatusCode::NOT_FOUND {
+                        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+                            let entry = NegativeEntry {
+                                status: status.as_u16(),
+                                body: body.clone(),
+                            };
+                            let _ = cache.set_negative(key, &entry);
+                        }
+                    }
+                    FetchError::Api(status.as_u16(), body)
+                }
+                Err(e) => FetchError::Request(e),
+            };
+            if tripped {
+                warn!(
+                    failures = self.config.circuit_breaker_threshold,
+                    cooldown_ms = self.config.circuit_breaker_cooldown_ms,
+                    error = %err,
+                    "circuit breaker open; failing fast"
+                );
+            }
+            if tripped || attempt >= max_retries {
+                return Err(err);
+            }
+            let ms = self.backoff_ms(attempt);
+            warn!(attempt, ms, error = %err, "retry after error");
+            metrics.record_retry(path);
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Full-jitter exponential backoff: uniform in `0..=min(cap, base * 2^attempt)`, so
+    /// concurrent retries spread out instead of arriving together.
+    fn backoff_ms(&self, attempt: u32) -> u64 {
+        let ceiling = self
+            .config
+            .retry_backoff_ms
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.config.max_backoff_ms);
+        jitter(ceiling)
+    }
+
+    /// Double the effective request spacing (capped) for the rest of the run; returns the new value.
+    fn widen_rate_limit(&self) -> u64 {
+        let widen = |ms: u64| {
+            ms.saturating_mul(2)
+                .clamp(MIN_WIDENED_RATE_MS, MAX_RATE_LIMIT_MS)
+        };
+        let prev = self
+            .rate_limit_ms
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |ms| Some(widen(ms)))
+            .unwrap_or_default();
+        widen(prev)
+    }
+
+    pub fn request_count(&self) -> u64 {
+        self.request_count.load(Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> FetchStats {
+        FetchStats {
+            requests: self.request_count(),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            rate_limit_ms: self.rate_limit_ms.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            coalesced: self.in_flight.coalesced.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// `Retry-After` as milliseconds: delta-seconds, or an HTTP date relative to `now`.
+fn retry_after_ms(headers: &HeaderMap, now: OffsetDateTime) -> Option<u64> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs.saturating_mul(1000).min(MAX_RETRY_AFTER_MS));
+    }
+    let at = OffsetDateTime::parse(value, &Rfc2822).ok()?;
+    let ms = (at - now).whole_milliseconds().max(0) as u64;
+    Some(ms.min(MAX_RETRY_AFTER_MS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Self-signed P-256 CA, for parsing only.
+    const TEST_CA_PEM: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBkDCCATegAwIBAgIUIC2nfCm5P0d7RFuwtpukSrL3cJcwCgYIKoZIzj0EAwIw
+HTEbMBkGA1UEAwwSaW5kaWdvLXBveSB0ZXN0IENBMCAXDTI2MTAxNDA4NDQ1MFoY
+DzIxMjYwOTIwMDg0NDUwWjAdMRswGQYDVQQDDBJpbmRpZ28tcG95IHRlc3QgQ0Ew
+WTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAS3oeOwNuo0SHHBmmiATlV19Het2Ure
+dMb0fQYmiNlu6quu8bzY1RtGvyLa8zXmMLZiLz3+ujFun0pNLQqO2soUo1MwUTAd
+BgNVHQ4EFgQUDuTpMdS4O33CTYsTDhzwQGrk45QwHwYDVR0jBBgwFoAUDuTpMdS4
+O33CTYsTDhzwQGrk45QwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNHADBE
+AiA+TmLNt/Q7wsTL6pi6vscX1oxeuF31KimpN7e1SGVLVgIgdKIfKwuOhS/cG5dT
+jC3bVGy8fIN6dLSq5mYXcphteE8=
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn client_builder_applies_proxy_and_root_certs() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = dir.path().join("ca.pem");
+        std::fs::write(&ca, TEST_CA_PEM).unwrap();
+        let junk = dir.path().join("junk.pem");
+        std::fs::write(&junk, "not a certificate").unwrap();
+        let config = |proxy: &str, certs: Vec<std::path::PathBuf>| FetchConfig {
+            proxy_url: Some(proxy.to_string()),
+            extra_root_certs: certs,
+            ..Default::default()
+        };
+        assert!(
+            client_builder(&config("http://user:pw@proxy:3128", vec![ca.clone()]))
+                .unwrap()
+                .build()
+                .is_ok()
+        );
+        assert!(client_builder(&config("::", vec![])).is_err());
+        assert!(client_builder(&config("http://proxy:3128", vec![junk])).is_err());
+        let debug = format!("{:?}", config("http://user:pw@proxy:3128", vec![]));
+        assert!(debug.contains("http://proxy:3128") && !debug.contains("pw"));
+    }
+
+    #[test]
+    fn identical_in_flight_requests_share_one_call() {
+        let in_flight = Arc::new(InFlight::default());
+        let calls = Arc::new(AtomicU64::new(0));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let results: Vec<String> = rt.block_on(async {
+            let mut tasks = tokio::task::JoinSet::new();
+            for _ in 0..3 {
+                let (in_flight, calls) = (Arc::clone(&in_flight), Arc::clone(&calls));
+                tasks.spawn(async move {
+                    let fetch = async {
+                        calls.fetch_add(1, Ordering::Relaxed);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        Ok("body".to_string())
+                    };
+                    in_flight.run("k", fetch).await.unwrap()
+                });
+            }
+            let mut out = Vec::new();
+            while let Some(r) = tasks.join_next().await {
+                out.push(r.unwrap());
+            }
+            out
+        });
+        assert_eq!(results, vec!["body"; 3]);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(in_flight.coalesced.load(Ordering::Relaxed), 2);
+        assert!(in_flight.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn retry_after_seconds_and_http_date() {
+        let now = OffsetDateTime::from_unix_timestamp(1_445_412_470).unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after_ms(&headers, now), None);
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after_ms(&headers, now), Some(3000));
+        // 2015-10-21T07:28:00Z is 10 s after `now`.
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after_ms(&headers, now), Some(10_000));
+    }
+
+    #[test]
+    fn auth_token_redacted_in_debug() {
+        let config = FetchConfig {
+            auth_token: Some("s3cret".into()),
+            ..Default::default()
+        };
+        assert_eq!(config.resolved_auth_token().as_deref(), Some("s3cret"));
+        assert!(!format!("{:?}", config).contains("s3cret"));
+        let http = HttpClient::new(config, String::new(), HeaderMap::new(), None).unwrap();
+        assert_eq!(http.stats().requests, 0);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_probes_after_cooldown() {
+        let breaker = CircuitBreaker::default();
+        let cooldown = Duration::from_secs(30);
+        let t0 = Instant::now();
+        assert!(!breaker.record_failure(t0, 3, cooldown));
+        assert!(!breaker.record_failure(t0, 3, cooldown));
+        assert!(breaker.check(t0).is_ok());
+        assert!(breaker.record_failure(t0, 3, cooldown));
+        assert!(breaker.check(t0 + Duration::from_secs(10)).unwrap_err() > 19_000);
+        // Probe after the cooldown: one more failure re-opens at once.
+        let later = t0 + cooldown;
+        assert!(breaker.check(later).is_ok());
+        assert!(breaker.record_failure(later, 3, cooldown));
+        assert!(breaker.check(later).is_err());
+        breaker.record_success();
+        assert!(breaker.check(later).is_ok());
+        let disabled = CircuitBreaker::default();
+        for _ in 0..10 {
+            assert!(!disabled.record_failure(t0, 0, cooldown));
+        }
+    }
+
+    #[test]
+    fn endpoint_policy_overrides_by_label() {
+        let mut policies = EndpointPolicies::default();
+        policies.apply_timeout("/account_txs=120").unwrap();
+        policies.apply_retries("/account_txs=1").unwrap();
+        policies.apply_retries("/txs/:id/utxos=6").unwrap();
+        assert_eq!(
+            policies.get("/account_txs?offset=1000&limit=1000"),
+            Some(&EndpointPolicy {
+                timeout_ms: Some(120_000),
+                max_retries: Some(1),
+            })
+        );
+        let hash = "ab".repeat(32);
+        let utxos = policies.get(&format!("/txs/{}/utxos", hash)).unwrap();
+        assert_eq!((utxos.timeout_ms, utxos.max_retries), (None, Some(6)));
+        assert_eq!(policies.get("/tx_utxos"), None);
+        assert!(policies.apply_timeout("account_txs=5").is_err());
+        assert!(policies.apply_retries("/tx_utxos=many").is_err());
+    }
+
+    #[test]
+    fn backoff_is_jittered_below_the_cap() {
+        let config = FetchConfig {
+            retry_backoff_ms: 500,
+            max_backoff_ms: 2_000,
+            offline: true,
+            ..Default::default()
+        };
+        let http = HttpClient::new(config, String::new(), HeaderMap::new(), None).unwrap();
+        let waits: Vec<u64> = (0..200).map(|_| http.backoff_ms(10)).collect();
+        assert!(waits.iter().all(|&ms| ms <= 2_000));
+        assert!(waits.iter().any(|&ms| ms != waits[0]));
+        assert!((0..50).all(|_| http.backoff_ms(0) <= 500));
+        assert_eq!(jitter(0), 0);
+    }
+
+    #[test]
+    fn widening_doubles_and_caps() {
+        let config = FetchConfig {
+            rate_limit_ms: 200,
+            offline: true,
+            ..Default::default()
+        };
+        let http = HttpClient::new(config, String::new(), HeaderMap::new(), None).unwrap();
+        assert_eq!(http.widen_rate_limit(), 400);
+        for _ in 0..20 {
+            http.widen_rate_limit();
+        }
+        assert_eq!(http.stats().rate_limit_ms, MAX_RATE_LIMIT_MS);
+    }
+
+    #[test]
+    fn not_found_answers_are_recorded_and_replayed_as_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache.sqlite")).unwrap();
+        let entry = NegativeEntry {
+            status: 404,
+            body: "Not Found".into(),
+        };
+        cache.set_negative("k", &entry).unwrap();
+        let config = FetchConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let http = HttpClient::new(config, String::new(), HeaderMap::new(), Some(cache)).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let parsed: Option<serde_json::Value> = rt
+            .block_on(http.get_parsed("/addresses/addr1x", "k", CacheClass::AddressUtxos))
+            .unwrap();
+        assert!(parsed.is_none());
+        let responses = http.take_responses();
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].status, Some(404));
+
+        // Replaying the archive gives the same answer, not a missing response.
+        let config = FetchConfig {
+            replay_responses: Some(Arc::new(replay::RecordedResponses::new(&responses))),
+            ..Default::default()
+        };
+        let http = HttpClient::new(config, String::new(), HeaderMap::new(), None).unwrap();
+        let replayed: Option<serde_json::Value> = rt
+            .block_on(http.get_parsed("/addresses/addr1x", "k", CacheClass::AddressUtxos))
+            .unwrap();
+        assert!(replayed.is_none());
+        assert_eq!(http.take_responses(), responses);
+    }
+}
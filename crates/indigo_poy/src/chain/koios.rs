@@ -0,0 +1,865 @@
+//! Koios REST backend (default provider).
+
+use crate::chain::assets::{cip68_ticker_decimals, AssetInfo};
+use crate::chain::cache::{Cache, CacheClass, Watermark};
+use crate::chain::fetch::{
+    ChainTip, FetchConfig, FetchError, FetchStats, KoiosAccountTx, KoiosInlineDatum, KoiosTxUtxos,
+    KoiosUtxo, RawResponse, RewardWithdrawal, TxMetadata,
+};
+use crate::chain::http::HttpClient;
+use crate::chain::provider::ChainProvider;
+use crate::BoxFuture;
+use reqwest::header::HeaderMap;
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+/// Koios (PostgREST) caps responses at 1000 rows; longer results are paged with offset/limit.
+const PAGE_SIZE: usize = 1000;
+/// Watermark scope in the cache.
+const WATERMARK_SCOPE: &str = "koios";
+/// Blocks re-read below the watermark on incremental runs, so shallow rollbacks are picked up.
+const RESCAN_BLOCKS: u64 = 10;
+
+/// Cache key for an address's merged tx history (maintained alongside its watermark).
+fn account_txs_merged_key(address: &str) -> String {
+    let req = serde_json::json!({ "address": address, "merged": true });
+    Cache::key_for(&req.to_string())
+}
+
+/// Drop entries above `after` from `known`, append `tail`, dedup by hash, order by height.
+fn merge_tail(
+    mut known: Vec<KoiosAccountTx>,
+    after: u64,
+    tail: Vec<KoiosAccountTx>,
+) -> Vec<KoiosAccountTx> {
+    known.retain(|t| t.block_height.is_none_or(|h| h <= after));
+    known.extend(tail);
+    sort_dedup(&mut known);
+    known
+}
+
+/// Keep the txs within the slot bounds; `/account_txs` takes none, so they are applied here.
+/// Txs without a slot are kept only when there are no bounds.
+fn in_slot_range(
+    mut txs: Vec<KoiosAccountTx>,
+    from_slot: Option<u64>,
+    to_slot: Option<u64>,
+) -> Vec<KoiosAccountTx> {
+    txs.retain(|t| match t.slot_no {
+        Some(slot) => from_slot.is_none_or(|f| slot >= f) && to_slot.is_none_or(|to| slot <= to),
+        None => from_slot.is_none() && to_slot.is_none(),
+    });
+    txs
+}
+
+fn sort_dedup(txs: &mut Vec<KoiosAccountTx>) {
+    txs.sort_by(|a, b| (a.block_height, &a.tx_hash).cmp(&(b.block_height, &b.tx_hash)));
+    txs.dedup_by(|a, b| a.tx_hash == b.tx_hash);
+}
+
+/// Cache key for one `account_txs` page: the address and offset, since the request carries no
+/// slot bounds (those are applied to the fetched rows). The first page keeps the pre-pagination
+/// key of an unbounded query so existing caches stay valid.
+fn account_txs_page_key(address: &str, offset: usize) -> Result<String, FetchError> {
+    let mut req = serde_json::json!({
+        "address": address,
+        "from": null,
+        "to": null
+    });
+    if offset > 0 {
+        req["offset"] = offset.into();
+    }
+    let norm = serde_json::to_string(&req)
+        .map_err(|_| FetchError::Api(0, "serialize request".to_string()))?;
+    Ok(Cache::key_for(&norm))
+}
+
+#[derive(serde::Deserialize)]
+struct KoiosAccountAddresses {
+    #[serde(default)]
+    addresses: Vec<String>,
+}
+
+/// Row of `/tip`.
+#[derive(serde::Deserialize)]
+struct KoiosTip {
+    abs_slot: u64,
+    #[serde(default, alias = "block_no")]
+    block_height: Option<u64>,
+}
+
+fn parse_tip(body: &str) -> Result<ChainTip, FetchError> {
+    let rows: Vec<KoiosTip> =
+        serde_json::from_str(body).map_err(|e| FetchError::Api(0, format!("parse tip: {}", e)))?;
+    let tip = rows
+        .into_iter()
+        .next()
+        .ok_or_else(|| FetchError::Api(0, "tip: empty response".into()))?;
+    Ok(ChainTip {
+        slot: tip.abs_slot,
+        block_height: tip.block_height,
+    })
+}
+
+/// `/datum_info` takes at most this many hashes per request.
+const DATUM_INFO_BATCH: usize = 100;
+
+/// Row of `/datum_info`.
+#[derive(serde::Deserialize)]
+struct KoiosDatumInfo {
+    datum_hash: String,
+    bytes: String,
+    #[serde(default)]
+    value: Option<serde_json::Value>,
+}
+
+fn parse_datum_info(body: &str) -> Result<BTreeMap<String, KoiosInlineDatum>, FetchError> {
+    let rows: Vec<KoiosDatumInfo> = serde_json::from_str(body)
+        .map_err(|e| FetchError::Api(0, format!("parse datum_info: {}", e)))?;
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let datum = KoiosInlineDatum {
+                bytes: r.bytes,
+                value: r.value,
+            };
+            (r.datum_hash, datum)
+        })
+        .collect())
+}
+
+/// Row of `/asset_info`. `cip68_metadata` maps CIP-67 labels to reference datums.
+#[derive(serde::Deserialize)]
+struct KoiosAssetInfo {
+    policy_id: String,
+    #[serde(default)]
+    asset_name: Option<String>,
+    #[serde(default)]
+    token_registry_metadata: Option<KoiosRegistryMetadata>,
+    #[serde(default)]
+    cip68_metadata: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+#[derive(serde::Deserialize)]
+struct KoiosRegistryMetadata {
+    #[serde(default)]
+    ticker: Option<String>,
+    #[serde(default)]
+    decimals: Option<u8>,
+}
+
+/// Registry metadata wins; CIP-68 reference data fills the gaps; the ASCII name is the last
+/// resort for the ticker.
+fn parse_asset_info(body: &str) -> Result<Option<AssetInfo>, FetchError> {
+    let rows: Vec<KoiosAssetInfo> = serde_json::from_str(body)
+        .map_err(|e| FetchError::Api(0, format!("parse asset_info: {}", e)))?;
+    let Some(row) = rows.into_iter().next() else {
+        return Ok(None);
+    };
+    let mut info = AssetInfo::from_name(&row.policy_id, row.asset_name.as_deref().unwrap_or(""));
+    let (cip68_ticker, cip68_decimals) = row
+        .cip68_metadata
+        .as_ref()
+        .and_then(|m| m.values().next())
+        .map(cip68_ticker_decimals)
+        .unwrap_or_default();
+    let registry = row.token_registry_metadata;
+    let (reg_ticker, reg_decimals) = registry.map(|r| (r.ticker, r.decimals)).unwrap_or_default();
+    info.ticker = reg_ticker.or(cip68_ticker).or(info.ticker);
+    info.decimals = reg_decimals.or(cip68_decimals).unwrap_or(0);
+    Ok(Some(info))
+}
+
+/// `/tx_info` takes at most this many hashes per request.
+const TX_INFO_BATCH: usize = 50;
+
+/// Row of `/account_updates`.
+#[derive(serde::Deserialize)]
+struct KoiosAccountUpdates {
+    #[serde(default)]
+    updates: Vec<KoiosAccountUpdate>,
+}
+
+#[derive(serde::Deserialize)]
+struct KoiosAccountUpdate {
+    action_type: String,
+    tx_hash: String,
+}
+
+/// Hashes of the txs that withdrew rewards, sorted and deduplicated.
+fn parse_withdrawal_txs(body: &str) -> Result<Vec<String>, FetchError> {
+    let rows: Vec<KoiosAccountUpdates> = serde_json::from_str(body)
+        .map_err(|e| FetchError::Api(0, format!("parse account_updates: {}", e)))?;
+    let mut hashes: Vec<String> = rows
+        .into_iter()
+        .flat_map(|r| r.updates)
+        .filter(|u| u.action_type == "withdrawal")
+        .map(|u| u.tx_hash)
+        .collect();
+    hashes.sort();
+    hashes.dedup();
+    Ok(hashes)
+}
+
+/// Row of `/tx_info` with only withdrawals requested.
+#[derive(serde::Deserialize)]
+struct KoiosTxWithdrawals {
+    tx_hash: String,
+    #[serde(default)]
+    withdrawals: Option<Vec<KoiosWithdrawal>>,
+}
+
+#[derive(serde::Deserialize)]
+struct KoiosWithdrawal {
+    amount: String,
+    stake_addr: String,
+}
+
+/// Withdrawals from `stake_address` in a `/tx_info` response; other stake addresses withdrawing
+/// in the same tx are skipped.
+fn parse_tx_withdrawals(
+    body: &str,
+    stake_address: &str,
+) -> Result<Vec<RewardWithdrawal>, FetchError> {
+    let rows: Vec<KoiosTxWithdrawals> = serde_json::from_str(body)
+        .map_err(|e| FetchError::Api(0, format!("parse tx_info: {}", e)))?;
+    let mut out = Vec::new();
+    for row in rows {
+        for w in row.withdrawals.unwrap_or_default() {
+            if w.stake_addr != stake_address {
+                continue;
+            }
+            let amount_lovelace = w
+                .amount
+                .trim()
+                .parse()
+                .map_err(|_| FetchError::Api(0, format!("tx_info: bad amount {}", w.amount)))?;
+            out.push(RewardWithdrawal {
+                tx_hash: row.tx_hash.clone(),
+                stake_address: w.stake_addr,
+                amount_lovelace,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Row of `/tx_metadata`; `metadata` is null for txs without any.
+#[derive(serde::Deserialize)]
+struct KoiosTxMetadataRow {
+    #[serde(default)]
+    metadata: Option<TxMetadata>,
+}
+
+fn parse_tx_metadata(body: &str) -> Result<Option<TxMetadata>, FetchError> {
+    let rows: Vec<KoiosTxMetadataRow> = serde_json::from_str(body)
+        .map_err(|e| FetchError::Api(0, format!("parse tx_metadata: {}", e)))?;
+    Ok(rows.into_iter().find_map(|r| r.metadata))
+}
+
+/// Koios API client. Cache keys are request-semantic JSON so caches survive URL changes.
+pub struct KoiosProvider {
+    http: HttpClient,
+}
+
+impl KoiosProvider {
+    pub fn new(config: FetchConfig, cache: Option<Cache>) -> Result<Self, FetchError> {
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| config.network.koios_url().to_string());
+        Ok(Self {
+            http: HttpClient::new(config, base_url, HeaderMap::new(), cache)?,
+        })
+    }
+
+    async fn account_txs_impl(
+        &self,
+        address: &str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> Result<Vec<KoiosAccountTx>, FetchError> {
+        let txs = self.account_txs_history(address).await?;
+        Ok(in_slot_range(txs, from_slot, to_slot))
+    }
+
+    /// The address's whole history. With a cache, it is fetched once and kept as a merged row
+    /// plus a block-height watermark; later online runs only request txs after the watermark
+    /// (less a small rollback margin). Offline runs serve the merged row as-is.
+    async fn account_txs_history(&self, address: &str) -> Result<Vec<KoiosAccountTx>, FetchError> {
+        // Recording captures the plain page sequence so a replay (which has no cache) matches.
+        let Some(cache) = self.http.cache().filter(|_| !self.http.recording()) else {
+            return self.account_txs_pages(address, None).await;
+        };
+        let merged_key = account_txs_merged_key(address);
+        let merged: Option<Vec<KoiosAccountTx>> = cache.get_json(&merged_key)?.and_then(|s| {
+            let txs = serde_json::from_str(&s).ok()?;
+            self.http.record_response(&s);
+            Some(txs)
+        });
+        let watermark = cache.watermark(WATERMARK_SCOPE, address)?;
+        let txs = match (merged, watermark) {
+            (Some(txs), _) if self.http.offline() => return Ok(txs),
+            (Some(known), Some(wm)) => {
+                let after = wm.block_height.saturating_sub(RESCAN_BLOCKS);
+                let tail = self.account_txs_pages(address, Some(after)).await?;
+                debug!(after, new = tail.len(), "account_txs incremental");
+                merge_tail(known, after, tail)
+            }
+            _ => {
+                let mut txs = self.account_txs_pages(address, None).await?;
+                sort_dedup(&mut txs);
+                txs
+            }
+        };
+        if self.http.offline() {
+            return Ok(txs);
+        }
+        if let Ok(json) = serde_json::to_string(&txs) {
+            cache.set_json(&merged_key, &json)?;
+        }
+        if let Some(top) = txs.iter().rev().find(|t| t.block_height.is_some()) {
+            let wm = Watermark {
+                block_height: top.block_height.unwrap_or_default(),
+                slot: top.slot_no,
+            };
+            cache.set_watermark(WATERMARK_SCOPE, address, wm)?;
+        }
+        Ok(txs)
+    }
+
+    /// Page through `/account_txs`. `after_block_height` requests only the tail. The tail, and
+    /// every page of an online recording run, bypass the response cache: the answer changes as
+    /// blocks arrive, and what is recorded must be the history as of now.
+    async fn account_txs_pages(
+        &self,
+        address: &str,
+        after_block_height: Option<u64>,
+    ) -> Result<Vec<KoiosAccountTx>, FetchError> {
+        let mut post_body = serde_json::json!({ "_addresses": [address] });
+        if let Some(after) = after_block_height {
+            post_body["_after_block_height"] = after.into();
+        }
+        let uncached =
+            after_block_height.is_some() || (self.http.recording() && !self.http.offline());
+        let mut parsed: Vec<KoiosAccountTx> = Vec::new();
+        loop {
+            let offset = parsed.len();
+            let path = format!("/account_txs?offset={}&limit={}", offset, PAGE_SIZE);
+            let body = if uncached {
+                self.http
+                    .request_uncached(&path, Some(post_body.clone()))
+                    .await?
+            } else {
+                let cache_key = account_txs_page_key(address, offset)?;
+                self.http
+                    .request_json(
+                        &path,
+                        &cache_key,
+                        CacheClass::TxHistory,
+                        Some(post_body.clone()),
+                    )
+                    .await?
+            };
+            let page: Vec<KoiosAccountTx> = serde_json::from_str(&body)
+                .map_err(|e| FetchError::Api(0, format!("parse account_txs: {}", e)))?;
+            let len = page.len();
+            parsed.extend(page);
+            if len < PAGE_SIZE {
+                break;
+            }
+            debug!(offset = parsed.len(), "account_txs next page");
+        }
+        info!(count = parsed.len(), "account_txs");
+        Ok(parsed)
+    }
+
+    async fn tip_impl(&self) -> Result<ChainTip, FetchError> {
+        parse_tip(&self.http.request_uncached("/tip", None).await?)
+    }
+
+    async fn address_utxos_impl(&self, address: &str) -> Result<Vec<KoiosUtxo>, FetchError> {
+        let req = serde_json::json!({ "address": address });
+        let norm =
+            serde_json::to_string(&req).map_err(|_| FetchError::Api(0, "serialize".to_string()))?;
+        let cache_key = Cache::key_for(&norm);
+        let path = format!("/address_utxos?_address={}", urlencoding::encode(address));
+        let body = self
+            .http
+            .get_json(&path, &cache_key, CacheClass::AddressUtxos)
+            .await?;
+        serde_json::from_str(&body)
+            .map_err(|e| FetchError::Api(0, format!("parse address_utxos: {}", e)))
+    }
+
+    async fn stake_addresses_impl(&self, stake_address: &str) -> Result<Vec<String>, FetchError> {
+        let req = serde_json::json!({ "stake_address": stake_address });
+        let cache_key = Cache::key_for(&req.to_string());
+        let post_body = serde_json::json!({ "_stake_addresses": [stake_address] });
+        let body = self
+            .http
+            .request_json(
+                "/account_addresses",
+                &cache_key,
+                CacheClass::AccountAddresses,
+                Some(post_body),
+            )
+            .await?;
+        let rows: Vec<KoiosAccountAddresses> = serde_json::from_str(&body)
+            .map_err(|e| FetchError::Api(0, format!("parse account_addresses: {}", e)))?;
+        let mut addresses: Vec<String> = rows.into_iter().flat_map(|r| r.addresses).collect();
+        addresses.sort();
+        addresses.dedup();
+        Ok(addresses)
+    }
+
+    /// Batches of sorted hashes, each cached as one request (datums never change).
+    async fn datum_info_impl(
+        &self,
+        datum_hashes: &[String],
+    ) -> Result<BTreeMap<String, KoiosInlineDatum>, FetchError> {
+        let mut out = BTreeMap::new();
+        for batch in datum_hashes.chunks(DATUM_INFO_BATCH) {
+            let req = serde_json::json!({ "datum_info": batch });
+            let cache_key = Cache::key_for(&req.to_string());
+            let post_body = serde_json::json!({ "_datum_hashes": batch });
+            let body = self
+                .http
+                .request_json(
+                    "/datum_info",
+                    &cache_key,
+                    CacheClass::DatumInfo,
+                    Some(post_body),
+                )
+                .await?;
+            out.extend(parse_datum_info(&body)?);
+        }
+        Ok(out)
+    }
+
+    /// Withdrawal txs from `/account_updates`, then their amounts from `/tx_info`.
+    async fn reward_withdrawals_impl(
+        &self,
+        stake_address: &str,
+    ) -> Result<Vec<RewardWithdrawal>, FetchError> {
+        let req = serde_json::json!({ "account_updates": stake_address });
+        let cache_key = Cache::key_for(&req.to_string());
+        let post_body = serde_json::json!({ "_stake_addresses": [stake_address] });
+        let body = self
+            .http
+            .request_json(
+                "/account_updates",
+                &cache_key,
+                CacheClass::AccountAddresses,
+                Some(post_body),
+            )
+            .await?;
+        let hashes = parse_withdrawal_txs(&body)?;
+        let mut out = Vec::new();
+        for batch in hashes.chunks(TX_INFO_BATCH) {
+            let req = serde_json::json!({ "tx_info_withdrawals": batch });
+            let cache_key = Cache::key_for(&req.to_string());
+            let post_body = serde_json::json!({
+                "_tx_hashes": batch,
+                "_inputs": false,
+                "_metadata": false,
+                "_assets": false,
+                "_withdrawals": true,
+                "_certs": false,
+                "_scripts": false,
+                "_bytecode": false,
+            });
+            let body = self
+                .http
+                .request_json("/tx_info", &cache_key, CacheClass::TxUtxos, Some(post_body))
+                .await?;
+            out.extend(parse_tx_withdrawals(&body, stake_address)?);
+        }
+        Ok(out)
+    }
+
+    async fn asset_info_impl(
+        &self,
+        policy_id: &str,
+        asset_name: &str,
+    ) -> Result<Option<AssetInfo>, FetchError> {
+        let req = serde_json::json!({ "asset_info": [policy_id, asset_name] });
+        let cache_key = Cache::key_for(&req.to_string());
+        let post_body = serde_json::json!({ "_asset_list": [[policy_id, asset_name]] });
+        let body = self
+            .http
+            .request_json(
+                "/asset_info",
+                &cache_key,
+                CacheClass::AssetInfo,
+                Some(post_body),
+            )
+            .await?;
+        parse_asset_info(&body)
+    }
+
+    async fn policy_minted_impl(&self, policy_id: &str) -> Result<Option<bool>, FetchError> {
+        let req = serde_json::json!({ "policy_asset_list": policy_id });
+        let cache_key = Cache::key_for(&req.to_string());
+        let path = format!(
+            "/policy_asset_list?_asset_policy={}&limit=1",
+            urlencoding::encode(policy_id)
+        );
+        let body = self
+            .http
+            .get_json(&path, &cache_key, CacheClass::AssetInfo)
+            .await?;
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&body)
+            .map_err(|e| FetchError::Api(0, format!("parse policy_asset_list: {}", e)))?;
+        Ok(Some(!rows.is_empty()))
+    }
+
+    async fn tx_metadata_impl(&self, tx_hash: &str) -> Result<Option<TxMetadata>, FetchError> {
+        let req = serde_json::json!({ "tx_metadata": tx_hash });
+        let cache_key = Cache::key_for(&req.to_string());
+        let post_body = serde_json::json!({ "_tx_hashes": [tx_hash] });
+        let body = self
+            .http
+            .request_json(
+                "/tx_metadata",
+                &cache_key,
+                CacheClass::TxMetadata,
+                Some(post_body),
+            )
+            .await?;
+        parse_tx_metadata(&body)
+    }
+
+    async fn tx_utxos_impl(&self, tx_hash: &str) -> Result<KoiosTxUtxos, FetchError> {
+        let req = serde_json::json!({ "tx_hash": tx_hash, "inline_datum": true });
+        let norm =
+            serde_json::to_string(&req).map_err(|_| FetchError::Api(0, "serialize".to_string()))?;
+        let cache_key = Cache::key_for(&norm);
+        let path = format!(
+            "/tx_utxos?_tx_hash={}&_inline_datum=true",
+            urlencoding::encode(tx_hash)
+        );
+        let body = self
+            .http
+            .get_json(&path, &cache_key, CacheClass::TxUtxos)
+            .await?;
+        serde_json::from_str(&body)
+            .map_err(|e| FetchError::Api(0, format!("parse tx_utxos: {}", e)))
+    }
+}
+
+impl ChainProvider for KoiosProvider {
+    fn name(&self) -> &str {
+        "koios"
+    }
+
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+        Box::pin(self.account_txs_impl(address, from_slot, to_slot))
+    }
+
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+        Box::pin(self.tx_utxos_impl(tx_hash))
+    }
+
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(self.tip_impl())
+    }
+
+    fn datum_info<'a>(
+        &'a self,
+        datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(self.datum_info_impl(datum_hashes))
+    }
+
+    fn tx_metadata<'a>(
+        &'a self,
+        tx_hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+        Box::pin(self.tx_metadata_impl(tx_hash))
+    }
+
+    fn asset_info<'a>(
+        &'a self,
+        policy_id: &'a str,
+        asset_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<AssetInfo>, FetchError>> {
+        Box::pin(self.asset_info_impl(policy_id, asset_name))
+    }
+
+    fn policy_minted<'a>(
+        &'a self,
+        policy_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<bool>, FetchError>> {
+        Box::pin(self.policy_minted_impl(policy_id))
+    }
+
+    fn reward_withdrawals<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RewardWithdrawal>, FetchError>> {
+        Box::pin(self.reward_withdrawals_impl(stake_address))
+    }
+
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+        Box::pin(self.address_utxos_impl(address))
+    }
+
+    fn stake_addresses<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(self.stake_addresses_impl(stake_address))
+    }
+
+    fn stats(&self) -> FetchStats {
+        self.http.stats()
+    }
+
+    fn take_responses(&self) -> Vec<RawResponse> {
+        self.http.take_responses()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tip_reads_slot_and_height() {
+        let body = r#"[{"hash":"h","epoch_no":500,"abs_slot":130000000,"epoch_slot":1,
+                        "block_height":11000000,"block_time":1721000000}]"#;
+        let tip = parse_tip(body).unwrap();
+        assert_eq!(tip.slot, 130_000_000);
+        assert_eq!(tip.block_height, Some(11_000_000));
+        let tx = |h| KoiosAccountTx {
+            tx_hash: "aa".into(),
+            block_height: h,
+            block_time: None,
+            epoch_no: None,
+            slot_no: Some(130_000_000 - 100),
+        };
+        assert_eq!(tip.confirmations(&tx(Some(11_000_000))), Some(1));
+        // Without a height, 100 slots ≈ 5 blocks.
+        assert_eq!(tip.confirmations(&tx(None)), Some(6));
+        assert!(parse_tip("[]").is_err());
+    }
+
+    #[test]
+    fn datum_info_rows_key_by_hash() {
+        let body = r#"[{"datum_hash":"dh1","creation_tx_hash":"aa","bytes":"d87980",
+                        "value":{"constructor":0,"fields":[]}}]"#;
+        let datums = parse_datum_info(body).unwrap();
+        assert_eq!(datums["dh1"].bytes, "d87980");
+        assert_eq!(datums["dh1"].value.as_ref().unwrap()["constructor"], 0);
+    }
+
+    #[test]
+    fn tx_metadata_rows_parse_by_label() {
+        let body = r#"[{"tx_hash":"aa","metadata":{"674":{"msg":["Indigo SP"]},"721":{}}}]"#;
+        let metadata = parse_tx_metadata(body).unwrap().unwrap();
+        assert_eq!(metadata["674"]["msg"][0], "Indigo SP");
+        assert_eq!(
+            parse_tx_metadata(r#"[{"tx_hash":"bb","metadata":null}]"#).unwrap(),
+            None
+        );
+        assert_eq!(parse_tx_metadata("[]").unwrap(), None);
+    }
+
+    #[test]
+    fn withdrawals_from_account_updates_and_tx_info() {
+        let updates = r#"[{"stake_address":"stake1u9","updates":[
+            {"action_type":"registration","tx_hash":"aa","epoch_no":300},
+            {"action_type":"withdrawal","tx_hash":"cc","epoch_no":400},
+            {"action_type":"withdrawal","tx_hash":"bb","epoch_no":350}]}]"#;
+        assert_eq!(parse_withdrawal_txs(updates).unwrap(), ["bb", "cc"]);
+        let info = r#"[{"tx_hash":"bb","withdrawals":[
+            {"amount":"1250000","stake_addr":"stake1u9"},
+            {"amount":"7","stake_addr":"stake1other"}]},
+            {"tx_hash":"cc","withdrawals":null}]"#;
+        let w = parse_tx_withdrawals(info, "stake1u9").unwrap();
+        assert_eq!(
+            w,
+            vec![RewardWithdrawal {
+                tx_hash: "bb".into(),
+                stake_address: "stake1u9".into(),
+                amount_lovelace: 1_250_000,
+            }]
+        );
+    }
+
+    #[test]
+    fn asset_info_prefers_registry_then_cip68() {
+        let body = r#"[{"policy_id":"f66d","asset_name":"69555344","asset_name_ascii":"iUSD",
+                        "token_registry_metadata":{"ticker":"iUSD","decimals":6},
+                        "cip68_metadata":null}]"#;
+        let info = parse_asset_info(body).unwrap().unwrap();
+        assert_eq!((info.ticker.as_deref(), info.decimals), (Some("iUSD"), 6));
+        let body = r#"[{"policy_id":"aa","asset_name":"0014df10494e4459",
+                        "token_registry_metadata":null,
+                        "cip68_metadata":{"100":{"constructor":0,"fields":[{"map":[
+                          {"k":{"bytes":"646563696d616c73"},"v":{"int":6}}]},{"int":1}]}}}]"#;
+        let info = parse_asset_info(body).unwrap().unwrap();
+        assert_eq!((info.ticker.as_deref(), info.decimals), (Some("INDY"), 6));
+        assert_eq!(parse_asset_info("[]").unwrap(), None);
+    }
+
+    fn tx_page(start: usize, len: usize) -> String {
+        let txs: Vec<serde_json::Value> = (start..start + len)
+            .map(|i| serde_json::json!({ "tx_hash": format!("{:064x}", i), "slot_no": i }))
+            .collect();
+        serde_json::Value::Array(txs).to_string()
+    }
+
+    fn tx(hash: &str, height: u64) -> KoiosAccountTx {
+        KoiosAccountTx {
+            tx_hash: hash.to_string(),
+            block_height: Some(height),
+            block_time: None,
+            epoch_no: None,
+            slot_no: Some(height * 20),
+        }
+    }
+
+    #[test]
+    fn merge_tail_replaces_rescanned_blocks() {
+        // "c" at 105 was rolled back; the tail re-reads from 100 and brings "d".
+        let known = vec![tx("a", 90), tx("b", 100), tx("c", 105)];
+        let tail = vec![tx("d", 106), tx("b", 100)];
+        let merged = merge_tail(known, 99, tail);
+        let hashes: Vec<&str> = merged.iter().map(|t| t.tx_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["a", "b", "d"]);
+    }
+
+    #[test]
+    fn account_txs_follows_pages_until_short_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache.sqlite")).unwrap();
+        for (offset, len) in [(0, PAGE_SIZE), (PAGE_SIZE, PAGE_SIZE), (2 * PAGE_SIZE, 7)] {
+            let key = account_txs_page_key("addr1x", offset).unwrap();
+            cache.set_json(&key, &tx_page(offset, len)).unwrap();
+        }
+        let config = FetchConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let provider = KoiosProvider::new(config, Some(cache)).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let txs = rt
+            .block_on(provider.account_txs("addr1x", None, None))
+            .unwrap();
+        assert_eq!(txs.len(), 2 * PAGE_SIZE + 7);
+        let stats = provider.stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (3, 0));
+        // Offline runs leave the watermark alone; the next online run will set it.
+        let cache = provider.http.cache().unwrap();
+        assert!(cache
+            .watermark(WATERMARK_SCOPE, "addr1x")
+            .unwrap()
+            .is_none());
+        let responses = provider.take_responses();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].sha256, Cache::key_for(&responses[0].body));
+        assert!(provider.take_responses().is_empty());
+        assert_eq!(
+            txs.last().unwrap().slot_no,
+            Some((2 * PAGE_SIZE + 6) as u64)
+        );
+    }
+
+    #[test]
+    fn replayable_runs_request_the_history_instead_of_serving_cached_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache.sqlite")).unwrap();
+        let key = account_txs_page_key("addr1x", 0).unwrap();
+        cache.set_json(&key, &tx_page(0, 1)).unwrap();
+        // Upstream now has a second tx; the recorded responses stand in for it.
+        let post_body = serde_json::json!({ "_addresses": ["addr1x"] });
+        let path = format!("/account_txs?offset=0&limit={}", PAGE_SIZE);
+        let mut upstream = RawResponse::new(tx_page(0, 2));
+        upstream.requests = vec![crate::chain::replay::fixture_key(&path, Some(&post_body))];
+        let config = FetchConfig {
+            replayable: true,
+            replay_responses: Some(std::sync::Arc::new(
+                crate::chain::replay::RecordedResponses::new(&[upstream.clone()]),
+            )),
+            ..Default::default()
+        };
+        let provider = KoiosProvider::new(config, Some(cache)).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        for run in 1..=2 {
+            let txs = rt
+                .block_on(provider.account_txs("addr1x", None, None))
+                .unwrap();
+            assert_eq!(txs.len(), 2, "run {}", run);
+            assert_eq!(provider.stats().requests, run);
+            let responses = provider.take_responses();
+            assert_eq!(responses.len(), 1);
+            assert_eq!(responses[0].sha256, upstream.sha256);
+        }
+    }
+
+    #[test]
+    fn account_txs_fails_on_a_page_that_is_not_a_tx_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache.sqlite")).unwrap();
+        let key = account_txs_page_key("addr1x", 0).unwrap();
+        cache.set_json(&key, r#"{"error":"rate limited"}"#).unwrap();
+        let config = FetchConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let provider = KoiosProvider::new(config, Some(cache)).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let err = rt
+            .block_on(provider.account_txs("addr1x", None, None))
+            .unwrap_err();
+        assert!(
+            matches!(&err, FetchError::Api(0, m) if m.starts_with("parse account_txs")),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn account_txs_applies_slot_bounds_to_pages_and_merged_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open(dir.path().join("cache.sqlite")).unwrap();
+        let key = account_txs_page_key("addr1x", 0).unwrap();
+        cache.set_json(&key, &tx_page(0, 10)).unwrap();
+        let merged = vec![tx("a", 1), tx("b", 2), tx("c", 3)];
+        cache
+            .set_json(
+                &account_txs_merged_key("addr1y"),
+                &serde_json::to_string(&merged).unwrap(),
+            )
+            .unwrap();
+        let config = FetchConfig {
+            offline: true,
+            ..Default::default()
+        };
+        let provider = KoiosProvider::new(config, Some(cache)).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let slots = |address, from, to| -> Vec<Option<u64>> {
+            rt.block_on(provider.account_txs(address, from, to))
+                .unwrap()
+                .into_iter()
+                .map(|t| t.slot_no)
+                .collect()
+        };
+        assert_eq!(
+            slots("addr1x", Some(3), Some(5)),
+            [Some(3), Some(4), Some(5)]
+        );
+        assert_eq!(slots("addr1y", Some(40), None), [Some(40), Some(60)]);
+        assert_eq!(slots("addr1y", None, None).len(), 3);
+    }
+}
@@ -0,0 +1,371 @@
+//! Self-hosted backend: Kupo for UTxO matches, Ogmios (optional) for the chain tip.
+//!
+//! Kupo only knows outputs matching its configured patterns. Run it with a pattern covering the
+//! tracked addresses (or `*`); `tx_utxos` inputs are reconstructed from matches seen while
+//! scanning `account_txs`, so call that first for each address. Tx metadata is not served, and
+//! inline datums only show up as their `datum_hash` (Kupo reports it for both datum kinds) until
+//! resolved through `/datums`.
+
+use crate::chain::cache::{Cache, CacheClass};
+use crate::chain::fetch::{
+    ChainTip, FetchConfig, FetchError, FetchStats, KoiosAccountTx, KoiosAsset, KoiosInlineDatum,
+    KoiosPaymentAddr, KoiosTxUtxos, KoiosUtxo, RawResponse,
+};
+use crate::chain::http::{client_builder, HttpClient};
+use crate::chain::network::Network;
+use crate::chain::normalize::unix_time_from_slot;
+use crate::chain::provider::ChainProvider;
+use crate::BoxFuture;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::info;
+
+pub const DEFAULT_KUPO_URL: &str = "http://localhost:1442";
+
+#[derive(Clone, Debug, Deserialize)]
+struct KupoPoint {
+    slot_no: u64,
+    /// Spending tx; present on `spent_at` (Kupo >= 2.7).
+    #[serde(default)]
+    transaction_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct KupoValue {
+    coins: u64,
+    /// `"policy.asset_name_hex"` (or bare policy id) → quantity.
+    #[serde(default)]
+    assets: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct KupoMatch {
+    #[serde(default)]
+    address: String,
+    transaction_id: String,
+    output_index: u32,
+    value: KupoValue,
+    #[serde(default)]
+    datum_hash: Option<String>,
+    created_at: KupoPoint,
+    #[serde(default)]
+    spent_at: Option<KupoPoint>,
+}
+
+/// `/datums/{hash}` body; `null` (or a null `datum`) when unknown.
+#[derive(Clone, Debug, Deserialize)]
+struct KupoDatum {
+    datum: Option<String>,
+}
+
+fn normalize_match(m: &KupoMatch) -> KoiosUtxo {
+    let assets: Vec<KoiosAsset> = m
+        .value
+        .assets
+        .iter()
+        .map(|(unit, qty)| {
+            let (policy, name) = unit.split_once('.').unwrap_or((unit.as_str(), ""));
+            KoiosAsset {
+                policy_id: policy.to_string(),
+                asset_name: name.to_string(),
+                quantity: qty.to_string(),
+            }
+        })
+        .collect();
+    KoiosUtxo {
+        tx_hash: m.transaction_id.clone(),
+        tx_index: m.output_index,
+        value: m.value.coins.to_string(),
+        datum_hash: m.datum_hash.clone(),
+        asset_list: (!assets.is_empty()).then_some(assets),
+        inline_datum: None,
+        resolved_datum: None,
+        payment_addr: (!m.address.is_empty()).then(|| KoiosPaymentAddr::bech32(&m.address)),
+    }
+}
+
+/// Txs touching an address: creators of its outputs and (when known) spenders of them.
+fn txs_from_matches(network: Network, matches: &[KupoMatch]) -> Vec<KoiosAccountTx> {
+    let mut by_hash: BTreeMap<String, u64> = BTreeMap::new();
+    let points = matches.iter().flat_map(|m| {
+        let created = Some((m.transaction_id.clone(), m.created_at.slot_no));
+        let spent = m
+            .spent_at
+            .as_ref()
+            .and_then(|p| Some((p.transaction_id.clone()?, p.slot_no)));
+        created.into_iter().chain(spent)
+    });
+    for (hash, slot) in points {
+        by_hash.entry(hash).or_insert(slot);
+    }
+    let mut txs: Vec<KoiosAccountTx> = by_hash
+        .into_iter()
+        .map(|(tx_hash, slot)| KoiosAccountTx {
+            tx_hash,
+            block_height: None,
+            block_time: unix_time_from_slot(network, slot),
+            epoch_no: None,
+            slot_no: Some(slot),
+        })
+        .collect();
+    txs.sort_by(|a, b| a.slot_no.cmp(&b.slot_no).then(a.tx_hash.cmp(&b.tx_hash)));
+    txs
+}
+
+/// Kupo (+ Ogmios) client for fully self-hosted fetching.
+pub struct KupoProvider {
+    http: HttpClient,
+    network: Network,
+    ogmios_url: Option<String>,
+    /// Spending tx hash → inputs it consumed, collected from `account_txs` scans.
+    spent_inputs: Mutex<HashMap<String, Vec<KoiosUtxo>>>,
+}
+
+impl KupoProvider {
+    pub fn new(config: FetchConfig, cache: Option<Cache>) -> Result<Self, FetchError> {
+        let base_url = config
+            .base_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_KUPO_URL.to_string());
+        let ogmios_url = config.ogmios_url.clone();
+        let network = config.network;
+        Ok(Self {
+            http: HttpClient::new(config, base_url, HeaderMap::new(), cache)?,
+            network,
+            ogmios_url,
+            spent_inputs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn matches(
+        &self,
+        pattern_and_query: &str,
+        class: CacheClass,
+    ) -> Result<Vec<KupoMatch>, FetchError> {
+        let path = format!("/matches/{}", pattern_and_query);
+        let key = HttpClient::path_cache_key("kupo", &path);
+        Ok(self
+            .http
+            .get_parsed(&path, &key, class)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// Current tip slot from Ogmios (`queryNetwork/tip`, JSON-RPC over HTTP). Never cached.
+    pub async fn tip_slot(&self) -> Result<u64, FetchError> {
+        let url = self
+            .ogmios_url
+            .as_deref()
+            .ok_or_else(|| FetchError::Config("kupo: ogmios_url not set".into()))?;
+        let client = client_builder(self.http.config())?.build()?;
+        let body = serde_json::json!({ "jsonrpc": "2.0", "method": "queryNetwork/tip" });
+        let res: serde_json::Value = client.post(url).json(&body).send().await?.json().await?;
+        res.pointer("/result/slot")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| FetchError::Api(0, format!("ogmios tip: {}", res)))
+    }
+
+    async fn account_txs_impl(
+        &self,
+        address: &str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> Result<Vec<KoiosAccountTx>, FetchError> {
+        let matches = self
+            .matches(
+                &format!("{}?order=oldest_first", urlencoding::encode(address)),
+                CacheClass::TxHistory,
+            )
+            .await?;
+        {
+            let mut spent = self.spent_inputs.lock().unwrap();
+            for m in &matches {
+                if let Some(tx) = m.spent_at.as_ref().and_then(|p| p.transaction_id.clone()) {
+                    let inputs = spent.entry(tx).or_default();
+                    let utxo = normalize_match(m);
+                    if !inputs
+                        .iter()
+                        .any(|u| u.tx_hash == utxo.tx_hash && u.tx_index == utxo.tx_index)
+                    {
+                        inputs.push(utxo);
+                    }
+                }
+            }
+        }
+        let parsed: Vec<KoiosAccountTx> = txs_from_matches(self.network, &matches)
+            .into_iter()
+            .filter(|t| {
+                let slot = t.slot_no.unwrap_or(0);
+                from_slot.is_none_or(|f| slot >= f) && to_slot.is_none_or(|to| slot <= to)
+            })
+            .collect();
+        info!(count = parsed.len(), "account_txs");
+        Ok(parsed)
+    }
+
+    async fn address_utxos_impl(&self, address: &str) -> Result<Vec<KoiosUtxo>, FetchError> {
+        let matches = self
+            .matches(
+                &format!("{}?unspent", urlencoding::encode(address)),
+                CacheClass::AddressUtxos,
+            )
+            .await?;
+        Ok(matches.iter().map(normalize_match).collect())
+    }
+
+    /// Kupo accepts a stake address as a pattern, matching every address delegating to it.
+    async fn stake_addresses_impl(&self, stake_address: &str) -> Result<Vec<String>, FetchError> {
+        let matches = self
+            .matches(
+                &urlencoding::encode(stake_address),
+                CacheClass::AccountAddresses,
+            )
+            .await?;
+        let mut addresses: Vec<String> = matches.into_iter().map(|m| m.address).collect();
+        addresses.retain(|a| !a.is_empty());
+        addresses.sort();
+        addresses.dedup();
+        Ok(addresses)
+    }
+
+    /// `/datums/{hash}` per hash; Kupo only stores datums it saw in matching outputs.
+    async fn datum_info_impl(
+        &self,
+        datum_hashes: &[String],
+    ) -> Result<BTreeMap<String, KoiosInlineDatum>, FetchError> {
+        let mut out = BTreeMap::new();
+        for hash in datum_hashes {
+            let path = format!("/datums/{}", urlencoding::encode(hash));
+            let key = HttpClient::path_cache_key("kupo", &path);
+            let found: Option<KupoDatum> = self
+                .http
+                .get_parsed(&path, &key, CacheClass::DatumInfo)
+                .await?;
+            if let Some(bytes) = found.and_then(|d| d.datum) {
+                out.insert(hash.clone(), KoiosInlineDatum { bytes, value: None });
+            }
+        }
+        Ok(out)
+    }
+
+    async fn tx_utxos_impl(&self, tx_hash: &str) -> Result<KoiosTxUtxos, FetchError> {
+        let outputs = self
+            .matches(
+                &format!("*@{}", urlencoding::encode(tx_hash)),
+                CacheClass::TxUtxos,
+            )
+            .await?;
+        let inputs = self
+            .spent_inputs
+            .lock()
+            .unwrap()
+            .get(tx_hash)
+            .cloned()
+            .unwrap_or_default();
+        Ok(KoiosTxUtxos {
+            inputs: Some(inputs),
+            outputs: Some(outputs.iter().map(normalize_match).collect()),
+            metadata: None,
+        })
+    }
+}
+
+impl ChainProvider for KupoProvider {
+    fn name(&self) -> &str {
+        "kupo"
+    }
+
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+        Box::pin(self.account_txs_impl(address, from_slot, to_slot))
+    }
+
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+        Box::pin(self.tx_utxos_impl(tx_hash))
+    }
+
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+        Box::pin(self.address_utxos_impl(address))
+    }
+
+    fn stake_addresses<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(self.stake_addresses_impl(stake_address))
+    }
+
+    /// Slot only: Ogmios's tip carries no block height.
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(async move {
+            Ok(ChainTip {
+                slot: self.tip_slot().await?,
+                block_height: None,
+            })
+        })
+    }
+
+    fn datum_info<'a>(
+        &'a self,
+        datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(self.datum_info_impl(datum_hashes))
+    }
+
+    fn stats(&self) -> FetchStats {
+        self.http.stats()
+    }
+
+    fn take_responses(&self) -> Vec<RawResponse> {
+        self.http.take_responses()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MATCHES: &str = r#"[
+        {"transaction_index":0,"transaction_id":"aa","output_index":0,"address":"addr1x",
+         "value":{"coins":5000000,"assets":{"f0ff.69555344":42,"beef":1}},
+         "datum_hash":null,"created_at":{"slot_no":4492900,"header_hash":"h1"},
+         "spent_at":{"slot_no":4493000,"header_hash":"h2","transaction_id":"bb"}},
+        {"transaction_index":1,"transaction_id":"cc","output_index":1,"address":"addr1x",
+         "value":{"coins":1000000},"datum_hash":"dh",
+         "created_at":{"slot_no":4492950,"header_hash":"h3"},"spent_at":null}
+    ]"#;
+
+    #[test]
+    fn matches_normalize_to_koios_shape() {
+        let matches: Vec<KupoMatch> = serde_json::from_str(MATCHES).unwrap();
+        let utxo = normalize_match(&matches[0]);
+        assert_eq!(utxo.value, "5000000");
+        let assets = utxo.asset_list.unwrap();
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets[0].policy_id, "beef");
+        assert_eq!(assets[0].asset_name, "");
+        assert_eq!(assets[1].asset_name, "69555344");
+        assert_eq!(
+            normalize_match(&matches[1]).datum_hash.as_deref(),
+            Some("dh")
+        );
+    }
+
+    #[test]
+    fn account_txs_include_creators_and_spenders_in_slot_order() {
+        let matches: Vec<KupoMatch> = serde_json::from_str(MATCHES).unwrap();
+        let txs = txs_from_matches(Network::Mainnet, &matches);
+        let hashes: Vec<&str> = txs.iter().map(|t| t.tx_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["aa", "cc", "bb"]);
+        assert_eq!(txs[0].block_time, Some(1_596_059_191));
+    }
+}
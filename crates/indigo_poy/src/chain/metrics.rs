@@ -0,0 +1,269 @@
+//! Per-endpoint request metrics (counts, retries, cache hits, latency) for long-running
+//! deployments, rendered in the Prometheus text exposition format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the request latency histogram buckets; `+Inf` is implicit.
+const LATENCY_BUCKETS_SECS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Plain per-endpoint counters as (metric name, help, accessor).
+type Counter = (&'static str, &'static str, fn(&EndpointMetrics) -> u64);
+
+const COUNTERS: [Counter; 4] = [
+    (
+        "request_retries_total",
+        "Requests retried after an error.",
+        |m| m.retries,
+    ),
+    ("throttled_total", "429 responses waited out.", |m| {
+        m.throttled
+    }),
+    (
+        "cache_hits_total",
+        "Responses served from the cache.",
+        |m| m.cache_hits,
+    ),
+    (
+        "cache_misses_total",
+        "Cache lookups that went upstream.",
+        |m| m.cache_misses,
+    ),
+];
+
+/// Counters for one endpoint.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EndpointMetrics {
+    /// Upstream responses by HTTP status (0 = transport error).
+    pub responses: BTreeMap<u16, u64>,
+    pub retries: u64,
+    /// 429 responses waited out.
+    pub throttled: u64,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    /// Cumulative counts per [`LATENCY_BUCKETS_SECS`] bound, then `+Inf`.
+    pub latency_buckets: [u64; LATENCY_BUCKETS_SECS.len() + 1],
+    pub latency_sum_secs: f64,
+}
+
+impl EndpointMetrics {
+    /// Upstream attempts, whatever their outcome.
+    pub fn requests(&self) -> u64 {
+        self.responses.values().sum()
+    }
+
+    fn observe(&mut self, latency: Duration) {
+        let secs = latency.as_secs_f64();
+        for (bucket, bound) in self.latency_buckets.iter_mut().zip(LATENCY_BUCKETS_SECS) {
+            if secs <= bound {
+                *bucket += 1;
+            }
+        }
+        self.latency_buckets[LATENCY_BUCKETS_SECS.len()] += 1;
+        self.latency_sum_secs += secs;
+    }
+}
+
+/// Request metrics shared by every HTTP client of a [`crate::chain::Fetcher`], keyed by
+/// endpoint label (see [`endpoint_label`]).
+#[derive(Debug, Default)]
+pub struct Metrics {
+    endpoints: Mutex<BTreeMap<String, EndpointMetrics>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with<F: FnOnce(&mut EndpointMetrics)>(&self, path: &str, f: F) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        f(endpoints.entry(endpoint_label(path)).or_default());
+    }
+
+    /// One upstream attempt that got `status` (0 for a transport error) after `latency`.
+    pub fn record_response(&self, path: &str, status: u16, latency: Duration) {
+        self.with(path, |m| {
+            *m.responses.entry(status).or_default() += 1;
+            m.observe(latency);
+        });
+    }
+
+    pub fn record_retry(&self, path: &str) {
+        self.with(path, |m| m.retries += 1);
+    }
+
+    pub fn record_throttled(&self, path: &str) {
+        self.with(path, |m| m.throttled += 1);
+    }
+
+    pub fn record_cache(&self, path: &str, hit: bool) {
+        self.with(path, |m| {
+            if hit {
+                m.cache_hits += 1;
+            } else {
+                m.cache_misses += 1;
+            }
+        });
+    }
+
+    /// Copy of the counters by endpoint label.
+    pub fn snapshot(&self) -> BTreeMap<String, EndpointMetrics> {
+        self.endpoints.lock().unwrap().clone()
+    }
+
+    /// Prometheus text exposition of every counter, prefixed `indigo_poy_`.
+    pub fn render_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+        let header = |out: &mut String, name: &str, kind: &str, help: &str| {
+            let _ = writeln!(out, "# HELP indigo_poy_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE indigo_poy_{} {}", name, kind);
+        };
+        header(
+            &mut out,
+            "requests_total",
+            "counter",
+            "Upstream requests by endpoint and HTTP status (0 = transport error).",
+        );
+        for (endpoint, m) in &snapshot {
+            for (status, n) in &m.responses {
+                let _ = writeln!(
+                    out,
+                    "indigo_poy_requests_total{{endpoint=\"{}\",status=\"{}\"}} {}",
+                    escape_label(endpoint),
+                    status,
+                    n
+                );
+            }
+        }
+        for (name, help, value) in COUNTERS {
+            header(&mut out, name, "counter", help);
+            for (endpoint, m) in &snapshot {
+                let _ = writeln!(
+                    out,
+                    "indigo_poy_{}{{endpoint=\"{}\"}} {}",
+                    name,
+                    escape_label(endpoint),
+                    value(m)
+                );
+            }
+        }
+        header(
+            &mut out,
+            "request_duration_seconds",
+            "histogram",
+            "Upstream request latency.",
+        );
+        for (endpoint, m) in &snapshot {
+            let e = escape_label(endpoint);
+            let bounds = LATENCY_BUCKETS_SECS.iter().map(|b| b.to_string());
+            for (le, n) in bounds.chain(["+Inf".to_string()]).zip(m.latency_buckets) {
+                let _ = writeln!(
+                    out,
+                    "indigo_poy_request_duration_seconds_bucket{{endpoint=\"{}\",le=\"{}\"}} {}",
+                    e, le, n
+                );
+            }
+            let _ = writeln!(
+                out,
+                "indigo_poy_request_duration_seconds_sum{{endpoint=\"{}\"}} {}",
+                e, m.latency_sum_secs
+            );
+            let _ = writeln!(
+                out,
+                "indigo_poy_request_duration_seconds_count{{endpoint=\"{}\"}} {}",
+                e,
+                m.requests()
+            );
+        }
+        out
+    }
+
+    /// Write [`Metrics::render_prometheus`] to `path` atomically (temp file + rename), as
+    /// node_exporter's textfile collector expects.
+    pub fn write_textfile(&self, path: &Path) -> std::io::Result<()> {
+        let tmp = path.with_extension("prom.tmp");
+        std::fs::write(&tmp, self.render_prometheus())?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+/// Endpoint label of a request path: query string dropped, and per-request segments (hashes,
+/// addresses, numbers) replaced by `:id`, so label cardinality stays bounded.
+pub fn endpoint_label(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|s| {
+            let hex = s.len() >= 16 && s.chars().all(|c| c.is_ascii_hexdigit());
+            let bech32 = ["addr", "stake", "pool", "asset"]
+                .iter()
+                .any(|p| s.starts_with(p) && s.contains('1') && s.len() > 20);
+            let number = !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+            if hex || bech32 || number {
+                ":id"
+            } else {
+                s
+            }
+        })
+        .collect();
+    segments.join("/")
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_labels_drop_ids() {
+        assert_eq!(endpoint_label("/tx_utxos?_tx_hash=ab&x=1"), "/tx_utxos");
+        assert_eq!(
+            endpoint_label(&format!("/txs/{}/utxos", "ab".repeat(32))),
+            "/txs/:id/utxos"
+        );
+        assert_eq!(
+            endpoint_label("/addresses/addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6/transactions"),
+            "/addresses/:id/transactions"
+        );
+        assert_eq!(endpoint_label("/blocks/latest"), "/blocks/latest");
+    }
+
+    #[test]
+    fn render_counts_and_histogram() {
+        let metrics = Metrics::new();
+        metrics.record_cache("/tx_utxos?_tx_hash=aa", false);
+        metrics.record_response("/tx_utxos?_tx_hash=aa", 503, Duration::from_millis(80));
+        metrics.record_retry("/tx_utxos?_tx_hash=aa");
+        metrics.record_response("/tx_utxos?_tx_hash=aa", 200, Duration::from_millis(300));
+        metrics.record_cache("/tx_utxos?_tx_hash=bb", true);
+        let m = &metrics.snapshot()["/tx_utxos"];
+        assert_eq!((m.requests(), m.retries, m.cache_hits), (2, 1, 1));
+        let text = metrics.render_prometheus();
+        for line in [
+            "# TYPE indigo_poy_requests_total counter",
+            "indigo_poy_requests_total{endpoint=\"/tx_utxos\",status=\"503\"} 1",
+            "indigo_poy_request_retries_total{endpoint=\"/tx_utxos\"} 1",
+            "indigo_poy_cache_misses_total{endpoint=\"/tx_utxos\"} 1",
+            "indigo_poy_request_duration_seconds_bucket{endpoint=\"/tx_utxos\",le=\"0.1\"} 1",
+            "indigo_poy_request_duration_seconds_bucket{endpoint=\"/tx_utxos\",le=\"+Inf\"} 2",
+            "indigo_poy_request_duration_seconds_count{endpoint=\"/tx_utxos\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing: {}", line);
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("indigo_poy.prom");
+        metrics.write_textfile(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+}
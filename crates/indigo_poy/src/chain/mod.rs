@@ -1,9 +1,38 @@
 //! Chain data fetching, caching, rate limiting, and normalization.
 
+pub mod assets;
+mod blockfrost;
 mod cache;
+mod failover;
 pub(crate) mod fetch;
+mod http;
+mod koios;
+mod kupo;
+pub mod metrics;
+pub mod network;
+#[cfg(all(feature = "node", unix))]
+pub mod node;
 mod normalize;
+pub mod provider;
+mod replay;
+pub mod xpub;
 
-pub use cache::Cache;
-pub use fetch::{FetchConfig, Fetcher};
-pub use normalize::normalize_slot_time;
+pub use blockfrost::BlockfrostProvider;
+pub use cache::{Cache, CacheClass, CacheStats, CacheTtl, NegativeEntry, Watermark};
+pub use failover::{served_by, FailoverProvider};
+pub use fetch::{
+    ChainTip, FetchConfig, FetchError, FetchStats, Fetcher, RawResponse, RewardWithdrawal,
+    TxMetadata, AUTH_TOKEN_ENV, NODE_SOCKET_ENV,
+};
+pub use http::{EndpointPolicies, EndpointPolicy};
+pub use koios::KoiosProvider;
+pub use kupo::KupoProvider;
+pub use metrics::Metrics;
+pub use network::{is_script_address, is_stake_address, Network};
+pub use normalize::{
+    normalize_slot_time, parse_value, resolve_slot, slot_from_unix_time, unix_time_from_slot,
+    OutputValue, ValueError,
+};
+pub use provider::{ChainProvider, ProviderKind};
+pub use replay::RecordedResponses;
+pub use xpub::{AccountXpub, DerivedAddresses};
@@ -0,0 +1,263 @@
+//! Cardano network selection (mainnet / preprod / preview).
+
+use crate::compute::annualize::EPOCH_SECS;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Slot timing of one era: from `start_slot` (at Unix time `start_time`), each slot lasts
+/// `slot_secs`. Shelley and every later era share one-second slots, so two entries suffice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EraParams {
+    pub start_slot: u64,
+    pub start_time: i64,
+    pub slot_secs: u64,
+}
+
+/// Byron: 20 s slots from the mainnet system start; Shelley from slot 4 492 800 (epoch 208).
+const MAINNET_ERAS: [EraParams; 2] = [
+    EraParams {
+        start_slot: 0,
+        start_time: 1_506_203_091,
+        slot_secs: 20,
+    },
+    EraParams {
+        start_slot: 4_492_800,
+        start_time: 1_596_059_091,
+        slot_secs: 1,
+    },
+];
+/// Byron: 20 s slots for the first four epochs; Shelley from slot 86 400.
+const PREPROD_ERAS: [EraParams; 2] = [
+    EraParams {
+        start_slot: 0,
+        start_time: 1_654_041_600,
+        slot_secs: 20,
+    },
+    EraParams {
+        start_slot: 86_400,
+        start_time: 1_655_769_600,
+        slot_secs: 1,
+    },
+];
+/// Preview starts in Shelley-era timing.
+const PREVIEW_ERAS: [EraParams; 1] = [EraParams {
+    start_slot: 0,
+    start_time: 1_666_656_000,
+    slot_secs: 1,
+}];
+
+/// Target network. Selects API endpoints, address encoding, slot/time mapping, and the cache file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Preprod,
+    Preview,
+}
+
+impl Network {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Preprod => "preprod",
+            Network::Preview => "preview",
+        }
+    }
+
+    /// Network magic for the node-to-client handshake.
+    pub fn magic(&self) -> u64 {
+        match self {
+            Network::Mainnet => 764_824_073,
+            Network::Preprod => 1,
+            Network::Preview => 2,
+        }
+    }
+
+    /// Network id in Shelley address headers (1 = mainnet, 0 = any testnet).
+    pub fn address_network_id(&self) -> u8 {
+        match self {
+            Network::Mainnet => 1,
+            Network::Preprod | Network::Preview => 0,
+        }
+    }
+
+    /// Bech32 prefixes for payment and stake addresses.
+    pub fn address_hrps(&self) -> (&'static str, &'static str) {
+        match self {
+            Network::Mainnet => ("addr", "stake"),
+            Network::Preprod | Network::Preview => ("addr_test", "stake_test"),
+        }
+    }
+
+    /// True if a bech32 address belongs to this network family. Testnets share a prefix, so
+    /// preprod vs preview cannot be told apart from the address alone.
+    pub fn matches_address(&self, address: &str) -> bool {
+        let testnet = address.starts_with("addr_test") || address.starts_with("stake_test");
+        testnet != (*self == Network::Mainnet)
+    }
+
+    pub fn koios_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://api.koios.rest/api/v1",
+            Network::Preprod => "https://preprod.koios.rest/api/v1",
+            Network::Preview => "https://preview.koios.rest/api/v1",
+        }
+    }
+
+    pub fn blockfrost_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://cardano-mainnet.blockfrost.io/api/v0",
+            Network::Preprod => "https://cardano-preprod.blockfrost.io/api/v0",
+            Network::Preview => "https://cardano-preview.blockfrost.io/api/v0",
+        }
+    }
+
+    /// Era slot timing from genesis, oldest first.
+    pub fn eras(&self) -> &'static [EraParams] {
+        match self {
+            Network::Mainnet => &MAINNET_ERAS,
+            Network::Preprod => &PREPROD_ERAS,
+            Network::Preview => &PREVIEW_ERAS,
+        }
+    }
+
+    /// First Shelley slot and its Unix time; one slot per second from there on.
+    pub fn shelley_start(&self) -> (u64, i64) {
+        let eras = self.eras();
+        let shelley = eras[eras.len() - 1];
+        (shelley.start_slot, shelley.start_time)
+    }
+
+    /// Epoch in force at a Unix time. Byron's 21 600 twenty-second slots and Shelley's 432 000
+    /// one-second slots make every epoch five days, so epochs count from the system start.
+    pub fn epoch_at(&self, unix_time: i64) -> u64 {
+        (unix_time - self.eras()[0].start_time).max(0) as u64 / EPOCH_SECS as u64
+    }
+
+    /// Unix time at which `epoch` starts.
+    pub fn epoch_start(&self, epoch: u64) -> i64 {
+        self.eras()[0].start_time + epoch as i64 * EPOCH_SECS
+    }
+}
+
+/// True for reward (stake) addresses, `stake1…` / `stake_test1…`.
+pub fn is_stake_address(address: &str) -> bool {
+    address.starts_with("stake1") || address.starts_with("stake_test1")
+}
+
+/// Key-hash credentials (payment and/or stake) in a bech32 Shelley address. Script credentials,
+/// pointers, Byron addresses, and undecodable input yield none.
+pub fn address_key_hashes(address: &str) -> Vec<[u8; 28]> {
+    let Ok((_, bytes)) = bech32::decode(address) else {
+        return vec![];
+    };
+    let Some(header) = bytes.first() else {
+        return vec![];
+    };
+    let hash_at =
+        |offset: usize| -> Option<[u8; 28]> { bytes.get(offset..offset + 28)?.try_into().ok() };
+    // Header type (high nibble), CIP-19: which parts are key hashes.
+    let (payment_is_key, stake_is_key) = match header >> 4 {
+        0 => (true, true),
+        1 => (false, true),
+        2 | 4 | 6 => (true, false),
+        14 => return hash_at(1).into_iter().collect(),
+        _ => (false, false),
+    };
+    let mut out = Vec::new();
+    if payment_is_key {
+        out.extend(hash_at(1));
+    }
+    if stake_is_key {
+        out.extend(hash_at(29));
+    }
+    out
+}
+
+/// Payment credential (key or script hash) of a bech32 Shelley base, pointer, or enterprise
+/// address.
+pub fn payment_credential(address: &str) -> Option<[u8; 28]> {
+    let (_, bytes) = bech32::decode(address).ok()?;
+    if bytes.first()? >> 4 > 7 {
+        return None;
+    }
+    bytes.get(1..29)?.try_into().ok()
+}
+
+/// True if a bech32 Shelley address has a script payment credential (header types 1, 3, 5,
+/// and 7), as Indigo's validators do.
+pub fn is_script_address(address: &str) -> bool {
+    let Ok((_, bytes)) = bech32::decode(address) else {
+        return false;
+    };
+    bytes
+        .first()
+        .is_some_and(|h| h >> 4 <= 7 && (h >> 4) & 1 == 1)
+}
+
+/// Stake key hash of a base address (header types 0 and 1) or key-hash reward address
+/// (type 14). Script stake credentials, pointers, and enterprise addresses have none.
+pub fn stake_key_hash(address: &str) -> Option<[u8; 28]> {
+    let (_, bytes) = bech32::decode(address).ok()?;
+    let offset = match bytes.first()? >> 4 {
+        0 | 1 => 29,
+        14 => 1,
+        _ => return None,
+    };
+    bytes.get(offset..offset + 28)?.try_into().ok()
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "mainnet" => Ok(Network::Mainnet),
+            "preprod" => Ok(Network::Preprod),
+            "preview" => Ok(Network::Preview),
+            other => Err(format!(
+                "unknown network: {} (expected mainnet, preprod, or preview)",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_match_addresses() {
+        assert_eq!("Preprod".parse::<Network>(), Ok(Network::Preprod));
+        assert!("testnet".parse::<Network>().is_err());
+        assert!(Network::Mainnet.matches_address("addr1qx"));
+        assert!(!Network::Mainnet.matches_address("addr_test1qz"));
+        assert!(Network::Preview.matches_address("stake_test1uq"));
+        assert!(is_stake_address("stake1u9x") && !is_stake_address("addr1qx"));
+        assert!(address_key_hashes("not-an-address").is_empty());
+        assert_eq!(stake_key_hash("not-an-address"), None);
+        let address = |header: u8| {
+            let payload = [vec![header], vec![7; 28]].concat();
+            bech32::encode::<bech32::Bech32>(bech32::Hrp::parse("addr").unwrap(), &payload).unwrap()
+        };
+        assert!(is_script_address(&address(0x71)) && is_script_address(&address(0x11)));
+        assert_eq!(payment_credential(&address(0x71)), Some([7; 28]));
+        assert_eq!(payment_credential("stake1u9x"), None);
+        assert!(!is_script_address(&address(0x61)) && !is_script_address("not-an-address"));
+        assert_eq!(Network::Mainnet.epoch_at(1_596_059_091), 208);
+        assert_eq!(Network::Preprod.epoch_start(4), 1_655_769_600);
+        assert_eq!(
+            serde_json::to_string(&Network::Preview).unwrap(),
+            "\"preview\""
+        );
+    }
+}
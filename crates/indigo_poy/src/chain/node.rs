@@ -0,0 +1,237 @@
+//! Local cardano-node backend (node-to-client local-state-query over the node socket).
+//!
+//! The ledger state only holds the *current* UTxO set, so `address_utxos` is served by the node
+//! while tx history (`account_txs`, `tx_utxos`) goes to an optional history provider (e.g. a
+//! local Kupo). Without one, history calls fail with a config error. Never cached: the point of
+//! this backend is reading the node's own view.
+
+use crate::chain::assets::AssetInfo;
+use crate::chain::fetch::{
+    ChainTip, FetchError, FetchStats, KoiosAccountTx, KoiosAsset, KoiosInlineDatum, KoiosTxUtxos,
+    KoiosUtxo, RawResponse, RewardWithdrawal, TxMetadata,
+};
+use crate::chain::provider::ChainProvider;
+use crate::BoxFuture;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Digest};
+use pallas_network::facades::NodeClient;
+use pallas_network::miniprotocols::localstate::queries_v16::{
+    self, TransactionOutput, UTxOByAddress, Value,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Reads UTxOs straight from a local node; delegates history to `history`.
+pub struct NodeProvider {
+    socket_path: PathBuf,
+    /// Network magic for the n2c handshake (see [`crate::chain::Network::magic`]).
+    magic: u64,
+    history: Option<Box<dyn ChainProvider>>,
+}
+
+fn node_err(e: impl std::fmt::Display) -> FetchError {
+    FetchError::Api(0, format!("node: {}", e))
+}
+
+fn normalize_utxos(result: UTxOByAddress) -> Vec<KoiosUtxo> {
+    let mut out: Vec<KoiosUtxo> = result
+        .utxo
+        .to_vec()
+        .into_iter()
+        .map(|(input, output)| {
+            let (amount, datum_hash, inline_datum) = match output {
+                TransactionOutput::Current(o) => {
+                    let inline = o.inline_datum.map(|(_, datum)| datum.0.to_vec());
+                    (
+                        o.amount,
+                        inline
+                            .as_ref()
+                            .map(|cbor| hex::encode(Blake2b::<U32>::digest(cbor))),
+                        inline.map(|cbor| KoiosInlineDatum {
+                            bytes: hex::encode(cbor),
+                            value: None,
+                        }),
+                    )
+                }
+                TransactionOutput::Legacy(o) => {
+                    (o.amount, o.datum_hash.map(|h| h.to_string()), None)
+                }
+            };
+            let (coin, assets) = match amount {
+                Value::Coin(c) => (u64::from(c), vec![]),
+                Value::Multiasset(c, multi) => {
+                    let assets = multi
+                        .to_vec()
+                        .into_iter()
+                        .flat_map(|(policy, names)| {
+                            names
+                                .to_vec()
+                                .into_iter()
+                                .map(move |(name, qty)| KoiosAsset {
+                                    policy_id: policy.to_string(),
+                                    asset_name: hex::encode(name.as_slice()),
+                                    quantity: u64::from(qty).to_string(),
+                                })
+                        })
+                        .collect();
+                    (u64::from(c), assets)
+                }
+            };
+            KoiosUtxo {
+                tx_hash: input.transaction_id.to_string(),
+                tx_index: u64::from(input.index) as u32,
+                value: coin.to_string(),
+                datum_hash,
+                asset_list: (!assets.is_empty()).then_some(assets),
+                inline_datum,
+                resolved_datum: None,
+                payment_addr: None,
+            }
+        })
+        .collect();
+    out.sort_by(|a, b| (&a.tx_hash, a.tx_index).cmp(&(&b.tx_hash, b.tx_index)));
+    out
+}
+
+impl NodeProvider {
+    pub fn new(socket_path: PathBuf, magic: u64, history: Option<Box<dyn ChainProvider>>) -> Self {
+        Self {
+            socket_path,
+            magic,
+            history,
+        }
+    }
+
+    fn history(&self) -> Result<&dyn ChainProvider, FetchError> {
+        self.history.as_deref().ok_or_else(|| {
+            FetchError::Config(
+                "node provider has no tx history; set a history provider (e.g. kupo)".into(),
+            )
+        })
+    }
+
+    async fn tip_impl(&self) -> Result<ChainTip, FetchError> {
+        let mut client = NodeClient::connect(&self.socket_path, self.magic)
+            .await
+            .map_err(node_err)?;
+        let result = async {
+            let sq = client.statequery();
+            sq.acquire(None).await.map_err(node_err)?;
+            let point = queries_v16::get_chain_point(sq).await.map_err(node_err)?;
+            let block_no = queries_v16::get_chain_block_no(sq)
+                .await
+                .map_err(node_err)?;
+            sq.send_release().await.map_err(node_err)?;
+            Ok(ChainTip {
+                slot: point.slot_or_default(),
+                block_height: Some(u64::from(block_no.block_number)),
+            })
+        }
+        .await;
+        client.abort().await;
+        result
+    }
+
+    async fn address_utxos_impl(&self, address: &str) -> Result<Vec<KoiosUtxo>, FetchError> {
+        let (_, bytes) = bech32::decode(address).map_err(node_err)?;
+        let mut client = NodeClient::connect(&self.socket_path, self.magic)
+            .await
+            .map_err(node_err)?;
+        let result = async {
+            let sq = client.statequery();
+            sq.acquire(None).await.map_err(node_err)?;
+            let era = queries_v16::get_current_era(sq).await.map_err(node_err)?;
+            let utxos = queries_v16::get_utxo_by_address(sq, era, vec![bytes.into()])
+                .await
+                .map_err(node_err)?;
+            sq.send_release().await.map_err(node_err)?;
+            Ok(normalize_utxos(utxos))
+        }
+        .await;
+        client.abort().await;
+        result
+    }
+}
+
+impl ChainProvider for NodeProvider {
+    fn name(&self) -> &str {
+        "node"
+    }
+
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+        Box::pin(async move {
+            self.history()?
+                .account_txs(address, from_slot, to_slot)
+                .await
+        })
+    }
+
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+        Box::pin(async move { self.history()?.tx_utxos(tx_hash).await })
+    }
+
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+        Box::pin(self.address_utxos_impl(address))
+    }
+
+    fn stake_addresses<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(async move { self.history()?.stake_addresses(stake_address).await })
+    }
+
+    /// Straight from the node's ledger state.
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(self.tip_impl())
+    }
+
+    fn datum_info<'a>(
+        &'a self,
+        datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(async move { self.history()?.datum_info(datum_hashes).await })
+    }
+
+    fn tx_metadata<'a>(
+        &'a self,
+        tx_hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+        Box::pin(async move { self.history()?.tx_metadata(tx_hash).await })
+    }
+
+    fn reward_withdrawals<'a>(
+        &'a self,
+        stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RewardWithdrawal>, FetchError>> {
+        Box::pin(async move { self.history()?.reward_withdrawals(stake_address).await })
+    }
+
+    fn asset_info<'a>(
+        &'a self,
+        policy_id: &'a str,
+        asset_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<AssetInfo>, FetchError>> {
+        Box::pin(async move { self.history()?.asset_info(policy_id, asset_name).await })
+    }
+
+    fn stats(&self) -> FetchStats {
+        self.history.as_ref().map(|h| h.stats()).unwrap_or_default()
+    }
+
+    /// Only history responses are raw payloads; ledger-state queries are not recorded.
+    fn take_responses(&self) -> Vec<RawResponse> {
+        self.history
+            .as_ref()
+            .map(|h| h.take_responses())
+            .unwrap_or_default()
+    }
+}
@@ -1,5 +1,8 @@
-//! Normalization of slot/time for deterministic requests.
+//! Normalization of slot/time for deterministic requests, and of providers' output values.
 
+use crate::chain::fetch::KoiosAsset;
+use crate::chain::network::Network;
+use serde_json::Value;
 use thiserror::Error;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
@@ -12,6 +15,175 @@ pub enum NormalizeError {
     InvalidTime(String),
 }
 
+/// A provider's output `value` that is not an amount [`parse_value`] can read.
+#[derive(Error, Debug, PartialEq)]
+pub enum ValueError {
+    #[error("invalid lovelace amount: {0}")]
+    Lovelace(String),
+    #[error("invalid quantity of {unit}: {quantity}")]
+    Quantity { unit: String, quantity: String },
+    #[error("unrecognized value: {0}")]
+    Shape(String),
+}
+
+/// ADA and native assets on one output.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutputValue {
+    pub lovelace: u64,
+    pub assets: Vec<(String, String, u128)>,
+}
+
+impl OutputValue {
+    /// The assets as Koios lists them.
+    pub fn asset_list(&self) -> Vec<KoiosAsset> {
+        self.assets
+            .iter()
+            .map(|(policy, name, quantity)| KoiosAsset {
+                policy_id: policy.clone(),
+                asset_name: name.clone(),
+                quantity: quantity.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// Lovelace from a decimal string (surrounding whitespace allowed).
+pub fn parse_lovelace(s: &str) -> Result<u64, ValueError> {
+    s.trim()
+        .parse::<u64>()
+        .map_err(|_| ValueError::Lovelace(s.to_string()))
+}
+
+/// Parse an output `value` in any form providers return:
+/// - a lovelace amount, as a number or decimal string (Koios);
+/// - an object with `lovelace` / `coins` (or Ogmios' `ada: {lovelace}`), and assets under
+///   `assets` or, Ogmios-style, keyed by policy ID at the top level;
+/// - a list of `{unit, quantity}` (Blockfrost), `unit` being `lovelace` or policy + name.
+///
+/// Assets may be a map of `policy.name` (or `policy$name`, or policy + name) to quantity, a map
+/// of policy to names to quantities, or a list of `{policy_id, asset_name, quantity}`.
+pub fn parse_value(value: &Value) -> Result<OutputValue, ValueError> {
+    match value {
+        Value::Number(_) | Value::String(_) => Ok(OutputValue {
+            lovelace: lovelace_of(value)?,
+            assets: vec![],
+        }),
+        Value::Array(amounts) => {
+            let mut out = OutputValue::default();
+            for amount in amounts {
+                let unit = amount.get("unit").and_then(Value::as_str);
+                let quantity = amount.get("quantity");
+                match (unit, quantity) {
+                    (Some("lovelace"), Some(q)) => out.lovelace = lovelace_of(q)?,
+                    (Some(unit), Some(q)) => push_asset(&mut out.assets, unit, None, q)?,
+                    _ => return Err(ValueError::Shape(amount.to_string())),
+                }
+            }
+            Ok(out)
+        }
+        Value::Object(fields) => {
+            let mut out = OutputValue::default();
+            for (key, field) in fields {
+                match key.as_str() {
+                    "lovelace" | "coins" => out.lovelace = lovelace_of(field)?,
+                    "ada" => {
+                        let lovelace = field.get("lovelace").unwrap_or(field);
+                        out.lovelace = lovelace_of(lovelace)?;
+                    }
+                    "assets" => parse_assets(&mut out.assets, field)?,
+                    policy if is_policy_id(policy) => {
+                        let names = field
+                            .as_object()
+                            .ok_or_else(|| ValueError::Shape(field.to_string()))?;
+                        for (name, q) in names {
+                            push_asset(&mut out.assets, policy, Some(name), q)?;
+                        }
+                    }
+                    _ => return Err(ValueError::Shape(value.to_string())),
+                }
+            }
+            Ok(out)
+        }
+        _ => Err(ValueError::Shape(value.to_string())),
+    }
+}
+
+fn parse_assets(out: &mut Vec<(String, String, u128)>, assets: &Value) -> Result<(), ValueError> {
+    match assets {
+        Value::Object(entries) => {
+            for (key, entry) in entries {
+                match entry.as_object() {
+                    Some(names) if is_policy_id(key) => {
+                        for (name, q) in names {
+                            push_asset(out, key, Some(name), q)?;
+                        }
+                    }
+                    _ => push_asset(out, key, None, entry)?,
+                }
+            }
+        }
+        Value::Array(list) => {
+            for asset in list {
+                let field = |name: &str| asset.get(name).and_then(Value::as_str);
+                let (Some(policy), Some(quantity)) = (field("policy_id"), asset.get("quantity"))
+                else {
+                    return Err(ValueError::Shape(asset.to_string()));
+                };
+                push_asset(
+                    out,
+                    policy,
+                    Some(field("asset_name").unwrap_or("")),
+                    quantity,
+                )?;
+            }
+        }
+        Value::Null => {}
+        other => return Err(ValueError::Shape(other.to_string())),
+    }
+    Ok(())
+}
+
+/// Add an asset given as `unit` (policy and name together) or as policy plus `name`.
+fn push_asset(
+    out: &mut Vec<(String, String, u128)>,
+    unit: &str,
+    name: Option<&str>,
+    quantity: &Value,
+) -> Result<(), ValueError> {
+    let (policy, name) = match name {
+        Some(name) => (unit, name),
+        None => unit
+            .split_once(['.', '$'])
+            .or_else(|| unit.is_char_boundary(56).then(|| unit.split_at(56)))
+            .ok_or_else(|| ValueError::Shape(unit.to_string()))?,
+    };
+    let invalid = || ValueError::Quantity {
+        unit: format!("{}.{}", policy, name),
+        quantity: quantity.to_string(),
+    };
+    let quantity = match quantity {
+        Value::Number(n) => n.as_u64().map(u128::from).ok_or_else(invalid)?,
+        Value::String(s) => s.trim().parse::<u128>().map_err(|_| invalid())?,
+        _ => return Err(invalid()),
+    };
+    out.push((policy.to_lowercase(), name.to_lowercase(), quantity));
+    Ok(())
+}
+
+fn lovelace_of(value: &Value) -> Result<u64, ValueError> {
+    match value {
+        Value::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| ValueError::Lovelace(n.to_string())),
+        Value::String(s) => parse_lovelace(s),
+        other => Err(ValueError::Lovelace(other.to_string())),
+    }
+}
+
+fn is_policy_id(s: &str) -> bool {
+    s.len() == 56 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
 /// Parse slot number from string (decimal).
 #[allow(dead_code)]
 pub fn parse_slot(s: &str) -> Result<u64, NormalizeError> {
@@ -27,6 +199,37 @@ pub fn parse_time_rfc3339(s: &str) -> Result<i64, NormalizeError> {
     Ok(dt.unix_timestamp())
 }
 
+/// Slot containing Unix timestamp `ts` on `network`, across Byron and Shelley-onward slot
+/// lengths; `None` before the network's system start.
+pub fn slot_from_unix_time(network: Network, ts: i64) -> Option<u64> {
+    let era = network.eras().iter().rev().find(|e| ts >= e.start_time)?;
+    let delta = u64::try_from(ts - era.start_time).ok()?;
+    Some(era.start_slot + delta / era.slot_secs)
+}
+
+/// Unix timestamp at the start of `slot` on `network`, across eras.
+pub fn unix_time_from_slot(network: Network, slot: u64) -> Option<i64> {
+    let era = network.eras().iter().rev().find(|e| slot >= e.start_slot)?;
+    let secs = (slot - era.start_slot).checked_mul(era.slot_secs)?;
+    i64::try_from(secs).ok().map(|d| era.start_time + d)
+}
+
+/// Absolute slot for a `--from`/`--to` bound: a slot number as-is, an RFC3339 time converted
+/// with the network's era parameters.
+pub fn resolve_slot(network: Network, slot_or_time: &str) -> Result<Option<u64>, NormalizeError> {
+    match normalize_slot_time(slot_or_time)? {
+        (Some(slot), _) => Ok(Some(slot)),
+        (None, Some(ts)) => slot_from_unix_time(network, ts).map(Some).ok_or_else(|| {
+            NormalizeError::InvalidTime(format!(
+                "{} is before the {} system start",
+                slot_or_time.trim(),
+                network
+            ))
+        }),
+        (None, None) => Ok(None),
+    }
+}
+
 /// Normalize slot_or_time input: if it looks like a number, treat as slot; else RFC3339.
 /// Returns (slot_opt, unix_ts_opt). Caller uses the appropriate one for the API.
 pub fn normalize_slot_time(
@@ -61,10 +264,103 @@ mod tests {
         assert!(ts.is_some());
     }
 
+    #[test]
+    fn shelley_slot_from_time() {
+        let m = Network::Mainnet;
+        assert_eq!(slot_from_unix_time(m, 1_596_059_091), Some(4_492_800));
+        assert_eq!(slot_from_unix_time(m, 1_596_059_191), Some(4_492_900));
+        assert_eq!(slot_from_unix_time(m, 0), None);
+        assert_eq!(unix_time_from_slot(m, 4_492_900), Some(1_596_059_191));
+        assert_eq!(
+            unix_time_from_slot(Network::Preview, 100),
+            Some(1_666_656_100)
+        );
+    }
+
+    #[test]
+    fn byron_slots_are_twenty_seconds() {
+        let m = Network::Mainnet;
+        assert_eq!(unix_time_from_slot(m, 0), Some(1_506_203_091));
+        assert_eq!(unix_time_from_slot(m, 1_000), Some(1_506_223_091));
+        assert_eq!(slot_from_unix_time(m, 1_506_223_100), Some(1_000));
+        // The Byron/Shelley boundary lines up in both directions.
+        assert_eq!(unix_time_from_slot(m, 4_492_799), Some(1_596_059_071));
+        assert_eq!(slot_from_unix_time(m, 1_596_059_090), Some(4_492_799));
+        assert_eq!(
+            slot_from_unix_time(Network::Preprod, 1_655_769_600),
+            Some(86_400)
+        );
+    }
+
+    #[test]
+    fn resolve_time_bounds_to_slots() {
+        // 2024-01-01T00:00:00Z = 1_704_067_200 → 4_492_800 + (1_704_067_200 - 1_596_059_091).
+        let slot = resolve_slot(Network::Mainnet, "2024-01-01T00:00:00Z").unwrap();
+        assert_eq!(slot, Some(112_500_909));
+        assert_eq!(resolve_slot(Network::Mainnet, "42").unwrap(), Some(42));
+        assert_eq!(resolve_slot(Network::Mainnet, " ").unwrap(), None);
+        assert!(resolve_slot(Network::Mainnet, "2010-01-01T00:00:00Z").is_err());
+    }
+
     #[test]
     fn normalize_empty() {
         let (slot, ts) = normalize_slot_time("").unwrap();
         assert_eq!(slot, None);
         assert_eq!(ts, None);
     }
+
+    #[test]
+    fn values_in_every_provider_form() {
+        use serde_json::json;
+        let policy = "f0".repeat(28);
+        let lovelace = |v: Value| parse_value(&v).unwrap().lovelace;
+        assert_eq!(lovelace(json!("1500000")), 1_500_000);
+        assert_eq!(lovelace(json!(1_500_000)), 1_500_000);
+        assert_eq!(lovelace(json!({"ada": {"lovelace": 7}})), 7);
+
+        let expected = vec![(policy.clone(), "69555344".to_string(), 25)];
+        let forms = [
+            json!({"coins": 2_000_000, "assets": {format!("{policy}.69555344"): 25}}),
+            json!({"lovelace": "2000000", "assets": {policy.clone(): {"69555344": "25"}}}),
+            json!({"ada": {"lovelace": 2_000_000}, policy.clone(): {"69555344": 25}}),
+            json!([{"unit": "lovelace", "quantity": "2000000"},
+                   {"unit": format!("{policy}69555344"), "quantity": "25"}]),
+            json!({"lovelace": 2_000_000,
+                   "assets": [{"policy_id": policy, "asset_name": "69555344", "quantity": "25"}]}),
+        ];
+        for form in forms {
+            let value = parse_value(&form).unwrap();
+            assert_eq!(
+                (value.lovelace, value.assets),
+                (2_000_000, expected.clone()),
+                "{form}"
+            );
+        }
+
+        assert_eq!(
+            parse_value(&json!("1.5")),
+            Err(ValueError::Lovelace("1.5".into()))
+        );
+        assert!(matches!(
+            parse_value(&json!({"coins": 1, "assets": {"ab.cd": -3}})),
+            Err(ValueError::Quantity { .. })
+        ));
+        assert!(matches!(
+            parse_value(&json!({"amount": 1})),
+            Err(ValueError::Shape(_))
+        ));
+        assert!(parse_value(&json!(null)).is_err());
+
+        // Utxos normalize their value as they are read, or fail to parse.
+        use crate::chain::fetch::KoiosUtxo;
+        let utxo = |value: Value| {
+            serde_json::from_value::<KoiosUtxo>(
+                json!({"tx_hash": "aa", "tx_index": 0, "value": value}),
+            )
+        };
+        let read = utxo(json!({"coins": 3, "assets": {format!("{policy}.69555344"): 25}})).unwrap();
+        assert_eq!(read.value, "3");
+        assert_eq!(read.asset_list.unwrap()[0].quantity, "25");
+        assert!(utxo(json!("n/a")).is_err());
+    }
 }
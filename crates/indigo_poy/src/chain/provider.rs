@@ -0,0 +1,395 @@
+//! Pluggable chain data sources behind a common async trait.
+
+use crate::chain::assets::AssetInfo;
+use crate::chain::fetch::{
+    ChainTip, FetchError, FetchStats, KoiosAccountTx, KoiosInlineDatum, KoiosTxUtxos, KoiosUtxo,
+    RawResponse, RewardWithdrawal, TxMetadata,
+};
+use crate::BoxFuture;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A chain data backend. Implementations normalize their responses into the Koios-shaped types
+/// used by the parsers, so reconstruction is independent of where the data came from.
+pub trait ChainProvider: Send + Sync {
+    /// Stable provider name recorded in evidence bundles (e.g. "koios").
+    fn name(&self) -> &str;
+
+    /// Transactions touching `address`, optionally bounded by slot.
+    fn account_txs<'a>(
+        &'a self,
+        address: &'a str,
+        from_slot: Option<u64>,
+        to_slot: Option<u64>,
+    ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>>;
+
+    /// Inputs and outputs of one transaction.
+    fn tx_utxos<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>>;
+
+    /// Current UTxOs at `address`.
+    fn address_utxos<'a>(
+        &'a self,
+        address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>>;
+
+    /// Payment addresses that have been used with stake address `stake_address`, sorted.
+    fn stake_addresses<'a>(
+        &'a self,
+        _stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+        Box::pin(async move {
+            Err(FetchError::Config(format!(
+                "{}: stake address lookup not supported",
+                self.name()
+            )))
+        })
+    }
+
+    /// ADA reward withdrawals made from `stake_address`.
+    fn reward_withdrawals<'a>(
+        &'a self,
+        _stake_address: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<RewardWithdrawal>, FetchError>> {
+        Box::pin(async move {
+            Err(FetchError::Config(format!(
+                "{}: reward withdrawals not supported",
+                self.name()
+            )))
+        })
+    }
+
+    /// Metadata of a tx by label; `None` when the tx has none or the provider cannot serve it.
+    fn tx_metadata<'a>(
+        &'a self,
+        _tx_hash: &'a str,
+    ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Datum bodies of `datum_hashes` (sorted, unique), keyed by hash; unknown hashes are
+    /// absent. Default: no datum lookup.
+    fn datum_info<'a>(
+        &'a self,
+        _datum_hashes: &'a [String],
+    ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+        Box::pin(async { Ok(BTreeMap::new()) })
+    }
+
+    /// Ticker and decimals of one asset (hex name); `None` when the provider has no metadata
+    /// for it. Default: no asset lookup.
+    fn asset_info<'a>(
+        &'a self,
+        _policy_id: &'a str,
+        _asset_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<AssetInfo>, FetchError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Whether any asset was minted under `policy_id`; `None` when the provider cannot tell.
+    /// Default: no policy lookup.
+    fn policy_minted<'a>(
+        &'a self,
+        _policy_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<bool>, FetchError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Latest block. Default: not supported.
+    fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+        Box::pin(async move {
+            Err(FetchError::Config(format!(
+                "{}: chain tip not supported",
+                self.name()
+            )))
+        })
+    }
+
+    /// Request counters and effective rate limit so far.
+    fn stats(&self) -> FetchStats {
+        FetchStats::default()
+    }
+
+    /// Raw responses served since the last call (see [`crate::chain::Fetcher::take_responses`]).
+    /// Providers without raw payloads return none.
+    fn take_responses(&self) -> Vec<RawResponse> {
+        vec![]
+    }
+}
+
+/// Built-in provider selection (CLI `--provider`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProviderKind {
+    #[default]
+    Koios,
+    Blockfrost,
+    /// Self-hosted Kupo (+ Ogmios).
+    Kupo,
+    /// Local cardano-node socket (requires the `node` feature).
+    Node,
+    /// Fixtures captured with `FetchConfig::record_dir`, served from `FetchConfig::replay_dir`.
+    Replay,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Koios => "koios",
+            ProviderKind::Blockfrost => "blockfrost",
+            ProviderKind::Kupo => "kupo",
+            ProviderKind::Node => "node",
+            ProviderKind::Replay => "replay",
+        }
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "koios" => Ok(ProviderKind::Koios),
+            "blockfrost" => Ok(ProviderKind::Blockfrost),
+            "kupo" => Ok(ProviderKind::Kupo),
+            "node" => Ok(ProviderKind::Node),
+            "replay" => Ok(ProviderKind::Replay),
+            other => Err(format!(
+                "unknown provider: {} (expected koios, blockfrost, kupo, node, or replay)",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::Fetcher;
+
+    /// Echoes the slot bounds it was called with as a single fake tx.
+    struct EchoProvider;
+
+    impl ChainProvider for EchoProvider {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn account_txs<'a>(
+            &'a self,
+            address: &'a str,
+            from_slot: Option<u64>,
+            to_slot: Option<u64>,
+        ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+            Box::pin(async move {
+                Ok(vec![KoiosAccountTx {
+                    tx_hash: address.to_string(),
+                    block_height: from_slot,
+                    block_time: None,
+                    epoch_no: None,
+                    slot_no: to_slot,
+                }])
+            })
+        }
+
+        fn tx_utxos<'a>(
+            &'a self,
+            _tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+            Box::pin(async { Err(FetchError::OfflineMiss) })
+        }
+
+        fn address_utxos<'a>(
+            &'a self,
+            _address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+    }
+
+    #[test]
+    fn fetcher_delegates_to_custom_provider() {
+        let fetcher = Fetcher::with_provider(Box::new(EchoProvider));
+        assert_eq!(fetcher.provider_name(), "echo");
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let txs = rt
+            .block_on(fetcher.account_txs("addr1x", Some("100"), Some("200")))
+            .unwrap();
+        assert_eq!(txs[0].block_height, Some(100));
+        assert_eq!(txs[0].slot_no, Some(200));
+        assert_eq!(fetcher.request_count(), 0);
+    }
+
+    /// Two payment addresses under one stake key, sharing one tx.
+    struct WalletProvider;
+
+    impl ChainProvider for WalletProvider {
+        fn name(&self) -> &str {
+            "wallet"
+        }
+
+        fn account_txs<'a>(
+            &'a self,
+            address: &'a str,
+            _from_slot: Option<u64>,
+            _to_slot: Option<u64>,
+        ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+            let tx = |hash: &str, slot| KoiosAccountTx {
+                tx_hash: hash.to_string(),
+                block_height: None,
+                block_time: None,
+                epoch_no: None,
+                slot_no: Some(slot),
+            };
+            let txs = match address {
+                "addr1a" => vec![tx("shared", 20), tx("only_a", 10)],
+                _ => vec![tx("only_b", 30), tx("shared", 20)],
+            };
+            Box::pin(async move { Ok(txs) })
+        }
+
+        fn tx_utxos<'a>(
+            &'a self,
+            _tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+            let out = KoiosUtxo {
+                tx_hash: "x".into(),
+                ..KoiosUtxo::lovelace(1).with_datum_hash(Some("dh"))
+            };
+            Box::pin(async {
+                Ok(KoiosTxUtxos {
+                    inputs: Some(vec![]),
+                    outputs: Some(vec![out]),
+                    metadata: None,
+                })
+            })
+        }
+
+        fn address_utxos<'a>(
+            &'a self,
+            _address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn stake_addresses<'a>(
+            &'a self,
+            _stake_address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<String>, FetchError>> {
+            Box::pin(async { Ok(vec!["addr1a".to_string(), "addr1b".to_string()]) })
+        }
+
+        fn tx_metadata<'a>(
+            &'a self,
+            tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<Option<TxMetadata>, FetchError>> {
+            Box::pin(async move {
+                match tx_hash {
+                    "shared" => Ok(Some(TxMetadata::from([(
+                        "674".to_string(),
+                        serde_json::json!({ "msg": ["Indigo: SP deposit"] }),
+                    )]))),
+                    "only_a" => Err(FetchError::Api(500, "boom".into())),
+                    _ => Ok(None),
+                }
+            })
+        }
+
+        fn tip(&self) -> BoxFuture<'_, Result<ChainTip, FetchError>> {
+            Box::pin(async {
+                Ok(ChainTip {
+                    slot: 70,
+                    block_height: None,
+                })
+            })
+        }
+
+        fn datum_info<'a>(
+            &'a self,
+            datum_hashes: &'a [String],
+        ) -> BoxFuture<'a, Result<BTreeMap<String, KoiosInlineDatum>, FetchError>> {
+            assert_eq!(datum_hashes, ["dh".to_string()]);
+            let datum = KoiosInlineDatum {
+                bytes: "d87980".into(),
+                value: None,
+            };
+            Box::pin(async { Ok(BTreeMap::from([("dh".to_string(), datum)])) })
+        }
+    }
+
+    #[test]
+    fn stake_address_merges_payment_address_txs() {
+        let fetcher = Fetcher::with_provider(Box::new(WalletProvider));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let txs = rt
+            .block_on(fetcher.account_txs("stake1uxwallet", None, None))
+            .unwrap();
+        let hashes: Vec<&str> = txs.iter().map(|t| t.tx_hash.as_str()).collect();
+        assert_eq!(hashes, vec!["only_a", "shared", "only_b"]);
+        let multi = rt
+            .block_on(fetcher.account_txs_multi(&["addr1b".into(), "addr1a".into()], None, None))
+            .unwrap();
+        assert_eq!(multi.len(), 3);
+        assert_eq!(multi[0].tx_hash, "only_a");
+        // Metadata is attached per tx; a failed lookup leaves the UTxOs usable.
+        let hashes: Vec<String> = multi.iter().map(|t| t.tx_hash.clone()).collect();
+        let utxos = rt.block_on(fetcher.fetch_many(&hashes));
+        let shared = utxos[1].1.as_ref().unwrap();
+        assert!(shared.metadata_label("674").is_some());
+        assert!(utxos[0].1.as_ref().unwrap().metadata.is_none());
+        assert!(utxos[2].1.as_ref().unwrap().metadata.is_none());
+        // Hash-only datums of all txs are resolved in one lookup.
+        let mut txs: Vec<KoiosTxUtxos> = utxos.into_iter().map(|(_, r)| r.unwrap()).collect();
+        assert_eq!(rt.block_on(fetcher.resolve_datums(&mut txs)).unwrap(), 3);
+        let out = &txs[0].outputs.as_ref().unwrap()[0];
+        assert_eq!(out.datum().unwrap().bytes, "d87980");
+        // Providers without a lookup say so instead of returning nothing.
+        let echo = Fetcher::with_provider(Box::new(EchoProvider));
+        assert!(matches!(
+            rt.block_on(echo.account_txs("stake1uxwallet", None, None)),
+            Err(FetchError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn txs_near_the_tip_are_provisional() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let fetcher = Fetcher::with_provider(Box::new(WalletProvider));
+        let txs = rt
+            .block_on(fetcher.account_txs("stake1uxwallet", None, None))
+            .unwrap();
+        // Gating off: no tip query, nothing provisional.
+        let (confirmed, provisional, tip) =
+            rt.block_on(fetcher.split_provisional(txs.clone())).unwrap();
+        assert_eq!((confirmed.len(), provisional.len(), tip), (3, 0, None));
+        // Tip at slot 70: slot 10 has ~4 confirmations, slots 20 and 30 ~3.
+        let fetcher = fetcher.with_min_confirmations(4);
+        let (confirmed, provisional, tip) = rt.block_on(fetcher.split_provisional(txs)).unwrap();
+        assert_eq!(tip.unwrap().slot, 70);
+        assert_eq!(confirmed[0].tx_hash, "only_a");
+        assert_eq!(provisional.len(), 2);
+    }
+
+    #[test]
+    fn fetch_many_keeps_input_order() {
+        let fetcher = Fetcher::with_provider(Box::new(EchoProvider));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let hashes: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let results = rt.block_on(fetcher.fetch_many(&hashes));
+        let order: Vec<&str> = results.iter().map(|(h, _)| h.as_str()).collect();
+        assert_eq!(order, hashes.iter().map(String::as_str).collect::<Vec<_>>());
+        assert!(results.iter().all(|(_, r)| r.is_err()));
+    }
+
+    #[test]
+    fn provider_kind_round_trips() {
+        assert_eq!("Koios".parse::<ProviderKind>(), Ok(ProviderKind::Koios));
+        assert_eq!(ProviderKind::Koios.to_string(), "koios");
+        assert!("nope".parse::<ProviderKind>().is_err());
+    }
+}
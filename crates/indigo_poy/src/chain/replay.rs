@@ -0,0 +1,258 @@
+//! HTTP record/replay: capture every provider response as a fixture file, then serve a run
+//! from those files with `ProviderKind::Replay`.
+//!
+//! A record directory holds `manifest.json` (provider + network) and one `<key>.json` per
+//! request, keyed by SHA-256 of the request path and POST body. Fixtures are sanitized: only the
+//! path relative to the API root is stored, never the host or headers (auth stays out), and
+//! JSON bodies are pretty-printed so they can be reviewed and committed as test data.
+//!
+//! A bundle's responses archive can be replayed the same way: each [`RawResponse`] lists the
+//! requests it answered under the same keys, so [`RecordedResponses`] serves them back.
+
+use crate::chain::cache::Cache;
+use crate::chain::failover::CHAIN_SEPARATOR;
+use crate::chain::fetch::{FetchConfig, FetchError, RawResponse};
+use crate::chain::network::Network;
+use crate::chain::provider::ProviderKind;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Which provider's responses a record directory holds; replay parses them the same way.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Manifest {
+    pub provider: String,
+    pub network: Network,
+}
+
+/// One recorded response.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Fixture {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_body: Option<serde_json::Value>,
+    pub status: u16,
+    /// Parsed JSON when the body is JSON, otherwise the raw text.
+    pub body: serde_json::Value,
+}
+
+impl Fixture {
+    /// Body as served to the provider (JSON bodies re-serialized compactly).
+    pub fn body_text(&self) -> String {
+        match &self.body {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }
+    }
+}
+
+pub(crate) fn fixture_key(path: &str, post_body: Option<&serde_json::Value>) -> String {
+    let req = serde_json::json!({ "path": path, "post_body": post_body });
+    Cache::key_for(&req.to_string())
+}
+
+fn io_err(dir: &Path, e: impl std::fmt::Display) -> FetchError {
+    FetchError::Config(format!("record dir {}: {}", dir.display(), e))
+}
+
+pub(crate) fn write_manifest(dir: &Path, manifest: &Manifest) -> Result<(), FetchError> {
+    std::fs::create_dir_all(dir).map_err(|e| io_err(dir, e))?;
+    let json = serde_json::to_string_pretty(manifest).map_err(|e| io_err(dir, e))?;
+    std::fs::write(dir.join(MANIFEST_FILE), json).map_err(|e| io_err(dir, e))
+}
+
+pub(crate) fn read_manifest(dir: &Path) -> Result<Manifest, FetchError> {
+    let json = std::fs::read_to_string(dir.join(MANIFEST_FILE)).map_err(|e| io_err(dir, e))?;
+    serde_json::from_str(&json).map_err(|e| io_err(dir, e))
+}
+
+pub(crate) fn write_fixture(
+    dir: &Path,
+    path: &str,
+    post_body: Option<&serde_json::Value>,
+    status: u16,
+    body: &str,
+) -> Result<(), FetchError> {
+    let fixture = Fixture {
+        path: path.to_string(),
+        post_body: post_body.cloned(),
+        status,
+        body: serde_json::from_str(body)
+            .unwrap_or_else(|_| serde_json::Value::String(body.to_string())),
+    };
+    let file = dir.join(format!("{}.json", fixture_key(path, post_body)));
+    let json = serde_json::to_string_pretty(&fixture).map_err(|e| io_err(dir, e))?;
+    std::fs::write(file, json).map_err(|e| io_err(dir, e))
+}
+
+/// The fixture for a request, or a config error naming the missing request.
+pub(crate) fn read_fixture(
+    dir: &Path,
+    path: &str,
+    post_body: Option<&serde_json::Value>,
+) -> Result<Fixture, FetchError> {
+    let file = dir.join(format!("{}.json", fixture_key(path, post_body)));
+    let json = std::fs::read_to_string(&file)
+        .map_err(|_| FetchError::Config(format!("replay: no fixture for {}", path)))?;
+    serde_json::from_str(&json).map_err(|e| io_err(dir, e))
+}
+
+/// Response statuses and bodies of a bundle's archive by request key.
+#[derive(Debug, Default)]
+pub struct RecordedResponses {
+    by_request: HashMap<String, (u16, String)>,
+}
+
+impl RecordedResponses {
+    pub fn new(responses: &[RawResponse]) -> Self {
+        let by_request = responses
+            .iter()
+            .flat_map(|r| {
+                let answer = (r.status.unwrap_or(200), r.body.clone());
+                r.requests.iter().map(move |k| (k.clone(), answer.clone()))
+            })
+            .collect();
+        Self { by_request }
+    }
+
+    /// Status and body recorded for the request.
+    pub fn get(&self, path: &str, post_body: Option<&serde_json::Value>) -> Option<(u16, &str)> {
+        self.by_request
+            .get(&fixture_key(path, post_body))
+            .map(|(status, body)| (*status, body.as_str()))
+    }
+
+    /// Requests that can be answered.
+    pub fn len(&self) -> usize {
+        self.by_request.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_request.is_empty()
+    }
+}
+
+/// Provider and fallbacks of a recorded provider name (one provider, or a failover chain like
+/// `koios,blockfrost`). The node speaks a socket protocol, not HTTP, so it cannot be replayed.
+fn replayable_providers(name: &str) -> Result<(ProviderKind, Vec<ProviderKind>), FetchError> {
+    let mut chain = Vec::new();
+    for part in name.split(CHAIN_SEPARATOR) {
+        let provider: ProviderKind = part.parse().map_err(FetchError::Config)?;
+        if matches!(provider, ProviderKind::Node | ProviderKind::Replay) {
+            return Err(FetchError::Config(format!(
+                "replay: cannot replay {} recordings",
+                provider
+            )));
+        }
+        chain.push(provider);
+    }
+    let provider = chain.remove(0);
+    Ok((provider, chain))
+}
+
+/// Config serving `responses` as `provider` on `network`, with online semantics and no
+/// recording.
+pub(crate) fn responses_config(
+    config: &FetchConfig,
+    provider: &str,
+    network: Network,
+    responses: &[RawResponse],
+) -> Result<FetchConfig, FetchError> {
+    let (provider, fallback_providers) = replayable_providers(provider)?;
+    if responses.iter().all(|r| r.requests.is_empty()) {
+        return Err(FetchError::Config(
+            "replay: responses carry no request keys (archive predates replay support)".into(),
+        ));
+    }
+    Ok(FetchConfig {
+        provider,
+        fallback_providers,
+        network,
+        offline: false,
+        rate_limit_ms: 0,
+        record_dir: None,
+        replay_dir: None,
+        replay_responses: Some(Arc::new(RecordedResponses::new(responses))),
+        ..config.clone()
+    })
+}
+
+/// Config for replaying `dir`: the recorded provider, online semantics, no recording.
+pub(crate) fn replay_config(config: &FetchConfig, dir: &Path) -> Result<FetchConfig, FetchError> {
+    let manifest = read_manifest(dir)?;
+    let (provider, fallback_providers) = replayable_providers(&manifest.provider)?;
+    Ok(FetchConfig {
+        provider,
+        fallback_providers,
+        network: manifest.network,
+        offline: false,
+        rate_limit_ms: 0,
+        record_dir: None,
+        replay_dir: Some(dir.to_path_buf()),
+        ..config.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_roundtrip_by_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let body = serde_json::json!({ "_addresses": ["addr1x"] });
+        write_fixture(
+            dir.path(),
+            "/account_txs",
+            Some(&body),
+            200,
+            r#"[{"a": 1}]"#,
+        )
+        .unwrap();
+        write_fixture(dir.path(), "/plain", None, 404, "Not Found").unwrap();
+        let f = read_fixture(dir.path(), "/account_txs", Some(&body)).unwrap();
+        assert_eq!(f.body_text(), r#"[{"a":1}]"#);
+        assert_eq!(
+            read_fixture(dir.path(), "/plain", None)
+                .unwrap()
+                .body_text(),
+            "Not Found"
+        );
+        assert!(read_fixture(dir.path(), "/account_txs", None).is_err());
+    }
+
+    #[test]
+    fn recorded_responses_answer_by_request() {
+        let body = serde_json::json!({ "_tx_hashes": ["aa"] });
+        let mut response = RawResponse::new("[]".into());
+        response.requests = vec![fixture_key("/tx_utxos", Some(&body))];
+        let recorded = RecordedResponses::new(&[response.clone(), RawResponse::new("{}".into())]);
+        assert_eq!(recorded.get("/tx_utxos", Some(&body)), Some((200, "[]")));
+        assert_eq!(recorded.get("/tx_utxos", None), None);
+        assert_eq!(recorded.len(), 1);
+        let mut missing = RawResponse::new("Not Found".into());
+        missing.requests = vec![fixture_key("/addresses/addr1x", None)];
+        missing.status = Some(404);
+        let recorded = RecordedResponses::new(&[missing]);
+        assert_eq!(
+            recorded.get("/addresses/addr1x", None),
+            Some((404, "Not Found"))
+        );
+
+        let config = FetchConfig::default();
+        let replay =
+            responses_config(&config, "blockfrost", Network::Preview, &[response.clone()]).unwrap();
+        assert_eq!(replay.provider, ProviderKind::Blockfrost);
+        assert!(replay.replay_responses.is_some());
+        let chain =
+            responses_config(&config, "koios,blockfrost", Network::Mainnet, &[response]).unwrap();
+        assert_eq!(chain.provider, ProviderKind::Koios);
+        assert_eq!(chain.fallback_providers, vec![ProviderKind::Blockfrost]);
+        let bare = [RawResponse::new("[]".into())];
+        assert!(responses_config(&config, "koios", Network::Mainnet, &bare).is_err());
+        assert!(responses_config(&config, "node", Network::Mainnet, &bare).is_err());
+    }
+}
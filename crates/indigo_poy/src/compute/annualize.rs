@@ -0,0 +1,180 @@
+//! Annualizing a period's return: simple (APR) or compounded every epoch (APY), over a
+//! 365-day, 365.25-day, or epoch-count year.
+
+use super::fixed::{mul_div, ratio_pct};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Seconds in a Cardano epoch (5 days).
+pub const EPOCH_SECS: i64 = 432_000;
+/// Epochs in a 365-day year.
+pub const EPOCHS_PER_YEAR: u32 = 73;
+
+/// Simple or compounded annual return (CLI `--annualization`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateKind {
+    /// Period return scaled to a year.
+    #[default]
+    Apr,
+    /// APR also compounded once per epoch (`combined.apy_pct`).
+    Apy,
+}
+
+/// How long the year and the period are (CLI `--day-count`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DayCount {
+    #[serde(rename = "365")]
+    Days365,
+    #[default]
+    #[serde(rename = "365.25")]
+    Days365_25,
+    /// The period in epochs started, over 73 epochs a year.
+    #[serde(rename = "epochs")]
+    Epochs,
+}
+
+impl DayCount {
+    /// A year and `period_secs` in this day count's unit (seconds, or epochs started).
+    pub fn year_and_period(&self, period_secs: i64) -> (i128, i128) {
+        match self {
+            DayCount::Days365 => (31_536_000, period_secs.max(1) as i128),
+            DayCount::Days365_25 => (31_557_600, period_secs.max(1) as i128),
+            DayCount::Epochs => (
+                EPOCHS_PER_YEAR as i128,
+                (period_secs.max(1) as i128 + EPOCH_SECS as i128 - 1) / EPOCH_SECS as i128,
+            ),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DayCount::Days365 => "365",
+            DayCount::Days365_25 => "365.25",
+            DayCount::Epochs => "epochs",
+        }
+    }
+}
+
+impl RateKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateKind::Apr => "apr",
+            RateKind::Apy => "apy",
+        }
+    }
+}
+
+impl fmt::Display for DayCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for RateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DayCount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "365" => Ok(DayCount::Days365),
+            "365.25" => Ok(DayCount::Days365_25),
+            "epochs" => Ok(DayCount::Epochs),
+            other => Err(format!(
+                "unknown day count: {} (expected 365, 365.25, or epochs)",
+                other
+            )),
+        }
+    }
+}
+
+impl FromStr for RateKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "apr" => Ok(RateKind::Apr),
+            "apy" => Ok(RateKind::Apy),
+            other => Err(format!(
+                "unknown annualization: {} (expected apr or apy)",
+                other
+            )),
+        }
+    }
+}
+
+/// The convention a bundle's APR (and APY) were computed under. The default, 365.25-day
+/// simple APR, is what bundles without one used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annualization {
+    #[serde(default)]
+    pub rate: RateKind,
+    #[serde(default)]
+    pub day_count: DayCount,
+}
+
+impl Annualization {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Annual rate of `pnl` earned on `position` over `period_secs`, as `(part, whole)`.
+    fn annual_ratio(&self, pnl: i128, position: i128, period_secs: i64) -> Option<(i128, i128)> {
+        let (year, period) = self.day_count.year_and_period(period_secs);
+        Some((pnl.checked_mul(year)?, position.checked_mul(period)?))
+    }
+
+    /// Simple annual return, in percent.
+    pub fn apr_pct(&self, pnl: i128, position: i128, period_secs: i64) -> Option<f64> {
+        let (part, whole) = self.annual_ratio(pnl, position, period_secs)?;
+        ratio_pct(part, whole)
+    }
+
+    /// The APR compounded once per epoch, `(1 + APR / 73)^73 - 1`, in percent.
+    pub fn apy_pct(&self, pnl: i128, position: i128, period_secs: i64) -> Option<f64> {
+        const SCALE: i128 = 1_000_000_000_000;
+        let (part, whole) = self.annual_ratio(pnl, position, period_secs)?;
+        let per_epoch = mul_div(part, SCALE, whole.checked_mul(EPOCHS_PER_YEAR as i128)?)?;
+        let mut growth = SCALE;
+        for _ in 0..EPOCHS_PER_YEAR {
+            growth = mul_div(growth, SCALE.checked_add(per_epoch)?, SCALE)?;
+        }
+        ratio_pct(growth - SCALE, SCALE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conventions_annualize_the_same_return_differently() {
+        // 1 ADA earned on 100 ADA over 30 days (6 epochs).
+        let (pnl, position, secs) = (1_000_000, 100_000_000, 30 * 86_400);
+        let of = |rate, day_count| Annualization { rate, day_count };
+        let apr = |day_count| of(RateKind::Apr, day_count).apr_pct(pnl, position, secs);
+        assert_eq!(apr(DayCount::Days365), Some(12.166667));
+        assert_eq!(apr(DayCount::Days365_25), Some(12.175));
+        assert_eq!(apr(DayCount::Epochs), Some(12.166667));
+        // A partial epoch counts as a whole one.
+        assert_eq!(
+            of(RateKind::Apr, DayCount::Epochs).apr_pct(pnl, position, secs + 1),
+            Some(10.428571)
+        );
+
+        let apy = of(RateKind::Apy, DayCount::Days365)
+            .apy_pct(pnl, position, secs)
+            .unwrap();
+        assert!((apy - ((1.0 + 0.121_666_67 / 73.0f64).powi(73) - 1.0) * 100.0).abs() < 1e-5);
+
+        assert!(Annualization::default().is_default());
+        assert_eq!("365.25".parse::<DayCount>(), Ok(DayCount::Days365_25));
+        assert!("actual".parse::<DayCount>().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Where net PnL came from: the premium and rewards each source earned, the gain on SP
+//! withdrawals over their cost, the protocol fees and the dilution taken off them, and the
+//! principal not (yet) paid back. The parts sum to `net_pnl_lovelace` exactly; the principal
+//! is what is left once the others are counted, so an open position shows as a negative
+//! principal rather than a loss of yield.
+
+use serde::{Deserialize, Serialize};
+
+/// Net PnL split by source, in lovelace. Costs are negative.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PnlAttribution {
+    /// Realized SP liquidation premium before dilution.
+    pub liquidation_premium_lovelace: i64,
+    /// Premium lost to other depositors' net deposits (`dilution_effect_lovelace`), negated.
+    pub dilution_lovelace: i64,
+    pub rob_premium_lovelace: i64,
+    /// INDY staking rewards and the SP premium paid through INDY staking.
+    pub indy_rewards_lovelace: i64,
+    /// SP withdrawals above (below, when negative) their pro-rata cost basis.
+    #[serde(default)]
+    pub withdrawal_gain_lovelace: i64,
+    /// Protocol fees, negated.
+    pub fees_lovelace: i64,
+    /// ADA put in and not paid back by the period end, negated, with anything the sources
+    /// above do not explain.
+    pub principal_lovelace: i64,
+    /// The sum of the parts: `net_pnl_lovelace`.
+    pub total_lovelace: i64,
+}
+
+/// What each source contributed to `net_pnl`. The SP premium is `realized_premium` (after
+/// dilution) and `dilution` is put back on it, then taken off again as its own part.
+pub fn pnl_attribution(
+    net_pnl: i64,
+    realized_premium: u64,
+    dilution: u64,
+    rob_premium: u64,
+    indy_rewards: u64,
+    withdrawal_gain: i64,
+    fees: u64,
+) -> PnlAttribution {
+    let clamp = |x: i128| x.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    let mut a = PnlAttribution {
+        liquidation_premium_lovelace: clamp(realized_premium as i128 + dilution as i128),
+        dilution_lovelace: clamp(-(dilution as i128)),
+        rob_premium_lovelace: clamp(rob_premium as i128),
+        indy_rewards_lovelace: clamp(indy_rewards as i128),
+        withdrawal_gain_lovelace: withdrawal_gain,
+        fees_lovelace: clamp(-(fees as i128)),
+        principal_lovelace: 0,
+        total_lovelace: net_pnl,
+    };
+    // Each part is far below i64::MAX in practice; the residual keeps the sum exact anyway.
+    let counted = a.liquidation_premium_lovelace as i128
+        + a.dilution_lovelace as i128
+        + a.rob_premium_lovelace as i128
+        + a.indy_rewards_lovelace as i128
+        + a.withdrawal_gain_lovelace as i128
+        + a.fees_lovelace as i128;
+    a.principal_lovelace = clamp(net_pnl as i128 - counted);
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parts_sum_to_net_pnl() {
+        // 100 ADA in, 80 out: 10 of SP premium (2 more lost to dilution), 3 of ROB premium, 1
+        // in INDY rewards, 4 withdrawn above cost, and 62 of principal; 0.5 in fees. 38 ADA
+        // of principal still out.
+        let a = pnl_attribution(
+            -20_500_000,
+            10_000_000,
+            2_000_000,
+            3_000_000,
+            1_000_000,
+            4_000_000,
+            500_000,
+        );
+        assert_eq!(a.liquidation_premium_lovelace, 12_000_000);
+        assert_eq!(a.dilution_lovelace, -2_000_000);
+        assert_eq!(a.fees_lovelace, -500_000);
+        assert_eq!(a.principal_lovelace, -38_000_000);
+        assert_eq!(
+            a.liquidation_premium_lovelace
+                + a.dilution_lovelace
+                + a.rob_premium_lovelace
+                + a.indy_rewards_lovelace
+                + a.withdrawal_gain_lovelace
+                + a.fees_lovelace
+                + a.principal_lovelace,
+            a.total_lovelace
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Currency of account: the unit the flows are added up in. In ADA every figure is the
+//! lovelace one; in USD or iUSD each event is converted at the price of its time before it is
+//! summed, so the totals, PnL, and APR are what a stablecoin holder saw, not the ADA totals
+//! converted at one price afterwards.
+
+use super::annualize::Annualization;
+use super::pricing::PriceSeries;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit of account (CLI `--currency`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    #[default]
+    Ada,
+    Usd,
+    /// Indigo's USD-pegged iAsset, priced by its own series as it can leave the peg.
+    Iusd,
+}
+
+impl Currency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Currency::Ada => "ada",
+            Currency::Usd => "usd",
+            Currency::Iusd => "iusd",
+        }
+    }
+
+    /// Ticker, as shown in the report.
+    pub fn ticker(&self) -> &'static str {
+        match self {
+            Currency::Ada => "ADA",
+            Currency::Usd => "USD",
+            Currency::Iusd => "iUSD",
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "ada" => Ok(Currency::Ada),
+            "usd" => Ok(Currency::Usd),
+            "iusd" => Ok(Currency::Iusd),
+            other => Err(format!(
+                "unknown currency: {} (expected ada, usd, or iusd)",
+                other
+            )),
+        }
+    }
+}
+
+/// The currency to account in, and its price in units per ADA (unused for ADA).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountCurrency {
+    pub currency: Currency,
+    #[serde(default, skip_serializing_if = "PriceSeries::is_empty")]
+    pub prices: PriceSeries,
+}
+
+impl AccountCurrency {
+    /// Units of the currency per ADA at `timestamp`; `None` without a price.
+    pub fn price_at(&self, timestamp: i64) -> Option<f64> {
+        match self.currency {
+            Currency::Ada => Some(1.0),
+            _ => self.prices.price_at(timestamp),
+        }
+    }
+}
+
+/// The combined figures in the currency of account, each event converted at its time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountMetrics {
+    pub currency: Currency,
+    pub total_in: f64,
+    pub total_out: f64,
+    /// Premium and rewards, before protocol fees.
+    pub yield_earned: f64,
+    pub protocol_fees: f64,
+    /// Out less in and protocol fees.
+    pub net_pnl: f64,
+    /// The open position at the period-end price, when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_position: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl: Option<f64>,
+    /// Net PnL over the closing position, annualized as `combined.apr_pct`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apr_pct: Option<f64>,
+    /// Units per ADA at the period end.
+    pub end_price: f64,
+    /// Txs without a price, left out of the totals.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unpriced_txs: Vec<String>,
+}
+
+/// Running totals in the currency of account, fed one event at a time.
+pub(crate) struct AccountBook<'a> {
+    account: &'a AccountCurrency,
+    metrics: AccountMetrics,
+}
+
+impl<'a> AccountBook<'a> {
+    pub fn new(account: &'a AccountCurrency) -> Self {
+        Self {
+            account,
+            metrics: AccountMetrics {
+                currency: account.currency,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// One event's change to ADA in and out, yield earned, and fees, in lovelace. Signed: a
+    /// cancelled order takes its refund back out of ADA in.
+    pub fn record(&mut self, tx: &str, timestamp: i64, flows: [i128; 4]) {
+        if flows == [0; 4] {
+            return;
+        }
+        let Some(price) = self.account.price_at(timestamp) else {
+            if !self.metrics.unpriced_txs.iter().any(|t| t == tx) {
+                self.metrics.unpriced_txs.push(tx.to_string());
+            }
+            return;
+        };
+        let [ada_in, ada_out, earned, fees] = flows.map(|l| l as f64 / 1_000_000.0 * price);
+        let m = &mut self.metrics;
+        m.total_in += ada_in;
+        m.total_out += ada_out;
+        m.yield_earned += earned;
+        m.protocol_fees += fees;
+    }
+
+    /// The totals at `end` (Unix time), with the position left open there (lovelace) and the
+    /// APR over `period_secs` when a period was given.
+    pub fn finish(
+        self,
+        end: Option<i64>,
+        open_position: Option<u64>,
+        period_secs: Option<i64>,
+        annualization: Annualization,
+    ) -> AccountMetrics {
+        let mut m = self.metrics;
+        m.end_price = end.and_then(|ts| self.account.price_at(ts)).unwrap_or(0.0);
+        m.net_pnl = m.total_out - m.total_in - m.protocol_fees;
+        m.open_position = open_position.map(|l| l as f64 / 1_000_000.0 * m.end_price);
+        m.unrealized_pnl = m.open_position.map(|p| m.net_pnl + p);
+        // As for the lovelace APR: the position, or what is still in, is the capital.
+        let capital = m.open_position.unwrap_or(m.total_in - m.total_out);
+        let micro = |x: f64| (x * 1_000_000.0).round() as i128;
+        m.apr_pct = period_secs.and_then(|secs| {
+            annualization.apr_pct(micro(m.net_pnl.max(0.0)), micro(capital).max(1), secs)
+        });
+        m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute::pricing::PricePoint;
+
+    #[test]
+    fn each_event_is_converted_at_its_own_price() {
+        let account = AccountCurrency {
+            currency: Currency::Usd,
+            prices: PriceSeries::new([
+                PricePoint {
+                    timestamp: 0,
+                    price: 0.5,
+                },
+                PricePoint {
+                    timestamp: 100,
+                    price: 1.0,
+                },
+            ]),
+        };
+        let mut book = AccountBook::new(&account);
+        // 100 ADA in at $0.50; 110 ADA out, 10 of it premium, at $1.00.
+        book.record("in", 10, [100_000_000, 0, 0, 0]);
+        book.record("out", 150, [0, 110_000_000, 10_000_000, 0]);
+        let m = book.finish(Some(150), None, None, Annualization::default());
+        assert_eq!((m.total_in, m.total_out, m.net_pnl), (50.0, 110.0, 60.0));
+        assert_eq!(m.yield_earned, 10.0);
+        assert_eq!(m.end_price, 1.0);
+
+        let ada = AccountCurrency::default();
+        let mut book = AccountBook::new(&ada);
+        book.record("in", 10, [100_000_000, 0, 0, 0]);
+        let m = book.finish(Some(10), Some(100_000_000), None, Annualization::default());
+        assert_eq!((m.net_pnl, m.unrealized_pnl), (-100.0, Some(0.0)));
+        assert_eq!("iUSD".parse::<Currency>(), Ok(Currency::Iusd));
+    }
+}
@@ -0,0 +1,86 @@
+//! Fixed-point money math. Lovelace amounts are scaled by integer rates and prices in `i128`
+//! with checked arithmetic, so a bundle's metrics, and its reproducibility hash, come out the
+//! same on every platform. Rates and prices are micro-units, as Indigo's `OnChainDecimal`
+//! (10^6 = 1). Percentages leave as `f64` only once, from a single division of an integer
+//! result, for the report to format. The compounded rates (XIRR and the annualized TWR) need
+//! `powf`, whose last bits differ between libm builds; they are rounded to whole
+//! micro-percent ([`round_micro`]) before they reach the bundle.
+
+/// Micro-units per 1.
+pub const MICRO: i128 = 1_000_000;
+
+/// `a * b / c` rounded half away from zero; `None` on overflow or `c == 0`.
+pub fn mul_div(a: i128, b: i128, c: i128) -> Option<i128> {
+    if c == 0 {
+        return None;
+    }
+    let product = a.checked_mul(b)?;
+    let (quotient, remainder) = (product / c, product % c);
+    let round_up = remainder.checked_abs()?.checked_mul(2)? >= c.checked_abs()?;
+    Some(match (round_up, (product < 0) != (c < 0)) {
+        (true, true) => quotient - 1,
+        (true, false) => quotient + 1,
+        (false, _) => quotient,
+    })
+}
+
+/// A rate or price given as a decimal, in micro-units. Rounded: decimals read from datums
+/// and CLI flags have at most six places, so this recovers them exactly.
+pub fn to_micro(x: f64) -> i128 {
+    (x * MICRO as f64).round() as i128
+}
+
+/// `lovelace` times `micro` micro-units, in lovelace, clamped to `u64`.
+pub fn scale_lovelace(lovelace: u128, micro: i128) -> u64 {
+    let lovelace = i128::try_from(lovelace).unwrap_or(i128::MAX);
+    match mul_div(lovelace, micro, MICRO) {
+        Some(x) => x.clamp(0, u64::MAX as i128) as u64,
+        None if micro > 0 => u64::MAX,
+        None => 0,
+    }
+}
+
+/// `x` rounded to whole micro-units, half away from zero.
+pub fn round_micro(x: f64) -> f64 {
+    to_micro(x) as f64 / MICRO as f64
+}
+
+/// `value` as an `i64`, saturating at its bounds.
+pub fn saturating_i64(value: i128) -> i64 {
+    i64::try_from(value).unwrap_or(if value < 0 { i64::MIN } else { i64::MAX })
+}
+
+/// `pct` percent of `lovelace`.
+pub fn percent_of(lovelace: u64, pct: f64) -> u64 {
+    mul_div(lovelace as i128, to_micro(pct), 100 * MICRO)
+        .map_or(0, |x| x.clamp(0, u64::MAX as i128) as u64)
+}
+
+/// `part / whole` in percent, through an integer count of micro-percent; `None` when `whole`
+/// is 0 or the ratio overflows.
+pub fn ratio_pct(part: i128, whole: i128) -> Option<f64> {
+    mul_div(part, 100 * MICRO, whole).map(|x| x as f64 / MICRO as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounds_half_away_from_zero_and_checks_overflow() {
+        assert_eq!(mul_div(5, 1, 2), Some(3));
+        assert_eq!(mul_div(-5, 1, 2), Some(-3));
+        assert_eq!(mul_div(4, 1, 3), Some(1));
+        assert_eq!(mul_div(1, 1, 0), None);
+        assert_eq!(mul_div(i128::MAX, 2, 1), None);
+
+        // 2.1% premium on 10 ADA, and a price of 0.35 ADA per unit.
+        assert_eq!(percent_of(10_000_000, 2.1), 210_000);
+        assert_eq!(scale_lovelace(3, to_micro(0.35)), 1);
+        assert_eq!(scale_lovelace(u128::MAX, MICRO), u64::MAX);
+        assert_eq!(ratio_pct(1, 3), Some(33.333333));
+        assert_eq!(ratio_pct(1, 0), None);
+        assert_eq!(round_micro(4.919_333_499_9), 4.919_333);
+        assert_eq!(round_micro(-0.000_000_5), -0.000_001);
+    }
+}
@@ -0,0 +1,25 @@
+//! Metric hooks: the extension point of the compute pipeline.
+//!
+//! A [`MetricHook`] accumulates a metric of its own over the events [`compute_metrics`] runs
+//! on, and is handed the finished metrics to derive its output from. Outputs land in
+//! `extra_metrics` under the hook's name, so a library user can add a figure to the bundle
+//! without forking the pipeline. Pass hooks to [`compute_metrics_with_hooks`].
+//!
+//! [`compute_metrics`]: super::compute_metrics
+//! [`compute_metrics_with_hooks`]: super::compute_metrics_with_hooks
+
+use super::metrics::ComputedMetrics;
+use crate::indigo::Event;
+
+/// A custom accumulator run alongside the built-in metrics.
+pub trait MetricHook {
+    /// Key of the output in `extra_metrics`. A later hook with the same name replaces an
+    /// earlier one's output.
+    fn name(&self) -> &str;
+
+    /// Called once per event, in time order (slot, then tx hash).
+    fn observe(&mut self, event: &Event);
+
+    /// The output, once every event was observed; `metrics` is the built-in result.
+    fn finish(&mut self, metrics: &ComputedMetrics) -> serde_json::Value;
+}
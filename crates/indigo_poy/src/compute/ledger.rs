@@ -0,0 +1,95 @@
+//! Running ledger: the SP balance, ROB open notional, and cumulative rewards after every event,
+//! in time order. Each figure only moves with the event that moved the metrics, so a balance
+//! that goes negative, or rewards that jump, point at the tx a heuristic got wrong.
+
+use crate::indigo::{Confidence, Event, EventKind};
+use serde::{Deserialize, Serialize};
+
+/// The position after one event.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<u64>,
+    pub tx_hash: String,
+    /// Event kind, as tagged in `events`.
+    pub kind: String,
+    pub confidence: Confidence,
+    /// SP principal: deposits less the cost basis of withdrawals and the principal liquidations
+    /// paid back.
+    pub sp_balance_lovelace: i64,
+    /// ROB ADA placed (cancellations netted out) and not yet filled.
+    pub rob_open_lovelace: i64,
+    /// Premium and rewards earned so far, before protocol fees.
+    pub cumulative_rewards_lovelace: u64,
+}
+
+/// How one event moved the ledger, as metrics saw it.
+pub(crate) struct LedgerChange {
+    pub entry: LedgerEntry,
+    pub sp_lovelace: i64,
+    pub rob_lovelace: i64,
+    pub rewards_lovelace: u64,
+}
+
+impl LedgerChange {
+    pub fn new(ev: &Event, sp_lovelace: i64, rob_lovelace: i64, rewards_lovelace: u64) -> Self {
+        Self {
+            entry: LedgerEntry {
+                timestamp: ev.timestamp.unix_timestamp(),
+                slot: ev.slot,
+                tx_hash: ev.tx_hash.clone(),
+                kind: kind_name(&ev.kind),
+                confidence: ev.confidence,
+                ..Default::default()
+            },
+            sp_lovelace,
+            rob_lovelace,
+            rewards_lovelace,
+        }
+    }
+}
+
+fn kind_name(kind: &EventKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| Some(v.get("kind")?.as_str()?.to_string()))
+        .unwrap_or_default()
+}
+
+/// Principal at work once `tx_hash` is through: the SP balance plus the open ROB ADA after its
+/// last event; 0 when the ledger does not have it.
+pub fn principal_after(ledger: &[LedgerEntry], tx_hash: &str) -> u64 {
+    ledger
+        .iter()
+        .rev()
+        .find(|e| e.tx_hash == tx_hash)
+        .map_or(0, |e| {
+            let principal = e.sp_balance_lovelace as i128 + e.rob_open_lovelace as i128;
+            principal.clamp(0, u64::MAX as i128) as u64
+        })
+}
+
+/// Put the changes in time order (slot, then tx hash; a tx's events keep their order) and
+/// run the balances through them.
+pub(crate) fn ledger(mut changes: Vec<LedgerChange>) -> Vec<LedgerEntry> {
+    changes.sort_by(|a, b| {
+        let key = |c: &LedgerChange| (c.entry.timestamp, c.entry.slot, c.entry.tx_hash.clone());
+        key(a).cmp(&key(b))
+    });
+    let (mut sp, mut rob, mut rewards) = (0i64, 0i64, 0u64);
+    changes
+        .into_iter()
+        .map(|c| {
+            sp = sp.saturating_add(c.sp_lovelace);
+            rob = rob.saturating_add(c.rob_lovelace);
+            rewards = rewards.saturating_add(c.rewards_lovelace);
+            LedgerEntry {
+                sp_balance_lovelace: sp,
+                rob_open_lovelace: rob,
+                cumulative_rewards_lovelace: rewards,
+                ..c.entry
+            }
+        })
+        .collect()
+}
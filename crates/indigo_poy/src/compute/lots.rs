@@ -0,0 +1,416 @@
+//! Cost-basis lots: each Stability Pool deposit opens a lot of iAsset at the ADA it cost, and
+//! withdrawals and liquidations close lots first-in-first-out or last-in-first-out.
+//!
+//! Lots are kept per iAsset, in the account datum's base units. A withdrawal disposes of the
+//! iAsset it took out for the ADA it returned; a pro-rata liquidation disposes of the wallet's
+//! share of the iAsset burnt for the ADA it received. Priced per-tx estimates carry no iAsset
+//! quantity, so their ADA cannot be matched to lots: their txs are listed as untracked.
+
+use super::fixed::saturating_i64;
+use crate::indigo::{Event, EventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Which open lot a disposal closes first (CLI `--cost-basis`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LotMethod {
+    /// Oldest lot first.
+    #[default]
+    Fifo,
+    /// Newest lot first.
+    Lifo,
+}
+
+impl LotMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LotMethod::Fifo => "fifo",
+            LotMethod::Lifo => "lifo",
+        }
+    }
+}
+
+impl fmt::Display for LotMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LotMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "fifo" => Ok(LotMethod::Fifo),
+            "lifo" => Ok(LotMethod::Lifo),
+            other => Err(format!(
+                "unknown cost-basis method: {} (expected fifo or lifo)",
+                other
+            )),
+        }
+    }
+}
+
+/// A deposit's iAsset and what is left of it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lot {
+    pub tx_hash: String,
+    pub timestamp: i64,
+    /// iAsset unit (policy id + token name, hex).
+    pub iasset: String,
+    pub quantity: u128,
+    /// ADA the deposit cost.
+    pub cost_lovelace: u64,
+    pub remaining_quantity: u128,
+    /// Cost basis of `remaining_quantity`.
+    pub remaining_cost_lovelace: u64,
+}
+
+/// What closed part of a lot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisposalKind {
+    Withdrawal,
+    Liquidation,
+}
+
+/// The part of one disposal matched to one lot.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LotDisposal {
+    pub tx_hash: String,
+    pub timestamp: i64,
+    pub kind: DisposalKind,
+    pub iasset: String,
+    /// Deposit that opened the lot.
+    pub lot_tx_hash: String,
+    pub lot_timestamp: i64,
+    pub quantity: u128,
+    /// The lot's cost basis of `quantity`.
+    pub cost_lovelace: u64,
+    /// The disposal's ADA, split across its lots by quantity.
+    pub proceeds_lovelace: u64,
+    pub gain_lovelace: i64,
+}
+
+/// Lots of the period under one method.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LotReport {
+    pub method: LotMethod,
+    /// Proceeds less cost basis over `disposals`.
+    pub realized_gain_lovelace: i64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub disposals: Vec<LotDisposal>,
+    /// Lots with iAsset left at the end of the period.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub open_lots: Vec<Lot>,
+    /// iAsset disposed of beyond the open lots (deposited before the period), per iAsset. Its
+    /// basis is unknown, so its share of the proceeds is left out of the gain.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unmatched_quantity: BTreeMap<String, u128>,
+    /// SP txs without an iAsset quantity, whose ADA is not in any lot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untracked_txs: Vec<String>,
+}
+
+/// Match the SP withdrawals and liquidations among `events` to the deposit lots, in time order.
+pub fn track_lots(events: &[Event], method: LotMethod) -> LotReport {
+    let mut events: Vec<&Event> = events.iter().collect();
+    events.sort_by_key(|ev| (ev.timestamp, ev.slot));
+    let mut lots: BTreeMap<String, Vec<Lot>> = BTreeMap::new();
+    let mut report = LotReport {
+        method,
+        ..Default::default()
+    };
+    for ev in events {
+        let iasset = ev.asset_unit().unwrap_or("unknown").to_string();
+        let timestamp = ev.timestamp.unix_timestamp();
+        let (kind, quantity, proceeds) = match &ev.kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace,
+                iasset_quantity: Some(quantity),
+                ..
+            } => {
+                lots.entry(iasset.clone()).or_default().push(Lot {
+                    tx_hash: ev.tx_hash.clone(),
+                    timestamp,
+                    iasset,
+                    quantity: *quantity,
+                    cost_lovelace: *amount_lovelace,
+                    remaining_quantity: *quantity,
+                    remaining_cost_lovelace: *amount_lovelace,
+                });
+                continue;
+            }
+            EventKind::StabilityPoolWithdraw {
+                amount_lovelace,
+                iasset_quantity: Some(quantity),
+                ..
+            } => (DisposalKind::Withdrawal, *quantity, *amount_lovelace),
+            EventKind::StabilityPoolLiquidation {
+                ada_received_lovelace,
+                iasset_lost_quantity: Some(quantity),
+                ..
+            } => (DisposalKind::Liquidation, *quantity, *ada_received_lovelace),
+            EventKind::StabilityPoolDeposit { .. }
+            | EventKind::StabilityPoolWithdraw { .. }
+            | EventKind::StabilityPoolLiquidation { .. } => {
+                report.untracked_txs.push(ev.tx_hash.clone());
+                continue;
+            }
+            _ => continue,
+        };
+        let open = lots.entry(iasset.clone()).or_default();
+        let (mut left, mut proceeds_left) = (quantity, proceeds);
+        while left > 0 {
+            let lot = match method {
+                LotMethod::Fifo => open.iter_mut().find(|l| l.remaining_quantity > 0),
+                LotMethod::Lifo => open.iter_mut().rev().find(|l| l.remaining_quantity > 0),
+            };
+            let Some(lot) = lot else {
+                *report.unmatched_quantity.entry(iasset.clone()).or_default() += left;
+                break;
+            };
+            let take = left.min(lot.remaining_quantity);
+            let cost = if take == lot.remaining_quantity {
+                lot.remaining_cost_lovelace
+            } else {
+                share(lot.remaining_cost_lovelace, take, lot.remaining_quantity)
+            };
+            let proceeds_part = if take == left {
+                proceeds_left
+            } else {
+                share(proceeds_left, take, left)
+            };
+            lot.remaining_quantity -= take;
+            lot.remaining_cost_lovelace -= cost;
+            left -= take;
+            proceeds_left -= proceeds_part;
+            report.disposals.push(LotDisposal {
+                tx_hash: ev.tx_hash.clone(),
+                timestamp,
+                kind,
+                iasset: iasset.clone(),
+                lot_tx_hash: lot.tx_hash.clone(),
+                lot_timestamp: lot.timestamp,
+                quantity: take,
+                cost_lovelace: cost,
+                proceeds_lovelace: proceeds_part,
+                gain_lovelace: saturating_i64(proceeds_part as i128 - cost as i128),
+            });
+        }
+    }
+    report.realized_gain_lovelace = saturating_i64(
+        report
+            .disposals
+            .iter()
+            .map(|d| d.gain_lovelace as i128)
+            .sum(),
+    );
+    report.open_lots = lots
+        .into_values()
+        .flatten()
+        .filter(|l| l.remaining_quantity > 0)
+        .collect();
+    report
+}
+
+/// Cost basis of each SP withdrawal among `events`, by index (`None` for the other events).
+///
+/// Unlike [`track_lots`], which needs a method and iAsset quantities, this keeps one average
+/// cost per pool, in time order. A partial withdrawal takes the share of the pool's open cost
+/// that its iAsset is of the iAsset held; without quantities, the open cost is paid back
+/// first. Liquidations take cost the same way, so what a withdrawal returns above its share
+/// is a gain, not principal.
+pub fn withdrawal_cost_basis(events: &[Event]) -> Vec<Option<u64>> {
+    #[derive(Default)]
+    struct Pool {
+        cost: u64,
+        quantity: u128,
+        /// Every deposit into the pool named its iAsset quantity.
+        sized: bool,
+    }
+    impl Pool {
+        /// Cost of `quantity` out of the pool (or of `lovelace`, unsized), taken off it.
+        fn take(&mut self, quantity: Option<u128>, lovelace: u64) -> u64 {
+            let cost = match quantity {
+                Some(q) if self.sized && self.quantity > 0 => {
+                    let q = q.min(self.quantity);
+                    let cost = share(self.cost, q, self.quantity);
+                    self.quantity -= q;
+                    cost
+                }
+                _ => lovelace.min(self.cost),
+            };
+            self.cost -= cost;
+            cost
+        }
+    }
+    let mut order: Vec<usize> = (0..events.len()).collect();
+    order.sort_by_key(|&i| (events[i].timestamp, events[i].slot));
+    let mut pools: BTreeMap<&str, Pool> = BTreeMap::new();
+    let mut basis = vec![None; events.len()];
+    for i in order {
+        let ev = &events[i];
+        let pool = pools
+            .entry(ev.asset_unit().unwrap_or("unknown"))
+            .or_insert(Pool {
+                sized: true,
+                ..Default::default()
+            });
+        match &ev.kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace,
+                iasset_quantity,
+                ..
+            } => {
+                pool.cost = pool.cost.saturating_add(*amount_lovelace);
+                match iasset_quantity {
+                    Some(q) => pool.quantity = pool.quantity.saturating_add(*q),
+                    None => pool.sized = false,
+                }
+            }
+            EventKind::StabilityPoolWithdraw {
+                amount_lovelace,
+                iasset_quantity,
+                ..
+            } => basis[i] = Some(pool.take(*iasset_quantity, *amount_lovelace)),
+            EventKind::StabilityPoolLiquidation {
+                ada_received_lovelace,
+                realized_premium_lovelace,
+                iasset_lost_quantity,
+                ..
+            } => {
+                pool.take(
+                    *iasset_lost_quantity,
+                    ada_received_lovelace.saturating_sub(*realized_premium_lovelace),
+                );
+            }
+            _ => {}
+        }
+    }
+    basis
+}
+
+/// `amount * part / whole`, rounded down; `part <= whole`.
+fn share(amount: u64, part: u128, whole: u128) -> u64 {
+    (amount as u128 * part / whole.max(1)) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit(ts: i64, tx: &str, quantity: u128, lovelace: u64) -> Event {
+        Event::at(
+            ts,
+            tx,
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace: lovelace,
+                iasset_amount: None,
+                iasset_quantity: Some(quantity),
+                tx_hash: tx.into(),
+            },
+        )
+    }
+
+    #[test]
+    fn fifo_and_lifo_close_different_lots() {
+        let events = [
+            deposit(100, "d1", 100, 100_000_000),
+            deposit(200, "d2", 100, 150_000_000),
+            Event::at(
+                300,
+                "w1",
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 260_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: Some(150),
+                    tx_hash: "w1".into(),
+                },
+            ),
+            Event::at(
+                400,
+                "w2",
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 5_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: "w2".into(),
+                },
+            ),
+        ];
+
+        // FIFO: all of d1 (cost 100) and half of d2 (cost 75) for 260.
+        let fifo = track_lots(&events, LotMethod::Fifo);
+        assert_eq!(fifo.disposals.len(), 2);
+        assert_eq!(fifo.disposals[0].lot_tx_hash, "d1");
+        assert_eq!(fifo.disposals[0].proceeds_lovelace, 173_333_333);
+        assert_eq!(fifo.disposals[1].cost_lovelace, 75_000_000);
+        assert_eq!(fifo.realized_gain_lovelace, 85_000_000);
+        assert_eq!(fifo.open_lots.len(), 1);
+        assert_eq!(fifo.open_lots[0].remaining_cost_lovelace, 75_000_000);
+        assert_eq!(fifo.untracked_txs, vec!["w2".to_string()]);
+
+        // LIFO: all of d2 (cost 150) and half of d1 (cost 50).
+        let lifo = track_lots(&events, LotMethod::Lifo);
+        assert_eq!(lifo.disposals[0].lot_tx_hash, "d2");
+        assert_eq!(lifo.realized_gain_lovelace, 60_000_000);
+        assert_eq!(lifo.open_lots[0].tx_hash, "d1");
+        assert_eq!(lifo.open_lots[0].remaining_quantity, 50);
+
+        let over = [
+            deposit(100, "d1", 100, 100_000_000),
+            Event::at(
+                200,
+                "l1",
+                EventKind::StabilityPoolLiquidation {
+                    iasset_burnt: String::new(),
+                    ada_received_lovelace: 220_000_000,
+                    realized_premium_lovelace: 0,
+                    dilution_effect: None,
+                    pool_deposit_quantity: None,
+                    user_deposit_quantity: None,
+                    iasset_lost_quantity: Some(200),
+                    oracle_price: None,
+                    tx_hash: "l1".into(),
+                    slot: None,
+                },
+            ),
+        ];
+        let report = track_lots(&over, LotMethod::Fifo);
+        assert_eq!(report.disposals[0].kind, DisposalKind::Liquidation);
+        assert_eq!(report.realized_gain_lovelace, 10_000_000);
+        assert_eq!(report.unmatched_quantity.get("unknown"), Some(&100));
+        assert!(report.open_lots.is_empty());
+    }
+
+    #[test]
+    fn partial_withdrawal_takes_its_share_of_the_cost() {
+        let withdraw = |ts, tx: &str, lovelace, quantity| {
+            Event::at(
+                ts,
+                tx,
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: lovelace,
+                    iasset_amount: None,
+                    iasset_quantity: quantity,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        // 100 iAsset for 100 ADA and 100 for 150: the average cost is 1.25 ADA. A quarter
+        // of the iAsset out for 70 ADA costs 62.5 of it; then, unsized, 200 ADA costs the
+        // 187.5 left.
+        let events = [
+            withdraw(300, "w1", 70_000_000, Some(50)),
+            deposit(100, "d1", 100, 100_000_000),
+            deposit(200, "d2", 100, 150_000_000),
+            withdraw(400, "w2", 200_000_000, None),
+        ];
+        let basis = withdrawal_cost_basis(&events);
+        assert_eq!(basis, [Some(62_500_000), None, None, Some(187_500_000)]);
+    }
+}
@@ -1,7 +1,25 @@
-//! Computed metrics: PnL, APR, realized premium, dilution.
+//! Computed metrics: PnL, APR, XIRR, TWR, realized premium, dilution.
 
-use crate::indigo::{EventKind, IndigoEvents};
+use super::annualize::{Annualization, RateKind};
+use super::attribution::{pnl_attribution, PnlAttribution};
+use super::benchmark::{benchmark, BenchmarkMetrics, StakingRate};
+use super::currency::{AccountBook, AccountCurrency, AccountMetrics};
+use super::fixed::{mul_div, percent_of, ratio_pct, round_micro, scale_lovelace, to_micro, MICRO};
+use super::hooks::MetricHook;
+use super::ledger::{ledger, LedgerChange, LedgerEntry};
+use super::lots::{track_lots, withdrawal_cost_basis, LotMethod, LotReport};
+use super::pending::{pending_rewards, PendingRewards};
+use super::pricing::PriceSeries;
+use super::risk::{risk_metrics, RiskMetrics};
+use super::statement::{statements, Statements};
+use super::twr::{time_weighted_return, FlowStep, TimeWeightedReturn};
+use super::window::{trailing_windows, WindowMetrics};
+use super::xirr::xirr;
+use crate::chain::fetch::RewardWithdrawal;
+use crate::chain::network::Network;
+use crate::indigo::{order_lifecycles, Confidence, Event, EventKind, IndigoEvents, OrderLifecycle};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ComputeInput {
@@ -12,13 +30,69 @@ pub struct ComputeInput {
     pub period_end_ts: Option<i64>,
     /// Current total ADA in position (lovelace) if known.
     pub current_ada_position: Option<u64>,
+    /// Principal already at work at the period start, e.g. deposited before the claim a
+    /// `--since-last-claim` period starts at. Without a current position, the capital is this
+    /// plus what the period moved in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening_principal_lovelace: Option<u64>,
+    /// ADA staking reward withdrawals in the period. Their amounts are not Indigo yield and
+    /// are taken out of the out>in reward heuristic.
+    #[serde(default)]
+    pub reward_withdrawals: Vec<RewardWithdrawal>,
+    /// USD per ADA over time, to value each event at the price of its time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ada_usd: Option<PriceSeries>,
+    /// Match SP withdrawals and liquidations to deposit lots under this method.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_basis: Option<LotMethod>,
+    /// How `apr_pct` (and `apy_pct`) are annualized.
+    #[serde(default, skip_serializing_if = "Annualization::is_default")]
+    pub annualization: Annualization,
+    /// Annual staking reward rate to compare the yield with (`combined.benchmark`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub staking_benchmark: Option<StakingRate>,
+    /// Build monthly and per-epoch statements (`metrics.statements`), epochs numbered on this
+    /// network.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statements: Option<Network>,
+    /// Liquidation ADA the open SP accounts earned and have not claimed (from the open
+    /// position). With it, `combined.pending` estimates the unclaimed rewards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unclaimed_sp_lovelace: Option<u64>,
+    /// Currency to also add the flows up in (`metrics.account`), each event converted at its
+    /// price.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<AccountCurrency>,
 }
 
+/// How other depositors moved the wallet's share of the Stability Pool, from the pro-rata
+/// liquidations (see [`dilution_model`]).
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DilutionModel {
+    /// The wallet's compounded deposit at the last liquidation, in iAsset base units. `None`
+    /// when the liquidations span several pools.
     pub total_iasset_at_risk: Option<String>,
+    /// The wallet's share of the pool at the last liquidation; `None` across several pools.
     pub user_share_pct: Option<f64>,
+    /// Premium lost to other depositors' net deposits (0 when their withdrawals outweighed
+    /// them).
     pub dilution_effect_lovelace: Option<u64>,
+    /// Realized premium of the pro-rata liquidations had other depositors not moved the
+    /// wallet's share: their realized premium plus the dilution effect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undiluted_premium_lovelace: Option<u64>,
+    /// The wallet's share at each pro-rata liquidation, in slot order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub share_history: Vec<SharePoint>,
+}
+
+/// The wallet's share of one pool at one liquidation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SharePoint {
+    /// iAsset unit of the pool.
+    pub iasset: String,
+    pub slot: Option<u64>,
+    pub user_share_pct: f64,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -27,7 +101,100 @@ pub struct ComputedMetrics {
     pub rob: RobMetrics,
     pub indy_staking: IndyStakingMetrics,
     pub combined: CombinedMetrics,
+    /// Net PnL split by source. Present only when the period has events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attribution: Option<PnlAttribution>,
     pub dilution: Option<DilutionModel>,
+    /// Present only when the period has CDP events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cdp: Option<CdpMetrics>,
+    /// Present only when the period has governance events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub governance: Option<GovernanceMetrics>,
+    /// How much of the headline figures rests on each confidence level. Present only when
+    /// the period has events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<ConfidenceMetrics>,
+    /// USD figures; present only when an ADA/USD price series was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fiat: Option<FiatMetrics>,
+    /// The combined figures in the currency of account, when one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<AccountMetrics>,
+    /// Cost-basis lots, when a method was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lots: Option<LotReport>,
+    /// Monthly and per-epoch statements, when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statements: Option<Statements>,
+    /// SP balance, ROB open notional, and cumulative rewards after every event, in time order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ledger: Vec<LedgerEntry>,
+    /// Inconsistent inputs met while computing: a sum that overflowed or went below zero (and
+    /// was clamped), or an event at odds with itself. Empty when all was consistent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Outputs of the [`MetricHook`]s the metrics were computed with, by hook name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_metrics: BTreeMap<String, serde_json::Value>,
+}
+
+/// The combined flows in USD, each event valued at the ADA/USD price at its timestamp.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FiatMetrics {
+    /// ADA in, at the price on each deposit or placement.
+    pub total_in_usd: f64,
+    /// ADA out, at the price on each receipt.
+    pub total_out_usd: f64,
+    #[serde(default, skip_serializing_if = "is_zero_f64")]
+    pub protocol_fees_usd: f64,
+    /// Out less in and protocol fees.
+    pub net_pnl_usd: f64,
+    /// ADA/USD at the period end.
+    pub end_price_usd: f64,
+    /// `net_pnl_usd` less the lovelace net PnL at `end_price_usd`: what ADA's price moves in
+    /// the period added to (or took from) the USD result.
+    pub currency_effect_usd: f64,
+    /// Tx hash → ADA/USD applied to its events.
+    pub event_prices: BTreeMap<String, f64>,
+}
+
+/// Lovelace behind a figure, split by the confidence of the events it comes from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceSplit {
+    pub high_lovelace: u64,
+    pub medium_lovelace: u64,
+    pub heuristic_lovelace: u64,
+}
+
+impl ConfidenceSplit {
+    fn add(&mut self, confidence: Confidence, lovelace: u64) {
+        let bucket = match confidence {
+            Confidence::High => &mut self.high_lovelace,
+            Confidence::Medium => &mut self.medium_lovelace,
+            Confidence::Heuristic => &mut self.heuristic_lovelace,
+        };
+        *bucket = bucket.saturating_add(lovelace);
+    }
+
+    pub fn total_lovelace(&self) -> u64 {
+        self.high_lovelace
+            .saturating_add(self.medium_lovelace)
+            .saturating_add(self.heuristic_lovelace)
+    }
+
+    /// Share of the total that is heuristic, in percent; `None` for a zero total.
+    pub fn heuristic_pct(&self) -> Option<f64> {
+        let total = self.total_lovelace();
+        (total > 0).then(|| self.heuristic_lovelace as f64 / total as f64 * 100.0)
+    }
+}
+
+/// Confidence breakdown of realized premium (SP, ROB, and INDY SP premium) and of ADA out.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceMetrics {
+    pub premium: ConfidenceSplit,
+    pub ada_out: ConfidenceSplit,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -38,6 +205,53 @@ pub struct StabilityPoolMetrics {
     pub total_realized_premium_lovelace: u64,
     pub net_ada_from_liquidations_lovelace: i64,
     pub liquidation_count: u64,
+    /// The deposits' cost of what the withdrawals took out, split pro rata over a partial
+    /// withdrawal (see [`withdrawal_cost_basis`]).
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub withdrawals_cost_basis_lovelace: u64,
+    /// Withdrawals less that cost: ADA taken out above (or below) what it cost to put in.
+    #[serde(default, skip_serializing_if = "is_zero_i64")]
+    pub withdrawal_gain_lovelace: i64,
+    /// iAsset unit (`unknown` when the events do not name it) → that pool's share of the above.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub by_asset: BTreeMap<String, SpAssetMetrics>,
+    /// Sum of `liquidations[].pnl_lovelace`, losses included; `None` without oracle-priced
+    /// liquidations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub liquidation_pnl_lovelace: Option<i64>,
+    /// P&L of each oracle-priced liquidation (see [`liquidation_pnl`]), in slot order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub liquidations: Vec<LiquidationPnl>,
+}
+
+/// One liquidation valued in iAsset terms: the ADA received against the ADA value of the
+/// wallet's iAsset it burnt, at the oracle price of its slot.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationPnl {
+    pub tx_hash: String,
+    pub slot: Option<u64>,
+    pub iasset: String,
+    pub ada_received_lovelace: u64,
+    pub iasset_lost_quantity: u128,
+    /// ADA per iAsset.
+    pub oracle_price: f64,
+    /// `iasset_lost_quantity` at `oracle_price`.
+    pub iasset_cost_lovelace: u64,
+    /// ADA received less the iAsset cost; negative when the liquidation lost value, which
+    /// `realized_premium_lovelace` floors at 0.
+    pub pnl_lovelace: i64,
+}
+
+/// One iAsset's Stability Pool, in lovelace.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpAssetMetrics {
+    pub deposits_lovelace: u64,
+    pub withdrawals_lovelace: u64,
+    pub liquidations_ada_received_lovelace: u64,
+    pub realized_premium_lovelace: u64,
+    pub liquidation_count: u64,
+    /// Withdrawals and liquidation ADA less deposits.
+    pub net_pnl_lovelace: i64,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -45,8 +259,20 @@ pub struct RobMetrics {
     pub total_placed_lovelace: u64,
     pub total_filled_lovelace: u64,
     pub total_premium_received_lovelace: u64,
+    /// Premium of the fills with a known one, weighted by the ADA filled.
     pub avg_premium_pct: Option<f64>,
     pub fill_count: u64,
+    /// ADA returned by cancelled orders, already netted out of `total_placed_lovelace` and ADA
+    /// in. `None` when no order was cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_cancelled_lovelace: Option<u64>,
+    /// Seconds between fills that left no order open and the next placement. `None` when no
+    /// such gap was seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_in_cooldown_secs: Option<u64>,
+    /// Placed, filled, cancelled and still-open ADA per tracked order (needs ROB datum hashes).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orders: Vec<OrderLifecycle>,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -54,6 +280,99 @@ pub struct IndyStakingMetrics {
     pub total_rewards_lovelace: u64,
     pub total_sp_premium_lovelace: u64,
     pub reward_tx_count: u64,
+    /// INDY received in rewards, base units (6 decimals). `None` without an INDY policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_indy_quantity: Option<u64>,
+    /// ADA per INDY used for the ADA equivalent (see [`IndyStakingMetrics::price_indy`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indy_price_ada: Option<f64>,
+    /// `total_indy_quantity` at `indy_price_ada`. Not part of PnL: the price is an input, not
+    /// chain data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_indy_ada_equivalent_lovelace: Option<u64>,
+    /// INDY received from airdrops, base units. One-off, so not a reward: it stays out of the
+    /// totals above, PnL, and APR. `None` when no airdrop was seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub airdrop_indy_quantity: Option<u64>,
+    /// INDY released by vesting unlocks, base units; kept out like airdrops.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vesting_indy_quantity: Option<u64>,
+}
+
+impl IndyStakingMetrics {
+    /// Value the INDY rewards at `indy_price_ada` ADA per INDY. Both have 6 decimals, so the
+    /// lovelace equivalent is the base-unit quantity times the price.
+    pub fn price_indy(&mut self, indy_price_ada: f64) {
+        self.indy_price_ada = Some(indy_price_ada);
+        self.total_indy_ada_equivalent_lovelace = self
+            .total_indy_quantity
+            .map(|q| scale_lovelace(q as u128, to_micro(indy_price_ada)));
+    }
+}
+
+/// CDP activity. Collateral is the owner's own ADA moving in and out of a position, not yield,
+/// so none of it counts towards the combined totals.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CdpMetrics {
+    /// Opening collateral plus deposits.
+    pub collateral_posted_lovelace: u64,
+    /// Withdrawals plus collateral returned on close.
+    pub collateral_withdrawn_lovelace: u64,
+    /// iAsset unit → quantity minted.
+    pub iasset_minted: BTreeMap<String, u64>,
+    /// iAsset unit → quantity burnt.
+    pub iasset_burnt: BTreeMap<String, u64>,
+    /// Network fees of CDP txs.
+    pub fees_paid_lovelace: u64,
+    pub open_count: u64,
+    pub close_count: u64,
+    /// Redemptions others made against the CDPs.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub redemption_count: u64,
+    /// Collateral the redeemers took, in exchange for clearing debt.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub collateral_redeemed_lovelace: u64,
+    /// iAsset unit → debt cleared by redemptions.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub iasset_redeemed: BTreeMap<String, u64>,
+    /// iAsset unit → outstanding debt: minted minus burnt and redeemed. Only the period's
+    /// events count, so this is the full debt when the period covers the CDPs' opening, and the
+    /// change in debt otherwise.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub iasset_debt: BTreeMap<String, i64>,
+}
+
+impl CdpMetrics {
+    fn outstanding_debt(&self, diag: &mut Diagnostics) -> BTreeMap<String, i64> {
+        let mut debt: BTreeMap<&str, i128> = BTreeMap::new();
+        for (quantities, sign) in [
+            (&self.iasset_minted, 1i128),
+            (&self.iasset_burnt, -1),
+            (&self.iasset_redeemed, -1),
+        ] {
+            for (unit, quantity) in quantities {
+                *debt.entry(unit).or_default() += sign * *quantity as i128;
+            }
+        }
+        debt.into_iter()
+            .filter(|(_, d)| *d != 0)
+            .map(|(unit, d)| {
+                let field = format!("cdp.iasset_debt[{}]", unit);
+                (unit.to_string(), diag.signed(d, &field))
+            })
+            .collect()
+    }
+}
+
+/// INDY governance participation, in INDY base units. Locked INDY is the owner's own and
+/// stays out of the combined totals.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GovernanceMetrics {
+    pub indy_locked: u64,
+    pub indy_unlocked: u64,
+    pub vote_count: u64,
+    pub proposal_count: u64,
+    pub proposal_deposits_indy: u64,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -62,6 +381,51 @@ pub struct CombinedMetrics {
     pub total_ada_out_lovelace: u64,
     pub net_pnl_lovelace: i64,
     pub apr_pct: Option<f64>,
+    /// `apr_pct` compounded once per epoch, when the convention asks for APY.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub apy_pct: Option<f64>,
+    /// Convention `apr_pct` and `apy_pct` were annualized under; omitted for the default
+    /// 365.25-day APR.
+    #[serde(default, skip_serializing_if = "Annualization::is_default")]
+    pub annualization: Annualization,
+    /// ADA staking rewards withdrawn in the period, kept out of ADA out and PnL. `None` when
+    /// withdrawals were not fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ada_staking_rewards_lovelace: Option<u64>,
+    /// ADA paid to the protocol treasury / fee collectors, subtracted from net PnL. `None` when
+    /// no protocol fee was seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_fees_lovelace: Option<u64>,
+    /// Money-weighted annual return: the XIRR of each event's ADA in and out, with the
+    /// remaining position taken out at the period end. Unlike `apr_pct`, each deposit only
+    /// counts for the time it was at work. `None` without both money in and money out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xirr_pct: Option<f64>,
+    /// Time-weighted return: premium and rewards over the principal at work, chained across
+    /// deposits and withdrawals. It does not depend on their timing, so it compares wallets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub twr: Option<TimeWeightedReturn>,
+    /// Value of the open position (`ComputeInput::current_ada_position`), when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_position_lovelace: Option<u64>,
+    /// Net PnL with the open position taken out at that value: ADA out plus the position,
+    /// less ADA in and protocol fees. `net_pnl_lovelace` counts only what came back, so an open
+    /// position reads as a loss there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unrealized_pnl_lovelace: Option<i64>,
+    /// Yield of the last 30 days, 90 days, and year, each annualized as `apr_pct` over the
+    /// principal at work in it; windows longer than the period are left out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trailing: Vec<WindowMetrics>,
+    /// The yield against delegating the same ADA, or holding it, with a staking rate given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark: Option<BenchmarkMetrics>,
+    /// Drawdown and volatility of the per-epoch returns; `None` with under two epochs of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<RiskMetrics>,
+    /// Rewards earned and not yet claimed, estimated; kept out of PnL and the rates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<PendingRewards>,
 }
 
 /// Compute all metrics from events and optional period/position.
@@ -70,116 +434,898 @@ pub fn compute_metrics(input: &ComputeInput) -> ComputedMetrics {
     let mut rob = RobMetrics::default();
     let mut indy = IndyStakingMetrics::default();
     let mut combined = CombinedMetrics::default();
+    let mut cdp: Option<CdpMetrics> = None;
+    let mut governance: Option<GovernanceMetrics> = None;
+    let mut placed_orders: HashSet<&str> = HashSet::new();
+    // (ADA filled at a known premium, that ADA times its premium in micro-percent).
+    let mut priced_fills: (i128, i128) = (0, 0);
+    let mut diag = Diagnostics::default();
 
     let mut total_in: u64 = 0;
     let mut total_out: u64 = 0;
+    let mut withdrawn: HashMap<&str, u64> = HashMap::new();
+    for w in &input.reward_withdrawals {
+        let sum = withdrawn.entry(w.tx_hash.as_str()).or_default();
+        diag.add(
+            sum,
+            w.amount_lovelace,
+            "combined.ada_staking_rewards_lovelace",
+            &w.tx_hash,
+        );
+    }
 
-    for ev in input.events.all_events() {
+    let mut confidence: Option<ConfidenceMetrics> = None;
+    let asset = |ev: &Event| ev.asset_unit().unwrap_or("unknown").to_string();
+    let mut steps: Vec<FlowStep> = Vec::new();
+    let mut changes: Vec<LedgerChange> = Vec::new();
+    let mut reward_claims: Vec<(i64, u64)> = Vec::new();
+    // SP events come first in `all_events`, so their index there is their index here.
+    let sp_basis = withdrawal_cost_basis(&input.events.stability_pool);
+    let mut withdrawal_gain: i128 = 0;
+    let sp_balance = |sp: &StabilityPoolMetrics| {
+        sp.total_deposits_lovelace as i128
+            - sp.withdrawals_cost_basis_lovelace as i128
+            - sp.total_liquidations_ada_received_lovelace as i128
+            + sp.total_realized_premium_lovelace as i128
+    };
+    let rob_open =
+        |rob: &RobMetrics| rob.total_placed_lovelace as i128 - rob.total_filled_lovelace as i128;
+    let prices = input.ada_usd.as_ref().filter(|s| !s.is_empty());
+    let mut fiat = prices.map(|_| FiatMetrics::default());
+    let mut book = input.currency.as_ref().map(AccountBook::new);
+    for (i, ev) in input.events.all_events().enumerate() {
+        let premium = |sp: &StabilityPoolMetrics, rob: &RobMetrics, indy: &IndyStakingMetrics| {
+            sp.total_realized_premium_lovelace
+                .saturating_add(rob.total_premium_received_lovelace)
+                .saturating_add(indy.total_sp_premium_lovelace)
+        };
+        let (out_before, premium_before) = (total_out, premium(&sp, &rob, &indy));
+        let (in_before, rewards_before) = (total_in, indy.total_rewards_lovelace);
+        let fees_before = combined.protocol_fees_lovelace.unwrap_or(0);
+        let (sp_before, rob_before) = (sp_balance(&sp), rob_open(&rob));
+        let gain_before = withdrawal_gain;
         match &ev.kind {
             EventKind::StabilityPoolDeposit {
                 amount_lovelace, ..
             } => {
-                sp.total_deposits_lovelace =
-                    sp.total_deposits_lovelace.saturating_add(*amount_lovelace);
-                total_in = total_in.saturating_add(*amount_lovelace);
+                diag.add(
+                    &mut sp.total_deposits_lovelace,
+                    *amount_lovelace,
+                    "stability_pool.total_deposits_lovelace",
+                    &ev.tx_hash,
+                );
+                let pool = sp.by_asset.entry(asset(ev)).or_default();
+                diag.add(
+                    &mut pool.deposits_lovelace,
+                    *amount_lovelace,
+                    "stability_pool.by_asset.deposits_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut total_in,
+                    *amount_lovelace,
+                    "combined.total_ada_in_lovelace",
+                    &ev.tx_hash,
+                );
             }
             EventKind::StabilityPoolWithdraw {
                 amount_lovelace, ..
             } => {
-                sp.total_withdrawals_lovelace = sp
-                    .total_withdrawals_lovelace
-                    .saturating_add(*amount_lovelace);
-                total_out = total_out.saturating_add(*amount_lovelace);
+                diag.add(
+                    &mut sp.total_withdrawals_lovelace,
+                    *amount_lovelace,
+                    "stability_pool.total_withdrawals_lovelace",
+                    &ev.tx_hash,
+                );
+                let pool = sp.by_asset.entry(asset(ev)).or_default();
+                diag.add(
+                    &mut pool.withdrawals_lovelace,
+                    *amount_lovelace,
+                    "stability_pool.by_asset.withdrawals_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut total_out,
+                    *amount_lovelace,
+                    "combined.total_ada_out_lovelace",
+                    &ev.tx_hash,
+                );
+                let basis = sp_basis
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .unwrap_or(*amount_lovelace);
+                diag.add(
+                    &mut sp.withdrawals_cost_basis_lovelace,
+                    basis,
+                    "stability_pool.withdrawals_cost_basis_lovelace",
+                    &ev.tx_hash,
+                );
+                withdrawal_gain += *amount_lovelace as i128 - basis as i128;
             }
             EventKind::StabilityPoolLiquidation {
                 ada_received_lovelace,
                 realized_premium_lovelace,
                 ..
             } => {
-                sp.total_liquidations_ada_received_lovelace = sp
-                    .total_liquidations_ada_received_lovelace
-                    .saturating_add(*ada_received_lovelace);
-                sp.total_realized_premium_lovelace = sp
-                    .total_realized_premium_lovelace
-                    .saturating_add(*realized_premium_lovelace);
-                sp.liquidation_count = sp.liquidation_count.saturating_add(1);
-                total_out = total_out.saturating_add(*ada_received_lovelace);
+                if realized_premium_lovelace > ada_received_lovelace {
+                    diag.warn(format!(
+                        "liquidation at tx {} realized more premium than the ADA it paid",
+                        ev.tx_hash
+                    ));
+                }
+                diag.add(
+                    &mut sp.total_liquidations_ada_received_lovelace,
+                    *ada_received_lovelace,
+                    "stability_pool.total_liquidations_ada_received_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut sp.total_realized_premium_lovelace,
+                    *realized_premium_lovelace,
+                    "stability_pool.total_realized_premium_lovelace",
+                    &ev.tx_hash,
+                );
+                sp.liquidation_count += 1;
+                let pool = sp.by_asset.entry(asset(ev)).or_default();
+                diag.add(
+                    &mut pool.liquidations_ada_received_lovelace,
+                    *ada_received_lovelace,
+                    "stability_pool.by_asset.liquidations_ada_received_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut pool.realized_premium_lovelace,
+                    *realized_premium_lovelace,
+                    "stability_pool.by_asset.realized_premium_lovelace",
+                    &ev.tx_hash,
+                );
+                pool.liquidation_count += 1;
+                diag.add(
+                    &mut total_out,
+                    *ada_received_lovelace,
+                    "combined.total_ada_out_lovelace",
+                    &ev.tx_hash,
+                );
             }
             EventKind::RobOrderPlace {
-                amount_lovelace, ..
+                order_id,
+                amount_lovelace,
+                ..
             } => {
-                rob.total_placed_lovelace =
-                    rob.total_placed_lovelace.saturating_add(*amount_lovelace);
-                total_in = total_in.saturating_add(*amount_lovelace);
+                if let Some(id) = order_id {
+                    placed_orders.insert(id);
+                }
+                diag.add(
+                    &mut rob.total_placed_lovelace,
+                    *amount_lovelace,
+                    "rob.total_placed_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut total_in,
+                    *amount_lovelace,
+                    "combined.total_ada_in_lovelace",
+                    &ev.tx_hash,
+                );
             }
             EventKind::RobOrderFill {
                 filled_lovelace,
                 premium_pct,
                 ..
             } => {
-                let premium = (*filled_lovelace as f64) * premium_pct.unwrap_or(0.0) / 100.0;
-                rob.total_filled_lovelace =
-                    rob.total_filled_lovelace.saturating_add(*filled_lovelace);
-                rob.total_premium_received_lovelace = rob
-                    .total_premium_received_lovelace
-                    .saturating_add(premium as u64);
-                rob.fill_count = rob.fill_count.saturating_add(1);
-                total_out = total_out.saturating_add(*filled_lovelace);
+                let premium = percent_of(*filled_lovelace, premium_pct.unwrap_or(0.0));
+                diag.add(
+                    &mut rob.total_filled_lovelace,
+                    *filled_lovelace,
+                    "rob.total_filled_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut rob.total_premium_received_lovelace,
+                    premium,
+                    "rob.total_premium_received_lovelace",
+                    &ev.tx_hash,
+                );
+                rob.fill_count += 1;
+                diag.add(
+                    &mut total_out,
+                    *filled_lovelace,
+                    "combined.total_ada_out_lovelace",
+                    &ev.tx_hash,
+                );
                 if let Some(p) = premium_pct {
-                    rob.avg_premium_pct = Some(rob.avg_premium_pct.map_or(*p, |a| (a + p) / 2.0));
+                    priced_fills.0 += *filled_lovelace as i128;
+                    priced_fills.1 += *filled_lovelace as i128 * to_micro(*p);
+                }
+            }
+            EventKind::RobCooldown {
+                duration_secs: Some(secs),
+                ..
+            } => {
+                let idle = rob.time_in_cooldown_secs.get_or_insert(0);
+                diag.add(idle, *secs, "rob.time_in_cooldown_secs", &ev.tx_hash);
+            }
+            EventKind::RobOrderCancel {
+                order_id,
+                returned_lovelace,
+                ..
+            } => {
+                // Refunded ADA was never at work: take it back out of what was placed. An order
+                // placed before the period never counted as ADA in, so its refund is skipped.
+                if order_id
+                    .as_deref()
+                    .is_some_and(|id| placed_orders.contains(id))
+                {
+                    diag.sub(
+                        &mut rob.total_placed_lovelace,
+                        *returned_lovelace,
+                        "rob.total_placed_lovelace",
+                        &ev.tx_hash,
+                    );
+                    diag.sub(
+                        &mut total_in,
+                        *returned_lovelace,
+                        "combined.total_ada_in_lovelace",
+                        &ev.tx_hash,
+                    );
                 }
+                let cancelled = rob.total_cancelled_lovelace.get_or_insert(0);
+                diag.add(
+                    cancelled,
+                    *returned_lovelace,
+                    "rob.total_cancelled_lovelace",
+                    &ev.tx_hash,
+                );
             }
             EventKind::IndyStakingReward {
-                amount_lovelace, ..
+                amount_lovelace,
+                indy_quantity,
+                tx_hash,
+                ..
             } => {
-                indy.total_rewards_lovelace =
-                    indy.total_rewards_lovelace.saturating_add(*amount_lovelace);
-                indy.reward_tx_count = indy.reward_tx_count.saturating_add(1);
-                total_out = total_out.saturating_add(*amount_lovelace);
+                if let Some(q) = indy_quantity {
+                    let total = indy.total_indy_quantity.get_or_insert(0);
+                    diag.add(total, *q, "indy_staking.total_indy_quantity", &ev.tx_hash);
+                }
+                // The tx's ADA delta includes any staking reward it withdrew.
+                let ada_rewards = withdrawn.get(tx_hash.as_str()).copied().unwrap_or(0);
+                let amount = amount_lovelace.saturating_sub(ada_rewards);
+                if amount > 0 {
+                    diag.add(
+                        &mut indy.total_rewards_lovelace,
+                        amount,
+                        "indy_staking.total_rewards_lovelace",
+                        &ev.tx_hash,
+                    );
+                    diag.add(
+                        &mut total_out,
+                        amount,
+                        "combined.total_ada_out_lovelace",
+                        &ev.tx_hash,
+                    );
+                    reward_claims.push((ev.timestamp.unix_timestamp(), amount));
+                }
+                if amount > 0 || indy_quantity.is_some() {
+                    indy.reward_tx_count += 1;
+                }
             }
             EventKind::IndySpPremium {
                 amount_lovelace, ..
             } => {
-                indy.total_sp_premium_lovelace = indy
-                    .total_sp_premium_lovelace
-                    .saturating_add(*amount_lovelace);
-                total_out = total_out.saturating_add(*amount_lovelace);
+                diag.add(
+                    &mut indy.total_sp_premium_lovelace,
+                    *amount_lovelace,
+                    "indy_staking.total_sp_premium_lovelace",
+                    &ev.tx_hash,
+                );
+                diag.add(
+                    &mut total_out,
+                    *amount_lovelace,
+                    "combined.total_ada_out_lovelace",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::IndyAirdrop { indy_quantity, .. } => {
+                let total = indy.airdrop_indy_quantity.get_or_insert(0);
+                diag.add(
+                    total,
+                    indy_quantity.unwrap_or(0),
+                    "indy_staking.airdrop_indy_quantity",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::IndyVestingUnlock { indy_quantity, .. } => {
+                let total = indy.vesting_indy_quantity.get_or_insert(0);
+                diag.add(
+                    total,
+                    indy_quantity.unwrap_or(0),
+                    "indy_staking.vesting_indy_quantity",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::CdpOpen {
+                collateral_lovelace,
+                ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                diag.add(
+                    &mut cdp.collateral_posted_lovelace,
+                    *collateral_lovelace,
+                    "cdp.collateral_posted_lovelace",
+                    &ev.tx_hash,
+                );
+                cdp.open_count += 1;
+            }
+            EventKind::CdpDeposit {
+                amount_lovelace, ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                diag.add(
+                    &mut cdp.collateral_posted_lovelace,
+                    *amount_lovelace,
+                    "cdp.collateral_posted_lovelace",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::CdpWithdraw {
+                amount_lovelace, ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                diag.add(
+                    &mut cdp.collateral_withdrawn_lovelace,
+                    *amount_lovelace,
+                    "cdp.collateral_withdrawn_lovelace",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::CdpClose {
+                collateral_returned_lovelace,
+                ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                diag.add(
+                    &mut cdp.collateral_withdrawn_lovelace,
+                    *collateral_returned_lovelace,
+                    "cdp.collateral_withdrawn_lovelace",
+                    &ev.tx_hash,
+                );
+                cdp.close_count += 1;
+            }
+            EventKind::CdpMint {
+                iasset, quantity, ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                let minted = cdp.iasset_minted.entry(iasset.clone()).or_default();
+                diag.add(minted, *quantity, "cdp.iasset_minted", &ev.tx_hash);
+            }
+            EventKind::CdpBurn {
+                iasset, quantity, ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                let burnt = cdp.iasset_burnt.entry(iasset.clone()).or_default();
+                diag.add(burnt, *quantity, "cdp.iasset_burnt", &ev.tx_hash);
+            }
+            EventKind::CdpRedeemedAgainst {
+                iasset,
+                redeemed_quantity,
+                ada_returned_lovelace,
+                ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                cdp.redemption_count += 1;
+                diag.add(
+                    &mut cdp.collateral_redeemed_lovelace,
+                    *ada_returned_lovelace,
+                    "cdp.collateral_redeemed_lovelace",
+                    &ev.tx_hash,
+                );
+                let redeemed = cdp.iasset_redeemed.entry(iasset.clone()).or_default();
+                diag.add(
+                    redeemed,
+                    *redeemed_quantity,
+                    "cdp.iasset_redeemed",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::CdpFee {
+                amount_lovelace, ..
+            } => {
+                let cdp = cdp.get_or_insert_with(CdpMetrics::default);
+                diag.add(
+                    &mut cdp.fees_paid_lovelace,
+                    *amount_lovelace,
+                    "cdp.fees_paid_lovelace",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::ProtocolFeePaid {
+                amount_lovelace, ..
+            } => {
+                let fees = combined.protocol_fees_lovelace.get_or_insert(0);
+                diag.add(
+                    fees,
+                    *amount_lovelace,
+                    "combined.protocol_fees_lovelace",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::GovStakeLock { indy_quantity, .. } => {
+                let gov = governance.get_or_insert_with(GovernanceMetrics::default);
+                diag.add(
+                    &mut gov.indy_locked,
+                    *indy_quantity,
+                    "governance.indy_locked",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::GovStakeUnlock { indy_quantity, .. } => {
+                let gov = governance.get_or_insert_with(GovernanceMetrics::default);
+                diag.add(
+                    &mut gov.indy_unlocked,
+                    *indy_quantity,
+                    "governance.indy_unlocked",
+                    &ev.tx_hash,
+                );
+            }
+            EventKind::GovVote { .. } => {
+                let gov = governance.get_or_insert_with(GovernanceMetrics::default);
+                gov.vote_count += 1;
+            }
+            EventKind::GovProposalDeposit { indy_quantity, .. } => {
+                let gov = governance.get_or_insert_with(GovernanceMetrics::default);
+                gov.proposal_count += 1;
+                diag.add(
+                    &mut gov.proposal_deposits_indy,
+                    *indy_quantity,
+                    "governance.proposal_deposits_indy",
+                    &ev.tx_hash,
+                );
             }
             _ => {}
         }
+        let split = confidence.get_or_insert_with(ConfidenceMetrics::default);
+        split.ada_out.add(ev.confidence, total_out - out_before);
+        split
+            .premium
+            .add(ev.confidence, premium(&sp, &rob, &indy) - premium_before);
+        // Premium and rewards are yield; the rest of the ADA out is principal coming back.
+        let earned = (premium(&sp, &rob, &indy) - premium_before)
+            + (indy.total_rewards_lovelace - rewards_before);
+        let fees = combined.protocol_fees_lovelace.unwrap_or(0) - fees_before;
+        changes.push(LedgerChange::new(
+            ev,
+            diag.signed(sp_balance(&sp) - sp_before, "ledger.sp_balance_lovelace"),
+            diag.signed(rob_open(&rob) - rob_before, "ledger.rob_open_lovelace"),
+            earned,
+        ));
+        // A withdrawal's ADA above its cost basis is a gain on the principal, not principal.
+        let realized = earned as i128 + (withdrawal_gain - gain_before);
+        let external =
+            total_in as i128 - in_before as i128 + realized - (total_out - out_before) as i128;
+        let step = FlowStep {
+            timestamp: ev.timestamp.unix_timestamp(),
+            external_lovelace: diag.signed(external, "external flow"),
+            gain_lovelace: diag.signed(realized - fees as i128, "gain"),
+        };
+        if step.external_lovelace != 0 || step.gain_lovelace != 0 {
+            steps.push(step);
+        }
+        if let Some(book) = &mut book {
+            book.record(
+                &ev.tx_hash,
+                step.timestamp,
+                [
+                    total_in as i128 - in_before as i128,
+                    total_out as i128 - out_before as i128,
+                    earned as i128,
+                    fees as i128,
+                ],
+            );
+        }
+        if let (Some(fiat), Some(price)) =
+            (&mut fiat, prices.and_then(|s| s.price_at(step.timestamp)))
+        {
+            let usd = |lovelace: i64| lovelace as f64 / 1_000_000.0 * price;
+            fiat.total_in_usd += usd(total_in as i64 - in_before as i64);
+            fiat.total_out_usd += usd((total_out - out_before) as i64);
+            fiat.protocol_fees_usd += usd(fees as i64);
+            fiat.event_prices.insert(ev.tx_hash.clone(), price);
+        }
     }
 
-    sp.net_ada_from_liquidations_lovelace =
-        sp.total_liquidations_ada_received_lovelace
-            .saturating_sub(sp.total_deposits_lovelace) as i64;
+    // Events come grouped by kind; the principal's timeline needs them in time order.
+    steps.sort_by_key(|s| s.timestamp);
+    rob.orders = order_lifecycles(&input.events.rob);
+    rob.avg_premium_pct =
+        mul_div(priced_fills.1, 1, priced_fills.0).map(|p| p as f64 / MICRO as f64);
+    if let Some(cdp) = &mut cdp {
+        cdp.iasset_debt = cdp.outstanding_debt(&mut diag);
+    }
+    sp.net_ada_from_liquidations_lovelace = diag.signed(
+        sp.total_liquidations_ada_received_lovelace as i128 - sp.total_deposits_lovelace as i128,
+        "stability_pool.net_ada_from_liquidations_lovelace",
+    );
+    sp.liquidations = checked_liquidation_pnl(&input.events.stability_pool, &mut diag);
+    if !sp.liquidations.is_empty() {
+        sp.liquidation_pnl_lovelace = Some(diag.signed(
+            sp.liquidations.iter().map(|l| l.pnl_lovelace as i128).sum(),
+            "stability_pool.liquidation_pnl_lovelace",
+        ));
+    }
+    for pool in sp.by_asset.values_mut() {
+        pool.net_pnl_lovelace = diag.signed(
+            pool.withdrawals_lovelace as i128 + pool.liquidations_ada_received_lovelace as i128
+                - pool.deposits_lovelace as i128,
+            "stability_pool.by_asset.net_pnl_lovelace",
+        );
+    }
 
+    sp.withdrawal_gain_lovelace =
+        diag.signed(withdrawal_gain, "stability_pool.withdrawal_gain_lovelace");
     combined.total_ada_in_lovelace = total_in;
     combined.total_ada_out_lovelace = total_out;
-    combined.net_pnl_lovelace = total_out as i64 - total_in as i64;
+    combined.net_pnl_lovelace = diag.signed(
+        total_out as i128 - total_in as i128 - combined.protocol_fees_lovelace.unwrap_or(0) as i128,
+        "combined.net_pnl_lovelace",
+    );
+    if let Some(position) = input.current_ada_position {
+        combined.open_position_lovelace = Some(position);
+        combined.unrealized_pnl_lovelace = Some(diag.signed(
+            combined.net_pnl_lovelace as i128 + position as i128,
+            "combined.unrealized_pnl_lovelace",
+        ));
+    }
+    if !input.reward_withdrawals.is_empty() {
+        combined.ada_staking_rewards_lovelace = Some(withdrawn.values().sum());
+    }
 
+    // Without a valued position, the capital is the opening principal plus what went in less
+    // what came out; none is left at work once more came out than went in.
+    let opening = input.opening_principal_lovelace.unwrap_or(0);
+    let position = match input.current_ada_position {
+        Some(position) => position,
+        None => diag
+            .signed(
+                opening as i128 + total_in as i128 - total_out as i128,
+                "ada_position_lovelace",
+            )
+            .max(0) as u64,
+    };
     if let (Some(start), Some(end)) = (input.period_start_ts, input.period_end_ts) {
-        let period_secs = (end - start).max(1) as f64;
-        let position = input
-            .current_ada_position
-            .unwrap_or(total_in.saturating_sub(total_out))
-            .max(1) as f64;
-        let pnl = combined.net_pnl_lovelace.max(0) as f64;
-        combined.apr_pct = Some((pnl / position) * (365.25 * 24.0 * 3600.0 / period_secs) * 100.0);
+        let position = position.max(1) as i128;
+        let pnl = combined.net_pnl_lovelace.max(0) as i128;
+        let annualization = input.annualization;
+        combined.apr_pct = annualization.apr_pct(pnl, position, end - start);
+        if annualization.rate == RateKind::Apy {
+            combined.apy_pct = annualization.apy_pct(pnl, position, end - start);
+        }
+    }
+    combined.annualization = input.annualization;
+    let mut flows: Vec<(i64, f64)> = steps
+        .iter()
+        .map(|s| (s.timestamp, (s.gain_lovelace - s.external_lovelace) as f64))
+        .filter(|f| f.1 != 0.0)
+        .collect();
+    if let (Some(start), true) = (input.period_start_ts, opening > 0) {
+        flows.insert(0, (start, -(opening as f64)));
     }
+    let end = input
+        .period_end_ts
+        .or_else(|| flows.iter().map(|f| f.0).max());
+    if let (Some(end), true) = (end, position > 0) {
+        flows.push((end, position as f64));
+    }
+    combined.xirr_pct = xirr(&flows).map(|rate| round_micro(rate * 100.0));
+    if let (Some(fiat), Some(prices)) = (&mut fiat, prices) {
+        let end = input
+            .period_end_ts
+            .or_else(|| steps.last().map(|s| s.timestamp));
+        fiat.end_price_usd = end.and_then(|ts| prices.price_at(ts)).unwrap_or(0.0);
+        fiat.net_pnl_usd = fiat.total_out_usd - fiat.total_in_usd - fiat.protocol_fees_usd;
+        fiat.currency_effect_usd =
+            fiat.net_pnl_usd - combined.net_pnl_lovelace as f64 / 1_000_000.0 * fiat.end_price_usd;
+    }
+    let account = book.map(|book| {
+        book.finish(
+            end,
+            input.current_ada_position,
+            input
+                .period_start_ts
+                .zip(input.period_end_ts)
+                .map(|(s, e)| e - s),
+            input.annualization,
+        )
+    });
+    combined.twr = time_weighted_return(
+        &steps,
+        input.period_start_ts,
+        input.period_end_ts,
+        input.current_ada_position,
+        input.annualization,
+    );
+    // Principal before the first step: the closing position less what the period moved in.
+    let net_external: i128 = steps.iter().map(|s| s.external_lovelace as i128).sum();
+    let opening_capital = diag.signed(position as i128 - net_external, "opening capital");
+    combined.trailing = trailing_windows(
+        &steps,
+        input.period_start_ts,
+        input.period_end_ts,
+        opening_capital,
+        input.annualization,
+    );
+    combined.risk = risk_metrics(
+        &steps,
+        input.period_start_ts,
+        input.period_end_ts,
+        opening_capital,
+    );
+    combined.pending = input
+        .unclaimed_sp_lovelace
+        .map(|sp| pending_rewards(sp, &reward_claims, end));
+    combined.benchmark = input.staking_benchmark.as_ref().and_then(|rate| {
+        benchmark(
+            &steps,
+            input.period_start_ts,
+            input.period_end_ts,
+            opening_capital,
+            rate,
+        )
+    });
+
+    let dilution = dilution_model(&input.events.stability_pool);
+    let attribution = confidence.is_some().then(|| {
+        pnl_attribution(
+            combined.net_pnl_lovelace,
+            sp.total_realized_premium_lovelace,
+            dilution
+                .as_ref()
+                .and_then(|d| d.dilution_effect_lovelace)
+                .unwrap_or(0),
+            rob.total_premium_received_lovelace,
+            indy.total_rewards_lovelace
+                .saturating_add(indy.total_sp_premium_lovelace),
+            sp.withdrawal_gain_lovelace,
+            combined.protocol_fees_lovelace.unwrap_or(0),
+        )
+    });
 
     ComputedMetrics {
         stability_pool: sp,
         rob,
         indy_staking: indy,
         combined,
-        dilution: None,
+        attribution,
+        dilution,
+        cdp,
+        governance,
+        confidence,
+        fiat,
+        account,
+        lots: input
+            .cost_basis
+            .map(|method| track_lots(&input.events.stability_pool, method)),
+        statements: input.statements.map(|network| {
+            statements(
+                &steps,
+                input.period_start_ts,
+                input.period_end_ts,
+                opening_capital,
+                network,
+            )
+        }),
+        ledger: ledger(changes),
+        warnings: diag.warnings,
+        extra_metrics: BTreeMap::new(),
+    }
+}
+
+/// [`compute_metrics`], also running `hooks` over the events (in time order) and putting their
+/// outputs in `extra_metrics`.
+pub fn compute_metrics_with_hooks(
+    input: &ComputeInput,
+    hooks: &mut [&mut dyn MetricHook],
+) -> ComputedMetrics {
+    let mut metrics = compute_metrics(input);
+    let mut events: Vec<&Event> = input.events.all_events().collect();
+    events.sort_by(|a, b| {
+        let key = |e: &Event| (e.timestamp, e.slot, e.tx_hash.clone());
+        key(a).cmp(&key(b))
+    });
+    for ev in events {
+        for hook in hooks.iter_mut() {
+            hook.observe(ev);
+        }
+    }
+    for hook in hooks.iter_mut() {
+        let output = hook.finish(&metrics);
+        metrics
+            .extra_metrics
+            .insert(hook.name().to_string(), output);
+    }
+    metrics
+}
+
+/// P&L of the liquidations priced at the oracle (pro-rata ones, after
+/// [`crate::indigo::oracle::price_liquidations`]): ADA received minus the iAsset lost at the oracle
+/// price. Per-tx estimates carry no iAsset quantity and are left out.
+pub fn liquidation_pnl(events: &[Event]) -> Vec<LiquidationPnl> {
+    checked_liquidation_pnl(events, &mut Diagnostics::default())
+}
+
+fn checked_liquidation_pnl(events: &[Event], diag: &mut Diagnostics) -> Vec<LiquidationPnl> {
+    let mut liquidations: Vec<LiquidationPnl> = events
+        .iter()
+        .filter_map(|ev| match &ev.kind {
+            EventKind::StabilityPoolLiquidation {
+                iasset_burnt,
+                ada_received_lovelace,
+                iasset_lost_quantity: Some(lost),
+                oracle_price: Some(price),
+                ..
+            } => {
+                let cost = scale_lovelace(*lost, to_micro(*price));
+                Some(LiquidationPnl {
+                    tx_hash: ev.tx_hash.clone(),
+                    slot: ev.slot,
+                    iasset: iasset_burnt.clone(),
+                    ada_received_lovelace: *ada_received_lovelace,
+                    iasset_lost_quantity: *lost,
+                    oracle_price: *price,
+                    iasset_cost_lovelace: cost,
+                    pnl_lovelace: diag.signed(
+                        *ada_received_lovelace as i128 - cost as i128,
+                        "stability_pool.liquidations.pnl_lovelace",
+                    ),
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    liquidations.sort_by_key(|l| l.slot);
+    liquidations
+}
+
+/// Dilution of the wallet's SP share, from the pro-rata liquidations (those carrying the pool
+/// and wallet deposits); `None` without any.
+///
+/// Between two liquidations of a pool, the wallet's compounded deposit already reflects its own
+/// deposits and withdrawals. Without other depositors moving, the pool would be the others'
+/// part of the previous pool, compounded down by that liquidation's burn, plus the wallet's
+/// deposit. A bigger actual pool means others deposited and the wallet's share fell. Its premium
+/// then fell in proportion: the dilution effect of a liquidation is its realized premium times
+/// (actual pool / expected pool - 1), in fixed point. The first liquidation of each pool is the
+/// baseline.
+pub fn dilution_model(events: &[Event]) -> Option<DilutionModel> {
+    struct Seen {
+        pool: i128,
+        user: i128,
+        lost: i128,
+    }
+    let mut model = DilutionModel::default();
+    let mut last: HashMap<&str, Seen> = HashMap::new();
+    let (mut dilution, mut realized) = (0i128, 0u64);
+    let mut latest_user = 0u128;
+    let ordered = {
+        let mut ordered: Vec<&Event> = events.iter().collect();
+        ordered.sort_by_key(|e| e.slot.unwrap_or(0));
+        ordered
+    };
+    for event in ordered {
+        let EventKind::StabilityPoolLiquidation {
+            iasset_burnt,
+            realized_premium_lovelace,
+            pool_deposit_quantity: Some(pool),
+            user_deposit_quantity: Some(user),
+            iasset_lost_quantity: Some(lost),
+            ..
+        } = &event.kind
+        else {
+            continue;
+        };
+        let (Ok(pool_q), Ok(user_q), Ok(lost_q)) = (
+            i128::try_from(*pool),
+            i128::try_from(*user),
+            i128::try_from(*lost),
+        ) else {
+            continue;
+        };
+        if pool_q == 0 || user_q == 0 {
+            continue;
+        }
+        if let Some(prev) = last.get(iasset_burnt.as_str()) {
+            // The expected pool, and premium * (pool - expected) / expected, in integers.
+            let expected = mul_div(prev.pool - prev.user, prev.user - prev.lost, prev.user)
+                .and_then(|others| others.checked_add(user_q))
+                .filter(|&e| e > 0);
+            if let Some(term) = expected
+                .and_then(|e| mul_div(i128::from(*realized_premium_lovelace), pool_q - e, e))
+            {
+                dilution = dilution.saturating_add(term);
+            }
+        }
+        last.insert(
+            iasset_burnt,
+            Seen {
+                pool: pool_q,
+                user: user_q,
+                lost: lost_q,
+            },
+        );
+        realized = realized.saturating_add(*realized_premium_lovelace);
+        latest_user = *user;
+        model.share_history.push(SharePoint {
+            iasset: iasset_burnt.clone(),
+            slot: event.slot,
+            user_share_pct: ratio_pct(user_q, pool_q).unwrap_or(0.0),
+        });
+    }
+    let latest = model.share_history.last()?.user_share_pct;
+    let dilution = dilution.clamp(0, u64::MAX as i128) as u64;
+    if last.len() == 1 {
+        model.total_iasset_at_risk = Some(latest_user.to_string());
+        model.user_share_pct = Some(latest);
+    }
+    model.dilution_effect_lovelace = Some(dilution);
+    model.undiluted_premium_lovelace = Some(realized.saturating_add(dilution));
+    Some(model)
+}
+
+/// Warnings for [`ComputedMetrics::warnings`]. Totals are overflow-checked: one that would
+/// overflow, or go below zero, is clamped there and named here with its tx.
+#[derive(Default)]
+struct Diagnostics {
+    warnings: Vec<String>,
+}
+
+impl Diagnostics {
+    fn add(&mut self, total: &mut u64, amount: u64, field: &str, tx: &str) {
+        *total = total.checked_add(amount).unwrap_or_else(|| {
+            self.warn(format!("{} overflowed at tx {}; clamped", field, tx));
+            u64::MAX
+        });
+    }
+
+    fn sub(&mut self, total: &mut u64, amount: u64, field: &str, tx: &str) {
+        *total = total.checked_sub(amount).unwrap_or_else(|| {
+            self.warn(format!(
+                "{} went below zero at tx {}; clamped to 0",
+                field, tx
+            ));
+            0
+        });
+    }
+
+    /// `value` as a signed lovelace figure, clamped when it does not fit.
+    fn signed(&mut self, value: i128, field: &str) -> i64 {
+        i64::try_from(value).unwrap_or_else(|_| {
+            self.warn(format!("{} out of range ({}); clamped", field, value));
+            value.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+        })
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(message);
     }
 }
 
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+fn is_zero_i64(n: &i64) -> bool {
+    *n == 0
+}
+
+fn is_zero_f64(n: &f64) -> bool {
+    *n == 0.0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::indigo::{Event, EventKind};
+    use crate::compute::currency::Currency;
+    use crate::indigo::{Confidence, Event, EventKind, Provenance};
     use time::OffsetDateTime;
 
     #[test]
@@ -199,6 +1345,10 @@ mod tests {
                 ada_received_lovelace: 1_100_000,
                 realized_premium_lovelace: 100_000,
                 dilution_effect: None,
+                pool_deposit_quantity: None,
+                user_deposit_quantity: None,
+                iasset_lost_quantity: None,
+                oracle_price: None,
                 tx_hash: "abc".into(),
                 slot: Some(100),
             },
@@ -206,16 +1356,315 @@ mod tests {
             slot: Some(100),
             tx_hash: "abc".into(),
             extra: None,
+            confidence: Confidence::Heuristic,
+            provenance: Provenance::default(),
         });
         let input = ComputeInput {
             period_start_ts: Some(0),
             period_end_ts: Some(365 * 24 * 3600),
             current_ada_position: Some(1_000_000),
             events,
+            ..Default::default()
         };
         let m = compute_metrics(&input);
         assert!(m.combined.apr_pct.is_some());
         assert!(m.stability_pool.liquidation_count == 1);
+        // 1.1 ADA came back and 1 ADA is still in the position.
+        assert_eq!(m.combined.unrealized_pnl_lovelace, Some(2_100_000));
+    }
+
+    #[test]
+    fn sp_metrics_break_out_per_iasset() {
+        let event = |kind: EventKind| Event::at(0, "t", kind);
+        let liquidation = |iasset: &str, ada: u64, premium: u64| {
+            event(EventKind::StabilityPoolLiquidation {
+                iasset_burnt: iasset.into(),
+                ada_received_lovelace: ada,
+                realized_premium_lovelace: premium,
+                dilution_effect: None,
+                pool_deposit_quantity: None,
+                user_deposit_quantity: None,
+                iasset_lost_quantity: None,
+                oracle_price: None,
+                tx_hash: "t".into(),
+                slot: None,
+            })
+        };
+        let stability_pool = vec![
+            event(EventKind::StabilityPoolDeposit {
+                amount_lovelace: 10_000_000,
+                iasset_amount: Some("f0ff$69425443".into()),
+                iasset_quantity: None,
+                tx_hash: "t".into(),
+            }),
+            liquidation("f0ff$69425443", 11_000_000, 1_000_000),
+            liquidation("f0ff$69555344", 2_200_000, 200_000),
+            liquidation("f0ff$69555344", 1_100_000, 100_000),
+        ];
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let by_asset = &m.stability_pool.by_asset;
+        assert_eq!(by_asset.len(), 2);
+        let ibtc = &by_asset["f0ff$69425443"];
+        assert_eq!(ibtc.liquidation_count, 1);
+        assert_eq!(ibtc.net_pnl_lovelace, 1_000_000);
+        let iusd = &by_asset["f0ff$69555344"];
+        assert_eq!(iusd.liquidation_count, 2);
+        assert_eq!(iusd.realized_premium_lovelace, 300_000);
+        assert_eq!(iusd.net_pnl_lovelace, 3_300_000);
+    }
+
+    #[test]
+    fn oracle_priced_liquidations_keep_their_losses() {
+        let liquidation = |slot: u64, lost: Option<u128>, price: Option<f64>| {
+            Event::at(
+                0,
+                &format!("liq{slot}"),
+                EventKind::StabilityPoolLiquidation {
+                    iasset_burnt: "f0ff$69555344".into(),
+                    ada_received_lovelace: 25_000_000,
+                    realized_premium_lovelace: 0,
+                    dilution_effect: None,
+                    pool_deposit_quantity: None,
+                    user_deposit_quantity: None,
+                    iasset_lost_quantity: lost,
+                    oracle_price: price,
+                    tx_hash: format!("liq{slot}"),
+                    slot: Some(slot),
+                },
+            )
+            .with_slot(Some(slot))
+        };
+        // 10 iUSD at 2 ADA is a 5 ADA gain; at 3 ADA a 5 ADA loss. The unpriced one is left out.
+        let stability_pool = vec![
+            liquidation(200, Some(10_000_000), Some(3.0)),
+            liquidation(100, Some(10_000_000), Some(2.0)),
+            liquidation(300, None, None),
+        ];
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let sp = &m.stability_pool;
+        assert_eq!(sp.liquidations.len(), 2);
+        assert_eq!(sp.liquidations[0].tx_hash, "liq100");
+        assert_eq!(sp.liquidations[0].iasset_cost_lovelace, 20_000_000);
+        assert_eq!(sp.liquidations[1].pnl_lovelace, -5_000_000);
+        assert_eq!(sp.liquidation_pnl_lovelace, Some(0));
+    }
+
+    #[test]
+    fn opening_principal_is_the_capital_after_a_claim() {
+        let event = |kind: EventKind, ts: i64, tx_hash: &str| Event::at(ts, tx_hash, kind);
+        let reward = |ts, tx: &str| {
+            event(
+                EventKind::IndyStakingReward {
+                    amount_lovelace: 1_000_000,
+                    epoch: None,
+                    indy_quantity: None,
+                    tx_hash: tx.into(),
+                },
+                ts,
+                tx,
+            )
+        };
+        let mut events = IndigoEvents {
+            stability_pool: vec![event(
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace: 100_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: "in".into(),
+                },
+                0,
+                "in",
+            )],
+            indy_staking: vec![reward(100, "claim"), reward(200, "after")],
+            ..Default::default()
+        };
+        let ledger = compute_metrics(&ComputeInput {
+            events: events.clone(),
+            ..Default::default()
+        })
+        .ledger;
+        assert_eq!(
+            crate::compute::principal_after(&ledger, "claim"),
+            100_000_000
+        );
+        let claim = events.indy_staking[0].clone();
+        events.retain_after(&claim);
+        let year = 365 * 86_400 + 86_400 / 4;
+        let input = |opening| ComputeInput {
+            events: events.clone(),
+            period_start_ts: Some(100),
+            period_end_ts: Some(100 + year),
+            opening_principal_lovelace: opening,
+            ..Default::default()
+        };
+        // 1 ADA on the 100 ADA still deposited (less the 1 ADA paid out), not on 1 lovelace.
+        let apr = compute_metrics(&input(Some(100_000_000))).combined.apr_pct;
+        assert!((apr.unwrap() - 100.0 / 99.0).abs() < 1e-6);
+        let apr = compute_metrics(&input(None)).combined.apr_pct;
+        assert!(apr.unwrap() > 1e6);
+    }
+
+    #[test]
+    fn cancelled_order_takes_its_refund_out_of_the_account_total() {
+        use crate::compute::pricing::{PricePoint, PriceSeries};
+        let event = |kind: EventKind, ts: i64, tx_hash: &str| Event::at(ts, tx_hash, kind);
+        let rob = vec![
+            event(
+                EventKind::RobOrderPlace {
+                    order_id: Some("o1".into()),
+                    amount_lovelace: 100_000_000,
+                    tx_hash: "place".into(),
+                    slot: None,
+                },
+                0,
+                "place",
+            ),
+            event(
+                EventKind::RobOrderCancel {
+                    order_id: Some("o1".into()),
+                    returned_lovelace: 100_000_000,
+                    tx_hash: "cancel".into(),
+                    slot: None,
+                },
+                100,
+                "cancel",
+            ),
+        ];
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                rob,
+                ..Default::default()
+            },
+            currency: Some(AccountCurrency {
+                currency: Currency::Usd,
+                prices: PriceSeries::new([PricePoint {
+                    timestamp: 0,
+                    price: 0.5,
+                }]),
+            }),
+            ..Default::default()
+        });
+        assert_eq!(m.combined.total_ada_in_lovelace, 0);
+        let account = m.account.unwrap();
+        assert_eq!((account.total_in, account.net_pnl), (0.0, 0.0));
+    }
+
+    #[test]
+    fn usd_values_each_flow_at_its_own_price() {
+        use crate::compute::pricing::{PricePoint, PriceSeries};
+        let event = |kind: EventKind, ts: i64, tx_hash: &str| Event::at(ts, tx_hash, kind);
+        let stability_pool = vec![
+            event(
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace: 100_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: "in".into(),
+                },
+                0,
+                "in",
+            ),
+            event(
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 110_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: "out".into(),
+                },
+                1_000,
+                "out",
+            ),
+        ];
+        let prices = PriceSeries::new([
+            PricePoint {
+                timestamp: 0,
+                price: 0.5,
+            },
+            PricePoint {
+                timestamp: 900,
+                price: 1.0,
+            },
+        ]);
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool,
+                ..Default::default()
+            },
+            ada_usd: Some(prices.clone()),
+            cost_basis: None,
+            annualization: Annualization::default(),
+            staking_benchmark: None,
+            statements: None,
+            unclaimed_sp_lovelace: None,
+            currency: Some(AccountCurrency {
+                currency: Currency::Usd,
+                prices,
+            }),
+            ..Default::default()
+        });
+        // In USD as the currency of account, the totals are the per-event USD ones.
+        let account = m.account.unwrap();
+        assert_eq!((account.total_in, account.net_pnl), (50.0, 60.0));
+        assert_eq!(account.yield_earned, 0.0);
+        let fiat = m.fiat.unwrap();
+        assert_eq!(fiat.total_in_usd, 50.0);
+        assert_eq!(fiat.total_out_usd, 110.0);
+        assert_eq!(fiat.net_pnl_usd, 60.0);
+        // 10 ADA of PnL is 10 USD at the end price; ADA doubling while deposited made the rest.
+        assert_eq!(fiat.currency_effect_usd, 50.0);
+        assert_eq!(fiat.event_prices["in"], 0.5);
+        assert!(compute_metrics(&ComputeInput::default()).fiat.is_none());
+    }
+
+    #[test]
+    fn xirr_weighs_deposits_by_time_at_work() {
+        const YEAR: i64 = 365 * 24 * 3600;
+        let event = |kind: EventKind, ts: i64| Event::at(ts, "t", kind);
+        let deposit = |amount_lovelace| EventKind::StabilityPoolDeposit {
+            amount_lovelace,
+            iasset_amount: None,
+            iasset_quantity: None,
+            tx_hash: "t".into(),
+        };
+        let stability_pool = vec![
+            event(deposit(1_000_000_000), 0),
+            event(deposit(1_000_000_000), YEAR),
+            event(
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 2_150_000_000,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: "t".into(),
+                },
+                2 * YEAR,
+            ),
+        ];
+        let events = IndigoEvents {
+            stability_pool,
+            ..Default::default()
+        };
+        let m = compute_metrics(&ComputeInput {
+            period_start_ts: Some(0),
+            period_end_ts: Some(2 * YEAR),
+            current_ada_position: Some(0),
+            events,
+            ..Default::default()
+        });
+        // 1000·x² + 1000·x = 2150 with x = 1 + r.
+        let xirr = m.combined.xirr_pct.unwrap();
+        assert!((xirr - 4.919_333).abs() < 1e-4, "{xirr}");
     }
 
     #[test]
@@ -225,38 +1674,40 @@ mod tests {
             kind: EventKind::StabilityPoolDeposit {
                 amount_lovelace: 100,
                 iasset_amount: None,
+                iasset_quantity: None,
                 tx_hash: "a".into(),
             },
             timestamp: OffsetDateTime::from_unix_timestamp(1).unwrap(),
             slot: Some(1),
             tx_hash: "a".into(),
             extra: None,
+            confidence: Confidence::Heuristic,
+            provenance: Provenance::default(),
         });
         a.stability_pool.push(Event {
             kind: EventKind::StabilityPoolWithdraw {
                 amount_lovelace: 50,
                 iasset_amount: None,
+                iasset_quantity: None,
                 tx_hash: "b".into(),
             },
             timestamp: OffsetDateTime::from_unix_timestamp(2).unwrap(),
             slot: Some(2),
             tx_hash: "b".into(),
             extra: None,
+            confidence: Confidence::Heuristic,
+            provenance: Provenance::default(),
         });
         let mut b = IndigoEvents::default();
         b.stability_pool.push(a.stability_pool[1].clone());
         b.stability_pool.push(a.stability_pool[0].clone());
         let in1 = ComputeInput {
             events: a,
-            period_start_ts: None,
-            period_end_ts: None,
-            current_ada_position: None,
+            ..Default::default()
         };
         let in2 = ComputeInput {
             events: b,
-            period_start_ts: None,
-            period_end_ts: None,
-            current_ada_position: None,
+            ..Default::default()
         };
         let m1 = compute_metrics(&in1);
         let m2 = compute_metrics(&in2);
@@ -270,4 +1721,668 @@ mod tests {
         );
         assert_eq!(m1.combined.net_pnl_lovelace, m2.combined.net_pnl_lovelace);
     }
+
+    #[test]
+    fn avg_premium_is_weighted_by_fill_in_any_order() {
+        let fill = |tx: &str, filled_lovelace, premium_pct| {
+            Event::at(
+                1,
+                tx,
+                EventKind::RobOrderFill {
+                    order_id: None,
+                    filled_lovelace,
+                    premium_pct,
+                    reimbursement_pct: None,
+                    premium_estimated: false,
+                    remaining_lovelace: None,
+                    tx_hash: tx.into(),
+                    slot: Some(1),
+                },
+            )
+        };
+        let fills = [
+            fill("a", 100_000_000, Some(2.0)),
+            fill("b", 300_000_000, Some(1.0)),
+            fill("c", 100_000_000, Some(3.0)),
+            fill("d", 500_000_000, None),
+        ];
+        let avg = |order: &[usize]| {
+            let events = IndigoEvents {
+                rob: order.iter().map(|&i| fills[i].clone()).collect(),
+                ..Default::default()
+            };
+            compute_metrics(&ComputeInput {
+                events,
+                ..Default::default()
+            })
+            .rob
+            .avg_premium_pct
+        };
+        // (100·2 + 300·1 + 100·3) / 500; the fill without a premium carries no weight.
+        assert_eq!(avg(&[0, 1, 2, 3]), Some(1.6));
+        assert_eq!(avg(&[2, 3, 1, 0]), Some(1.6));
+        assert_eq!(avg(&[3]), None);
+    }
+
+    #[test]
+    fn reward_withdrawals_are_not_indy_rewards() {
+        let reward = |tx: &str, amount| {
+            Event::at(
+                1,
+                tx,
+                EventKind::IndyStakingReward {
+                    amount_lovelace: amount,
+                    epoch: None,
+                    indy_quantity: None,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let mut events = IndigoEvents::default();
+        // "a" is only a staking withdrawal; "b" mixes one with 300 of INDY reward.
+        events.indy_staking.push(reward("a", 500));
+        events.indy_staking.push(reward("b", 1_000));
+        let withdrawal = |tx: &str, amount| RewardWithdrawal {
+            tx_hash: tx.into(),
+            stake_address: "stake1u9".into(),
+            amount_lovelace: amount,
+        };
+        let input = ComputeInput {
+            events,
+            reward_withdrawals: vec![withdrawal("a", 500), withdrawal("b", 700)],
+            ..Default::default()
+        };
+        let m = compute_metrics(&input);
+        assert_eq!(m.indy_staking.total_rewards_lovelace, 300);
+        assert_eq!(m.indy_staking.reward_tx_count, 1);
+        assert_eq!(m.combined.total_ada_out_lovelace, 300);
+        assert_eq!(m.combined.ada_staking_rewards_lovelace, Some(1_200));
+        assert_eq!(
+            compute_metrics(&ComputeInput::default())
+                .combined
+                .ada_staking_rewards_lovelace,
+            None
+        );
+    }
+
+    #[test]
+    fn cdp_flows_stay_out_of_combined() {
+        let event = |kind| Event::at(1, "c", kind);
+        let cdp = vec![
+            event(EventKind::CdpOpen {
+                collateral_lovelace: 100_000_000,
+                tx_hash: "c".into(),
+            }),
+            event(EventKind::CdpMint {
+                iasset: "f0ff$69555344".into(),
+                quantity: 40,
+                tx_hash: "c".into(),
+            }),
+            event(EventKind::CdpFee {
+                amount_lovelace: 200_000,
+                tx_hash: "c".into(),
+            }),
+            event(EventKind::CdpClose {
+                collateral_returned_lovelace: 100_000_000,
+                tx_hash: "c".into(),
+            }),
+        ];
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                cdp,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let cdp = m.cdp.unwrap();
+        assert_eq!(cdp.collateral_posted_lovelace, 100_000_000);
+        assert_eq!(cdp.collateral_withdrawn_lovelace, 100_000_000);
+        assert_eq!(cdp.iasset_minted["f0ff$69555344"], 40);
+        assert_eq!(cdp.iasset_debt["f0ff$69555344"], 40);
+        assert_eq!(cdp.fees_paid_lovelace, 200_000);
+        assert_eq!((cdp.open_count, cdp.close_count), (1, 1));
+        assert_eq!(m.combined.total_ada_in_lovelace, 0);
+        assert!(compute_metrics(&ComputeInput::default()).cdp.is_none());
+        assert!(compute_metrics(&ComputeInput::default())
+            .governance
+            .is_none());
+    }
+
+    #[test]
+    fn cancelled_order_is_netted_out_of_ada_in() {
+        let event = |tx: &str, kind| Event::at(1, tx, kind);
+        let rob = vec![
+            event(
+                "p",
+                EventKind::RobOrderPlace {
+                    order_id: Some("p#0".into()),
+                    amount_lovelace: 100_000_000,
+                    tx_hash: "p".into(),
+                    slot: Some(1),
+                },
+            ),
+            event(
+                "c",
+                EventKind::RobOrderCancel {
+                    order_id: Some("p#0".into()),
+                    returned_lovelace: 100_000_000,
+                    tx_hash: "c".into(),
+                    slot: Some(2),
+                },
+            ),
+            // Placed before the period: not in ADA in, so nothing to net out.
+            event(
+                "c2",
+                EventKind::RobOrderCancel {
+                    order_id: Some("old#1".into()),
+                    returned_lovelace: 5_000_000,
+                    tx_hash: "c2".into(),
+                    slot: Some(3),
+                },
+            ),
+        ];
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                rob,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(m.rob.total_placed_lovelace, 0);
+        assert_eq!(m.rob.total_cancelled_lovelace, Some(105_000_000));
+        assert_eq!(m.combined.total_ada_in_lovelace, 0);
+        assert_eq!(m.combined.total_ada_out_lovelace, 0);
+        assert_eq!(m.rob.orders.len(), 2);
+    }
+
+    #[test]
+    fn outstanding_debt_beyond_an_i64_is_clamped_with_a_warning() {
+        let cdp = CdpMetrics {
+            iasset_minted: [("iUSD".to_string(), u64::MAX), ("iBTC".to_string(), 5)].into(),
+            iasset_burnt: [("iBTC".to_string(), 5)].into(),
+            ..Default::default()
+        };
+        let mut diag = Diagnostics::default();
+        let debt = cdp.outstanding_debt(&mut diag);
+        assert_eq!(debt, [("iUSD".to_string(), i64::MAX)].into());
+        assert_eq!(diag.warnings.len(), 1);
+        assert!(diag.warnings[0].starts_with("cdp.iasset_debt[iUSD] out of range"));
+    }
+
+    #[test]
+    fn inconsistent_inputs_leave_warnings() {
+        let deposit = |tx: &str, amount_lovelace| {
+            Event::at(
+                0,
+                tx,
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let liquidation = Event {
+            kind: EventKind::StabilityPoolLiquidation {
+                iasset_burnt: "x".into(),
+                ada_received_lovelace: 2_000_000,
+                realized_premium_lovelace: 0,
+                dilution_effect: None,
+                pool_deposit_quantity: None,
+                user_deposit_quantity: None,
+                iasset_lost_quantity: None,
+                oracle_price: None,
+                tx_hash: "liq".into(),
+                slot: None,
+            },
+            ..deposit("liq", 0)
+        };
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![deposit("a", 10_000_000), liquidation.clone()],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        // The net is negative, not floored at 0.
+        assert_eq!(
+            m.stability_pool.net_ada_from_liquidations_lovelace,
+            -8_000_000
+        );
+        assert!(m.warnings.is_empty());
+
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![deposit("a", u64::MAX), deposit("b", 1)],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(m.stability_pool.total_deposits_lovelace, u64::MAX);
+        assert!(m
+            .warnings
+            .iter()
+            .any(|w| w == "stability_pool.total_deposits_lovelace overflowed at tx b; clamped"));
+
+        // An iAsset loss costing more than an i64 of lovelace clamps the P&L, with a warning.
+        let mut costly = liquidation.clone();
+        if let EventKind::StabilityPoolLiquidation {
+            iasset_lost_quantity,
+            oracle_price,
+            ..
+        } = &mut costly.kind
+        {
+            *iasset_lost_quantity = Some(u64::MAX as u128);
+            *oracle_price = Some(1.0);
+        }
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![costly],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(m.stability_pool.liquidation_pnl_lovelace, Some(i64::MIN));
+        assert!(m
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("stability_pool.liquidations.pnl_lovelace out of range")));
+    }
+
+    #[test]
+    fn hooks_see_the_events_in_time_order_and_land_in_extra_metrics() {
+        /// The txs in the order seen, and the SP deposits the built-in metrics counted.
+        #[derive(Default)]
+        struct Order(Vec<String>);
+        impl MetricHook for Order {
+            fn name(&self) -> &str {
+                "order"
+            }
+            fn observe(&mut self, event: &Event) {
+                self.0.push(event.tx_hash.clone());
+            }
+            fn finish(&mut self, metrics: &ComputedMetrics) -> serde_json::Value {
+                serde_json::json!({
+                    "txs": self.0,
+                    "deposits": metrics.stability_pool.total_deposits_lovelace,
+                })
+            }
+        }
+        let event = |ts: i64, tx: &str, amount_lovelace| {
+            Event::at(
+                ts,
+                tx,
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let input = ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![event(20, "late", 1_000_000)],
+                rob: vec![Event {
+                    kind: EventKind::RobOrderPlace {
+                        order_id: None,
+                        amount_lovelace: 5_000_000,
+                        tx_hash: "early".into(),
+                        slot: None,
+                    },
+                    ..event(10, "early", 0)
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut order = Order::default();
+        let m = compute_metrics_with_hooks(&input, &mut [&mut order]);
+        assert_eq!(
+            m.extra_metrics["order"],
+            serde_json::json!({"txs": ["early", "late"], "deposits": 1_000_000})
+        );
+        assert!(compute_metrics(&input).extra_metrics.is_empty());
+    }
+
+    #[test]
+    fn partial_withdrawal_realizes_a_gain_over_its_share_of_the_cost() {
+        let event = |ts: i64, tx: &str, kind| Event::at(ts, tx, kind);
+        // Half the iAsset out for 60 ADA: 50 of cost basis, 10 of gain, 50 still at work.
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![
+                    event(
+                        10,
+                        "dep",
+                        EventKind::StabilityPoolDeposit {
+                            amount_lovelace: 100_000_000,
+                            iasset_amount: None,
+                            iasset_quantity: Some(1_000),
+                            tx_hash: "dep".into(),
+                        },
+                    ),
+                    event(
+                        20,
+                        "wd",
+                        EventKind::StabilityPoolWithdraw {
+                            amount_lovelace: 60_000_000,
+                            iasset_amount: None,
+                            iasset_quantity: Some(500),
+                            tx_hash: "wd".into(),
+                        },
+                    ),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let sp = &m.stability_pool;
+        assert_eq!(sp.withdrawals_cost_basis_lovelace, 50_000_000);
+        assert_eq!(sp.withdrawal_gain_lovelace, 10_000_000);
+        assert_eq!(m.ledger[1].sp_balance_lovelace, 50_000_000);
+        let a = m.attribution.unwrap();
+        assert_eq!(
+            (a.withdrawal_gain_lovelace, a.principal_lovelace),
+            (10_000_000, -50_000_000)
+        );
+    }
+
+    #[test]
+    fn ledger_runs_in_time_order_across_kinds() {
+        let event = |ts: i64, tx: &str, kind| Event::at(ts, tx, kind);
+        let events = IndigoEvents {
+            stability_pool: vec![
+                event(
+                    10,
+                    "dep",
+                    EventKind::StabilityPoolDeposit {
+                        amount_lovelace: 100_000_000,
+                        iasset_amount: None,
+                        iasset_quantity: None,
+                        tx_hash: "dep".into(),
+                    },
+                ),
+                event(
+                    30,
+                    "liq",
+                    EventKind::StabilityPoolLiquidation {
+                        iasset_burnt: "x".into(),
+                        ada_received_lovelace: 22_000_000,
+                        realized_premium_lovelace: 2_000_000,
+                        dilution_effect: None,
+                        pool_deposit_quantity: None,
+                        user_deposit_quantity: None,
+                        iasset_lost_quantity: None,
+                        oracle_price: None,
+                        tx_hash: "liq".into(),
+                        slot: Some(30),
+                    },
+                ),
+            ],
+            rob: vec![event(
+                20,
+                "place",
+                EventKind::RobOrderPlace {
+                    order_id: None,
+                    amount_lovelace: 50_000_000,
+                    tx_hash: "place".into(),
+                    slot: Some(20),
+                },
+            )],
+            ..Default::default()
+        };
+        let m = compute_metrics(&ComputeInput {
+            events,
+            ..Default::default()
+        });
+        let rows: Vec<(&str, i64, i64, u64)> = m
+            .ledger
+            .iter()
+            .map(|e| {
+                (
+                    e.kind.as_str(),
+                    e.sp_balance_lovelace,
+                    e.rob_open_lovelace,
+                    e.cumulative_rewards_lovelace,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("stability_pool_deposit", 100_000_000, 0, 0),
+                ("rob_order_place", 100_000_000, 50_000_000, 0),
+                (
+                    "stability_pool_liquidation",
+                    80_000_000,
+                    50_000_000,
+                    2_000_000
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn indy_rewards_report_quantity_and_ada_equivalent() {
+        let reward = |tx: &str, ada, indy| {
+            Event::at(
+                1,
+                tx,
+                EventKind::IndyStakingReward {
+                    amount_lovelace: ada,
+                    epoch: None,
+                    indy_quantity: indy,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                indy_staking: vec![
+                    reward("a", 0, Some(12_500_000)),
+                    reward("b", 1_000_000, Some(2_500_000)),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut indy = m.indy_staking;
+        assert_eq!(indy.reward_tx_count, 2);
+        assert_eq!(indy.total_indy_quantity, Some(15_000_000));
+        assert_eq!(indy.total_indy_ada_equivalent_lovelace, None);
+        // 15 INDY at 0.8 ADA.
+        indy.price_indy(0.8);
+        assert_eq!(indy.total_indy_ada_equivalent_lovelace, Some(12_000_000));
+        assert_eq!(m.combined.total_ada_out_lovelace, 1_000_000);
+    }
+
+    #[test]
+    fn governance_participation_is_counted() {
+        let event = |kind| Event::at(1, "g", kind);
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                governance: vec![
+                    event(EventKind::GovStakeLock {
+                        indy_quantity: 500,
+                        tx_hash: "g".into(),
+                    }),
+                    event(EventKind::GovVote {
+                        poll: "p#0".into(),
+                        tx_hash: "g".into(),
+                    }),
+                    event(EventKind::GovProposalDeposit {
+                        indy_quantity: 1_000,
+                        tx_hash: "g".into(),
+                    }),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(
+            m.governance,
+            Some(GovernanceMetrics {
+                indy_locked: 500,
+                indy_unlocked: 0,
+                vote_count: 1,
+                proposal_count: 1,
+                proposal_deposits_indy: 1_000,
+            })
+        );
+        assert_eq!(m.combined.total_ada_in_lovelace, 0);
+    }
+
+    #[test]
+    fn cdp_redemptions_are_tracked() {
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                cdp: vec![Event::at(
+                    1,
+                    "r",
+                    EventKind::CdpRedeemedAgainst {
+                        iasset: "f0ff$69555344".into(),
+                        redeemed_quantity: 10,
+                        ada_returned_lovelace: 25_000_000,
+                        tx_hash: "r".into(),
+                    },
+                )],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let cdp = m.cdp.unwrap();
+        assert_eq!(cdp.redemption_count, 1);
+        assert_eq!(cdp.collateral_redeemed_lovelace, 25_000_000);
+        assert_eq!(cdp.iasset_redeemed["f0ff$69555344"], 10);
+        // The CDP was opened before the period: only the change in debt is known.
+        assert_eq!(cdp.iasset_debt["f0ff$69555344"], -10);
+        assert_eq!(cdp.collateral_withdrawn_lovelace, 0);
+        assert_eq!(m.combined.total_ada_out_lovelace, 0);
+    }
+
+    #[test]
+    fn protocol_fees_reduce_net_pnl() {
+        let event = |kind| Event::at(1, "f", kind);
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                indy_staking: vec![event(EventKind::IndyStakingReward {
+                    amount_lovelace: 3_000_000,
+                    epoch: None,
+                    indy_quantity: None,
+                    tx_hash: "f".into(),
+                })],
+                protocol_fees: vec![event(EventKind::ProtocolFeePaid {
+                    amount_lovelace: 500_000,
+                    tx_hash: "f".into(),
+                })],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        assert_eq!(m.combined.protocol_fees_lovelace, Some(500_000));
+        assert_eq!(m.combined.net_pnl_lovelace, 2_500_000);
+        let a = m.attribution.unwrap();
+        assert_eq!(
+            (
+                a.indy_rewards_lovelace,
+                a.fees_lovelace,
+                a.principal_lovelace
+            ),
+            (3_000_000, -500_000, 0)
+        );
+        let empty = compute_metrics(&ComputeInput::default());
+        assert_eq!(empty.combined.protocol_fees_lovelace, None);
+        assert_eq!(empty.attribution, None);
+    }
+
+    #[test]
+    fn premium_is_split_by_confidence() {
+        let liquidation = |premium, confidence| {
+            Event::at(
+                1,
+                "l",
+                EventKind::StabilityPoolLiquidation {
+                    iasset_burnt: "x".into(),
+                    ada_received_lovelace: 10_000_000,
+                    realized_premium_lovelace: premium,
+                    dilution_effect: None,
+                    pool_deposit_quantity: None,
+                    user_deposit_quantity: None,
+                    iasset_lost_quantity: None,
+                    oracle_price: None,
+                    tx_hash: "l".into(),
+                    slot: Some(1),
+                },
+            )
+            .with_confidence(confidence)
+        };
+        let m = compute_metrics(&ComputeInput {
+            events: IndigoEvents {
+                stability_pool: vec![
+                    liquidation(300_000, Confidence::High),
+                    liquidation(100_000, Confidence::Heuristic),
+                ],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let split = m.confidence.unwrap();
+        assert_eq!(split.premium.high_lovelace, 300_000);
+        assert_eq!(split.premium.heuristic_pct(), Some(25.0));
+        assert_eq!(split.ada_out.total_lovelace(), 20_000_000);
+        assert!(compute_metrics(&ComputeInput::default())
+            .confidence
+            .is_none());
+    }
+
+    #[test]
+    fn dilution_follows_other_depositors() {
+        let liquidation = |slot, pool, user, lost, premium| {
+            Event::at(
+                1,
+                "l",
+                EventKind::StabilityPoolLiquidation {
+                    iasset_burnt: "f0ff$69555344".into(),
+                    ada_received_lovelace: 0,
+                    realized_premium_lovelace: premium,
+                    dilution_effect: None,
+                    pool_deposit_quantity: Some(pool),
+                    user_deposit_quantity: Some(user),
+                    iasset_lost_quantity: Some(lost),
+                    oracle_price: None,
+                    tx_hash: "l".into(),
+                    slot: Some(slot),
+                },
+            )
+            .with_slot(Some(slot))
+        };
+        // The first liquidation burns a quarter of a 2,000 pool, leaving the wallet 375 of
+        // 1,500. Others then deposit 500: the wallet holds 375 of 2,000 instead of 1,500.
+        let events = IndigoEvents {
+            stability_pool: vec![
+                liquidation(2, 2_000, 375, 75, 3_000_000),
+                liquidation(1, 2_000, 500, 125, 2_000_000),
+            ],
+            ..Default::default()
+        };
+        let dilution = compute_metrics(&ComputeInput {
+            events,
+            ..Default::default()
+        })
+        .dilution
+        .unwrap();
+        assert_eq!(dilution.dilution_effect_lovelace, Some(1_000_000));
+        assert_eq!(dilution.undiluted_premium_lovelace, Some(6_000_000));
+        assert_eq!(dilution.user_share_pct, Some(18.75));
+        assert_eq!(dilution.total_iasset_at_risk.as_deref(), Some("375"));
+        let shares: Vec<f64> = dilution
+            .share_history
+            .iter()
+            .map(|p| p.user_share_pct)
+            .collect();
+        assert_eq!(shares, vec![25.0, 18.75]);
+        assert!(compute_metrics(&ComputeInput::default()).dilution.is_none());
+    }
 }
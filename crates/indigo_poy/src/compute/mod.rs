@@ -1,9 +1,34 @@
-//! PnL, APR, realized premium, dilution math.
+//! PnL and its attribution, APR / APY, XIRR, TWR, realized premium, dilution math, cost-basis lots, tax records,
+//! statements, currency of account, running ledger, custom metric hooks.
 
+pub mod annualize;
+mod attribution;
+pub mod benchmark;
+pub mod currency;
+pub mod fixed;
+mod hooks;
+mod ledger;
+pub mod lots;
 mod metrics;
+pub mod pending;
+pub mod pricing;
+mod risk;
+pub mod statement;
+pub mod tax;
+mod twr;
+mod window;
+mod xirr;
 
-pub use metrics::DilutionModel;
+pub use attribution::{pnl_attribution, PnlAttribution};
+pub use hooks::MetricHook;
+pub use ledger::{principal_after, LedgerEntry};
 pub use metrics::{
-    compute_metrics, CombinedMetrics, ComputeInput, ComputedMetrics, IndyStakingMetrics,
-    RobMetrics, StabilityPoolMetrics,
+    compute_metrics, compute_metrics_with_hooks, CdpMetrics, CombinedMetrics, ComputeInput,
+    ComputedMetrics, ConfidenceMetrics, ConfidenceSplit, FiatMetrics, GovernanceMetrics,
+    IndyStakingMetrics, RobMetrics, SpAssetMetrics, StabilityPoolMetrics,
 };
+pub use metrics::{dilution_model, liquidation_pnl, DilutionModel, LiquidationPnl, SharePoint};
+pub use risk::{risk_metrics, RiskMetrics};
+pub use twr::{time_weighted_return, FlowStep, TimeWeightedReturn, TwrPeriod};
+pub use window::{trailing_windows, WindowMetrics, TRAILING_WINDOW_DAYS};
+pub use xirr::xirr;
@@ -0,0 +1,65 @@
+//! Rewards accrued but not yet claimed, an estimate kept apart from the realized figures.
+//!
+//! The SP figure is read off the open accounts (`--mark-to-market`): the liquidation ADA each
+//! has earned and not withdrawn. INDY staking rewards are only known once claimed, so their
+//! pending share is extrapolated: the ADA the claims after the first paid, over the time they
+//! covered, run on from the last claim to the period end.
+
+use super::fixed::mul_div;
+use serde::{Deserialize, Serialize};
+
+/// Rewards earned and not claimed at the period end. Not part of any realized figure.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PendingRewards {
+    /// Liquidation ADA on the open SP accounts; already in `open_position_lovelace`.
+    pub sp_lovelace: u64,
+    /// INDY staking rewards accrued since the last claim, at the claims' past rate. `None`
+    /// with fewer than two claims to take a rate from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indy_staking_estimated_lovelace: Option<u64>,
+    pub total_lovelace: u64,
+}
+
+/// Pending rewards at `period_end`, from the open accounts' `sp_lovelace` and the INDY
+/// staking `claims` (timestamp, ADA), in any order.
+pub fn pending_rewards(
+    sp_lovelace: u64,
+    claims: &[(i64, u64)],
+    period_end: Option<i64>,
+) -> PendingRewards {
+    let mut claims = claims.to_vec();
+    claims.sort_unstable();
+    // The first claim paid for time before the history began, so the rate starts after it.
+    let estimate = match (claims.first(), claims.last(), period_end) {
+        (Some(&(first, _)), Some(&(last, _)), Some(end)) if last > first => {
+            let paid: i128 = claims[1..].iter().map(|&(_, ada)| ada as i128).sum();
+            mul_div(paid, (end - last).max(0) as i128, (last - first) as i128)
+                .map(|ada| ada.clamp(0, u64::MAX as i128) as u64)
+        }
+        _ => None,
+    };
+    PendingRewards {
+        sp_lovelace,
+        indy_staking_estimated_lovelace: estimate,
+        total_lovelace: sp_lovelace.saturating_add(estimate.unwrap_or(0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indy_estimate_runs_the_claim_rate_on() {
+        const EPOCH: i64 = 432_000;
+        // 3 ADA claimed per epoch after the first claim; two epochs since the last one.
+        let claims = [(2 * EPOCH, 3_000_000), (0, 9_000_000), (EPOCH, 3_000_000)];
+        let pending = pending_rewards(1_000_000, &claims, Some(4 * EPOCH));
+        assert_eq!(pending.indy_staking_estimated_lovelace, Some(6_000_000));
+        assert_eq!(pending.total_lovelace, 7_000_000);
+
+        let one = pending_rewards(1_000_000, &claims[..1], Some(4 * EPOCH));
+        assert_eq!(one.indy_staking_estimated_lovelace, None);
+        assert_eq!(one.total_lovelace, 1_000_000);
+    }
+}
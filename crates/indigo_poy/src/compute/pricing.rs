@@ -0,0 +1,134 @@
+//! Historical prices: a dated ADA/USD series, to value each event at the price of its time.
+//!
+//! A price file is CSV, one `timestamp,price` per line (USD per ADA). The timestamp is Unix
+//! seconds, RFC 3339, or a `YYYY-MM-DD` date (midnight UTC). Blank lines, `#` comments, and a
+//! header line are skipped. An event is valued at the last price at or before it, or at the
+//! first one after when none precedes it, as liquidations are with the oracle feed.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
+use time::{Date, OffsetDateTime};
+
+#[derive(Error, Debug)]
+pub enum PriceError {
+    #[error("read prices: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("prices line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+    #[error("prices: no price in the file")]
+    Empty,
+}
+
+/// A price from `timestamp` (Unix seconds) on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PricePoint {
+    pub timestamp: i64,
+    pub price: f64,
+}
+
+/// Prices in timestamp order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriceSeries {
+    points: Vec<PricePoint>,
+}
+
+impl PriceSeries {
+    pub fn new(points: impl IntoIterator<Item = PricePoint>) -> Self {
+        let mut points: Vec<PricePoint> = points.into_iter().collect();
+        points.sort_by_key(|p| p.timestamp);
+        Self { points }
+    }
+
+    /// Read a price file (see the module docs).
+    pub fn load(path: &Path) -> Result<Self, PriceError> {
+        Self::parse_csv(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse_csv(text: &str) -> Result<Self, PriceError> {
+        let mut points = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parse_err = |reason: &str| PriceError::Parse {
+                line: i + 1,
+                reason: reason.to_string(),
+            };
+            let (ts, price) = line
+                .split_once(',')
+                .ok_or_else(|| parse_err("expected `timestamp,price`"))?;
+            let Ok(price) = price.trim().parse::<f64>() else {
+                // A header names its columns; anything else after the first line is an error.
+                if i == 0 {
+                    continue;
+                }
+                return Err(parse_err("price is not a number"));
+            };
+            if !price.is_finite() || price <= 0.0 {
+                return Err(parse_err("price must be positive"));
+            }
+            let timestamp = parse_timestamp(ts.trim()).ok_or_else(|| {
+                parse_err("timestamp is not Unix seconds, RFC 3339, or YYYY-MM-DD")
+            })?;
+            points.push(PricePoint { timestamp, price });
+        }
+        if points.is_empty() {
+            return Err(PriceError::Empty);
+        }
+        Ok(Self::new(points))
+    }
+
+    /// Price in force at `timestamp`: the last at or before it, else the first after it.
+    pub fn price_at(&self, timestamp: i64) -> Option<f64> {
+        let at = self.points.partition_point(|p| p.timestamp <= timestamp);
+        at.checked_sub(1)
+            .or((at < self.points.len()).then_some(at))
+            .map(|i| self.points[i].price)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+}
+
+fn parse_timestamp(s: &str) -> Option<i64> {
+    if let Ok(ts) = s.parse::<i64>() {
+        return Some(ts);
+    }
+    if let Ok(t) = OffsetDateTime::parse(s, &Rfc3339) {
+        return Some(t.unix_timestamp());
+    }
+    let format = time::macros::format_description!("[year]-[month]-[day]");
+    Date::parse(s, &format)
+        .ok()
+        .map(|d| d.midnight().assume_utc().unix_timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn price_file_reads_every_timestamp_form() {
+        let series = PriceSeries::parse_csv(
+            "timestamp,usd_per_ada\n# daily close\n2024-01-02,0.60\n1704067200,0.59\n\n2024-01-03T00:00:00Z,0.62\n",
+        )
+        .unwrap();
+        // 2024-01-01 is 1704067200.
+        assert_eq!(series.price_at(0), Some(0.59));
+        assert_eq!(series.price_at(1_704_067_200 + 86_400 + 1), Some(0.60));
+        assert_eq!(series.price_at(2_000_000_000), Some(0.62));
+        assert!(matches!(
+            PriceSeries::parse_csv("2024-01-02,0.6\n2024-01-03,abc"),
+            Err(PriceError::Parse { line: 2, .. })
+        ));
+        assert!(matches!(
+            PriceSeries::parse_csv("ts,price\n"),
+            Err(PriceError::Empty)
+        ));
+    }
+}
@@ -0,0 +1,108 @@
+//! Risk of the yield, from per-epoch returns: how far the position fell from a high, how much
+//! the return varied, and the return per unit of that variation. SP yield comes in lumps at
+//! each liquidation, which a period APR averages away.
+
+use super::annualize::{EPOCHS_PER_YEAR, EPOCH_SECS};
+use super::twr::FlowStep;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RiskMetrics {
+    /// Full epochs with principal at work, each one return.
+    pub epochs: u64,
+    /// Largest fall of the chained per-epoch returns from a prior high, in percent (0 when it
+    /// never fell).
+    pub max_drawdown_pct: f64,
+    /// Standard deviation of the per-epoch returns, annualized over 73 epochs, in percent.
+    pub volatility_pct: f64,
+    /// Mean per-epoch return over its standard deviation, annualized; no risk-free rate is
+    /// taken off. `None` when the returns did not vary.
+    pub return_to_volatility: Option<f64>,
+}
+
+/// Per-epoch returns of the period (gain over the principal at the epoch's start, as for the
+/// trailing windows), and their risk measures. `None` with fewer than two epochs of returns.
+pub fn risk_metrics(
+    steps: &[FlowStep],
+    period_start: Option<i64>,
+    period_end: Option<i64>,
+    opening_capital: i64,
+) -> Option<RiskMetrics> {
+    let start = period_start.or_else(|| steps.iter().map(|s| s.timestamp).min())?;
+    let end = period_end.or_else(|| steps.iter().map(|s| s.timestamp).max())?;
+    let mut capital = opening_capital.max(0) as i128;
+    let mut returns = Vec::new();
+    let (mut next, mut epoch_start) = (0usize, start);
+    while epoch_start + EPOCH_SECS <= end {
+        let epoch_end = epoch_start + EPOCH_SECS;
+        let at_start = capital;
+        let mut gain = 0i128;
+        while let Some(step) = steps.get(next).filter(|s| s.timestamp < epoch_end) {
+            gain += step.gain_lovelace as i128;
+            capital = (capital + step.external_lovelace as i128).max(0);
+            next += 1;
+        }
+        // Principal that arrived in the epoch works from then on; with none before it, the
+        // epoch's gain has no measured base.
+        if at_start > 0 {
+            returns.push(gain as f64 / at_start as f64);
+        }
+        epoch_start = epoch_end;
+    }
+    if returns.len() < 2 {
+        return None;
+    }
+    let (mut index, mut high, mut drawdown) = (1.0f64, 1.0f64, 0.0f64);
+    for r in &returns {
+        index *= 1.0 + r;
+        high = high.max(index);
+        drawdown = drawdown.max(1.0 - index / high);
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean) * (r - mean)).sum::<f64>() / (n - 1.0);
+    let annual = (EPOCHS_PER_YEAR as f64).sqrt();
+    let std = variance.sqrt();
+    Some(RiskMetrics {
+        epochs: returns.len() as u64,
+        max_drawdown_pct: drawdown * 100.0,
+        volatility_pct: std * annual * 100.0,
+        return_to_volatility: (std > 0.0).then(|| mean / std * annual),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lumpy_yield_shows_its_risk() {
+        let step = |epoch: i64, external_lovelace, gain_lovelace| FlowStep {
+            timestamp: epoch * EPOCH_SECS + 1,
+            external_lovelace,
+            gain_lovelace,
+        };
+        // 1000 ADA at work: +2% in epoch 0, a 1% loss (fees over a liquidation's premium) in
+        // epoch 1, nothing in epoch 2, +1% in epoch 3.
+        let steps = [
+            step(0, 0, 20_000_000),
+            step(1, 0, -10_000_000),
+            step(3, 0, 10_000_000),
+        ];
+        let risk = risk_metrics(&steps, Some(0), Some(4 * EPOCH_SECS), 1_000_000_000).unwrap();
+        assert_eq!(risk.epochs, 4);
+        assert!((risk.max_drawdown_pct - 1.0).abs() < 1e-9);
+        // Returns 2, -1, 0, 1 (%): mean 0.5, sample standard deviation 1.290994.
+        let std = (5.0f64 / 3.0).sqrt() / 100.0;
+        assert!((risk.volatility_pct - std * 73f64.sqrt() * 100.0).abs() < 1e-9);
+        let ratio = risk.return_to_volatility.unwrap();
+        assert!((ratio - 0.005 / std * 73f64.sqrt()).abs() < 1e-9);
+
+        // Steady yield: no drawdown and no variation.
+        let steady = [step(0, 0, 1_000_000), step(1, 0, 1_000_000)];
+        let risk = risk_metrics(&steady, Some(0), Some(2 * EPOCH_SECS), 100_000_000).unwrap();
+        assert_eq!(risk.max_drawdown_pct, 0.0);
+        assert_eq!(risk.return_to_volatility, None);
+        assert_eq!(risk_metrics(&steady, Some(0), Some(EPOCH_SECS), 1), None);
+    }
+}
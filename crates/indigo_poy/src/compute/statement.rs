@@ -0,0 +1,194 @@
+//! Account statements per calendar month (UTC) and per epoch, as a brokerage would send them:
+//! the balance at the start, the deposits, withdrawals and yield of the period, and the
+//! balance at the end.
+//!
+//! The balance is the principal at work, as for the trailing windows: it opens at the
+//! period's opening capital and moves with each deposit and withdrawal. Yield is paid out to
+//! the wallet (liquidation ADA, rewards), so it is counted in the withdrawals as well, and
+//! `closing = opening + deposits - withdrawals + yield` holds for every statement.
+
+use super::twr::FlowStep;
+use crate::chain::network::Network;
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, OffsetDateTime};
+
+/// One month or epoch of the account.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    /// `YYYY-MM` for a month, the epoch number for an epoch.
+    pub period: String,
+    /// Start of the month or epoch, or of the computed period when that starts later.
+    pub start_ts: i64,
+    /// End of the month or epoch, or of the computed period when that ends sooner.
+    pub end_ts: i64,
+    pub opening_balance_lovelace: i64,
+    /// ADA put into the position.
+    pub deposits_lovelace: i64,
+    /// ADA that left the position: principal returned and yield paid out, less protocol fees
+    /// paid alongside.
+    pub withdrawals_lovelace: i64,
+    /// Premium and rewards earned, less protocol fees.
+    pub yield_lovelace: i64,
+    pub closing_balance_lovelace: i64,
+}
+
+/// Statements of the period, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Statements {
+    pub monthly: Vec<Statement>,
+    /// Epochs numbered on the bundle's network.
+    pub epochs: Vec<Statement>,
+}
+
+/// Monthly and per-epoch statements from `period_start` to `period_end`, the balance opening
+/// at `opening_capital`. Empty without a period.
+pub fn statements(
+    steps: &[FlowStep],
+    period_start: Option<i64>,
+    period_end: Option<i64>,
+    opening_capital: i64,
+    network: Network,
+) -> Statements {
+    let start = period_start.or_else(|| steps.iter().map(|s| s.timestamp).min());
+    let end = period_end.or_else(|| steps.iter().map(|s| s.timestamp).max());
+    let (Some(start), Some(end)) = (start, end) else {
+        return Statements::default();
+    };
+    Statements {
+        monthly: statements_by(steps, start, end, opening_capital, month_of),
+        epochs: statements_by(steps, start, end, opening_capital, |ts| {
+            let epoch = network.epoch_at(ts);
+            (
+                epoch.to_string(),
+                network.epoch_start(epoch),
+                network.epoch_start(epoch + 1),
+            )
+        }),
+    }
+}
+
+/// The month of `ts` as `(YYYY-MM, its first second, the next month's first second)`.
+fn month_of(ts: i64) -> (String, i64, i64) {
+    let date = OffsetDateTime::from_unix_timestamp(ts)
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+        .date();
+    let first = |year: i32, month: Month| {
+        Date::from_calendar_date(year, month, 1)
+            .map(|d| d.midnight().assume_utc().unix_timestamp())
+            .unwrap_or(ts)
+    };
+    let (year, month) = (date.year(), date.month());
+    let next_year = if month == Month::December {
+        year + 1
+    } else {
+        year
+    };
+    (
+        format!("{:04}-{:02}", year, month as u8),
+        first(year, month),
+        first(next_year, month.next()),
+    )
+}
+
+/// One statement per span `bounds` puts `start..=end` in; `bounds(ts)` names the span of `ts`
+/// and its `[start, end)`.
+fn statements_by(
+    steps: &[FlowStep],
+    start: i64,
+    end: i64,
+    opening_capital: i64,
+    bounds: impl Fn(i64) -> (String, i64, i64),
+) -> Vec<Statement> {
+    let mut out = Vec::new();
+    let (mut balance, mut next, mut at) = (opening_capital, 0usize, start);
+    // Steps before the period moved the principal before the first statement opens.
+    while let Some(step) = steps.get(next).filter(|s| s.timestamp < start) {
+        balance = balance.saturating_add(step.external_lovelace);
+        next += 1;
+    }
+    loop {
+        let (period, _, span_end) = bounds(at);
+        let last = span_end > end;
+        let mut statement = Statement {
+            period,
+            start_ts: at,
+            end_ts: span_end.min(end),
+            opening_balance_lovelace: balance,
+            ..Default::default()
+        };
+        while let Some(step) = steps
+            .get(next)
+            .filter(|s| s.timestamp < span_end || (last && s.timestamp <= end))
+        {
+            balance = balance.saturating_add(step.external_lovelace);
+            statement.deposits_lovelace += step.external_lovelace.max(0);
+            statement.withdrawals_lovelace += step.gain_lovelace - step.external_lovelace.min(0);
+            statement.yield_lovelace += step.gain_lovelace;
+            next += 1;
+        }
+        statement.closing_balance_lovelace = balance;
+        out.push(statement);
+        if last || span_end == end {
+            break;
+        }
+        at = span_end;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_balance_month_by_month_and_epoch_by_epoch() {
+        // 2024-01-30 00:00 UTC: 1000 ADA in; 2024-02-10: a liquidation pays 110 ADA for 100
+        // ADA of principal; 2024-03-05: 5 ADA in rewards. The period ends 2024-03-10.
+        let (jan30, feb10, mar5, mar10) =
+            (1_706_572_800, 1_707_523_200, 1_709_596_800, 1_710_028_800);
+        let step = |timestamp, external_lovelace, gain_lovelace| FlowStep {
+            timestamp,
+            external_lovelace,
+            gain_lovelace,
+        };
+        let steps = [
+            step(jan30, 1_000_000_000, 0),
+            step(feb10, -100_000_000, 10_000_000),
+            step(mar5, 0, 5_000_000),
+        ];
+        let s = statements(&steps, Some(jan30), Some(mar10), 0, Network::Mainnet);
+        let periods: Vec<&str> = s.monthly.iter().map(|m| m.period.as_str()).collect();
+        assert_eq!(periods, ["2024-01", "2024-02", "2024-03"]);
+        let feb = &s.monthly[1];
+        assert_eq!(feb.start_ts, 1_706_745_600);
+        assert_eq!(feb.opening_balance_lovelace, 1_000_000_000);
+        assert_eq!(feb.withdrawals_lovelace, 110_000_000);
+        assert_eq!(feb.yield_lovelace, 10_000_000);
+        assert_eq!(feb.closing_balance_lovelace, 900_000_000);
+        let mar = &s.monthly[2];
+        assert_eq!(mar.end_ts, mar10);
+        assert_eq!(mar.withdrawals_lovelace, mar.yield_lovelace);
+        for st in s.monthly.iter().chain(&s.epochs) {
+            assert_eq!(
+                st.closing_balance_lovelace,
+                st.opening_balance_lovelace + st.deposits_lovelace - st.withdrawals_lovelace
+                    + st.yield_lovelace
+            );
+        }
+
+        // 40 days over epoch boundaries: nine epochs, numbered on mainnet.
+        assert_eq!(s.epochs.len(), 9);
+        assert_eq!(
+            s.epochs[0].period,
+            Network::Mainnet.epoch_at(jan30).to_string()
+        );
+        assert_eq!(
+            s.epochs.last().unwrap().closing_balance_lovelace,
+            900_000_000
+        );
+        assert_eq!(
+            statements(&[], None, None, 0, Network::Mainnet),
+            Statements::default()
+        );
+    }
+}
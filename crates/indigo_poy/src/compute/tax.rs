@@ -0,0 +1,264 @@
+//! Acquisition and disposal records for capital-gains reporting, in no particular
+//! jurisdiction's format: exporters map them onto their own forms.
+//!
+//! An SP deposit acquires a share of the pool at the ADA it cost. A withdrawal or a pro-rata
+//! liquidation disposes of part of that share, matched to the deposits by [`track_lots`]; a
+//! liquidation also acquires the ADA it paid out, and an INDY staking reward or SP premium
+//! payout acquires its ADA as income. Each record carries its lovelace value and, with a price
+//! series, its fiat value at the event's time; disposals also carry the cost basis and the
+//! gain in both.
+
+use super::lots::{track_lots, DisposalKind, LotMethod};
+use super::pricing::PriceSeries;
+use crate::indigo::{EventKind, IndigoEvents};
+use serde::{Deserialize, Serialize};
+
+/// Asset name of ADA in records; SP shares are named by their iAsset unit.
+pub const ADA: &str = "ADA";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxRecordKind {
+    Acquisition,
+    Disposal,
+}
+
+/// What the record came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxReason {
+    SpDeposit,
+    SpWithdrawal,
+    Liquidation,
+    /// INDY staking reward or SP premium paid in ADA.
+    Reward,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaxRecord {
+    pub tx_hash: String,
+    pub timestamp: i64,
+    pub kind: TaxRecordKind,
+    pub reason: TaxReason,
+    /// [`ADA`], or the iAsset unit of an SP share.
+    pub asset: String,
+    /// Lovelace for ADA; iAsset base units for an SP share.
+    pub quantity: u128,
+    /// ADA value at the event: the cost of an acquisition, the proceeds of a disposal.
+    pub value_lovelace: u64,
+    /// `value_lovelace` at the price in force at `timestamp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_fiat: Option<f64>,
+    /// Disposals: the deposit the share was acquired by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquired_tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquired_timestamp: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_basis_lovelace: Option<u64>,
+    /// `cost_basis_lovelace` at the price in force at `acquired_timestamp`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_basis_fiat: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain_lovelace: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gain_fiat: Option<f64>,
+}
+
+/// Records of the period, in time order.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaxReport {
+    pub method: LotMethod,
+    pub records: Vec<TaxRecord>,
+    /// SP txs whose iAsset quantity is unknown, so no record could be made of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untracked_txs: Vec<String>,
+}
+
+/// Acquisition and disposal records of `events`, SP shares matched under `method`, valued at
+/// `prices` (fiat per ADA) when given.
+pub fn tax_records(
+    events: &IndigoEvents,
+    method: LotMethod,
+    prices: Option<&PriceSeries>,
+) -> TaxReport {
+    let fiat = |lovelace: i64, timestamp: i64| {
+        prices
+            .and_then(|p| p.price_at(timestamp))
+            .map(|price| lovelace as f64 / 1_000_000.0 * price)
+    };
+    let record =
+        |kind, reason, asset: &str, quantity, value_lovelace: u64, tx: &str, ts| TaxRecord {
+            tx_hash: tx.to_string(),
+            timestamp: ts,
+            kind,
+            reason,
+            asset: asset.to_string(),
+            quantity,
+            value_lovelace,
+            value_fiat: fiat(value_lovelace as i64, ts),
+            acquired_tx_hash: None,
+            acquired_timestamp: None,
+            cost_basis_lovelace: None,
+            cost_basis_fiat: None,
+            gain_lovelace: None,
+            gain_fiat: None,
+        };
+    let lots = track_lots(&events.stability_pool, method);
+    let mut records = Vec::new();
+    for ev in events.all_events() {
+        let ts = ev.timestamp.unix_timestamp();
+        let iasset = ev.asset_unit().unwrap_or("unknown");
+        match &ev.kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace,
+                iasset_quantity: Some(quantity),
+                ..
+            } => records.push(record(
+                TaxRecordKind::Acquisition,
+                TaxReason::SpDeposit,
+                iasset,
+                *quantity,
+                *amount_lovelace,
+                &ev.tx_hash,
+                ts,
+            )),
+            EventKind::StabilityPoolLiquidation {
+                ada_received_lovelace,
+                iasset_lost_quantity: Some(_),
+                ..
+            } => records.push(record(
+                TaxRecordKind::Acquisition,
+                TaxReason::Liquidation,
+                ADA,
+                *ada_received_lovelace as u128,
+                *ada_received_lovelace,
+                &ev.tx_hash,
+                ts,
+            )),
+            EventKind::IndyStakingReward {
+                amount_lovelace, ..
+            }
+            | EventKind::IndySpPremium {
+                amount_lovelace, ..
+            } if *amount_lovelace > 0 => records.push(record(
+                TaxRecordKind::Acquisition,
+                TaxReason::Reward,
+                ADA,
+                *amount_lovelace as u128,
+                *amount_lovelace,
+                &ev.tx_hash,
+                ts,
+            )),
+            _ => {}
+        }
+    }
+    for d in &lots.disposals {
+        let reason = match d.kind {
+            DisposalKind::Withdrawal => TaxReason::SpWithdrawal,
+            DisposalKind::Liquidation => TaxReason::Liquidation,
+        };
+        let mut r = record(
+            TaxRecordKind::Disposal,
+            reason,
+            &d.iasset,
+            d.quantity,
+            d.proceeds_lovelace,
+            &d.tx_hash,
+            d.timestamp,
+        );
+        r.cost_basis_fiat = fiat(d.cost_lovelace as i64, d.lot_timestamp);
+        r.gain_fiat = r.value_fiat.zip(r.cost_basis_fiat).map(|(v, c)| v - c);
+        r.acquired_tx_hash = Some(d.lot_tx_hash.clone());
+        r.acquired_timestamp = Some(d.lot_timestamp);
+        r.cost_basis_lovelace = Some(d.cost_lovelace);
+        r.gain_lovelace = Some(d.gain_lovelace);
+        records.push(r);
+    }
+    // Acquisitions before disposals of the same tx.
+    records.sort_by(|a, b| {
+        (a.timestamp, &a.tx_hash, a.kind == TaxRecordKind::Disposal).cmp(&(
+            b.timestamp,
+            &b.tx_hash,
+            b.kind == TaxRecordKind::Disposal,
+        ))
+    });
+    TaxReport {
+        method,
+        records,
+        untracked_txs: lots.untracked_txs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute::pricing::PricePoint;
+    use crate::indigo::Event;
+
+    #[test]
+    fn liquidation_acquires_ada_and_disposes_of_the_share() {
+        let events = IndigoEvents {
+            stability_pool: vec![
+                Event::at(
+                    100,
+                    "dep",
+                    EventKind::StabilityPoolDeposit {
+                        amount_lovelace: 100_000_000,
+                        iasset_amount: None,
+                        iasset_quantity: Some(100),
+                        tx_hash: "dep".into(),
+                    },
+                ),
+                Event::at(
+                    200,
+                    "liq",
+                    EventKind::StabilityPoolLiquidation {
+                        iasset_burnt: String::new(),
+                        ada_received_lovelace: 55_000_000,
+                        realized_premium_lovelace: 5_000_000,
+                        dilution_effect: None,
+                        pool_deposit_quantity: None,
+                        user_deposit_quantity: None,
+                        iasset_lost_quantity: Some(50),
+                        oracle_price: None,
+                        tx_hash: "liq".into(),
+                        slot: None,
+                    },
+                ),
+            ],
+            ..Default::default()
+        };
+        let prices = PriceSeries::new([
+            PricePoint {
+                timestamp: 0,
+                price: 0.5,
+            },
+            PricePoint {
+                timestamp: 150,
+                price: 0.4,
+            },
+        ]);
+        let report = tax_records(&events, LotMethod::Fifo, Some(&prices));
+        let kinds: Vec<(TaxRecordKind, TaxReason)> =
+            report.records.iter().map(|r| (r.kind, r.reason)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (TaxRecordKind::Acquisition, TaxReason::SpDeposit),
+                (TaxRecordKind::Acquisition, TaxReason::Liquidation),
+                (TaxRecordKind::Disposal, TaxReason::Liquidation),
+            ]
+        );
+        assert_eq!(report.records[1].asset, ADA);
+        let disposal = &report.records[2];
+        assert_eq!(disposal.cost_basis_lovelace, Some(50_000_000));
+        assert_eq!(disposal.gain_lovelace, Some(5_000_000));
+        // 55 ADA at 0.40 against 50 ADA at 0.50: a fiat loss on an ADA gain.
+        assert!((disposal.value_fiat.unwrap() - 22.0).abs() < 1e-9);
+        assert!((disposal.gain_fiat.unwrap() + 3.0).abs() < 1e-9);
+
+        let unpriced = tax_records(&events, LotMethod::Fifo, None);
+        assert_eq!(unpriced.records[2].gain_fiat, None);
+    }
+}
@@ -0,0 +1,148 @@
+//! Time-weighted return: sub-period returns between external cash flows, chained.
+
+use super::annualize::Annualization;
+use super::fixed::{round_micro, saturating_i64};
+use serde::{Deserialize, Serialize};
+
+/// One event's effect on the position, in lovelace: principal moved in (positive) or out
+/// (negative), and yield earned (premium and rewards, less protocol fees).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FlowStep {
+    pub timestamp: i64,
+    pub external_lovelace: i64,
+    pub gain_lovelace: i64,
+}
+
+/// Return between two external cash flows.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TwrPeriod {
+    pub start_ts: i64,
+    pub end_ts: i64,
+    /// Principal at work during the sub-period.
+    pub capital_lovelace: u64,
+    pub gain_lovelace: i64,
+    pub return_pct: f64,
+}
+
+/// Time-weighted return of the period: independent of when and how much was deposited, so
+/// it compares across wallets.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimeWeightedReturn {
+    /// Chained return over the whole period.
+    pub cumulative_pct: f64,
+    /// `cumulative_pct` compounded to a year of the run's day count (see
+    /// [`crate::compute::annualize::DayCount`]); `None` for an empty span.
+    pub annualized_pct: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub periods: Vec<TwrPeriod>,
+}
+
+/// Chain the returns of the sub-periods `steps` delimit: each step with an external flow ends
+/// one. A sub-period returns its gain over the principal at work; the principal at
+/// `period_start` is `closing_position` less the period's net external flow. Sub-periods without
+/// principal (yield on a position opened before the period, with no closing position known)
+/// cannot be measured and are skipped. `None` when none can be.
+pub fn time_weighted_return(
+    steps: &[FlowStep],
+    period_start: Option<i64>,
+    period_end: Option<i64>,
+    closing_position: Option<u64>,
+    annualization: Annualization,
+) -> Option<TimeWeightedReturn> {
+    let first = period_start.or_else(|| steps.iter().map(|s| s.timestamp).min())?;
+    let end = period_end
+        .or_else(|| steps.iter().map(|s| s.timestamp).max())?
+        .max(first);
+    let net_external: i128 = steps.iter().map(|s| s.external_lovelace as i128).sum();
+    let mut capital = closing_position.map_or(0, |c| (c as i128 - net_external).max(0));
+    let (mut start, mut gain) = (first, 0i128);
+    let mut periods = Vec::new();
+    let mut close = |start: i64, end: i64, capital: i128, gain: i128| {
+        if capital > 0 && (end > start || gain != 0) {
+            periods.push(TwrPeriod {
+                start_ts: start,
+                end_ts: end,
+                capital_lovelace: u64::try_from(capital).unwrap_or(u64::MAX),
+                gain_lovelace: saturating_i64(gain),
+                return_pct: gain as f64 / capital as f64 * 100.0,
+            });
+        }
+    };
+    for step in steps {
+        gain += step.gain_lovelace as i128;
+        if step.external_lovelace != 0 {
+            close(start, step.timestamp, capital, gain);
+            capital = (capital + step.external_lovelace as i128).max(0);
+            (start, gain) = (step.timestamp, 0);
+        }
+    }
+    close(start, end, capital, gain);
+    if periods.is_empty() {
+        return None;
+    }
+    let growth: f64 = periods.iter().map(|p| 1.0 + p.return_pct / 100.0).product();
+    let (year, span) = annualization.day_count.year_and_period(end - first);
+    Some(TimeWeightedReturn {
+        cumulative_pct: (growth - 1.0) * 100.0,
+        annualized_pct: (end > first && growth > 0.0)
+            .then(|| round_micro((growth.powf(year as f64 / span as f64) - 1.0) * 100.0)),
+        periods,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute::annualize::DayCount;
+
+    #[test]
+    fn deposit_timing_does_not_change_the_return() {
+        const HALF: i64 = 31_557_600 / 2;
+        let step = |timestamp, external_lovelace, gain_lovelace| FlowStep {
+            timestamp,
+            external_lovelace,
+            gain_lovelace,
+        };
+        // 5% per half-year on 100 ADA, then on 1000 ADA after a 900 ADA deposit.
+        let late = [
+            step(0, 100_000_000, 0),
+            step(HALF, 900_000_000, 5_000_000),
+            step(2 * HALF, 0, 50_000_000),
+        ];
+        let twr =
+            time_weighted_return(&late, Some(0), Some(2 * HALF), None, Default::default()).unwrap();
+        assert_eq!(twr.periods.len(), 2);
+        assert!((twr.cumulative_pct - 10.25).abs() < 1e-9);
+        assert!((twr.annualized_pct.unwrap() - 10.25).abs() < 1e-9);
+
+        // The same 5% per half-year on 1000 ADA, then on 100 ADA after a 900 ADA withdrawal;
+        // the closing position gives the opening principal.
+        let early = [
+            step(HALF, -900_000_000, 50_000_000),
+            step(2 * HALF, 0, 5_000_000),
+        ];
+        let closing = Some(100_000_000);
+        let twr =
+            time_weighted_return(&early, Some(0), Some(2 * HALF), closing, Default::default());
+        assert!((twr.unwrap().cumulative_pct - 10.25).abs() < 1e-9);
+
+        assert_eq!(
+            time_weighted_return(&early, Some(0), None, None, Default::default()),
+            None
+        );
+
+        // A 365-day year is shorter than the span, so the same growth annualizes lower.
+        let days365 = Annualization {
+            day_count: DayCount::Days365,
+            ..Default::default()
+        };
+        let twr = time_weighted_return(&late, Some(0), Some(2 * HALF), None, days365).unwrap();
+        let expected = (1.1025f64.powf(31_536_000.0 / 31_557_600.0) - 1.0) * 100.0;
+        assert!((twr.annualized_pct.unwrap() - expected).abs() <= 5e-7);
+        assert_eq!(
+            round_micro(twr.annualized_pct.unwrap()),
+            twr.annualized_pct.unwrap()
+        );
+        assert!(twr.annualized_pct.unwrap() < 10.25);
+    }
+}
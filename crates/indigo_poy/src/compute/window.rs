@@ -0,0 +1,129 @@
+//! Trailing-window returns: the yield of the last 30 days, 90 days, and year, so a change in
+//! the pool (a parameter update, a run of liquidations) shows apart from the lifetime APR.
+
+use super::annualize::Annualization;
+use super::twr::FlowStep;
+use serde::{Deserialize, Serialize};
+
+/// Trailing windows reported, in days.
+pub const TRAILING_WINDOW_DAYS: [u32; 3] = [30, 90, 365];
+
+/// Return over the last `days` days of the period.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowMetrics {
+    pub days: u32,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    /// Premium and rewards earned in the window, less protocol fees.
+    pub earned_lovelace: i64,
+    /// Principal at work, averaged over the window's time.
+    pub avg_capital_lovelace: u64,
+    /// `earned_lovelace` over `avg_capital_lovelace`, annualized; `None` without capital.
+    pub apr_pct: Option<f64>,
+}
+
+/// The trailing windows that fit in the period: principal is `opening_capital` at the first
+/// step, moved by each step's external flow. A window longer than the history is left out,
+/// as it would only repeat the lifetime figures.
+pub fn trailing_windows(
+    steps: &[FlowStep],
+    period_start: Option<i64>,
+    period_end: Option<i64>,
+    opening_capital: i64,
+    annualization: Annualization,
+) -> Vec<WindowMetrics> {
+    let Some(first) = period_start.or_else(|| steps.iter().map(|s| s.timestamp).min()) else {
+        return vec![];
+    };
+    let Some(end) = period_end.or_else(|| steps.iter().map(|s| s.timestamp).max()) else {
+        return vec![];
+    };
+    TRAILING_WINDOW_DAYS
+        .iter()
+        .filter_map(|&days| {
+            let start = end - days as i64 * 86_400;
+            (start >= first)
+                .then(|| window(steps, start, end, days, opening_capital, annualization))
+        })
+        .collect()
+}
+
+fn window(
+    steps: &[FlowStep],
+    start: i64,
+    end: i64,
+    days: u32,
+    opening_capital: i64,
+    annualization: Annualization,
+) -> WindowMetrics {
+    let (mut capital, mut earned) = (opening_capital.max(0) as i128, 0i64);
+    // Lovelace-seconds of principal at work in the window.
+    let (mut weighted, mut at) = (0i128, start);
+    for step in steps.iter().filter(|s| s.timestamp <= end) {
+        if step.timestamp > start {
+            weighted += capital * (step.timestamp - at) as i128;
+            at = step.timestamp;
+            earned = earned.saturating_add(step.gain_lovelace);
+        }
+        capital = (capital + step.external_lovelace as i128).max(0);
+    }
+    weighted += capital * (end - at) as i128;
+    let span = (end - start).max(1);
+    let avg_capital = (weighted / span as i128).clamp(0, u64::MAX as i128);
+    WindowMetrics {
+        days,
+        start_ts: start,
+        end_ts: end,
+        earned_lovelace: earned,
+        avg_capital_lovelace: avg_capital as u64,
+        apr_pct: (avg_capital > 0)
+            .then(|| annualization.apr_pct(earned as i128, avg_capital, span))
+            .flatten(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_see_only_their_own_yield() {
+        const DAY: i64 = 86_400;
+        let step = |day: i64, external_lovelace, gain_lovelace| FlowStep {
+            timestamp: day * DAY,
+            external_lovelace,
+            gain_lovelace,
+        };
+        // 1000 ADA from day 0. 1 ADA earned on day 100; 10 ADA on day 380, after 1000 ADA more
+        // went in on day 350.
+        let steps = [
+            step(0, 1_000_000_000, 0),
+            step(100, 0, 1_000_000),
+            step(350, 1_000_000_000, 0),
+            step(380, 0, 10_000_000),
+        ];
+        let windows = trailing_windows(
+            &steps,
+            Some(0),
+            Some(400 * DAY),
+            0,
+            Annualization::default(),
+        );
+        assert_eq!(
+            windows.iter().map(|w| w.days).collect::<Vec<_>>(),
+            [30, 90, 365]
+        );
+        // Last 30 days: 2000 ADA at work throughout.
+        assert_eq!(windows[0].avg_capital_lovelace, 2_000_000_000);
+        assert_eq!(windows[0].earned_lovelace, 10_000_000);
+        assert_eq!(windows[0].apr_pct, Some(6.0875));
+        // Last 90 days: 1000 ADA for 40 of them, 2000 for 50.
+        assert_eq!(windows[1].avg_capital_lovelace, 1_555_555_555);
+        // The year from day 35 still includes day 100.
+        assert_eq!(windows[2].earned_lovelace, 11_000_000);
+
+        let short = trailing_windows(&steps, Some(0), Some(60 * DAY), 0, Default::default());
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].apr_pct, Some(0.0));
+    }
+}
@@ -0,0 +1,74 @@
+//! Money-weighted return: the XIRR of dated cash flows.
+
+const SECS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+
+/// Annual rate `r` at which the flows' net present value is zero: XIRR with Excel's actual/365
+/// convention. Flows are `(unix_ts, amount)`, money put in negative, money taken out positive.
+/// `None` unless the flows have both signs, or when no rate above -100% balances them.
+pub fn xirr(flows: &[(i64, f64)]) -> Option<f64> {
+    if !flows.iter().any(|f| f.1 < 0.0) || !flows.iter().any(|f| f.1 > 0.0) {
+        return None;
+    }
+    let t0 = flows.iter().map(|f| f.0).min()?;
+    let npv = |rate: f64| -> f64 {
+        flows
+            .iter()
+            .map(|&(ts, amount)| amount / (1.0 + rate).powf((ts - t0) as f64 / SECS_PER_YEAR))
+            .sum()
+    };
+    // Bisect: the bracket widens upward until the NPV changes sign.
+    let mut lo = -0.999_999;
+    let mut hi = 1.0;
+    let lo_sign = npv(lo).signum();
+    while npv(hi).signum() == lo_sign {
+        hi *= 2.0;
+        if hi > 1e9 {
+            return None;
+        }
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.0;
+        if npv(mid).signum() == lo_sign {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let rate = (lo + hi) / 2.0;
+    rate.is_finite().then_some(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::date;
+
+    fn ts(d: time::Date) -> i64 {
+        d.midnight().assume_utc().unix_timestamp()
+    }
+
+    #[test]
+    fn matches_spreadsheet_xirr() {
+        // Excel's XIRR documentation example: =XIRR(...) is 0.373362535.
+        let flows = [
+            (ts(date!(2008 - 01 - 01)), -10_000.0),
+            (ts(date!(2008 - 03 - 01)), 2_750.0),
+            (ts(date!(2008 - 10 - 30)), 4_250.0),
+            (ts(date!(2009 - 02 - 15)), 3_250.0),
+            (ts(date!(2009 - 04 - 01)), 2_750.0),
+        ];
+        assert!((xirr(&flows).unwrap() - 0.373_362_535).abs() < 1e-8);
+
+        // A second deposit a year in earns for one year only: 1000 in, 1000 more after 365
+        // days, 2150 back after 730. The rate solves 1000·x² + 1000·x = 2150, x = 1 + r.
+        let flows = [
+            (ts(date!(2021 - 01 - 01)), -1_000.0),
+            (ts(date!(2022 - 01 - 01)), -1_000.0),
+            (ts(date!(2023 - 01 - 01)), 2_150.0),
+        ];
+        let rate = xirr(&flows).unwrap();
+        assert!((1000.0 * (1.0 + rate).powi(2) + 1000.0 * (1.0 + rate) - 2150.0).abs() < 1e-6);
+
+        assert_eq!(xirr(&[(0, -1.0), (1, -1.0)]), None);
+    }
+}
@@ -0,0 +1,224 @@
+//! Data-quality pass over reconstructed events: patterns that are possible on chain but more
+//! likely a tx the parsers misread. Nothing is dropped or corrected; each hit is a
+//! [`DataWarning`] for the bundle and the report, to check and, if need be, override.
+
+use crate::indigo::events::{Event, EventKind, IndigoEvents};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Premium above this share of the principal (percent) is flagged unless another threshold is
+/// given.
+pub const DEFAULT_MAX_PREMIUM_PCT: f64 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataWarningKind {
+    /// SP liquidation premium over the iAsset value lost, or a ROB fill's premium, above the
+    /// threshold.
+    HighPremium,
+    /// SP liquidation that took no iAsset from the wallet: zero quantity lost, or all the ADA
+    /// received counted as premium.
+    ZeroIassetLiquidation,
+    /// SP withdrawal of more ADA than the deposits seen, less withdrawals and the principal
+    /// liquidations paid back, hold. Pools are taken together, as deposits do not always name
+    /// their iAsset.
+    WithdrawalOverBalance,
+}
+
+/// One suspicious event.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DataWarning {
+    pub kind: DataWarningKind,
+    pub tx_hash: String,
+    pub timestamp: i64,
+    pub message: String,
+}
+
+/// Warnings for `events`, in time order (slot, then tx hash); premium over `max_premium_pct`
+/// percent is flagged.
+pub fn detect_anomalies(events: &IndigoEvents, max_premium_pct: f64) -> Vec<DataWarning> {
+    let mut ordered: Vec<&Event> = events.all_events().collect();
+    ordered.sort_by(|a, b| {
+        let key = |e: &Event| (e.timestamp, e.slot, e.tx_hash.clone());
+        key(a).cmp(&key(b))
+    });
+    let mut warnings = Vec::new();
+    // Known SP balance per pool, in lovelace.
+    let mut balances: BTreeMap<&str, i128> = BTreeMap::new();
+    for ev in ordered {
+        let mut warn = |kind, message: String| {
+            warnings.push(DataWarning {
+                kind,
+                tx_hash: ev.tx_hash.clone(),
+                timestamp: ev.timestamp.unix_timestamp(),
+                message,
+            })
+        };
+        let pool = ev.asset_unit().unwrap_or("unknown");
+        let balance = balances.entry(pool).or_default();
+        match &ev.kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace, ..
+            } => *balance += *amount_lovelace as i128,
+            EventKind::StabilityPoolWithdraw {
+                amount_lovelace, ..
+            } => {
+                if *amount_lovelace as i128 > *balance {
+                    warn(
+                        DataWarningKind::WithdrawalOverBalance,
+                        format!(
+                            "withdrew {} lovelace with {} known to be deposited",
+                            amount_lovelace,
+                            (*balance).max(0)
+                        ),
+                    );
+                }
+                *balance -= *amount_lovelace as i128;
+            }
+            EventKind::StabilityPoolLiquidation {
+                ada_received_lovelace,
+                realized_premium_lovelace,
+                iasset_lost_quantity,
+                ..
+            } => {
+                let principal = ada_received_lovelace.saturating_sub(*realized_premium_lovelace);
+                *balance -= principal as i128;
+                if *iasset_lost_quantity == Some(0)
+                    || (*ada_received_lovelace > 0 && principal == 0)
+                {
+                    warn(
+                        DataWarningKind::ZeroIassetLiquidation,
+                        format!(
+                            "liquidation paid {} lovelace for no {} lost",
+                            ada_received_lovelace, pool
+                        ),
+                    );
+                } else if principal > 0 {
+                    let pct = *realized_premium_lovelace as f64 / principal as f64 * 100.0;
+                    if pct > max_premium_pct {
+                        warn(
+                            DataWarningKind::HighPremium,
+                            format!(
+                                "liquidation premium {:.2}% of the iAsset value lost, over {}%",
+                                pct, max_premium_pct
+                            ),
+                        );
+                    }
+                }
+            }
+            EventKind::RobOrderFill {
+                premium_pct: Some(pct),
+                ..
+            } if *pct > max_premium_pct => warn(
+                DataWarningKind::HighPremium,
+                format!("ROB fill premium {:.2}%, over {}%", pct, max_premium_pct),
+            ),
+            _ => {}
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn liquidation(ada_received_lovelace: u64, realized_premium_lovelace: u64) -> EventKind {
+        EventKind::StabilityPoolLiquidation {
+            iasset_burnt: "unknown".into(),
+            ada_received_lovelace,
+            realized_premium_lovelace,
+            dilution_effect: None,
+            pool_deposit_quantity: None,
+            user_deposit_quantity: None,
+            iasset_lost_quantity: None,
+            oracle_price: None,
+            tx_hash: String::new(),
+            slot: None,
+        }
+    }
+
+    #[test]
+    fn flags_premium_zero_iasset_and_overdrawn_pool() {
+        let events = IndigoEvents {
+            stability_pool: vec![
+                Event::at(
+                    4,
+                    "over",
+                    EventKind::StabilityPoolWithdraw {
+                        amount_lovelace: 90_000_000,
+                        iasset_amount: None,
+                        iasset_quantity: None,
+                        tx_hash: "over".into(),
+                    },
+                ),
+                Event::at(
+                    1,
+                    "dep",
+                    EventKind::StabilityPoolDeposit {
+                        amount_lovelace: 100_000_000,
+                        iasset_amount: None,
+                        iasset_quantity: None,
+                        tx_hash: "dep".into(),
+                    },
+                ),
+                // 30 ADA of premium on 20 of principal; then 10 ADA, all of it premium.
+                Event::at(2, "rich", liquidation(50_000_000, 30_000_000)),
+                Event::at(3, "free", liquidation(10_000_000, 10_000_000)),
+            ],
+            ..Default::default()
+        };
+        let warnings = detect_anomalies(&events, DEFAULT_MAX_PREMIUM_PCT);
+        let hits: Vec<(DataWarningKind, &str)> = warnings
+            .iter()
+            .map(|w| (w.kind, w.tx_hash.as_str()))
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                (DataWarningKind::HighPremium, "rich"),
+                (DataWarningKind::ZeroIassetLiquidation, "free"),
+                (DataWarningKind::WithdrawalOverBalance, "over"),
+            ]
+        );
+        assert!(warnings[2].message.contains("with 80000000 known"));
+        // A 200% threshold lets the premium through.
+        assert_eq!(detect_anomalies(&events, 200.0).len(), 2);
+    }
+
+    #[test]
+    fn pools_keep_their_own_balance() {
+        let sp = |ts, tx: &str, iasset: &str, withdraw: bool| {
+            let iasset_amount = Some(iasset.to_string());
+            let kind = if withdraw {
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 50_000_000,
+                    iasset_amount,
+                    iasset_quantity: None,
+                    tx_hash: tx.into(),
+                }
+            } else {
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace: 50_000_000,
+                    iasset_amount,
+                    iasset_quantity: None,
+                    tx_hash: tx.into(),
+                }
+            };
+            Event::at(ts, tx, kind)
+        };
+        // A deposit into iUSD does not cover a withdrawal from iBTC.
+        let events = IndigoEvents {
+            stability_pool: vec![
+                sp(1, "dep", "f0ff$69555344", false),
+                sp(2, "ibtc", "f0ff$69425443", true),
+                sp(3, "iusd", "f0ff$69555344", true),
+            ],
+            ..Default::default()
+        };
+        let warnings = detect_anomalies(&events, DEFAULT_MAX_PREMIUM_PCT);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, DataWarningKind::WithdrawalOverBalance);
+        assert_eq!(warnings[0].tx_hash, "ibtc");
+    }
+}
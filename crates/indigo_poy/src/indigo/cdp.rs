@@ -0,0 +1,291 @@
+//! CDPs: open, collateral deposit / withdraw, iAsset mint / burn, close, and tx fees.
+//!
+//! A CDP tx spends and/or creates a UTxO carrying a CDP datum (`cdp_datum_hashes`). Its
+//! collateral is the ADA on those UTxOs; minted and burnt iAssets are the tx-wide net change of
+//! iAsset quantities, since the provider does not report the mint field.
+//!
+//! A tx that spends and recreates a CDP with less collateral while burning a single iAsset is a
+//! redemption against it: the redeemer burns their iAsset to take collateral at the oracle price.
+//! It yields one `CdpRedeemedAgainst` and no withdraw, burn, or fee, as the redeemer pays for the
+//! tx. An owner repaying and withdrawing in the same tx has the same shape and is read the same.
+
+use crate::chain::assets::asset_unit;
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
+use crate::indigo::protocol_config::IndigoV2Config;
+use std::collections::BTreeMap;
+use time::OffsetDateTime;
+
+/// Reconstruct CDP events from account txs and tx UTxO data. Only txs touching a configured
+/// CDP datum yield events.
+pub fn reconstruct_cdp_events(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: &IndigoV2Config,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    for tx in account_txs {
+        let slot = tx.slot_no;
+        let ts = tx
+            .block_time
+            .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+            .unwrap_or(now);
+        let tx_hash = tx.tx_hash.clone();
+
+        let utxos = match get_tx_utxos(&tx_hash) {
+            Some(u) => u,
+            None => continue,
+        };
+
+        let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+        let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+        let cdp_in: Vec<&KoiosUtxo> = inputs.iter().filter(|u| is_cdp_utxo(u, config)).collect();
+        let cdp_out: Vec<&KoiosUtxo> = outputs.iter().filter(|u| is_cdp_utxo(u, config)).collect();
+        if cdp_in.is_empty() && cdp_out.is_empty() {
+            continue;
+        }
+        let mut push = |kind| {
+            // A redemption is inferred from the tx's shape; the rest from datum-matched UTxOs.
+            let confidence = match kind {
+                EventKind::CdpRedeemedAgainst { .. } => Confidence::Medium,
+                _ => Confidence::High,
+            };
+            events.push(Event {
+                kind,
+                timestamp: ts,
+                slot,
+                tx_hash: tx_hash.clone(),
+                extra: None,
+                confidence,
+                provenance: Provenance::from_utxos(cdp_in.clone(), cdp_out.clone()),
+            })
+        };
+
+        let collateral_in: u64 = cdp_in.iter().map(|u| parse_lovelace(&u.value)).sum();
+        let collateral_out: u64 = cdp_out.iter().map(|u| parse_lovelace(&u.value)).sum();
+        let deltas = iasset_deltas(inputs, outputs, config);
+        let adjusted = !cdp_in.is_empty() && !cdp_out.is_empty();
+        if let [(iasset, delta)] = deltas.iter().collect::<Vec<_>>()[..] {
+            if adjusted && *delta < 0 && collateral_in > collateral_out {
+                push(EventKind::CdpRedeemedAgainst {
+                    iasset: iasset.clone(),
+                    redeemed_quantity: delta.unsigned_abs().min(u64::MAX as u128) as u64,
+                    ada_returned_lovelace: collateral_in - collateral_out,
+                    tx_hash: tx_hash.clone(),
+                });
+                continue;
+            }
+        }
+        if cdp_in.is_empty() {
+            push(EventKind::CdpOpen {
+                collateral_lovelace: collateral_out,
+                tx_hash: tx_hash.clone(),
+            });
+        } else if cdp_out.is_empty() {
+            push(EventKind::CdpClose {
+                collateral_returned_lovelace: collateral_in,
+                tx_hash: tx_hash.clone(),
+            });
+        } else if collateral_out > collateral_in {
+            push(EventKind::CdpDeposit {
+                amount_lovelace: collateral_out - collateral_in,
+                tx_hash: tx_hash.clone(),
+            });
+        } else if collateral_in > collateral_out {
+            push(EventKind::CdpWithdraw {
+                amount_lovelace: collateral_in - collateral_out,
+                tx_hash: tx_hash.clone(),
+            });
+        }
+
+        for (iasset, delta) in deltas {
+            let quantity = delta.unsigned_abs().min(u64::MAX as u128) as u64;
+            push(if delta > 0 {
+                EventKind::CdpMint {
+                    iasset,
+                    quantity,
+                    tx_hash: tx_hash.clone(),
+                }
+            } else {
+                EventKind::CdpBurn {
+                    iasset,
+                    quantity,
+                    tx_hash: tx_hash.clone(),
+                }
+            });
+        }
+
+        let ada_in: u64 = inputs.iter().map(|u| parse_lovelace(&u.value)).sum();
+        let ada_out: u64 = outputs.iter().map(|u| parse_lovelace(&u.value)).sum();
+        if ada_in > ada_out {
+            push(EventKind::CdpFee {
+                amount_lovelace: ada_in - ada_out,
+                tx_hash: tx_hash.clone(),
+            });
+        }
+    }
+    events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
+    events
+}
+
+/// True if this UTxO carries a configured CDP datum.
+pub(crate) fn is_cdp_utxo(utxo: &KoiosUtxo, config: &IndigoV2Config) -> bool {
+    config.is_cdp_datum(utxo.effective_datum_hash().as_deref())
+}
+
+/// Net change (outputs minus inputs) of each iAsset in the tx, non-zero only. INDY is never an
+/// iAsset, whatever `iasset_policy_ids` allows.
+fn iasset_deltas(
+    inputs: &[KoiosUtxo],
+    outputs: &[KoiosUtxo],
+    config: &IndigoV2Config,
+) -> BTreeMap<String, i128> {
+    let mut deltas: BTreeMap<String, i128> = BTreeMap::new();
+    for (utxos, sign) in [(inputs, -1i128), (outputs, 1)] {
+        for asset in utxos.iter().flat_map(|u| u.asset_list.iter().flatten()) {
+            if !config.is_known_iasset_policy(&asset.policy_id)
+                || config.is_indy_policy(&asset.policy_id)
+            {
+                continue;
+            }
+            let quantity = asset.quantity.trim().parse::<i128>().unwrap_or(0);
+            *deltas
+                .entry(asset_unit(&asset.policy_id, &asset.asset_name))
+                .or_default() += sign * quantity;
+        }
+    }
+    deltas.retain(|_, d| *d != 0);
+    deltas
+}
+
+fn parse_lovelace(s: &str) -> u64 {
+    s.trim().parse::<u64>().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(lovelace: u64, datum: Option<&str>, iusd: u64) -> KoiosUtxo {
+        KoiosUtxo::lovelace(lovelace)
+            .with_datum_hash(datum)
+            .with_asset("f0ff", "69555344", iusd)
+    }
+
+    fn kinds(utxos: KoiosTxUtxos, config: &IndigoV2Config) -> Vec<EventKind> {
+        let txs = vec![KoiosAccountTx {
+            tx_hash: "t".into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(1),
+        }];
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        reconstruct_cdp_events(&txs, |_| Some(utxos.clone()), now, config)
+            .into_iter()
+            .map(|e| e.kind)
+            .collect()
+    }
+
+    #[test]
+    fn open_mint_and_close_burn() {
+        let config = IndigoV2Config {
+            cdp_datum_hashes: vec!["cd".into()],
+            iasset_policy_ids: vec!["f0ff".into()],
+            ..Default::default()
+        };
+        let open = KoiosTxUtxos {
+            inputs: Some(vec![utxo(100_200_000, None, 0)]),
+            outputs: Some(vec![utxo(100_000_000, Some("cd"), 0), utxo(0, None, 40)]),
+            metadata: None,
+        };
+        assert_eq!(
+            kinds(open, &config),
+            vec![
+                EventKind::CdpOpen {
+                    collateral_lovelace: 100_000_000,
+                    tx_hash: "t".into()
+                },
+                EventKind::CdpMint {
+                    iasset: "f0ff$69555344".into(),
+                    quantity: 40,
+                    tx_hash: "t".into()
+                },
+                EventKind::CdpFee {
+                    amount_lovelace: 200_000,
+                    tx_hash: "t".into()
+                },
+            ]
+        );
+        let close = KoiosTxUtxos {
+            inputs: Some(vec![
+                utxo(100_000_000, Some("cd"), 0),
+                utxo(2_000_000, None, 40),
+            ]),
+            outputs: Some(vec![utxo(101_800_000, None, 0)]),
+            metadata: None,
+        };
+        let closed = kinds(close, &config);
+        assert!(matches!(
+            closed[0],
+            EventKind::CdpClose {
+                collateral_returned_lovelace: 100_000_000,
+                ..
+            }
+        ));
+        assert!(matches!(closed[1], EventKind::CdpBurn { quantity: 40, .. }));
+        // Without CDP datum hashes nothing is a CDP.
+        let plain = KoiosTxUtxos {
+            inputs: Some(vec![utxo(1, Some("cd"), 0)]),
+            outputs: None,
+            metadata: None,
+        };
+        assert!(kinds(plain, &IndigoV2Config::default()).is_empty());
+    }
+
+    #[test]
+    fn redemption_against_cdp() {
+        let config = IndigoV2Config {
+            cdp_datum_hashes: vec!["cd".into()],
+            iasset_policy_ids: vec!["f0ff".into()],
+            ..Default::default()
+        };
+        let redeem = KoiosTxUtxos {
+            inputs: Some(vec![
+                utxo(100_000_000, Some("cd"), 0),
+                utxo(3_000_000, None, 10),
+            ]),
+            outputs: Some(vec![
+                utxo(75_000_000, Some("cd"), 0),
+                utxo(27_800_000, None, 0),
+            ]),
+            metadata: None,
+        };
+        assert_eq!(
+            kinds(redeem, &config),
+            vec![EventKind::CdpRedeemedAgainst {
+                iasset: "f0ff$69555344".into(),
+                redeemed_quantity: 10,
+                ada_returned_lovelace: 25_000_000,
+                tx_hash: "t".into()
+            }]
+        );
+        // A plain collateral withdrawal burns nothing.
+        let withdraw = KoiosTxUtxos {
+            inputs: Some(vec![utxo(100_000_000, Some("cd"), 0)]),
+            outputs: Some(vec![
+                utxo(75_000_000, Some("cd"), 0),
+                utxo(24_800_000, None, 0),
+            ]),
+            metadata: None,
+        };
+        assert!(matches!(
+            kinds(withdraw, &config)[0],
+            EventKind::CdpWithdraw {
+                amount_lovelace: 25_000_000,
+                ..
+            }
+        ));
+    }
+}
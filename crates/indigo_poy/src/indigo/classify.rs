@@ -0,0 +1,166 @@
+//! One category per tx, decided before the CDP / governance / SP / ROB / INDY parsers run, so a
+//! single tx (say a liquidation that also nets the wallet ADA) cannot be counted by several of
+//! them.
+
+use crate::chain::fetch::KoiosTxUtxos;
+use crate::indigo::cdp::is_cdp_utxo;
+use crate::indigo::governance::is_governance_utxo;
+use crate::indigo::indy_staking::{is_indy_distribution, net_indy};
+use crate::indigo::protocol_config::IndigoV2Config;
+use crate::indigo::stability_pool::is_sp_utxo;
+use serde::{Deserialize, Serialize};
+
+/// Which parser a tx is handed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxCategory {
+    Cdp,
+    Governance,
+    StabilityPool,
+    Rob,
+    IndyStaking,
+    /// INDY airdrop or vesting unlock, from a configured distributor.
+    IndyDistribution,
+    /// Not recognizably Indigo; no events.
+    Other,
+}
+
+/// Classify one tx, most specific evidence first:
+/// 1. a CDP datum among inputs or outputs (only with `cdp_datum_hashes` configured);
+/// 2. a governance staking or poll datum (only with governance datum hashes configured);
+/// 3. an input at a configured INDY airdrop distributor or vesting contract;
+/// 4. a Stability Pool UTxO (datum and iAsset policy per config) among inputs or outputs;
+/// 5. a ROB datum, or with no ROB datum hashes configured, ADA leaving with none coming back
+///    (the order-placement shape);
+/// 6. a net ADA gain or, with `indy_policy_id` configured, a net INDY gain, read as an INDY
+///    staking reward;
+/// 7. anything else.
+pub fn classify_tx(utxos: &KoiosTxUtxos, config: &IndigoV2Config) -> TxCategory {
+    let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+    let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+    let mut all = inputs.iter().chain(outputs.iter());
+    if all.clone().any(|u| is_cdp_utxo(u, config)) {
+        return TxCategory::Cdp;
+    }
+    if all.clone().any(|u| is_governance_utxo(u, config)) {
+        return TxCategory::Governance;
+    }
+    if is_indy_distribution(inputs, config) {
+        return TxCategory::IndyDistribution;
+    }
+    if all.clone().any(|u| is_sp_utxo(u, config)) {
+        return TxCategory::StabilityPool;
+    }
+    let lovelace = |us: &[crate::chain::fetch::KoiosUtxo]| -> u64 {
+        us.iter()
+            .map(|u| u.value.trim().parse::<u64>().unwrap_or(0))
+            .sum()
+    };
+    let (ada_in, ada_out) = (lovelace(inputs), lovelace(outputs));
+    let rob = if config.has_rob_datum_hashes() {
+        all.any(|u| config.is_rob_datum(u.effective_datum_hash().as_deref()))
+    } else {
+        ada_in > 0 && ada_out == 0
+    };
+    if rob {
+        TxCategory::Rob
+    } else if ada_out > ada_in || net_indy(inputs, outputs, config).is_some_and(|q| q > 0) {
+        TxCategory::IndyStaking
+    } else {
+        TxCategory::Other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::KoiosUtxo;
+
+    fn utxo(lovelace: u64, iasset: bool) -> KoiosUtxo {
+        KoiosUtxo::lovelace(lovelace).with_asset("f0ff", "69555344", iasset.into())
+    }
+
+    fn tx(inputs: Vec<KoiosUtxo>, outputs: Vec<KoiosUtxo>) -> KoiosTxUtxos {
+        KoiosTxUtxos {
+            inputs: Some(inputs),
+            outputs: Some(outputs),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn each_tx_gets_one_category() {
+        let config = IndigoV2Config::default();
+        // An SP output with an ADA gain is a liquidation, not also a reward.
+        let liquidation = tx(vec![utxo(2_000_000, false)], vec![utxo(3_000_000, true)]);
+        assert_eq!(
+            classify_tx(&liquidation, &config),
+            TxCategory::StabilityPool
+        );
+        let reward = tx(vec![utxo(2_000_000, false)], vec![utxo(2_500_000, false)]);
+        assert_eq!(classify_tx(&reward, &config), TxCategory::IndyStaking);
+        let place = tx(vec![utxo(2_000_000, false)], vec![]);
+        assert_eq!(classify_tx(&place, &config), TxCategory::Rob);
+        let spend = tx(vec![utxo(2_000_000, false)], vec![utxo(1_800_000, false)]);
+        assert_eq!(classify_tx(&spend, &config), TxCategory::Other);
+
+        let mut rob_datum = utxo(2_500_000, false);
+        rob_datum.datum_hash = Some("ab".into());
+        let config = IndigoV2Config {
+            rob_datum_hashes: vec!["ab".into()],
+            ..Default::default()
+        };
+        let fill = tx(vec![utxo(2_000_000, false)], vec![rob_datum]);
+        assert_eq!(classify_tx(&fill, &config), TxCategory::Rob);
+        assert_eq!(classify_tx(&place, &config), TxCategory::Other);
+
+        // A CDP mint carries iAssets like an SP deposit; the CDP datum decides.
+        let mut cdp_datum = utxo(50_000_000, false);
+        cdp_datum.datum_hash = Some("cd".into());
+        let config = IndigoV2Config {
+            cdp_datum_hashes: vec!["cd".into()],
+            ..Default::default()
+        };
+        let mint = tx(
+            vec![utxo(52_000_000, false)],
+            vec![cdp_datum, utxo(0, true)],
+        );
+        assert_eq!(classify_tx(&mint, &config), TxCategory::Cdp);
+
+        // An INDY payout counts as a reward even when fees leave the tx ADA-negative.
+        let config = IndigoV2Config {
+            iasset_policy_ids: vec!["beef".into()],
+            indy_policy_id: Some("f0ff".into()),
+            ..Default::default()
+        };
+        let indy = tx(vec![utxo(2_000_000, false)], vec![utxo(1_800_000, true)]);
+        assert_eq!(classify_tx(&indy, &config), TxCategory::IndyStaking);
+        // Unstaking pays out INDY too, but the staking position marks it as governance.
+        let mut position = utxo(2_000_000, false);
+        position.datum_hash = Some("5a".into());
+        let config = IndigoV2Config {
+            governance_staking_datum_hashes: vec!["5a".into()],
+            ..config
+        };
+        let unlock = tx(vec![position], vec![utxo(1_800_000, true)]);
+        assert_eq!(classify_tx(&unlock, &config), TxCategory::Governance);
+    }
+
+    #[test]
+    fn liquidation_is_counted_once() {
+        use crate::chain::fetch::KoiosAccountTx;
+        let txs = vec![KoiosAccountTx {
+            tx_hash: "liq".into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(1),
+        }];
+        let liquidation = tx(vec![utxo(2_000_000, false)], vec![utxo(3_000_000, true)]);
+        let now = time::OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events =
+            crate::indigo::reconstruct_all_events(&txs, |_| Some(liquidation.clone()), now, None);
+        assert_eq!(events.all_events().count(), 1);
+        assert_eq!(events.stability_pool.len(), 1);
+    }
+}
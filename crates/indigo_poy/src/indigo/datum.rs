@@ -0,0 +1,516 @@
+//! Plutus-data CBOR decoding, and the Indigo Stability Pool and ROB order datums read with it.
+//!
+//! Only the CBOR subset plutus data uses is accepted: integers (with bignum tags 2 / 3), byte
+//! strings, lists, maps, and constructor tags (121–127, 1280–1400, and the general form 102),
+//! each in definite or indefinite length.
+
+use crate::compute::fixed::MICRO;
+use thiserror::Error;
+
+/// Deepest nesting accepted; real Indigo datums are a handful of levels deep.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DatumError {
+    #[error("datum is not hex")]
+    Hex,
+    #[error("datum CBOR ends early")]
+    Truncated,
+    #[error("datum CBOR has {0} trailing bytes")]
+    Trailing(usize),
+    #[error("datum CBOR not plutus data at byte {0}")]
+    Unsupported(usize),
+    #[error("datum nested deeper than {MAX_DEPTH}")]
+    TooDeep,
+    #[error("unexpected datum shape: {0}")]
+    Shape(&'static str),
+}
+
+/// Decoded plutus data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlutusData {
+    Constr { tag: u64, fields: Vec<PlutusData> },
+    Map(Vec<(PlutusData, PlutusData)>),
+    List(Vec<PlutusData>),
+    Int(i128),
+    Bytes(Vec<u8>),
+}
+
+/// Decode hex CBOR (as in [`crate::chain::fetch::KoiosInlineDatum::bytes`]) into plutus data.
+pub fn decode_plutus_data(cbor_hex: &str) -> Result<PlutusData, DatumError> {
+    let bytes = hex::decode(cbor_hex.trim()).map_err(|_| DatumError::Hex)?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        pos: 0,
+    };
+    let data = reader.data(0)?;
+    match bytes.len() - reader.pos {
+        0 => Ok(data),
+        n => Err(DatumError::Trailing(n)),
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+/// Argument of a CBOR head: a length / value, or indefinite length.
+enum Arg {
+    Value(u64),
+    Indefinite,
+}
+
+const BREAK: u8 = 0xff;
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, DatumError> {
+        let b = *self.bytes.get(self.pos).ok_or(DatumError::Truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, n: u64) -> Result<&[u8], DatumError> {
+        let n = usize::try_from(n).map_err(|_| DatumError::Truncated)?;
+        let end = self.pos.checked_add(n).ok_or(DatumError::Truncated)?;
+        let slice = self.bytes.get(self.pos..end).ok_or(DatumError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn at_break(&mut self) -> Result<bool, DatumError> {
+        let b = *self.bytes.get(self.pos).ok_or(DatumError::Truncated)?;
+        if b == BREAK {
+            self.pos += 1;
+        }
+        Ok(b == BREAK)
+    }
+
+    /// Major type and argument of the next item.
+    fn head(&mut self) -> Result<(u8, Arg), DatumError> {
+        let start = self.pos;
+        let b = self.byte()?;
+        let info = b & 0x1f;
+        let arg = match info {
+            0..=23 => Arg::Value(u64::from(info)),
+            24..=27 => {
+                let width = 1u64 << (info - 24);
+                let value = self
+                    .take(width)?
+                    .iter()
+                    .fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+                Arg::Value(value)
+            }
+            31 => Arg::Indefinite,
+            _ => return Err(DatumError::Unsupported(start)),
+        };
+        Ok((b >> 5, arg))
+    }
+
+    fn data(&mut self, depth: usize) -> Result<PlutusData, DatumError> {
+        if depth > MAX_DEPTH {
+            return Err(DatumError::TooDeep);
+        }
+        let start = self.pos;
+        match self.head()? {
+            (0, Arg::Value(n)) => Ok(PlutusData::Int(i128::from(n))),
+            (1, Arg::Value(n)) => Ok(PlutusData::Int(-1 - i128::from(n))),
+            (2, arg) => Ok(PlutusData::Bytes(self.byte_string(arg, start)?)),
+            (4, arg) => Ok(PlutusData::List(self.items(arg, depth)?)),
+            (5, arg) => {
+                let mut entries = Vec::new();
+                let mut next = |r: &mut Self| -> Result<(), DatumError> {
+                    let k = r.data(depth + 1)?;
+                    entries.push((k, r.data(depth + 1)?));
+                    Ok(())
+                };
+                match arg {
+                    Arg::Value(n) => (0..n).try_for_each(|_| next(self))?,
+                    Arg::Indefinite => {
+                        while !self.at_break()? {
+                            next(self)?;
+                        }
+                    }
+                }
+                Ok(PlutusData::Map(entries))
+            }
+            (6, Arg::Value(tag)) => self.tagged(tag, start, depth),
+            _ => Err(DatumError::Unsupported(start)),
+        }
+    }
+
+    fn items(&mut self, arg: Arg, depth: usize) -> Result<Vec<PlutusData>, DatumError> {
+        let mut items = Vec::new();
+        match arg {
+            Arg::Value(n) => {
+                for _ in 0..n {
+                    items.push(self.data(depth + 1)?);
+                }
+            }
+            Arg::Indefinite => {
+                while !self.at_break()? {
+                    items.push(self.data(depth + 1)?);
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Definite bytes, or indefinite bytes as a run of definite chunks.
+    fn byte_string(&mut self, arg: Arg, start: usize) -> Result<Vec<u8>, DatumError> {
+        match arg {
+            Arg::Value(n) => Ok(self.take(n)?.to_vec()),
+            Arg::Indefinite => {
+                let mut out = Vec::new();
+                while !self.at_break()? {
+                    match self.head()? {
+                        (2, Arg::Value(n)) => out.extend_from_slice(self.take(n)?),
+                        _ => return Err(DatumError::Unsupported(start)),
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    fn tagged(&mut self, tag: u64, start: usize, depth: usize) -> Result<PlutusData, DatumError> {
+        let constr = |tag, r: &mut Self| -> Result<PlutusData, DatumError> {
+            match r.head()? {
+                (4, arg) => Ok(PlutusData::Constr {
+                    tag,
+                    fields: r.items(arg, depth)?,
+                }),
+                _ => Err(DatumError::Unsupported(start)),
+            }
+        };
+        match tag {
+            121..=127 => constr(tag - 121, self),
+            1280..=1400 => constr(tag - 1280 + 7, self),
+            102 => match self.data(depth + 1)? {
+                PlutusData::List(mut pair) if pair.len() == 2 => {
+                    let fields = match pair.pop() {
+                        Some(PlutusData::List(fields)) => fields,
+                        _ => return Err(DatumError::Unsupported(start)),
+                    };
+                    match pair.pop() {
+                        Some(PlutusData::Int(tag)) if tag >= 0 => Ok(PlutusData::Constr {
+                            tag: u64::try_from(tag).map_err(|_| DatumError::Unsupported(start))?,
+                            fields,
+                        }),
+                        _ => Err(DatumError::Unsupported(start)),
+                    }
+                }
+                _ => Err(DatumError::Unsupported(start)),
+            },
+            2 | 3 => {
+                let pos = self.pos;
+                let (major, arg) = self.head()?;
+                if major != 2 {
+                    return Err(DatumError::Unsupported(pos));
+                }
+                let magnitude = self.byte_string(arg, pos)?;
+                let significant: Vec<u8> = magnitude.into_iter().skip_while(|b| *b == 0).collect();
+                if significant.len() > 15 {
+                    return Err(DatumError::Unsupported(start));
+                }
+                let n = significant
+                    .iter()
+                    .fold(0i128, |acc, b| (acc << 8) | i128::from(*b));
+                Ok(PlutusData::Int(if tag == 2 { n } else { -1 - n }))
+            }
+            _ => Err(DatumError::Unsupported(start)),
+        }
+    }
+}
+
+/// Stability Pool snapshot (`P`, `D`, `S`, epoch, scale), integers as stored: Indigo's
+/// fixed-point values are left unscaled.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpSnapshot {
+    pub product: i128,
+    /// Deposit: iAsset in the pool (pool datum) or the account's share of it (account datum).
+    pub deposit: i128,
+    pub sum: i128,
+    pub epoch: i128,
+    pub scale: i128,
+}
+
+/// The pool UTxO's state for one iAsset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpPool {
+    /// iAsset token name (hex).
+    pub iasset: String,
+    pub snapshot: SpSnapshot,
+}
+
+/// One owner's Stability Pool account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpAccount {
+    /// Owner's payment key hash (hex).
+    pub owner: String,
+    /// iAsset token name (hex).
+    pub iasset: String,
+    pub snapshot: SpSnapshot,
+    /// An adjust / close request is waiting to be processed.
+    pub pending_request: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpDatum {
+    Pool(SpPool),
+    Account(SpAccount),
+}
+
+/// Decode an Indigo V2 Stability Pool datum:
+/// - pool: `Constr 0 [iasset, snapshot, epoch_to_scale_to_sum]`;
+/// - account: `Constr 1 [owner, iasset, snapshot, Maybe request]`;
+///
+/// where a snapshot is `Constr 0 [P, D, S, epoch, scale]` and each number is an integer or an
+/// `SPInteger` wrapper (`Constr 0 [int]`). Other SP datums (the epoch-sum snapshots) are
+/// [`DatumError::Shape`].
+pub fn decode_sp_datum(cbor_hex: &str) -> Result<SpDatum, DatumError> {
+    let PlutusData::Constr { tag, fields } = decode_plutus_data(cbor_hex)? else {
+        return Err(DatumError::Shape("not a constructor"));
+    };
+    match (tag, fields.as_slice()) {
+        (0, [iasset, snapshot, _]) => Ok(SpDatum::Pool(SpPool {
+            iasset: bytes_hex(iasset, "iasset")?,
+            snapshot: sp_snapshot(snapshot)?,
+        })),
+        (1, [owner, iasset, snapshot, request]) => Ok(SpDatum::Account(SpAccount {
+            owner: bytes_hex(owner, "owner")?,
+            iasset: bytes_hex(iasset, "iasset")?,
+            snapshot: sp_snapshot(snapshot)?,
+            pending_request: match request {
+                PlutusData::Constr { tag: 0, fields } if fields.len() == 1 => true,
+                PlutusData::Constr { tag: 1, fields } if fields.is_empty() => false,
+                _ => return Err(DatumError::Shape("request is not a Maybe")),
+            },
+        })),
+        _ => Err(DatumError::Shape("unknown constructor")),
+    }
+}
+
+fn bytes_hex(data: &PlutusData, field: &'static str) -> Result<String, DatumError> {
+    match data {
+        PlutusData::Bytes(b) => Ok(hex::encode(b)),
+        _ => Err(DatumError::Shape(field)),
+    }
+}
+
+/// An integer, bare or in a single-field wrapper (`SPInteger`, `OnChainDecimal`).
+fn wrapped_int(data: &PlutusData, field: &'static str) -> Result<i128, DatumError> {
+    match data {
+        PlutusData::Int(n) => Ok(*n),
+        PlutusData::Constr { tag: 0, fields } => match fields.as_slice() {
+            [PlutusData::Int(n)] => Ok(*n),
+            _ => Err(DatumError::Shape(field)),
+        },
+        _ => Err(DatumError::Shape(field)),
+    }
+}
+
+fn sp_snapshot(data: &PlutusData) -> Result<SpSnapshot, DatumError> {
+    let PlutusData::Constr { tag: 0, fields } = data else {
+        return Err(DatumError::Shape("snapshot"));
+    };
+    let [p, d, s, epoch, scale] = fields.as_slice() else {
+        return Err(DatumError::Shape("snapshot"));
+    };
+    Ok(SpSnapshot {
+        product: wrapped_int(p, "snapshot value")?,
+        deposit: wrapped_int(d, "snapshot value")?,
+        sum: wrapped_int(s, "snapshot value")?,
+        epoch: wrapped_int(epoch, "snapshot value")?,
+        scale: wrapped_int(scale, "snapshot value")?,
+    })
+}
+
+/// `OnChainDecimal` units per 1 (Indigo's fixed-point precision).
+const ON_CHAIN_DECIMAL_UNIT: f64 = MICRO as f64;
+
+/// An Indigo V2 ROB order's terms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RobOrderDatum {
+    /// Owner's payment key hash (hex).
+    pub owner: String,
+    /// iAsset token name (hex).
+    pub iasset: String,
+    /// Premium over the oracle price the order accepts, in percent.
+    pub premium_pct: f64,
+    /// Reimbursement paid to the order on redemption, in percent.
+    pub reimbursement_pct: f64,
+}
+
+/// Decode a ROB order datum: `Constr 0 [owner, iasset, premium, reimbursement]`, the two rates
+/// as `OnChainDecimal` fractions (`Constr 0 [int]`, 10^6 = 1, so 20_000 is 2%).
+pub fn decode_rob_datum(cbor_hex: &str) -> Result<RobOrderDatum, DatumError> {
+    let PlutusData::Constr { tag: 0, fields } = decode_plutus_data(cbor_hex)? else {
+        return Err(DatumError::Shape("not a ROB order"));
+    };
+    let [owner, iasset, premium, reimbursement] = fields.as_slice() else {
+        return Err(DatumError::Shape("not a ROB order"));
+    };
+    let pct = |data, field| -> Result<f64, DatumError> {
+        Ok(wrapped_int(data, field)? as f64 / (ON_CHAIN_DECIMAL_UNIT / 100.0))
+    };
+    Ok(RobOrderDatum {
+        owner: bytes_hex(owner, "owner")?,
+        iasset: bytes_hex(iasset, "iasset")?,
+        premium_pct: pct(premium, "premium")?,
+        reimbursement_pct: pct(reimbursement, "reimbursement")?,
+    })
+}
+
+/// An Indigo V2 price oracle's feed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OracleDatum {
+    /// ADA per iAsset. Both have 6 decimals, so this is also lovelace per iAsset base unit.
+    pub price: f64,
+    /// POSIX time (ms) after which the price is stale.
+    pub expiration_ms: i128,
+}
+
+/// Decode a price oracle datum: `Constr 0 [price, expiration]`, the price an `OnChainDecimal`
+/// (10^6 = 1 ADA).
+pub fn decode_oracle_datum(cbor_hex: &str) -> Result<OracleDatum, DatumError> {
+    let PlutusData::Constr { tag: 0, fields } = decode_plutus_data(cbor_hex)? else {
+        return Err(DatumError::Shape("not a price oracle"));
+    };
+    let [price, expiration] = fields.as_slice() else {
+        return Err(DatumError::Shape("not a price oracle"));
+    };
+    let PlutusData::Int(expiration_ms) = expiration else {
+        return Err(DatumError::Shape("oracle expiration"));
+    };
+    Ok(OracleDatum {
+        price: wrapped_int(price, "oracle price")? as f64 / ON_CHAIN_DECIMAL_UNIT,
+        expiration_ms: *expiration_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SP account of owner `ab…ab` in iUSD with `D` = 500, `P` = 1_000_000, no request.
+    const ACCOUNT_DEFINITE: &str = concat!(
+        "d87a84",
+        "581cabababababababababababababababababababababababababababab",
+        "4469555344",
+        "d87985",
+        "d879811a000f4240",
+        "d879811901f4",
+        "d8798100",
+        "d8798100",
+        "d8798100",
+        "d87a80",
+    );
+
+    /// The same account as the ledger serialises it: indefinite-length lists.
+    const ACCOUNT_INDEFINITE: &str = concat!(
+        "d87a9f",
+        "581cabababababababababababababababababababababababababababab",
+        "4469555344",
+        "d8799f",
+        "d8799f1a000f4240ff",
+        "d8799f1901f4ff",
+        "d8799f00ff",
+        "d8799f00ff",
+        "d8799f00ff",
+        "ff",
+        "d87a80",
+        "ff",
+    );
+
+    #[test]
+    fn decodes_account_in_either_length_encoding() {
+        let expected = SpDatum::Account(SpAccount {
+            owner: "ab".repeat(28),
+            iasset: "69555344".into(),
+            snapshot: SpSnapshot {
+                product: 1_000_000,
+                deposit: 500,
+                ..Default::default()
+            },
+            pending_request: false,
+        });
+        assert_eq!(decode_sp_datum(ACCOUNT_DEFINITE).unwrap(), expected);
+        assert_eq!(decode_sp_datum(ACCOUNT_INDEFINITE).unwrap(), expected);
+    }
+
+    #[test]
+    fn decodes_pool_with_bignum_and_general_constructor() {
+        // Constr 0 via tag 102, D = 2^64 as a bignum, pending map left undecoded.
+        let pool = concat!(
+            "d866",
+            "8200",
+            "83",
+            "4469555344",
+            "d87985",
+            "01",
+            "c249010000000000000000",
+            "20",
+            "02",
+            "03",
+            "a0",
+        );
+        let SpDatum::Pool(pool) = decode_sp_datum(pool).unwrap() else {
+            panic!("not a pool datum");
+        };
+        assert_eq!(pool.iasset, "69555344");
+        assert_eq!(
+            pool.snapshot,
+            SpSnapshot {
+                product: 1,
+                deposit: 1 << 64,
+                sum: -1,
+                epoch: 2,
+                scale: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn decodes_rob_order_rates() {
+        // Premium 2% (20_000), reimbursement 1.5% (15_000).
+        let order = concat!(
+            "d87984",
+            "581cabababababababababababababababababababababababababababab",
+            "4469555344",
+            "d87981194e20",
+            "d87981193a98",
+        );
+        let datum = decode_rob_datum(order).unwrap();
+        assert_eq!(datum.iasset, "69555344");
+        assert!((datum.premium_pct - 2.0).abs() < 1e-9);
+        assert!((datum.reimbursement_pct - 1.5).abs() < 1e-9);
+        assert!(decode_rob_datum(ACCOUNT_DEFINITE).is_err());
+    }
+
+    #[test]
+    fn oracle_datum_price_and_expiration() {
+        // Constr 0 [OnChainDecimal 2_000_000, 1_700_000_000_000]
+        let datum = decode_oracle_datum("d87982d879811a001e84801b0000018bcfe56800").unwrap();
+        assert!((datum.price - 2.0).abs() < 1e-9);
+        assert_eq!(datum.expiration_ms, 1_700_000_000_000);
+        assert!(decode_oracle_datum(ACCOUNT_DEFINITE).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_and_foreign_datums() {
+        assert_eq!(decode_plutus_data("zz"), Err(DatumError::Hex));
+        assert_eq!(decode_plutus_data("d87a84"), Err(DatumError::Truncated));
+        assert_eq!(decode_plutus_data("d8798000"), Err(DatumError::Trailing(1)));
+        // Text strings are not plutus data.
+        assert_eq!(decode_plutus_data("6161"), Err(DatumError::Unsupported(0)));
+        assert_eq!(
+            decode_sp_datum("d87980"),
+            Err(DatumError::Shape("unknown constructor"))
+        );
+        assert_eq!(
+            decode_plutus_data(&"81".repeat(100)),
+            Err(DatumError::TooDeep)
+        );
+    }
+}
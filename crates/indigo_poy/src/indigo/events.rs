@@ -1,6 +1,11 @@
-//! Unified event type for Stability Pool, ROB, and INDY staking.
+//! Unified event type for Stability Pool, ROB, INDY staking, CDPs, governance, and protocol
+//! fees.
 
+use crate::chain::assets::split_asset_unit;
+use crate::chain::fetch::KoiosUtxo;
+use crate::chain::network::is_script_address;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use time::OffsetDateTime;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -9,11 +14,16 @@ pub enum EventKind {
     StabilityPoolDeposit {
         amount_lovelace: u64,
         iasset_amount: Option<String>,
+        /// Change of the account datum's deposit field; `None` when inferred from ADA.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        iasset_quantity: Option<u128>,
         tx_hash: String,
     },
     StabilityPoolWithdraw {
         amount_lovelace: u64,
         iasset_amount: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        iasset_quantity: Option<u128>,
         tx_hash: String,
     },
     StabilityPoolLiquidation {
@@ -21,6 +31,20 @@ pub enum EventKind {
         ada_received_lovelace: u64,
         realized_premium_lovelace: u64,
         dilution_effect: Option<String>,
+        /// iAsset in the whole pool just before the liquidation (pool datum); `None` for the
+        /// per-tx ADA estimate.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pool_deposit_quantity: Option<u128>,
+        /// The wallet's compounded deposit then; `ada_received_lovelace` is its pro-rata share of
+        /// the ADA the pool received.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        user_deposit_quantity: Option<u128>,
+        /// The wallet's pro-rata share of the iAsset burnt.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        iasset_lost_quantity: Option<u128>,
+        /// Oracle price (ADA per iAsset) the realized premium was computed at.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        oracle_price: Option<f64>,
         tx_hash: String,
         slot: Option<u64>,
     },
@@ -35,16 +59,40 @@ pub enum EventKind {
         filled_lovelace: u64,
         premium_pct: Option<f64>,
         reimbursement_pct: Option<f64>,
+        /// `premium_pct` / `reimbursement_pct` are the tx ADA-delta estimate, not the order
+        /// datum's rates.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        premium_estimated: bool,
+        /// ADA left on the order after a partial fill; `None` for untracked orders.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        remaining_lovelace: Option<u64>,
         tx_hash: String,
         slot: Option<u64>,
     },
+    /// Order spent back to its owner without a fill.
+    RobOrderCancel {
+        order_id: Option<String>,
+        returned_lovelace: u64,
+        tx_hash: String,
+        slot: Option<u64>,
+    },
+    /// Idle time between a fill that left no order open and the next placement (`tx_hash`).
     RobCooldown {
         inferred_from_tx: bool,
         tx_hash: String,
+        /// The fill that started it.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        since_tx: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_secs: Option<u64>,
     },
     IndyStakingReward {
         amount_lovelace: u64,
         epoch: Option<u64>,
+        /// Net INDY received, in base units (6 decimals). `None` without `indy_policy_id` in
+        /// the config or when the tx moved no INDY.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        indy_quantity: Option<u64>,
         tx_hash: String,
     },
     IndySpPremium {
@@ -52,6 +100,83 @@ pub enum EventKind {
         tx_hash: String,
         slot: Option<u64>,
     },
+    /// One-off INDY distribution from a configured airdrop address. Not yield.
+    IndyAirdrop {
+        /// Net INDY received, base units; `None` without `indy_policy_id`.
+        indy_quantity: Option<u64>,
+        /// Net ADA received with it (usually the min-UTxO).
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    /// INDY released from a configured vesting contract. Not yield.
+    IndyVestingUnlock {
+        indy_quantity: Option<u64>,
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    CdpOpen {
+        collateral_lovelace: u64,
+        tx_hash: String,
+    },
+    CdpDeposit {
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    CdpWithdraw {
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    CdpMint {
+        iasset: String,
+        quantity: u64,
+        tx_hash: String,
+    },
+    CdpBurn {
+        iasset: String,
+        quantity: u64,
+        tx_hash: String,
+    },
+    CdpClose {
+        collateral_returned_lovelace: u64,
+        tx_hash: String,
+    },
+    /// Someone else redeemed `iasset` against the CDP: its debt shrank by `redeemed_quantity`
+    /// and `ada_returned_lovelace` of collateral went to the redeemer in exchange.
+    CdpRedeemedAgainst {
+        iasset: String,
+        redeemed_quantity: u64,
+        ada_returned_lovelace: u64,
+        tx_hash: String,
+    },
+    /// Network fee of a CDP tx (ADA in minus ADA out).
+    CdpFee {
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    /// ADA an Indigo tx paid to the protocol treasury or a fee collector.
+    ProtocolFeePaid {
+        amount_lovelace: u64,
+        tx_hash: String,
+    },
+    /// INDY locked into a governance staking position.
+    GovStakeLock {
+        indy_quantity: u64,
+        tx_hash: String,
+    },
+    GovStakeUnlock {
+        indy_quantity: u64,
+        tx_hash: String,
+    },
+    /// Vote on the poll whose UTxO (`tx_hash#index`) the tx spent.
+    GovVote {
+        poll: String,
+        tx_hash: String,
+    },
+    /// INDY deposited on a newly created poll.
+    GovProposalDeposit {
+        indy_quantity: u64,
+        tx_hash: String,
+    },
     OtherFlow {
         description: String,
         amount_lovelace: Option<u64>,
@@ -59,6 +184,70 @@ pub enum EventKind {
     },
 }
 
+/// How far an event's figures can be trusted, as set by the parser that produced it.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// Read from a matched datum, address, or policy.
+    High,
+    /// On a matched Indigo UTxO, but with amounts inferred from the shape of the tx.
+    Medium,
+    /// Guessed from ADA deltas alone.
+    #[default]
+    Heuristic,
+}
+
+impl Confidence {
+    pub fn is_heuristic(&self) -> bool {
+        *self == Confidence::Heuristic
+    }
+}
+
+/// The UTxOs an event was read from, so each figure can be traced to the chain.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Spent UTxOs, as `tx_hash#index`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+    /// Created UTxOs, as `tx_hash#index`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// Script addresses holding any of them, sorted; empty when the provider returns no
+    /// addresses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub script_addresses: Vec<String>,
+}
+
+impl Provenance {
+    pub fn from_utxos<'a>(
+        inputs: impl IntoIterator<Item = &'a KoiosUtxo>,
+        outputs: impl IntoIterator<Item = &'a KoiosUtxo>,
+    ) -> Self {
+        let mut addresses = BTreeSet::new();
+        let mut refs = |utxos: &mut dyn Iterator<Item = &'a KoiosUtxo>| -> Vec<String> {
+            utxos
+                .map(|u| {
+                    addresses.extend(u.address().filter(|a| is_script_address(a)));
+                    format!("{}#{}", u.tx_hash, u.tx_index)
+                })
+                .collect()
+        };
+        let inputs = refs(&mut inputs.into_iter());
+        let outputs = refs(&mut outputs.into_iter());
+        Self {
+            inputs,
+            outputs,
+            script_addresses: addresses.into_iter().map(str::to_string).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty() && self.outputs.is_empty() && self.script_addresses.is_empty()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Event {
     pub kind: EventKind,
@@ -67,12 +256,58 @@ pub struct Event {
     pub slot: Option<u64>,
     pub tx_hash: String,
     pub extra: Option<serde_json::Value>,
+    /// Events from bundles written before this was recorded read as `Heuristic`.
+    #[serde(default, skip_serializing_if = "Confidence::is_heuristic")]
+    pub confidence: Confidence,
+    #[serde(default, skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
 }
 
 impl Event {
     pub fn tx_hash(&self) -> &str {
         &self.tx_hash
     }
+
+    /// iAsset unit (`policy$asset_name`) the event names, if any.
+    pub fn asset_unit(&self) -> Option<&str> {
+        let unit = match &self.kind {
+            EventKind::StabilityPoolDeposit { iasset_amount, .. }
+            | EventKind::StabilityPoolWithdraw { iasset_amount, .. } => iasset_amount.as_deref(),
+            EventKind::StabilityPoolLiquidation { iasset_burnt, .. } => Some(iasset_burnt.as_str()),
+            EventKind::CdpMint { iasset, .. }
+            | EventKind::CdpBurn { iasset, .. }
+            | EventKind::CdpRedeemedAgainst { iasset, .. } => Some(iasset.as_str()),
+            _ => None,
+        };
+        unit.filter(|u| split_asset_unit(u).is_some())
+    }
+}
+
+/// Builder for test events: [`Event::at`] is a high-confidence event at `ts` in slot `ts`, and
+/// the `with_*` methods change the rest.
+#[cfg(test)]
+impl Event {
+    pub(crate) fn at(ts: i64, tx_hash: &str, kind: EventKind) -> Self {
+        Self {
+            kind,
+            timestamp: OffsetDateTime::from_unix_timestamp(ts).unwrap(),
+            slot: Some(ts as u64),
+            tx_hash: tx_hash.into(),
+            extra: None,
+            confidence: Confidence::High,
+            provenance: Provenance::default(),
+        }
+    }
+
+    pub(crate) fn with_slot(mut self, slot: Option<u64>) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    pub(crate) fn with_confidence(mut self, confidence: Confidence) -> Self {
+        self.confidence = confidence;
+        self
+    }
 }
 
 /// Collected Indigo-related events for an address.
@@ -81,6 +316,12 @@ pub struct IndigoEvents {
     pub stability_pool: Vec<Event>,
     pub rob: Vec<Event>,
     pub indy_staking: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cdp: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub governance: Vec<Event>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocol_fees: Vec<Event>,
     pub other: Vec<Event>,
 }
 
@@ -90,14 +331,160 @@ impl IndigoEvents {
             .iter()
             .chain(self.rob.iter())
             .chain(self.indy_staking.iter())
+            .chain(self.cdp.iter())
+            .chain(self.governance.iter())
+            .chain(self.protocol_fees.iter())
             .chain(self.other.iter())
     }
 
+    /// Distinct asset units named by any event, sorted.
+    pub fn asset_units(&self) -> Vec<String> {
+        let units: BTreeSet<&str> = self.all_events().filter_map(Event::asset_unit).collect();
+        units.into_iter().map(str::to_string).collect()
+    }
+
+    /// The latest claim of yield: an INDY staking reward, an SP premium payout, or an SP
+    /// withdrawal.
+    pub fn last_claim(&self) -> Option<&Event> {
+        self.all_events()
+            .filter(|e| {
+                matches!(
+                    e.kind,
+                    EventKind::IndyStakingReward { .. }
+                        | EventKind::IndySpPremium { .. }
+                        | EventKind::StabilityPoolWithdraw { .. }
+                )
+            })
+            .max_by_key(|e| (e.timestamp, e.slot, e.tx_hash.clone()))
+    }
+
+    /// Drop every event up to and including `claim`'s tx.
+    pub fn retain_after(&mut self, claim: &Event) {
+        let after = |e: &Event| match (e.slot, claim.slot) {
+            (Some(slot), Some(claimed)) => slot > claimed,
+            _ => e.timestamp > claim.timestamp,
+        };
+        for events in [
+            &mut self.stability_pool,
+            &mut self.rob,
+            &mut self.indy_staking,
+            &mut self.cdp,
+            &mut self.governance,
+            &mut self.protocol_fees,
+            &mut self.other,
+        ] {
+            events.retain(|e| after(e) && e.tx_hash != claim.tx_hash);
+        }
+    }
+
+    /// Add `other`'s events, each to its own list, and keep the lists in slot order. An event
+    /// already present (same tx and kind) is not added again.
+    pub fn merge(&mut self, other: IndigoEvents) {
+        fn extend(into: &mut Vec<Event>, from: Vec<Event>) {
+            for ev in from {
+                if !into
+                    .iter()
+                    .any(|e| e.tx_hash == ev.tx_hash && e.kind == ev.kind)
+                {
+                    into.push(ev);
+                }
+            }
+        }
+        extend(&mut self.stability_pool, other.stability_pool);
+        extend(&mut self.rob, other.rob);
+        extend(&mut self.indy_staking, other.indy_staking);
+        extend(&mut self.cdp, other.cdp);
+        extend(&mut self.governance, other.governance);
+        extend(&mut self.protocol_fees, other.protocol_fees);
+        extend(&mut self.other, other.other);
+        self.sort_by_slot_then_tx();
+    }
+
     pub fn sort_by_slot_then_tx(&mut self) {
         let key = |e: &Event| (e.slot.unwrap_or(0), e.tx_hash.clone());
         self.stability_pool.sort_by_key(key);
         self.rob.sort_by_key(key);
         self.indy_staking.sort_by_key(key);
+        self.cdp.sort_by_key(key);
+        self.governance.sort_by_key(key);
+        self.protocol_fees.sort_by_key(key);
         self.other.sort_by_key(key);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_after_the_last_claim() {
+        let event = |slot: u64, tx: &str, kind| Event::at(slot as i64, tx, kind);
+        let deposit = |slot, tx: &str| {
+            event(
+                slot,
+                tx,
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace: 1,
+                    iasset_amount: None,
+                    iasset_quantity: None,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let claim = |slot, tx: &str| {
+            event(
+                slot,
+                tx,
+                EventKind::IndyStakingReward {
+                    amount_lovelace: 1,
+                    epoch: None,
+                    indy_quantity: None,
+                    tx_hash: tx.into(),
+                },
+            )
+        };
+        let mut events = IndigoEvents {
+            stability_pool: vec![deposit(1, "a"), deposit(5, "c"), deposit(9, "e")],
+            indy_staking: vec![claim(3, "b"), claim(5, "c")],
+            ..Default::default()
+        };
+        let last = events.last_claim().unwrap().clone();
+        assert_eq!(last.tx_hash, "c");
+        events.retain_after(&last);
+        let left: Vec<&str> = events.all_events().map(|e| e.tx_hash.as_str()).collect();
+        assert_eq!(left, ["e"]);
+        assert!(events.last_claim().is_none());
+    }
+
+    #[test]
+    fn merge_adds_each_event_once() {
+        let deposit = |slot: i64, tx: &str, amount_lovelace| {
+            let kind = EventKind::StabilityPoolDeposit {
+                amount_lovelace,
+                iasset_amount: None,
+                iasset_quantity: None,
+                tx_hash: tx.into(),
+            };
+            Event::at(slot, tx, kind)
+        };
+        let mut events = IndigoEvents {
+            stability_pool: vec![deposit(1, "a", 1), deposit(5, "b", 2)],
+            ..Default::default()
+        };
+        events.merge(IndigoEvents {
+            stability_pool: vec![deposit(1, "a", 1), deposit(3, "b", 7), deposit(9, "c", 3)],
+            ..Default::default()
+        });
+        let left: Vec<(&str, u64)> = events
+            .stability_pool
+            .iter()
+            .map(|e| match e.kind {
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace, ..
+                } => (e.tx_hash.as_str(), amount_lovelace),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(left, [("a", 1), ("b", 7), ("b", 2), ("c", 3)]);
+    }
+}
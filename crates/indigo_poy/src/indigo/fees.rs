@@ -0,0 +1,120 @@
+//! Protocol fees: ADA an Indigo tx pays to the treasury or a fee collector.
+//!
+//! A tx's fee is the ADA its outputs put at a configured fee address (`protocol_fee_addresses`)
+//! less the ADA it spends from one, since collector UTxOs are spent and recreated. Needs output
+//! addresses from the provider: without them, or without configured addresses, no fees are
+//! found.
+
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
+use crate::indigo::protocol_config::IndigoV2Config;
+use time::OffsetDateTime;
+
+/// Reconstruct protocol fee events from Indigo txs (already classified; see
+/// [`crate::indigo::classify_tx`]) and tx UTxO data.
+pub fn reconstruct_protocol_fee_events(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: &IndigoV2Config,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    if config.protocol_fee_addresses.is_empty() {
+        return events;
+    }
+    for tx in account_txs {
+        let Some(utxos) = get_tx_utxos(&tx.tx_hash) else {
+            continue;
+        };
+        let at_fee_address = |utxos: &Option<Vec<KoiosUtxo>>| -> Vec<KoiosUtxo> {
+            utxos
+                .iter()
+                .flatten()
+                .filter(|u| {
+                    u.address()
+                        .is_some_and(|a| config.is_protocol_fee_address(a))
+                })
+                .cloned()
+                .collect()
+        };
+        let lovelace = |utxos: &[KoiosUtxo]| -> u64 {
+            utxos
+                .iter()
+                .map(|u| u.value.trim().parse::<u64>().unwrap_or(0))
+                .sum()
+        };
+        let (fee_in, fee_out) = (
+            at_fee_address(&utxos.inputs),
+            at_fee_address(&utxos.outputs),
+        );
+        let paid = lovelace(&fee_out).saturating_sub(lovelace(&fee_in));
+        if paid == 0 {
+            continue;
+        }
+        events.push(Event {
+            kind: EventKind::ProtocolFeePaid {
+                amount_lovelace: paid,
+                tx_hash: tx.tx_hash.clone(),
+            },
+            timestamp: tx
+                .block_time
+                .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                .unwrap_or(now),
+            slot: tx.slot_no,
+            tx_hash: tx.tx_hash.clone(),
+            extra: None,
+            confidence: Confidence::High,
+            provenance: Provenance::from_utxos(&fee_in, &fee_out),
+        });
+    }
+    events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(lovelace: u64, address: &str) -> KoiosUtxo {
+        KoiosUtxo::lovelace(lovelace).at(address)
+    }
+
+    #[test]
+    fn collector_gain_is_the_fee() {
+        let config = IndigoV2Config {
+            protocol_fee_addresses: vec!["addr1collector".into()],
+            ..Default::default()
+        };
+        let txs = vec![KoiosAccountTx {
+            tx_hash: "t".into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(1),
+        }];
+        let utxos = KoiosTxUtxos {
+            inputs: Some(vec![
+                utxo(50_000_000, "addr1collector"),
+                utxo(10_000_000, "addr1me"),
+            ]),
+            outputs: Some(vec![
+                utxo(51_500_000, "addr1collector"),
+                utxo(8_300_000, "addr1me"),
+            ]),
+            metadata: None,
+        };
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_protocol_fee_events(&txs, |_| Some(utxos.clone()), now, &config);
+        assert_eq!(
+            events[0].kind,
+            EventKind::ProtocolFeePaid {
+                amount_lovelace: 1_500_000,
+                tx_hash: "t".into()
+            }
+        );
+        let none = IndigoV2Config::default();
+        assert!(
+            reconstruct_protocol_fee_events(&txs, |_| Some(utxos.clone()), now, &none).is_empty()
+        );
+    }
+}
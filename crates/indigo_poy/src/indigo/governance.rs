@@ -0,0 +1,216 @@
+//! INDY governance: staking position lock / unlock, votes, and proposal deposits.
+//!
+//! Staked INDY sits on UTxOs with a governance staking datum and polls on UTxOs with a poll
+//! datum (`governance_staking_datum_hashes`, `governance_poll_datum_hashes`). A tx yields:
+//! - a lock or unlock for the net change of INDY on staking positions;
+//! - a proposal deposit for each poll it creates without spending one (the INDY on it);
+//! - a vote for each poll it spends and recreates next to a staking position.
+
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
+use crate::indigo::protocol_config::IndigoV2Config;
+use time::OffsetDateTime;
+
+/// Reconstruct governance events from account txs and tx UTxO data. Nothing is a
+/// governance UTxO without configured governance datum hashes.
+pub fn reconstruct_governance_events(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: &IndigoV2Config,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    for tx in account_txs {
+        let slot = tx.slot_no;
+        let ts = tx
+            .block_time
+            .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+            .unwrap_or(now);
+        let tx_hash = tx.tx_hash.clone();
+
+        let utxos = match get_tx_utxos(&tx_hash) {
+            Some(u) => u,
+            None => continue,
+        };
+
+        let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+        let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+        let staking =
+            |u: &&KoiosUtxo| config.is_gov_staking_datum(u.effective_datum_hash().as_deref());
+        let poll = |u: &&KoiosUtxo| config.is_gov_poll_datum(u.effective_datum_hash().as_deref());
+        let staked_in: Vec<&KoiosUtxo> = inputs.iter().filter(staking).collect();
+        let staked_out: Vec<&KoiosUtxo> = outputs.iter().filter(staking).collect();
+        let polls_in: Vec<&KoiosUtxo> = inputs.iter().filter(poll).collect();
+        let polls_out: Vec<&KoiosUtxo> = outputs.iter().filter(poll).collect();
+        let touches_stake = !staked_in.is_empty() || !staked_out.is_empty();
+        let mut push = |kind, provenance| {
+            // Votes are read from the tx's shape; the rest from datum-matched UTxOs.
+            let confidence = match kind {
+                EventKind::GovVote { .. } => Confidence::Medium,
+                _ => Confidence::High,
+            };
+            events.push(Event {
+                kind,
+                timestamp: ts,
+                slot,
+                tx_hash: tx_hash.clone(),
+                extra: None,
+                confidence,
+                provenance,
+            })
+        };
+        let positions = || Provenance::from_utxos(staked_in.clone(), staked_out.clone());
+
+        let delta = indy_on(&staked_out, config) - indy_on(&staked_in, config);
+        let indy_quantity = delta.unsigned_abs().min(u64::MAX as u128) as u64;
+        if delta > 0 {
+            push(
+                EventKind::GovStakeLock {
+                    indy_quantity,
+                    tx_hash: tx_hash.clone(),
+                },
+                positions(),
+            );
+        } else if delta < 0 {
+            push(
+                EventKind::GovStakeUnlock {
+                    indy_quantity,
+                    tx_hash: tx_hash.clone(),
+                },
+                positions(),
+            );
+        }
+
+        if polls_in.is_empty() {
+            for created in &polls_out {
+                let deposit = indy_on(std::slice::from_ref(created), config);
+                push(
+                    EventKind::GovProposalDeposit {
+                        indy_quantity: deposit.max(0).min(u64::MAX as i128) as u64,
+                        tx_hash: tx_hash.clone(),
+                    },
+                    Provenance::from_utxos([], [*created]),
+                );
+            }
+        } else if !polls_out.is_empty() && touches_stake {
+            for spent in &polls_in {
+                // The poll voted on, and the positions whose stake the vote counts.
+                let provenance = Provenance::from_utxos(
+                    std::iter::once(*spent).chain(staked_in.iter().copied()),
+                    polls_out.iter().chain(&staked_out).copied(),
+                );
+                push(
+                    EventKind::GovVote {
+                        poll: format!("{}#{}", spent.tx_hash, spent.tx_index),
+                        tx_hash: tx_hash.clone(),
+                    },
+                    provenance,
+                );
+            }
+        }
+    }
+    events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
+    events
+}
+
+/// True if this UTxO is a governance staking position or poll.
+pub(crate) fn is_governance_utxo(utxo: &KoiosUtxo, config: &IndigoV2Config) -> bool {
+    let datum = utxo.effective_datum_hash();
+    config.is_gov_staking_datum(datum.as_deref()) || config.is_gov_poll_datum(datum.as_deref())
+}
+
+/// INDY (base units) on `utxos`.
+fn indy_on(utxos: &[&KoiosUtxo], config: &IndigoV2Config) -> i128 {
+    utxos
+        .iter()
+        .flat_map(|u| u.asset_list.iter().flatten())
+        .filter(|a| config.is_indy_policy(&a.policy_id))
+        .map(|a| a.quantity.trim().parse::<i128>().unwrap_or(0))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(tx_hash: &str, datum: Option<&str>, indy: u64) -> KoiosUtxo {
+        KoiosUtxo {
+            tx_hash: tx_hash.into(),
+            ..KoiosUtxo::lovelace(2_000_000)
+                .with_datum_hash(datum)
+                .with_asset("1d", "494e4459", indy)
+        }
+    }
+
+    #[test]
+    fn lock_vote_propose_unlock() {
+        let config = IndigoV2Config {
+            indy_policy_id: Some("1d".into()),
+            governance_staking_datum_hashes: vec!["5a".into()],
+            governance_poll_datum_hashes: vec!["90".into()],
+            ..Default::default()
+        };
+        let get = |hash: &str| {
+            let (inputs, outputs) = match hash {
+                "lock" => (
+                    vec![utxo("w", None, 500)],
+                    vec![utxo("lock", Some("5a"), 500)],
+                ),
+                "vote" => (
+                    vec![utxo("lock", Some("5a"), 500), utxo("p", Some("90"), 0)],
+                    vec![utxo("vote", Some("5a"), 500), utxo("vote", Some("90"), 0)],
+                ),
+                "propose" => (
+                    vec![utxo("w", None, 1_000)],
+                    vec![utxo("propose", Some("90"), 1_000)],
+                ),
+                _ => (
+                    vec![utxo("vote", Some("5a"), 500)],
+                    vec![utxo("w", None, 500)],
+                ),
+            };
+            Some(KoiosTxUtxos {
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                metadata: None,
+            })
+        };
+        let txs: Vec<KoiosAccountTx> = ["lock", "vote", "propose", "unlock"]
+            .iter()
+            .enumerate()
+            .map(|(slot, hash)| KoiosAccountTx {
+                tx_hash: hash.to_string(),
+                block_height: None,
+                block_time: Some(1_700_000_000),
+                epoch_no: None,
+                slot_no: Some(slot as u64),
+            })
+            .collect();
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let kinds: Vec<EventKind> = reconstruct_governance_events(&txs, get, now, &config)
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::GovStakeLock {
+                    indy_quantity: 500,
+                    tx_hash: "lock".into()
+                },
+                EventKind::GovVote {
+                    poll: "p#0".into(),
+                    tx_hash: "vote".into()
+                },
+                EventKind::GovProposalDeposit {
+                    indy_quantity: 1_000,
+                    tx_hash: "propose".into()
+                },
+                EventKind::GovStakeUnlock {
+                    indy_quantity: 500,
+                    tx_hash: "unlock".into()
+                },
+            ]
+        );
+    }
+}
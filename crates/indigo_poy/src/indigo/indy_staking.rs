@@ -1,19 +1,23 @@
 //! INDY staking rewards vs SP premium vs other flows (best-effort from on-chain data).
+//!
+//! INDY paid out by a configured airdrop distributor or vesting contract is a one-off
+//! distribution, not a reward: those txs are classified apart (see [`is_indy_distribution`])
+//! and yield `IndyAirdrop` / `IndyVestingUnlock` events, which stay out of APR.
 
-use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos};
-use crate::indigo::events::{Event, EventKind};
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
 use crate::indigo::protocol_config::IndigoV2Config;
 use time::OffsetDateTime;
 
 /// Reconstruct INDY staking / SP premium / other reward-like flows.
-/// When `config.indy_policy_id` is set, we only treat txs that involve INDY token as INDY flows (future use; currently still ADA delta).
+/// A tx is a reward when it nets ADA or, with `config.indy_policy_id` set, INDY; the event
+/// carries both amounts.
 pub fn reconstruct_indy_staking_events(
     account_txs: &[KoiosAccountTx],
     get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
     now: OffsetDateTime,
     config: &IndigoV2Config,
 ) -> Vec<Event> {
-    let _ = config; // reserved: filter by indy_policy_id when matching reward UTxOs by asset
     let mut events = Vec::new();
     for tx in account_txs {
         let slot = tx.slot_no;
@@ -34,18 +38,28 @@ pub fn reconstruct_indy_staking_events(
         let in_ada: u64 = inputs.iter().map(|u| parse_lovelace(&u.value)).sum();
         let out_ada: u64 = outputs.iter().map(|u| parse_lovelace(&u.value)).sum();
 
-        if out_ada > in_ada {
-            let reward = out_ada - in_ada;
+        let indy_quantity = net_indy(inputs, outputs, config).filter(|q| *q > 0);
+        if out_ada > in_ada || indy_quantity.is_some() {
+            let reward = out_ada.saturating_sub(in_ada);
             events.push(Event {
                 kind: EventKind::IndyStakingReward {
                     amount_lovelace: reward,
                     epoch,
+                    indy_quantity: indy_quantity.map(|q| q.min(u64::MAX as i128) as u64),
                     tx_hash: tx_hash.clone(),
                 },
                 timestamp: ts,
                 slot,
                 tx_hash: tx_hash.clone(),
                 extra: None,
+                // An INDY payout is matched by policy; an ADA gain alone is a guess.
+                confidence: if indy_quantity.is_some() {
+                    Confidence::Medium
+                } else {
+                    Confidence::Heuristic
+                },
+                // A reward is the whole tx's net gain.
+                provenance: Provenance::from_utxos(inputs, outputs),
             });
         }
     }
@@ -53,6 +67,205 @@ pub fn reconstruct_indy_staking_events(
     events
 }
 
+/// Reconstruct INDY airdrops and vesting unlocks: one event per tx spending from a configured
+/// distributor, with the net INDY and ADA it paid out.
+pub fn reconstruct_indy_distribution_events(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: &IndigoV2Config,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    for tx in account_txs {
+        let ts = tx
+            .block_time
+            .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+            .unwrap_or(now);
+        let tx_hash = tx.tx_hash.clone();
+        let Some(utxos) = get_tx_utxos(&tx_hash) else {
+            continue;
+        };
+        let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+        let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+        let vesting = |u: &&KoiosUtxo| {
+            u.address()
+                .is_some_and(|a| config.is_indy_vesting_address(a))
+        };
+        let airdrop = |u: &&KoiosUtxo| {
+            u.address()
+                .is_some_and(|a| config.is_indy_airdrop_address(a))
+        };
+        let distributor = |u: &&KoiosUtxo| vesting(u) || airdrop(u);
+        if !inputs.iter().any(|u| distributor(&u)) {
+            continue;
+        }
+        // What left the distributor: its inputs minus whatever it got back as change.
+        let paid_out: Vec<KoiosUtxo> = outputs
+            .iter()
+            .filter(|u| !distributor(u))
+            .cloned()
+            .collect();
+        let other_in: Vec<KoiosUtxo> = inputs.iter().filter(|u| !distributor(u)).cloned().collect();
+        let amount_lovelace = lovelace(&paid_out).saturating_sub(lovelace(&other_in));
+        let indy_quantity =
+            net_indy(&other_in, &paid_out, config).map(|q| q.clamp(0, u64::MAX as i128) as u64);
+        let kind = if inputs.iter().any(|u| vesting(&u)) {
+            EventKind::IndyVestingUnlock {
+                indy_quantity,
+                amount_lovelace,
+                tx_hash: tx_hash.clone(),
+            }
+        } else {
+            EventKind::IndyAirdrop {
+                indy_quantity,
+                amount_lovelace,
+                tx_hash: tx_hash.clone(),
+            }
+        };
+        events.push(Event {
+            kind,
+            timestamp: ts,
+            slot: tx.slot_no,
+            tx_hash,
+            extra: None,
+            confidence: Confidence::High,
+            provenance: Provenance::from_utxos(
+                inputs.iter().filter(distributor),
+                outputs.iter().filter(|u| !distributor(u)),
+            ),
+        });
+    }
+    events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
+    events
+}
+
+/// True if the tx spends from a configured INDY airdrop distributor or vesting contract.
+pub(crate) fn is_indy_distribution(inputs: &[KoiosUtxo], config: &IndigoV2Config) -> bool {
+    inputs
+        .iter()
+        .filter_map(KoiosUtxo::address)
+        .any(|a| config.is_indy_airdrop_address(a) || config.is_indy_vesting_address(a))
+}
+
+/// Outputs' INDY minus inputs' INDY; `None` when no INDY policy is configured.
+pub(crate) fn net_indy(
+    inputs: &[KoiosUtxo],
+    outputs: &[KoiosUtxo],
+    config: &IndigoV2Config,
+) -> Option<i128> {
+    config.indy_policy_id.as_ref()?;
+    let indy = |utxos: &[KoiosUtxo]| -> i128 {
+        utxos
+            .iter()
+            .flat_map(|u| u.asset_list.iter().flatten())
+            .filter(|a| config.is_indy_policy(&a.policy_id))
+            .map(|a| a.quantity.trim().parse::<i128>().unwrap_or(0))
+            .sum()
+    };
+    Some(indy(outputs) - indy(inputs))
+}
+
 fn parse_lovelace(s: &str) -> u64 {
     s.trim().parse::<u64>().unwrap_or(0)
 }
+
+fn lovelace(utxos: &[KoiosUtxo]) -> u64 {
+    utxos.iter().map(|u| parse_lovelace(&u.value)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute::{compute_metrics, ComputeInput};
+    use crate::indigo::reconstruct_all_events;
+
+    fn utxo(address: &str, lovelace: u64, indy: u64) -> KoiosUtxo {
+        KoiosUtxo::lovelace(lovelace)
+            .at(address)
+            .with_asset("1d", "494e4459", indy)
+    }
+
+    #[test]
+    fn airdrops_and_vesting_unlocks_are_not_rewards() {
+        let config = IndigoV2Config {
+            indy_policy_id: Some("1d".into()),
+            indy_airdrop_addresses: vec!["addr1airdrop".into()],
+            indy_vesting_addresses: vec!["addr1vesting".into()],
+            ..Default::default()
+        };
+        // Without iAsset policies any UTxO holding a token would read as SP, so the reward is
+        // ADA only.
+        let get = |hash: &str| {
+            let (inputs, outputs) = match hash {
+                "drop" => (
+                    vec![utxo("addr1airdrop", 100_000_000, 9_000_000_000)],
+                    vec![
+                        utxo("addr1me", 1_500_000, 1_000_000_000),
+                        utxo("addr1airdrop", 98_300_000, 8_000_000_000),
+                    ],
+                ),
+                "vest" => (
+                    vec![
+                        utxo("addr1vesting", 2_000_000, 500_000_000),
+                        utxo("addr1me", 5_000_000, 0),
+                    ],
+                    vec![utxo("addr1me", 6_800_000, 500_000_000)],
+                ),
+                _ => (
+                    vec![utxo("addr1me", 5_000_000, 0)],
+                    vec![utxo("addr1me", 5_400_000, 0)],
+                ),
+            };
+            Some(KoiosTxUtxos {
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                metadata: None,
+            })
+        };
+        let txs: Vec<KoiosAccountTx> = ["drop", "vest", "reward"]
+            .iter()
+            .enumerate()
+            .map(|(slot, hash)| KoiosAccountTx {
+                tx_hash: hash.to_string(),
+                block_height: None,
+                block_time: Some(1_700_000_000),
+                epoch_no: None,
+                slot_no: Some(slot as u64),
+            })
+            .collect();
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_all_events(&txs, get, now, Some(&config));
+        let kinds: Vec<&EventKind> = events.indy_staking.iter().map(|e| &e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                &EventKind::IndyAirdrop {
+                    indy_quantity: Some(1_000_000_000),
+                    amount_lovelace: 1_500_000,
+                    tx_hash: "drop".into(),
+                },
+                &EventKind::IndyVestingUnlock {
+                    indy_quantity: Some(500_000_000),
+                    amount_lovelace: 1_800_000,
+                    tx_hash: "vest".into(),
+                },
+                &EventKind::IndyStakingReward {
+                    amount_lovelace: 400_000,
+                    epoch: None,
+                    indy_quantity: None,
+                    tx_hash: "reward".into(),
+                },
+            ]
+        );
+
+        let m = compute_metrics(&ComputeInput {
+            events,
+            ..Default::default()
+        });
+        assert_eq!(m.indy_staking.total_rewards_lovelace, 400_000);
+        assert_eq!(m.indy_staking.total_indy_quantity, None);
+        assert_eq!(m.indy_staking.airdrop_indy_quantity, Some(1_000_000_000));
+        assert_eq!(m.indy_staking.vesting_indy_quantity, Some(500_000_000));
+        assert_eq!(m.combined.total_ada_out_lovelace, 400_000);
+    }
+}
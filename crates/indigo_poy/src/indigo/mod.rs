@@ -1,39 +1,191 @@
 //! Indigo Protocol–specific parsers and event reconstruction.
 
+mod anomaly;
+mod cdp;
+mod classify;
+pub mod datum;
 pub(crate) mod events;
+mod fees;
+mod governance;
 mod indy_staking;
+mod non_indigo;
+pub mod oracle;
+mod overrides;
+mod parser;
+pub mod position;
 mod protocol_config;
+pub mod registry;
 mod rob;
+pub mod script_scan;
 mod stability_pool;
+pub mod validate;
 
-pub use events::{Event, EventKind, IndigoEvents};
-pub use indy_staking::reconstruct_indy_staking_events;
+pub use anomaly::{detect_anomalies, DataWarning, DataWarningKind, DEFAULT_MAX_PREMIUM_PCT};
+pub use cdp::reconstruct_cdp_events;
+pub use classify::{classify_tx, TxCategory};
+pub use events::{Confidence, Event, EventKind, IndigoEvents, Provenance};
+pub use fees::reconstruct_protocol_fee_events;
+pub use governance::reconstruct_governance_events;
+pub use indy_staking::{reconstruct_indy_distribution_events, reconstruct_indy_staking_events};
+pub use non_indigo::{non_indigo_flow, NonIndigoFlow, OwnAddresses};
+pub use overrides::{EventOverrides, OverridesError, TxOverride};
+pub use parser::{IndyStakingParser, ProtocolParser, RobParser, StabilityPoolParser};
 pub use protocol_config::IndigoV2Config;
-pub use rob::reconstruct_rob_events;
+pub use rob::{order_lifecycles, reconstruct_rob_events, OrderLifecycle, OrderStatus};
 pub use stability_pool::reconstruct_stability_pool_events;
 
 use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos};
+use std::collections::{HashMap, HashSet};
 use time::OffsetDateTime;
 
 /// Build full IndigoEvents from account txs and a lookup for tx UTxOs.
 /// Pass optional Indigo V2 (mainnet) config for accurate parsing (script/datum/policy IDs); when None or empty, uses heuristic mode.
+/// Txs are deduplicated by hash first, so lists concatenated from several addresses of one
+/// wallet yield each event once. Each tx is then given to exactly one parser (see
+/// [`classify_tx`]); txs without UTxO data or of no Indigo category yield nothing. Known DEX
+/// and exchange txs (see [`non_indigo_flow`]) yield one `OtherFlow` event each.
 pub fn reconstruct_all_events(
     account_txs: &[KoiosAccountTx],
     get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
     now: OffsetDateTime,
     config: Option<&IndigoV2Config>,
+) -> IndigoEvents {
+    reconstruct_all_events_with_overrides(
+        account_txs,
+        get_tx_utxos,
+        now,
+        config,
+        &EventOverrides::default(),
+        &OwnAddresses::default(),
+    )
+}
+
+/// [`reconstruct_all_events`], with txs in `overrides` handed to the category given there
+/// instead of the classified one, and their notes attached to their events. Txs moving ADA only
+/// among the `own` addresses are self-transfers, routed to `other` like DEX and exchange txs.
+pub fn reconstruct_all_events_with_overrides(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: Option<&IndigoV2Config>,
+    overrides: &EventOverrides,
+    own: &OwnAddresses,
+) -> IndigoEvents {
+    reconstruct_all_events_with_parsers(account_txs, get_tx_utxos, now, config, overrides, own, &[])
+}
+
+/// [`reconstruct_all_events_with_overrides`], also running `parsers` after the built-in SP, ROB,
+/// and INDY staking ones. Each parser is handed the txs of its category and its events join
+/// that category's list.
+pub fn reconstruct_all_events_with_parsers(
+    account_txs: &[KoiosAccountTx],
+    get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    now: OffsetDateTime,
+    config: Option<&IndigoV2Config>,
+    overrides: &EventOverrides,
+    own: &OwnAddresses,
+    parsers: &[&dyn ProtocolParser],
 ) -> IndigoEvents {
     let default_config = IndigoV2Config::default();
     let config = config.unwrap_or(&default_config);
-    let sp = reconstruct_stability_pool_events(account_txs, &get_tx_utxos, now, config);
-    let rob = reconstruct_rob_events(account_txs, &get_tx_utxos, now, config);
-    let indy = reconstruct_indy_staking_events(account_txs, &get_tx_utxos, now, config);
+    let mut seen = HashSet::new();
+    let unique: Vec<KoiosAccountTx> = account_txs
+        .iter()
+        .filter(|t| seen.insert(t.tx_hash.as_str()))
+        .cloned()
+        .collect();
+    let mut by_category: HashMap<TxCategory, Vec<KoiosAccountTx>> = HashMap::new();
+    let mut other = Vec::new();
+    for tx in unique {
+        let Some(utxos) = get_tx_utxos(&tx.tx_hash) else {
+            continue;
+        };
+        let category = match overrides.category(&tx.tx_hash) {
+            Some(category) => category,
+            None => match classify_tx(&utxos, config) {
+                // Only CDP and governance datums and INDY distributors outrank a known
+                // non-Indigo counterparty.
+                category @ (TxCategory::Cdp
+                | TxCategory::Governance
+                | TxCategory::IndyDistribution) => category,
+                category => match non_indigo_flow(&utxos, config, own) {
+                    Some(flow) => {
+                        let ts = tx
+                            .block_time
+                            .and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                            .unwrap_or(now);
+                        other.push(non_indigo::non_indigo_event(
+                            flow,
+                            &utxos,
+                            config,
+                            &tx.tx_hash,
+                            tx.slot_no,
+                            ts,
+                        ));
+                        TxCategory::Other
+                    }
+                    None => category,
+                },
+            },
+        };
+        by_category.entry(category).or_default().push(tx);
+    }
+    let txs = |category| by_category.get(&category).map_or(&[][..], Vec::as_slice);
+    let get: &dyn Fn(&str) -> Option<KoiosTxUtxos> = &get_tx_utxos;
+    let mut parsed = IndigoEvents {
+        other,
+        ..Default::default()
+    };
+    for parser in parser::BUILT_IN_PARSERS.iter().chain(parsers) {
+        let category = parser.category();
+        let events = parser.parse(txs(category), get, now, config);
+        category_events(&mut parsed, category).extend(events);
+    }
+    // Airdrops and vesting unlocks sit with the INDY events, but as their own kinds.
+    parsed
+        .indy_staking
+        .extend(reconstruct_indy_distribution_events(
+            txs(TxCategory::IndyDistribution),
+            &get_tx_utxos,
+            now,
+            config,
+        ));
+    let cdp = reconstruct_cdp_events(txs(TxCategory::Cdp), &get_tx_utxos, now, config);
+    let governance =
+        reconstruct_governance_events(txs(TxCategory::Governance), &get_tx_utxos, now, config);
+    // The redeemer, not the CDP owner, pays the fees of a redemption.
+    let redeemed: HashSet<&str> = cdp
+        .iter()
+        .filter(|e| matches!(e.kind, EventKind::CdpRedeemedAgainst { .. }))
+        .map(|e| e.tx_hash.as_str())
+        .collect();
+    let indigo_txs: Vec<KoiosAccountTx> = by_category
+        .iter()
+        .filter(|(category, _)| **category != TxCategory::Other)
+        .flat_map(|(_, txs)| txs)
+        .filter(|t| !redeemed.contains(t.tx_hash.as_str()))
+        .cloned()
+        .collect();
+    let protocol_fees = reconstruct_protocol_fee_events(&indigo_txs, &get_tx_utxos, now, config);
     let mut events = IndigoEvents {
-        stability_pool: sp,
-        rob,
-        indy_staking: indy,
-        other: vec![],
+        cdp,
+        governance,
+        protocol_fees,
+        ..parsed
     };
+    overrides.annotate(&mut events);
     events.sort_by_slot_then_tx();
     events
 }
+
+/// The list the events of a `category` parser go to.
+fn category_events(events: &mut IndigoEvents, category: TxCategory) -> &mut Vec<Event> {
+    match category {
+        TxCategory::Cdp => &mut events.cdp,
+        TxCategory::Governance => &mut events.governance,
+        TxCategory::StabilityPool => &mut events.stability_pool,
+        TxCategory::Rob => &mut events.rob,
+        TxCategory::IndyStaking | TxCategory::IndyDistribution => &mut events.indy_staking,
+        TxCategory::Other => &mut events.other,
+    }
+}
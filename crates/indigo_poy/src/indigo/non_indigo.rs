@@ -0,0 +1,196 @@
+//! Known non-Indigo flows: DEX swaps, exchange transfers, and transfers between the wallet's own
+//! addresses.
+//!
+//! Such txs often net the wallet ADA or move ADA out with nothing back, the shapes the INDY
+//! staking and ROB heuristics read as rewards and order placements. Recognizing them first
+//! routes them to `other`, each as one `OtherFlow` event. Only CDP and governance datums, which
+//! cannot be mistaken, are checked before them.
+
+use crate::chain::fetch::{KoiosTxUtxos, KoiosUtxo};
+use crate::chain::network::stake_key_hash;
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
+use crate::indigo::protocol_config::IndigoV2Config;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use time::OffsetDateTime;
+
+/// The wallet's addresses, to recognize transfers among them. A stake address covers every
+/// address under its stake key; so does a base address.
+#[derive(Clone, Debug, Default)]
+pub struct OwnAddresses {
+    addresses: BTreeSet<String>,
+    stake_keys: BTreeSet<[u8; 28]>,
+}
+
+impl OwnAddresses {
+    pub fn new<'a>(addresses: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut own = Self::default();
+        for address in addresses {
+            own.stake_keys.extend(stake_key_hash(address));
+            own.addresses.insert(address.clone());
+        }
+        own
+    }
+
+    pub fn contains(&self, address: &str) -> bool {
+        self.addresses.contains(address)
+            || stake_key_hash(address).is_some_and(|k| self.stake_keys.contains(&k))
+    }
+}
+
+/// What a known non-Indigo tx is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NonIndigoFlow {
+    Dex,
+    Exchange,
+    SelfTransfer,
+}
+
+impl NonIndigoFlow {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NonIndigoFlow::Dex => "dex",
+            NonIndigoFlow::Exchange => "exchange",
+            NonIndigoFlow::SelfTransfer => "self_transfer",
+        }
+    }
+}
+
+/// Recognize a tx touching a configured DEX script or exchange address, or one whose inputs
+/// and outputs all sit at the wallet's own addresses. Self-transfers need output addresses
+/// from the provider.
+pub fn non_indigo_flow(
+    utxos: &KoiosTxUtxos,
+    config: &IndigoV2Config,
+    own: &OwnAddresses,
+) -> Option<NonIndigoFlow> {
+    let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+    let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+    let addresses = || inputs.iter().chain(outputs).map(KoiosUtxo::address);
+    if addresses().any(|a| a.is_some_and(|a| config.is_dex_address(a))) {
+        return Some(NonIndigoFlow::Dex);
+    }
+    if addresses().any(|a| a.is_some_and(|a| config.is_exchange_address(a))) {
+        return Some(NonIndigoFlow::Exchange);
+    }
+    let all_own = addresses().all(|a| a.is_some_and(|a| own.contains(a)));
+    if !inputs.is_empty() && !outputs.is_empty() && all_own {
+        return Some(NonIndigoFlow::SelfTransfer);
+    }
+    None
+}
+
+/// The `OtherFlow` event for a recognized tx: the ADA it sends to the DEX or exchange, or for
+/// a self-transfer, all the ADA it moves.
+pub(crate) fn non_indigo_event(
+    flow: NonIndigoFlow,
+    utxos: &KoiosTxUtxos,
+    config: &IndigoV2Config,
+    tx_hash: &str,
+    slot: Option<u64>,
+    timestamp: OffsetDateTime,
+) -> Event {
+    let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
+    let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
+    let matched = |u: &&KoiosUtxo| match flow {
+        NonIndigoFlow::Dex => u.address().is_some_and(|a| config.is_dex_address(a)),
+        NonIndigoFlow::Exchange => u.address().is_some_and(|a| config.is_exchange_address(a)),
+        NonIndigoFlow::SelfTransfer => true,
+    };
+    let amount: u64 = outputs
+        .iter()
+        .filter(matched)
+        .map(|u| u.value.trim().parse::<u64>().unwrap_or(0))
+        .sum();
+    Event {
+        kind: EventKind::OtherFlow {
+            description: flow.as_str().to_string(),
+            amount_lovelace: Some(amount),
+            tx_hash: tx_hash.to_string(),
+        },
+        timestamp,
+        slot,
+        tx_hash: tx_hash.to_string(),
+        extra: None,
+        confidence: Confidence::High,
+        provenance: Provenance::from_utxos(
+            inputs.iter().filter(matched),
+            outputs.iter().filter(matched),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Base addresses with different payment keys under one stake key.
+    const MINE: &str = "addr1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgfpyysjzgfpyysjzgfpyysjzgfpyysjzgfpyysjzgfpyys73rqz5";
+    const MINE_TOO: &str = "addr1qypqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqsfpyysjzgfpyysjzgfpyysjzgfpyysjzgfpyysjzgfpyysudnhch";
+    // Enterprise address of script hash 0707…07.
+    const SWAP: &str = "addr1wyrswpc8qurswpc8qurswpc8qurswpc8qurswpc8qurswpcqwuenc";
+
+    fn utxo(address: &str, lovelace: u64) -> KoiosUtxo {
+        KoiosUtxo::lovelace(lovelace).at(address)
+    }
+
+    fn tx(inputs: Vec<KoiosUtxo>, outputs: Vec<KoiosUtxo>) -> KoiosTxUtxos {
+        KoiosTxUtxos {
+            inputs: Some(inputs),
+            outputs: Some(outputs),
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn swaps_exchange_transfers_and_self_transfers_are_recognized() {
+        let config = IndigoV2Config {
+            dex_addresses: vec!["07".repeat(28)],
+            exchange_addresses: vec!["addr1exchange".into()],
+            ..Default::default()
+        };
+        let own = OwnAddresses::new(&[MINE.to_string()]);
+        let swap = tx(vec![utxo(MINE, 50_000_000)], vec![utxo(SWAP, 49_800_000)]);
+        assert_eq!(
+            non_indigo_flow(&swap, &config, &own),
+            Some(NonIndigoFlow::Dex)
+        );
+        let deposit = tx(
+            vec![utxo(MINE, 50_000_000)],
+            vec![utxo("addr1exchange", 40_000_000), utxo(MINE, 9_800_000)],
+        );
+        assert_eq!(
+            non_indigo_flow(&deposit, &config, &own),
+            Some(NonIndigoFlow::Exchange)
+        );
+        let event = non_indigo_event(
+            NonIndigoFlow::Exchange,
+            &deposit,
+            &config,
+            "t",
+            None,
+            OffsetDateTime::from_unix_timestamp(0).unwrap(),
+        );
+        assert!(matches!(
+            event.kind,
+            EventKind::OtherFlow {
+                amount_lovelace: Some(40_000_000),
+                ..
+            }
+        ));
+        let moved = tx(
+            vec![utxo(MINE, 50_000_000)],
+            vec![utxo(MINE_TOO, 49_800_000)],
+        );
+        assert_eq!(
+            non_indigo_flow(&moved, &config, &own),
+            Some(NonIndigoFlow::SelfTransfer)
+        );
+        let paid = tx(
+            vec![utxo(MINE, 50_000_000)],
+            vec![utxo("addr1someone", 49_800_000)],
+        );
+        assert_eq!(non_indigo_flow(&paid, &config, &own), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Indigo price oracles: the ADA price of each iAsset over time, from the oracle UTxOs.
+//!
+//! Every oracle update spends the oracle UTxO and recreates it with a new price datum, so an
+//! oracle address's tx history is its price feed. A pro-rata SP liquidation is priced at the
+//! last update at or before its slot, or at the first one after when none precedes it in the
+//! fetched range. Its realized premium is then the ADA received minus the iAsset lost at that
+//! price (0 when the liquidation lost value).
+
+use crate::chain::assets::split_asset_unit;
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos};
+use crate::chain::Fetcher;
+use crate::compute::fixed::{scale_lovelace, to_micro};
+use crate::indigo::datum::decode_oracle_datum;
+use crate::indigo::{EventKind, IndigoEvents, IndigoV2Config};
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+/// Oracle prices per iAsset token name (hex): (slot, ADA per iAsset), in slot order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OracleFeed {
+    prices: BTreeMap<String, Vec<(u64, f64)>>,
+}
+
+impl OracleFeed {
+    /// Record the price of `iasset` (token name, hex) set at `slot`.
+    pub fn insert(&mut self, iasset: &str, slot: u64, price: f64) {
+        let feed = self.prices.entry(iasset.to_lowercase()).or_default();
+        let at = feed.partition_point(|(s, _)| *s <= slot);
+        feed.insert(at, (slot, price));
+    }
+
+    /// Record every oracle datum created by `txs` as a price of `iasset`.
+    pub fn extend_from_txs(
+        &mut self,
+        iasset: &str,
+        txs: &[KoiosAccountTx],
+        get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
+    ) {
+        for tx in txs {
+            let (Some(slot), Some(utxos)) = (tx.slot_no, get_tx_utxos(&tx.tx_hash)) else {
+                continue;
+            };
+            for output in utxos.outputs.iter().flatten() {
+                if let Some(Ok(datum)) = output.datum().map(|d| decode_oracle_datum(&d.bytes)) {
+                    self.insert(iasset, slot, datum.price);
+                }
+            }
+        }
+    }
+
+    /// Price of `iasset` in force at `slot`: the last update at or before it, else the first
+    /// after it.
+    pub fn price_near(&self, iasset: &str, slot: u64) -> Option<f64> {
+        let feed = self.prices.get(&iasset.to_lowercase())?;
+        let at = feed.partition_point(|(s, _)| *s <= slot);
+        at.checked_sub(1)
+            .or((at < feed.len()).then_some(at))
+            .map(|i| feed[i].1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prices.is_empty()
+    }
+}
+
+/// Set the realized premium of every pro-rata liquidation in `events` from `feed`. Returns
+/// how many were priced; per-tx estimates (no `iasset_lost_quantity`) are left as they are.
+pub fn price_liquidations(events: &mut IndigoEvents, feed: &OracleFeed) -> usize {
+    let mut priced = 0;
+    for event in &mut events.stability_pool {
+        let slot = event.slot.unwrap_or(0);
+        let EventKind::StabilityPoolLiquidation {
+            iasset_burnt,
+            ada_received_lovelace,
+            realized_premium_lovelace,
+            iasset_lost_quantity: Some(lost),
+            oracle_price,
+            ..
+        } = &mut event.kind
+        else {
+            continue;
+        };
+        let name = split_asset_unit(iasset_burnt).map_or(iasset_burnt.as_str(), |(_, n)| n);
+        let Some(price) = feed.price_near(name, slot) else {
+            continue;
+        };
+        let lost_lovelace = scale_lovelace(*lost, to_micro(price));
+        *realized_premium_lovelace = ada_received_lovelace.saturating_sub(lost_lovelace);
+        *oracle_price = Some(price);
+        priced += 1;
+    }
+    priced
+}
+
+/// Fetch the oracle feed of every iAsset in `config.oracle_addresses` over the range. Empty
+/// when none is configured; an oracle whose history cannot be fetched is skipped with a warning.
+pub async fn fetch_oracle_feed(
+    fetcher: &Fetcher,
+    config: &IndigoV2Config,
+    from_slot_or_time: Option<&str>,
+    to_slot_or_time: Option<&str>,
+) -> OracleFeed {
+    let mut feed = OracleFeed::default();
+    for (iasset, address) in &config.oracle_addresses {
+        let txs = match fetcher
+            .account_txs(address, from_slot_or_time, to_slot_or_time)
+            .await
+        {
+            Ok(txs) => txs,
+            Err(e) => {
+                warn!(iasset = %iasset, error = %e, "oracle history unavailable");
+                continue;
+            }
+        };
+        let hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
+        let mut fetched: BTreeMap<String, KoiosTxUtxos> = fetcher
+            .fetch_many(&hashes)
+            .await
+            .into_iter()
+            .filter_map(|(hash, r)| r.ok().map(|u| (hash, u)))
+            .collect();
+        if let Err(e) = fetcher.resolve_datums(fetched.values_mut()).await {
+            warn!(iasset = %iasset, error = %e, "oracle datums known only by hash stay unresolved");
+        }
+        feed.extend_from_txs(iasset, &txs, |hash| fetched.get(hash).cloned());
+        info!(iasset = %iasset, updates = txs.len(), "oracle feed");
+    }
+    feed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::KoiosUtxo;
+    use crate::indigo::{Confidence, Event, Provenance};
+    use time::OffsetDateTime;
+
+    #[test]
+    fn feed_reads_oracle_outputs_and_picks_nearest_price() {
+        let output = |bytes: &str| KoiosUtxo {
+            tx_hash: "o".into(),
+            ..KoiosUtxo::lovelace(2_000_000).with_inline_datum(bytes)
+        };
+        let txs: Vec<KoiosAccountTx> = [("p2", 200), ("p1", 100)]
+            .into_iter()
+            .map(|(hash, slot)| KoiosAccountTx {
+                tx_hash: hash.into(),
+                block_height: None,
+                block_time: None,
+                epoch_no: None,
+                slot_no: Some(slot),
+            })
+            .collect();
+        let get = |hash: &str| {
+            // 1.5 and 2.0 ADA, expiring at 0.
+            let price = if hash == "p1" {
+                "1a0016e360"
+            } else {
+                "1a001e8480"
+            };
+            Some(KoiosTxUtxos {
+                inputs: None,
+                outputs: Some(vec![output(&format!("d87982d87981{price}00"))]),
+                metadata: None,
+            })
+        };
+        let mut feed = OracleFeed::default();
+        feed.extend_from_txs("69555344", &txs, get);
+        assert_eq!(feed.price_near("69555344", 50), Some(1.5));
+        assert_eq!(feed.price_near("69555344", 150), Some(1.5));
+        assert_eq!(feed.price_near("69555344", 200), Some(2.0));
+        assert_eq!(feed.price_near("69425443", 200), None);
+
+        let liquidation = |lost| Event {
+            kind: EventKind::StabilityPoolLiquidation {
+                iasset_burnt: "f0ff$69555344".into(),
+                ada_received_lovelace: 25_000_000,
+                realized_premium_lovelace: 0,
+                dilution_effect: None,
+                pool_deposit_quantity: None,
+                user_deposit_quantity: None,
+                iasset_lost_quantity: lost,
+                oracle_price: None,
+                tx_hash: "liq".into(),
+                slot: Some(250),
+            },
+            timestamp: OffsetDateTime::from_unix_timestamp(0).unwrap(),
+            slot: Some(250),
+            tx_hash: "liq".into(),
+            extra: None,
+            confidence: Confidence::Heuristic,
+            provenance: Provenance::default(),
+        };
+        let mut events = IndigoEvents {
+            stability_pool: vec![liquidation(Some(12_000_000)), liquidation(None)],
+            ..Default::default()
+        };
+        assert_eq!(price_liquidations(&mut events, &feed), 1);
+        assert!(matches!(
+            events.stability_pool[0].kind,
+            EventKind::StabilityPoolLiquidation {
+                realized_premium_lovelace: 1_000_000,
+                oracle_price: Some(p),
+                ..
+            } if p == 2.0
+        ));
+    }
+}
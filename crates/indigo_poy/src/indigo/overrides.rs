@@ -0,0 +1,155 @@
+//! User overrides: reclassify or annotate specific txs the parsers misread.
+//!
+//! An overrides file maps tx hashes to a category and/or a note:
+//! `{"<tx_hash>": {"category": "other", "note": "exchange withdrawal, not an SP deposit"}}`.
+//! A category hands the tx to that parser instead of the one [`crate::indigo::classify_tx`]
+//! picks (`other` drops its events); a note is attached to the tx's events as `extra.note`. The
+//! overrides applied are recorded in the bundle, so a rebuild applies the same ones.
+
+use crate::indigo::classify::TxCategory;
+use crate::indigo::events::IndigoEvents;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum OverridesError {
+    #[error("read overrides: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("parse overrides: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// What to do with one tx.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TxOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<TxCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Overrides by tx hash (lowercase hex).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EventOverrides {
+    pub txs: BTreeMap<String, TxOverride>,
+}
+
+impl EventOverrides {
+    /// Read an overrides file. Unlike the protocol config, a file that cannot be read is an
+    /// error: silently dropping the user's corrections would change their numbers.
+    pub fn load(path: &Path) -> Result<Self, OverridesError> {
+        let overrides: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(Self {
+            txs: overrides
+                .txs
+                .into_iter()
+                .map(|(hash, o)| (hash.trim().to_lowercase(), o))
+                .collect(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    pub fn get(&self, tx_hash: &str) -> Option<&TxOverride> {
+        self.txs.get(&tx_hash.to_lowercase())
+    }
+
+    /// The category `tx_hash` is forced into, if any.
+    pub fn category(&self, tx_hash: &str) -> Option<TxCategory> {
+        self.get(tx_hash).and_then(|o| o.category)
+    }
+
+    /// Attach each tx's note to its events under `extra.note`.
+    pub fn annotate(&self, events: &mut IndigoEvents) {
+        for list in [
+            &mut events.stability_pool,
+            &mut events.rob,
+            &mut events.indy_staking,
+            &mut events.cdp,
+            &mut events.governance,
+            &mut events.protocol_fees,
+            &mut events.other,
+        ] {
+            for event in list {
+                let Some(note) = self.get(&event.tx_hash).and_then(|o| o.note.clone()) else {
+                    continue;
+                };
+                let mut extra = match event.extra.take() {
+                    Some(serde_json::Value::Object(map)) => map,
+                    Some(other) => serde_json::Map::from_iter([("value".to_string(), other)]),
+                    None => serde_json::Map::new(),
+                };
+                extra.insert("note".to_string(), note.into());
+                event.extra = Some(extra.into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+    use crate::indigo::reconstruct_all_events_with_overrides;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn overrides_reclassify_and_annotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overrides.json");
+        std::fs::write(
+            &path,
+            r#"{"AA": {"category": "other", "note": "exchange withdrawal"},
+                "bb": {"note": "checked by hand"}}"#,
+        )
+        .unwrap();
+        let overrides = EventOverrides::load(&path).unwrap();
+        assert_eq!(overrides.category("aa"), Some(TxCategory::Other));
+
+        // Both txs net ADA, so both would read as INDY staking rewards.
+        let utxo = |lovelace: u64| KoiosUtxo {
+            tx_hash: "w".into(),
+            ..KoiosUtxo::lovelace(lovelace)
+        };
+        let txs: Vec<KoiosAccountTx> = ["aa", "bb"]
+            .into_iter()
+            .map(|hash| KoiosAccountTx {
+                tx_hash: hash.into(),
+                block_height: None,
+                block_time: Some(1_700_000_000),
+                epoch_no: None,
+                slot_no: Some(1),
+            })
+            .collect();
+        let get = |_: &str| {
+            Some(KoiosTxUtxos {
+                inputs: Some(vec![utxo(2_000_000)]),
+                outputs: Some(vec![utxo(2_500_000)]),
+                metadata: None,
+            })
+        };
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_all_events_with_overrides(
+            &txs,
+            get,
+            now,
+            None,
+            &overrides,
+            &Default::default(),
+        );
+        assert_eq!(events.indy_staking.len(), 1);
+        assert_eq!(
+            events.indy_staking[0].extra,
+            Some(serde_json::json!({"note": "checked by hand"}))
+        );
+        assert!(matches!(
+            EventOverrides::load(&dir.path().join("missing.json")),
+            Err(OverridesError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,181 @@
+//! Protocol parsers: the extension point of the event pipeline.
+//!
+//! A [`ProtocolParser`] turns the txs of one [`TxCategory`] into events. The Stability Pool,
+//! ROB, and INDY staking parsers are built in; parsers for other Cardano yield protocols can be
+//! passed to [`crate::indigo::reconstruct_all_events_with_parsers`] without forking the
+//! pipeline. Such a parser usually takes the `Other` txs (those no Indigo parser claims) and
+//! its events land in `other`.
+
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos};
+use crate::indigo::classify::TxCategory;
+use crate::indigo::events::Event;
+use crate::indigo::indy_staking::reconstruct_indy_staking_events;
+use crate::indigo::protocol_config::IndigoV2Config;
+use crate::indigo::rob::reconstruct_rob_events;
+use crate::indigo::stability_pool::reconstruct_stability_pool_events;
+use time::OffsetDateTime;
+
+/// Reconstructs events from the txs classified as [`ProtocolParser::category`].
+pub trait ProtocolParser {
+    /// Which txs this parser is handed, and which event list its events go to.
+    fn category(&self) -> TxCategory;
+
+    /// Events of `account_txs`, with `now` as the timestamp of txs without a block time.
+    fn parse(
+        &self,
+        account_txs: &[KoiosAccountTx],
+        get_tx_utxos: &dyn Fn(&str) -> Option<KoiosTxUtxos>,
+        now: OffsetDateTime,
+        config: &IndigoV2Config,
+    ) -> Vec<Event>;
+}
+
+/// Stability Pool deposits, withdrawals, and liquidations.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StabilityPoolParser;
+
+impl ProtocolParser for StabilityPoolParser {
+    fn category(&self) -> TxCategory {
+        TxCategory::StabilityPool
+    }
+
+    fn parse(
+        &self,
+        account_txs: &[KoiosAccountTx],
+        get_tx_utxos: &dyn Fn(&str) -> Option<KoiosTxUtxos>,
+        now: OffsetDateTime,
+        config: &IndigoV2Config,
+    ) -> Vec<Event> {
+        reconstruct_stability_pool_events(account_txs, get_tx_utxos, now, config)
+    }
+}
+
+/// ROB order placements, fills, cancels, and cooldowns.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RobParser;
+
+impl ProtocolParser for RobParser {
+    fn category(&self) -> TxCategory {
+        TxCategory::Rob
+    }
+
+    fn parse(
+        &self,
+        account_txs: &[KoiosAccountTx],
+        get_tx_utxos: &dyn Fn(&str) -> Option<KoiosTxUtxos>,
+        now: OffsetDateTime,
+        config: &IndigoV2Config,
+    ) -> Vec<Event> {
+        reconstruct_rob_events(account_txs, get_tx_utxos, now, config)
+    }
+}
+
+/// INDY staking rewards.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IndyStakingParser;
+
+impl ProtocolParser for IndyStakingParser {
+    fn category(&self) -> TxCategory {
+        TxCategory::IndyStaking
+    }
+
+    fn parse(
+        &self,
+        account_txs: &[KoiosAccountTx],
+        get_tx_utxos: &dyn Fn(&str) -> Option<KoiosTxUtxos>,
+        now: OffsetDateTime,
+        config: &IndigoV2Config,
+    ) -> Vec<Event> {
+        reconstruct_indy_staking_events(account_txs, get_tx_utxos, now, config)
+    }
+}
+
+/// The parsers every reconstruction runs, before any passed in.
+pub(crate) const BUILT_IN_PARSERS: [&dyn ProtocolParser; 3] =
+    [&StabilityPoolParser, &RobParser, &IndyStakingParser];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::KoiosUtxo;
+    use crate::indigo::{reconstruct_all_events_with_parsers, Confidence, EventKind, Provenance};
+
+    /// A downstream parser: every tx no Indigo parser claims is a flow of "other protocol".
+    struct OtherProtocol;
+
+    impl ProtocolParser for OtherProtocol {
+        fn category(&self) -> TxCategory {
+            TxCategory::Other
+        }
+
+        fn parse(
+            &self,
+            account_txs: &[KoiosAccountTx],
+            get_tx_utxos: &dyn Fn(&str) -> Option<KoiosTxUtxos>,
+            now: OffsetDateTime,
+            _config: &IndigoV2Config,
+        ) -> Vec<Event> {
+            account_txs
+                .iter()
+                .filter(|tx| get_tx_utxos(&tx.tx_hash).is_some())
+                .map(|tx| Event {
+                    kind: EventKind::OtherFlow {
+                        description: "other protocol".into(),
+                        amount_lovelace: None,
+                        tx_hash: tx.tx_hash.clone(),
+                    },
+                    timestamp: now,
+                    slot: tx.slot_no,
+                    tx_hash: tx.tx_hash.clone(),
+                    extra: None,
+                    confidence: Confidence::Heuristic,
+                    provenance: Provenance::default(),
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn downstream_parsers_take_unclaimed_txs() {
+        let utxo = |lovelace: u64| KoiosUtxo {
+            tx_hash: "w".into(),
+            ..KoiosUtxo::lovelace(lovelace)
+        };
+        // "reward" nets ADA, so the INDY staking parser claims it; "flat" nets nothing.
+        let get = |hash: &str| {
+            let out = if hash == "reward" {
+                2_500_000
+            } else {
+                2_000_000
+            };
+            Some(KoiosTxUtxos {
+                inputs: Some(vec![utxo(2_000_000)]),
+                outputs: Some(vec![utxo(out)]),
+                metadata: None,
+            })
+        };
+        let txs: Vec<KoiosAccountTx> = ["reward", "flat"]
+            .into_iter()
+            .map(|hash| KoiosAccountTx {
+                tx_hash: hash.into(),
+                block_height: None,
+                block_time: Some(1_700_000_000),
+                epoch_no: None,
+                slot_no: Some(1),
+            })
+            .collect();
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_all_events_with_parsers(
+            &txs,
+            get,
+            now,
+            None,
+            &Default::default(),
+            &Default::default(),
+            &[&OtherProtocol],
+        );
+        assert_eq!(events.indy_staking.len(), 1);
+        assert_eq!(events.other.len(), 1);
+        assert_eq!(events.other[0].tx_hash, "flat");
+    }
+}
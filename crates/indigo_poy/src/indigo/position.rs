@@ -0,0 +1,154 @@
+//! Mark-to-market of the open position: the wallet's Stability Pool accounts valued at the
+//! oracle, and the ADA still on its open ROB orders.
+//!
+//! SP accounts are read off the SP script addresses' current UTxOs, with each deposit
+//! compounded to its pool's current state, as for pro-rata liquidations. Besides the iAsset, an account holds the ADA its share of liquidations earned and not yet claimed, and
+//! the ADA on its own UTxO.
+
+use crate::chain::fetch::KoiosTxUtxos;
+use crate::chain::network::payment_credential;
+use crate::chain::{FetchError, Fetcher};
+use crate::compute::fixed::{scale_lovelace, to_micro};
+use crate::indigo::oracle::OracleFeed;
+use crate::indigo::rob::order_lifecycles;
+use crate::indigo::stability_pool::open_sp_accounts;
+use crate::indigo::{IndigoEvents, IndigoV2Config};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use tracing::warn;
+
+/// One open SP account.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpPosition {
+    /// iAsset token name (hex).
+    pub iasset: String,
+    /// Compounded deposit, in iAsset base units.
+    pub deposit_quantity: u128,
+    /// Liquidation ADA the account earned and has not claimed.
+    pub pending_ada_lovelace: u64,
+    /// ADA on the account UTxO, returned when it closes.
+    pub utxo_lovelace: u64,
+    /// Latest oracle price (ADA per iAsset); `None` without a feed for the iAsset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oracle_price: Option<f64>,
+    /// `deposit_quantity` at `oracle_price`; 0 when unpriced.
+    pub iasset_value_lovelace: u64,
+}
+
+impl SpPosition {
+    pub fn value_lovelace(&self) -> u64 {
+        self.iasset_value_lovelace
+            .saturating_add(self.pending_ada_lovelace)
+            .saturating_add(self.utxo_lovelace)
+    }
+}
+
+/// The open position at report time.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenPosition {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stability_pool: Vec<SpPosition>,
+    /// ADA still on open ROB orders (those placed in the range).
+    pub rob_open_lovelace: u64,
+    /// Everything above, in lovelace.
+    pub value_lovelace: u64,
+}
+
+impl OpenPosition {
+    /// Price the SP accounts at the latest update of `feed` and total the position.
+    pub fn new(
+        mut stability_pool: Vec<SpPosition>,
+        rob_open_lovelace: u64,
+        feed: &OracleFeed,
+    ) -> Self {
+        for account in &mut stability_pool {
+            account.oracle_price = feed.price_near(&account.iasset, u64::MAX);
+            account.iasset_value_lovelace = account.oracle_price.map_or(0, |price| {
+                scale_lovelace(account.deposit_quantity, to_micro(price))
+            });
+        }
+        let value_lovelace = stability_pool
+            .iter()
+            .map(SpPosition::value_lovelace)
+            .fold(rob_open_lovelace, u64::saturating_add);
+        Self {
+            stability_pool,
+            rob_open_lovelace,
+            value_lovelace,
+        }
+    }
+}
+
+/// Value the open position of `addresses`: their SP accounts at the SP script addresses, and
+/// the open ROB orders among `events`.
+pub async fn fetch_open_position(
+    fetcher: &Fetcher,
+    config: &IndigoV2Config,
+    addresses: &[String],
+    feed: &OracleFeed,
+    events: &IndigoEvents,
+) -> Result<OpenPosition, FetchError> {
+    let owners: BTreeSet<String> = addresses
+        .iter()
+        .filter_map(|a| payment_credential(a))
+        .map(hex::encode)
+        .collect();
+    let mut accounts = Vec::new();
+    for entry in &config.stability_pool_script_hashes {
+        let Some(address) = IndigoV2Config::script_entry_address(entry, fetcher.network()) else {
+            continue;
+        };
+        let mut utxos = KoiosTxUtxos {
+            inputs: None,
+            outputs: Some(fetcher.address_utxos(&address).await?),
+            metadata: None,
+        };
+        if let Err(e) = fetcher.resolve_datums(std::iter::once(&mut utxos)).await {
+            warn!(error = %e, "SP datums known only by hash stay unresolved");
+        }
+        accounts.extend(open_sp_accounts(
+            utxos.outputs.as_deref().unwrap_or(&[]),
+            &owners,
+            config,
+        ));
+    }
+    if accounts
+        .iter()
+        .any(|a| feed.price_near(&a.iasset, u64::MAX).is_none())
+    {
+        warn!("open SP accounts without an oracle feed are valued without their iAsset");
+    }
+    let rob_open = order_lifecycles(&events.rob)
+        .iter()
+        .map(|o| o.open_lovelace)
+        .sum();
+    Ok(OpenPosition::new(accounts, rob_open, feed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn position_values_deposits_at_the_latest_price() {
+        let mut feed = OracleFeed::default();
+        feed.insert("69555344", 100, 1.5);
+        feed.insert("69555344", 200, 2.0);
+        let account = |iasset: &str| SpPosition {
+            iasset: iasset.into(),
+            deposit_quantity: 10_000_000,
+            pending_ada_lovelace: 3_000_000,
+            utxo_lovelace: 2_000_000,
+            ..Default::default()
+        };
+        let position = OpenPosition::new(
+            vec![account("69555344"), account("69425443")],
+            7_000_000,
+            &feed,
+        );
+        assert_eq!(position.stability_pool[0].iasset_value_lovelace, 20_000_000);
+        assert_eq!(position.stability_pool[1].oracle_price, None);
+        // 25 + 5 ADA in iUSD's pool, 5 unpriced in iBTC's, 7 on ROB orders.
+        assert_eq!(position.value_lovelace, 37_000_000);
+    }
+}
@@ -5,9 +5,16 @@
 //! or policy IDs. When empty, the tool falls back to heuristic detection.
 //!
 //! Load from: env `INDIGO_V2_CONFIG_PATH`, or `./config/indigo_v2.json`, or `./indigo_v2.json`.
+//! Testnets use `./config/indigo_v2.<network>.json` so mainnet IDs never leak into preprod runs.
+//! An installed registry (see [`crate::indigo::registry`]) is merged in underneath.
 
+use crate::chain::network::payment_credential;
+use crate::chain::xpub::script_address;
+use crate::chain::Network;
+use crate::indigo::registry::{load_registry, registry_path};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 /// Indigo Protocol V2 (current mainnet) identifiers (script hashes, datum hashes, policy IDs).
 /// Paste values from Indigo team / docs; leave empty for heuristic mode.
@@ -39,6 +46,57 @@ pub struct IndigoV2Config {
     /// INDY token policy ID (56-char hex). Used to recognize INDY rewards/flows.
     #[serde(default)]
     pub indy_policy_id: Option<String>,
+
+    /// CDP: script address(es) or validator hash(es) (hex).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cdp_script_hashes: Vec<String>,
+
+    /// CDP: datum hash(es) for CDP script UTxOs (hex).
+    /// CDPs have no heuristic fallback: without these, no CDP events are reconstructed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cdp_datum_hashes: Vec<String>,
+
+    /// Governance: datum hash(es) of INDY staking positions (locked INDY).
+    /// Like CDPs, governance has no heuristic fallback.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub governance_staking_datum_hashes: Vec<String>,
+
+    /// Governance: datum hash(es) of poll (proposal) UTxOs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub governance_poll_datum_hashes: Vec<String>,
+
+    /// Protocol treasury and fee collector addresses (bech32). ADA an Indigo tx pays to them is
+    /// a protocol fee, subtracted from net PnL.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protocol_fee_addresses: Vec<String>,
+
+    /// Price oracles: iAsset token name (hex) → oracle address (bech32).
+    /// When set, pro-rata SP liquidations are priced at the oracle's feed (see `indigo::oracle`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub oracle_addresses: BTreeMap<String, String>,
+
+    /// DEX scripts (e.g. Minswap, SundaeSwap order and pool contracts): bech32 addresses or
+    /// 56-char script hashes. Txs touching them are swaps, not Indigo flows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dex_addresses: Vec<String>,
+
+    /// Exchange (CEX) deposit and withdrawal addresses (bech32) or payment credential hashes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exchange_addresses: Vec<String>,
+
+    /// INDY airdrop distributors: bech32 addresses or payment credential hashes. INDY paid out
+    /// from them is an airdrop, kept out of staking rewards and APR.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indy_airdrop_addresses: Vec<String>,
+
+    /// INDY vesting contracts (team, investor, and foundation unlocks): bech32 addresses or
+    /// 56-char script hashes. Like airdrops, unlocks are kept out of staking rewards and APR.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub indy_vesting_addresses: Vec<String>,
+
+    /// SHA-256 of the registry merged in at load, if any. Not part of the config file.
+    #[serde(skip)]
+    pub registry_sha256: Option<String>,
 }
 
 impl IndigoV2Config {
@@ -50,25 +108,108 @@ impl IndigoV2Config {
         serde_json::from_str(&content).unwrap_or_default()
     }
 
-    /// Load config: env INDIGO_V2_CONFIG_PATH, then ./config/indigo_v2.json, then ./indigo_v2.json.
+    /// Load mainnet config: env INDIGO_V2_CONFIG_PATH, then ./config/indigo_v2.json, then ./indigo_v2.json.
     pub fn load() -> Self {
+        Self::load_for(Network::Mainnet)
+    }
+
+    /// Load config for `network`. The env override applies to any network; testnets otherwise
+    /// only read `./config/indigo_v2.<network>.json` and fall back to heuristic mode. The
+    /// network's installed registry, if any, is merged in.
+    pub fn load_for(network: Network) -> Self {
+        let mut config = Self::load_local(network);
+        if let Some((registry, sha256)) = load_registry(&registry_path(network)) {
+            config.merge_registry(registry, sha256);
+        }
+        config
+    }
+
+    fn load_local(network: Network) -> Self {
         if let Ok(path) = std::env::var("INDIGO_V2_CONFIG_PATH") {
             let p = Path::new(&path);
             if p.exists() {
                 return Self::load_from_path(p);
             }
         }
-        for candidate in [
-            Path::new("./config/indigo_v2.json"),
-            Path::new("./indigo_v2.json"),
-        ] {
+        let candidates: Vec<PathBuf> = match network {
+            Network::Mainnet => vec![
+                PathBuf::from("./config/indigo_v2.json"),
+                PathBuf::from("./indigo_v2.json"),
+            ],
+            other => vec![PathBuf::from(format!("./config/indigo_v2.{}.json", other))],
+        };
+        for candidate in candidates {
             if candidate.exists() {
-                return Self::load_from_path(candidate);
+                return Self::load_from_path(&candidate);
             }
         }
         Self::default()
     }
 
+    /// Add a registry's identifiers to these: lists gain the entries they lack, and a value
+    /// already set here wins over the registry's.
+    pub fn merge_registry(&mut self, registry: IndigoV2Config, sha256: String) {
+        fn union(into: &mut Vec<String>, from: Vec<String>) {
+            for entry in from {
+                let n = IndigoV2Config::norm_hex(&entry);
+                if !into.iter().any(|e| IndigoV2Config::norm_hex(e) == n) {
+                    into.push(entry);
+                }
+            }
+        }
+        // Destructured so a new field cannot be left out of the merge.
+        let IndigoV2Config {
+            stability_pool_script_hashes,
+            stability_pool_datum_hashes,
+            iasset_policy_ids,
+            rob_script_hashes,
+            rob_datum_hashes,
+            indy_policy_id,
+            cdp_script_hashes,
+            cdp_datum_hashes,
+            governance_staking_datum_hashes,
+            governance_poll_datum_hashes,
+            protocol_fee_addresses,
+            oracle_addresses,
+            dex_addresses,
+            exchange_addresses,
+            indy_airdrop_addresses,
+            indy_vesting_addresses,
+            registry_sha256: _,
+        } = registry;
+        union(
+            &mut self.stability_pool_script_hashes,
+            stability_pool_script_hashes,
+        );
+        union(
+            &mut self.stability_pool_datum_hashes,
+            stability_pool_datum_hashes,
+        );
+        union(&mut self.iasset_policy_ids, iasset_policy_ids);
+        union(&mut self.rob_script_hashes, rob_script_hashes);
+        union(&mut self.rob_datum_hashes, rob_datum_hashes);
+        self.indy_policy_id = self.indy_policy_id.take().or(indy_policy_id);
+        union(&mut self.cdp_script_hashes, cdp_script_hashes);
+        union(&mut self.cdp_datum_hashes, cdp_datum_hashes);
+        union(
+            &mut self.governance_staking_datum_hashes,
+            governance_staking_datum_hashes,
+        );
+        union(
+            &mut self.governance_poll_datum_hashes,
+            governance_poll_datum_hashes,
+        );
+        union(&mut self.protocol_fee_addresses, protocol_fee_addresses);
+        union(&mut self.dex_addresses, dex_addresses);
+        union(&mut self.exchange_addresses, exchange_addresses);
+        union(&mut self.indy_airdrop_addresses, indy_airdrop_addresses);
+        union(&mut self.indy_vesting_addresses, indy_vesting_addresses);
+        for (iasset, address) in oracle_addresses {
+            self.oracle_addresses.entry(iasset).or_insert(address);
+        }
+        self.registry_sha256 = Some(sha256);
+    }
+
     /// True if we have at least one iAsset policy ID (SP filtering is strict).
     pub fn has_iasset_policy_ids(&self) -> bool {
         !self.iasset_policy_ids.is_empty()
@@ -84,8 +225,34 @@ impl IndigoV2Config {
         !self.rob_datum_hashes.is_empty()
     }
 
+    /// Bech32 addresses of the SP and ROB scripts: entries that already are addresses as-is,
+    /// 56-char validator hashes as enterprise script addresses on `network`. Sorted, unique.
+    pub fn script_addresses(&self, network: Network) -> Vec<String> {
+        let mut out: Vec<String> = self
+            .stability_pool_script_hashes
+            .iter()
+            .chain(&self.rob_script_hashes)
+            .filter_map(|entry| Self::script_entry_address(entry, network))
+            .collect();
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// A script entry as a bech32 address: an address as-is, a 56-char validator hash as the
+    /// enterprise script address on `network`. `None` for anything else.
+    pub(crate) fn script_entry_address(entry: &str, network: Network) -> Option<String> {
+        let entry = entry.trim();
+        if entry.starts_with("addr") {
+            return Some(entry.to_string());
+        }
+        let mut hash = [0u8; 28];
+        hex::decode_to_slice(Self::norm_hex(entry), &mut hash).ok()?;
+        Some(script_address(network, &hash))
+    }
+
     /// Normalize for comparison: lowercase hex, no 0x prefix.
-    fn norm_hex(s: &str) -> String {
+    pub(crate) fn norm_hex(s: &str) -> String {
         s.trim().trim_start_matches("0x").to_lowercase()
     }
 
@@ -114,6 +281,73 @@ impl IndigoV2Config {
             .any(|h| Self::norm_hex(h) == n)
     }
 
+    /// Check if datum_hash matches a known CDP datum (never true without configured hashes).
+    pub fn is_cdp_datum(&self, datum_hash: Option<&str>) -> bool {
+        Self::listed(&self.cdp_datum_hashes, datum_hash)
+    }
+
+    /// Check if datum_hash matches a governance staking position (never true without hashes).
+    pub fn is_gov_staking_datum(&self, datum_hash: Option<&str>) -> bool {
+        Self::listed(&self.governance_staking_datum_hashes, datum_hash)
+    }
+
+    /// Check if datum_hash matches a governance poll (never true without hashes).
+    pub fn is_gov_poll_datum(&self, datum_hash: Option<&str>) -> bool {
+        Self::listed(&self.governance_poll_datum_hashes, datum_hash)
+    }
+
+    /// True if `datum_hash` is one of `hashes`; false for no datum or an empty list.
+    fn listed(hashes: &[String], datum_hash: Option<&str>) -> bool {
+        let Some(d) = datum_hash else {
+            return false;
+        };
+        let n = Self::norm_hex(d);
+        hashes.iter().any(|h| Self::norm_hex(h) == n)
+    }
+
+    /// True if `address` is a configured treasury or fee collector address.
+    pub fn is_protocol_fee_address(&self, address: &str) -> bool {
+        self.protocol_fee_addresses
+            .iter()
+            .any(|a| a.trim() == address.trim())
+    }
+
+    /// True if `address` is a configured DEX script.
+    pub fn is_dex_address(&self, address: &str) -> bool {
+        Self::address_listed(&self.dex_addresses, address)
+    }
+
+    /// True if `address` is a configured exchange address.
+    pub fn is_exchange_address(&self, address: &str) -> bool {
+        Self::address_listed(&self.exchange_addresses, address)
+    }
+
+    /// True if `address` is a configured INDY airdrop distributor.
+    pub fn is_indy_airdrop_address(&self, address: &str) -> bool {
+        Self::address_listed(&self.indy_airdrop_addresses, address)
+    }
+
+    /// True if `address` is a configured INDY vesting contract.
+    pub fn is_indy_vesting_address(&self, address: &str) -> bool {
+        Self::address_listed(&self.indy_vesting_addresses, address)
+    }
+
+    /// True if one of `entries` is `address` itself or the hash of its payment credential.
+    fn address_listed(entries: &[String], address: &str) -> bool {
+        let credential = payment_credential(address).map(hex::encode);
+        entries.iter().any(|entry| {
+            let entry = entry.trim();
+            entry == address.trim() || credential.as_deref() == Some(Self::norm_hex(entry).as_str())
+        })
+    }
+
+    /// True if `policy_id` is the configured INDY policy.
+    pub fn is_indy_policy(&self, policy_id: &str) -> bool {
+        self.indy_policy_id
+            .as_deref()
+            .is_some_and(|p| Self::norm_hex(p) == Self::norm_hex(policy_id))
+    }
+
     /// Check if datum_hash matches a known ROB datum.
     pub fn is_rob_datum(&self, datum_hash: Option<&str>) -> bool {
         let Some(d) = datum_hash else {
@@ -0,0 +1,117 @@
+//! Registry of official Indigo identifiers, to fill `IndigoV2Config` without hand-editing it.
+//!
+//! A registry is a JSON file in the `indigo_v2.json` format, published by whoever maintains the
+//! identifiers, together with its SHA-256 (out of band: a release note, a signed tag). The
+//! updater only installs a download matching the pinned hash, at
+//! `./config/indigo_v2.registry.json` (testnets: `indigo_v2.registry.<network>.json`). Loading
+//! merges it under the local config and remembers its hash, which the bundle then records.
+
+use crate::chain::Network;
+use crate::indigo::protocol_config::IndigoV2Config;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("registry server: status {0}")]
+    Status(u16),
+    #[error("registry SHA-256 is {actual}, expected {expected}")]
+    HashMismatch { expected: String, actual: String },
+    #[error("parse registry: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("write registry: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Where the installed registry for `network` lives.
+pub fn registry_path(network: Network) -> PathBuf {
+    match network {
+        Network::Mainnet => PathBuf::from("./config/indigo_v2.registry.json"),
+        other => PathBuf::from(format!("./config/indigo_v2.registry.{}.json", other)),
+    }
+}
+
+/// Hex SHA-256 of a registry file's bytes.
+pub fn registry_sha256(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+/// Parse `body` as a registry if it hashes to `expected_sha256` (hex, any case).
+pub fn verify_registry(
+    body: &[u8],
+    expected_sha256: &str,
+) -> Result<IndigoV2Config, RegistryError> {
+    let actual = registry_sha256(body);
+    let expected = expected_sha256.trim().to_lowercase();
+    if actual != expected {
+        return Err(RegistryError::HashMismatch { expected, actual });
+    }
+    Ok(serde_json::from_slice(body)?)
+}
+
+/// Download the registry at `url`, check it against `expected_sha256`, and write it to `path`.
+/// Nothing is written unless the hash matches. Returns the identifiers it holds.
+pub async fn update_registry(
+    url: &str,
+    expected_sha256: &str,
+    path: &Path,
+) -> Result<IndigoV2Config, RegistryError> {
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let res = client.get(url).send().await?;
+    let status = res.status();
+    if !status.is_success() {
+        return Err(RegistryError::Status(status.as_u16()));
+    }
+    let body = res.bytes().await?;
+    let registry = verify_registry(&body, expected_sha256)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, &body)?;
+    Ok(registry)
+}
+
+/// The installed registry at `path` with its SHA-256; `None` when absent or unreadable.
+pub fn load_registry(path: &Path) -> Option<(IndigoV2Config, String)> {
+    let body = std::fs::read(path).ok()?;
+    let registry = serde_json::from_slice(&body).ok()?;
+    Some((registry, registry_sha256(&body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_pinned_registry_is_accepted_and_merged_under_local_values() {
+        let body = br#"{"indy_policy_id": "1d", "cdp_datum_hashes": ["cd"]}"#;
+        let pinned = registry_sha256(body);
+        let registry = verify_registry(body, &pinned.to_uppercase()).unwrap();
+        assert!(matches!(
+            verify_registry(b"{}", &pinned),
+            Err(RegistryError::HashMismatch { .. })
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("indigo_v2.registry.json");
+        std::fs::write(&path, body).unwrap();
+        assert_eq!(load_registry(&path).unwrap().1, pinned);
+
+        let mut local = IndigoV2Config {
+            indy_policy_id: Some("beef".into()),
+            cdp_datum_hashes: vec!["ab".into(), "cd".into()],
+            ..Default::default()
+        };
+        local.merge_registry(registry, pinned.clone());
+        assert_eq!(local.indy_policy_id.as_deref(), Some("beef"));
+        assert_eq!(local.cdp_datum_hashes, vec!["ab", "cd"]);
+        assert_eq!(local.registry_sha256, Some(pinned));
+    }
+}
@@ -1,8 +1,24 @@
 //! Redemption Order Book: order placement, fills, premium/reimbursement %, cooldown.
+//!
+//! With ROB datum hashes configured, each order is followed UTxO by UTxO: its id is the output
+//! reference (`tx_hash#index`) it was placed at, and a partial fill's continuing output carries
+//! the same id on. Without them, placements and fills are inferred from tx ADA deltas and have
+//! no id.
+//!
+//! A tracked fill takes its premium and reimbursement from the spent order's datum (see
+//! [`crate::indigo::datum::decode_rob_datum`]). The whole-tx ADA delta, which batched
+//! redemptions skew, is only a fallback, flagged `premium_estimated`.
+//!
+//! Cooldowns are inferred from the events: the wait between a fill after which no order is
+//! left open and the next placement. The order datum's own time fields are not read.
 
-use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos};
-use crate::indigo::events::{Event, EventKind};
+use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+use crate::compute::fixed::ratio_pct;
+use crate::indigo::datum::decode_rob_datum;
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
 use crate::indigo::protocol_config::IndigoV2Config;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use time::OffsetDateTime;
 
 /// Reconstruct ROB-related events from account txs and tx UTxO data.
@@ -13,8 +29,12 @@ pub fn reconstruct_rob_events(
     now: OffsetDateTime,
     config: &IndigoV2Config,
 ) -> Vec<Event> {
+    let mut ordered: Vec<&KoiosAccountTx> = account_txs.iter().collect();
+    ordered.sort_by_key(|t| (t.slot_no.unwrap_or(0), t.tx_hash.clone()));
+    // Live order UTxO (`tx_hash#index`) → id of the order it belongs to.
+    let mut live: HashMap<String, String> = HashMap::new();
     let mut events = Vec::new();
-    for tx in account_txs {
+    for tx in ordered {
         let slot = tx.slot_no;
         let ts = tx
             .block_time
@@ -34,23 +54,46 @@ pub fn reconstruct_rob_events(
             let touches_rob = inputs
                 .iter()
                 .chain(outputs.iter())
-                .any(|u| config.is_rob_datum(u.datum_hash.as_deref()));
+                .any(|u| is_rob_utxo(u, config));
             if !touches_rob {
                 continue;
             }
+            for (kind, provenance) in
+                tracked_order_events(&tx_hash, slot, inputs, outputs, config, &mut live)
+            {
+                let confidence = match kind {
+                    EventKind::RobOrderFill {
+                        premium_estimated: true,
+                        ..
+                    } => Confidence::Medium,
+                    _ => Confidence::High,
+                };
+                events.push(Event {
+                    kind,
+                    timestamp: ts,
+                    slot,
+                    tx_hash: tx_hash.clone(),
+                    extra: None,
+                    confidence,
+                    provenance,
+                });
+            }
+            continue;
         }
 
         let in_ada: u64 = inputs.iter().map(|u| parse_lovelace(&u.value)).sum();
         let out_ada: u64 = outputs.iter().map(|u| parse_lovelace(&u.value)).sum();
 
         if out_ada > in_ada && in_ada > 0 {
-            let premium = ((out_ada - in_ada) as f64 / in_ada as f64) * 100.0;
+            let premium = ratio_pct((out_ada - in_ada) as i128, in_ada as i128).unwrap_or(0.0);
             events.push(Event {
                 kind: EventKind::RobOrderFill {
                     order_id: None,
                     filled_lovelace: out_ada,
                     premium_pct: Some(premium),
                     reimbursement_pct: Some(premium),
+                    premium_estimated: true,
+                    remaining_lovelace: None,
                     tx_hash: tx_hash.clone(),
                     slot,
                 },
@@ -58,6 +101,8 @@ pub fn reconstruct_rob_events(
                 slot,
                 tx_hash: tx_hash.clone(),
                 extra: None,
+                confidence: Confidence::Heuristic,
+                provenance: Provenance::from_utxos(inputs, outputs),
             });
         } else if in_ada > 0 && out_ada == 0 {
             events.push(Event {
@@ -71,13 +116,482 @@ pub fn reconstruct_rob_events(
                 slot,
                 tx_hash: tx_hash.clone(),
                 extra: None,
+                confidence: Confidence::Heuristic,
+                provenance: Provenance::from_utxos(inputs, []),
             });
         }
     }
     events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
+    let cooldowns = infer_cooldowns(&events);
+    events.extend(cooldowns);
+    events.sort_by_key(|e| (e.slot.unwrap_or(0), e.tx_hash.clone()));
     events
 }
 
+/// One [`EventKind::RobCooldown`] per fill-to-placement gap in chain-ordered `events`. A fill
+/// starts a cooldown once no tracked order is left open; a fill of an untracked order always
+/// does. Cancels do not: the refund is back with the owner, not idle in the book.
+fn infer_cooldowns(events: &[Event]) -> Vec<Event> {
+    let mut open: BTreeSet<&str> = BTreeSet::new();
+    let mut idle_since: Option<&Event> = None;
+    let mut cooldowns = Vec::new();
+    for event in events {
+        match &event.kind {
+            EventKind::RobOrderPlace { order_id, .. } => {
+                if let Some(fill) = idle_since.take() {
+                    let secs = (event.timestamp - fill.timestamp).whole_seconds().max(0) as u64;
+                    cooldowns.push(Event {
+                        kind: EventKind::RobCooldown {
+                            inferred_from_tx: true,
+                            tx_hash: event.tx_hash.clone(),
+                            since_tx: Some(fill.tx_hash.clone()),
+                            duration_secs: Some(secs),
+                        },
+                        // A gap between txs is inferred, however well the txs were read.
+                        confidence: event.confidence.max(Confidence::Medium),
+                        ..event.clone()
+                    });
+                }
+                if let Some(id) = order_id {
+                    open.insert(id);
+                }
+            }
+            EventKind::RobOrderFill {
+                order_id,
+                remaining_lovelace,
+                ..
+            } => match order_id {
+                Some(id) if *remaining_lovelace == Some(0) => {
+                    open.remove(id.as_str());
+                    if open.is_empty() {
+                        idle_since = Some(event);
+                    }
+                }
+                Some(_) => {}
+                None => idle_since = Some(event),
+            },
+            EventKind::RobOrderCancel {
+                order_id: Some(id), ..
+            } => {
+                open.remove(id.as_str());
+            }
+            _ => {}
+        }
+    }
+    cooldowns
+}
+
+fn is_rob_utxo(utxo: &KoiosUtxo, config: &IndigoV2Config) -> bool {
+    config.is_rob_datum(utxo.effective_datum_hash().as_deref())
+}
+
+/// Events of one tx for orders followed by output reference. A spent order continues into an
+/// order output with the same datum (or the only one, when one order is spent and one
+/// created); a spent order with no continuation closed, as a fill when the tx pays out an
+/// iAsset and as a cancel otherwise. Order outputs continuing nothing are placements. Each event
+/// comes with the order UTxOs it was read from.
+fn tracked_order_events(
+    tx_hash: &str,
+    slot: Option<u64>,
+    inputs: &[KoiosUtxo],
+    outputs: &[KoiosUtxo],
+    config: &IndigoV2Config,
+    live: &mut HashMap<String, String>,
+) -> Vec<(EventKind, Provenance)> {
+    let spent: Vec<&KoiosUtxo> = inputs.iter().filter(|u| is_rob_utxo(u, config)).collect();
+    let created: Vec<&KoiosUtxo> = outputs.iter().filter(|u| is_rob_utxo(u, config)).collect();
+    let mut continued = vec![false; created.len()];
+    let pays_iasset = outputs
+        .iter()
+        .filter(|u| !is_rob_utxo(u, config))
+        .flat_map(|u| u.asset_list.iter().flatten())
+        .any(|a| config.is_known_iasset_policy(&a.policy_id));
+
+    let ada_in: u64 = inputs.iter().map(|u| parse_lovelace(&u.value)).sum();
+    let ada_out: u64 = outputs.iter().map(|u| parse_lovelace(&u.value)).sum();
+    let estimate = (ada_out > ada_in)
+        .then(|| ratio_pct((ada_out - ada_in) as i128, ada_in as i128))
+        .flatten();
+    // (premium %, reimbursement %, estimated) of a fill of `order`.
+    let rates = |order: &KoiosUtxo| match order.datum().map(|d| decode_rob_datum(&d.bytes)) {
+        Some(Ok(datum)) => (
+            Some(datum.premium_pct),
+            Some(datum.reimbursement_pct),
+            false,
+        ),
+        _ => (estimate, estimate, estimate.is_some()),
+    };
+
+    let mut kinds = Vec::new();
+    for input in &spent {
+        let outref = format!("{}#{}", input.tx_hash, input.tx_index);
+        // An order placed before the fetched range is known by the first UTxO seen.
+        let order_id = live.remove(&outref).unwrap_or(outref);
+        let in_lovelace = parse_lovelace(&input.value);
+        let datum = input.effective_datum_hash();
+        let next = (0..created.len())
+            .find(|&i| !continued[i] && created[i].effective_datum_hash() == datum)
+            .or_else(|| (spent.len() == 1 && created.len() == 1 && !continued[0]).then_some(0));
+        match next {
+            Some(i) => {
+                continued[i] = true;
+                live.insert(
+                    format!("{}#{}", tx_hash, created[i].tx_index),
+                    order_id.clone(),
+                );
+                let out_lovelace = parse_lovelace(&created[i].value);
+                if out_lovelace < in_lovelace {
+                    let (premium_pct, reimbursement_pct, premium_estimated) = rates(input);
+                    kinds.push((
+                        EventKind::RobOrderFill {
+                            order_id: Some(order_id),
+                            filled_lovelace: in_lovelace - out_lovelace,
+                            premium_pct,
+                            reimbursement_pct,
+                            premium_estimated,
+                            remaining_lovelace: Some(out_lovelace),
+                            tx_hash: tx_hash.to_string(),
+                            slot,
+                        },
+                        Provenance::from_utxos([*input], [created[i]]),
+                    ));
+                }
+            }
+            None if pays_iasset => {
+                let (premium_pct, reimbursement_pct, premium_estimated) = rates(input);
+                kinds.push((
+                    EventKind::RobOrderFill {
+                        order_id: Some(order_id),
+                        filled_lovelace: in_lovelace,
+                        premium_pct,
+                        reimbursement_pct,
+                        premium_estimated,
+                        remaining_lovelace: Some(0),
+                        tx_hash: tx_hash.to_string(),
+                        slot,
+                    },
+                    Provenance::from_utxos([*input], []),
+                ))
+            }
+            None => kinds.push((
+                EventKind::RobOrderCancel {
+                    order_id: Some(order_id),
+                    returned_lovelace: in_lovelace,
+                    tx_hash: tx_hash.to_string(),
+                    slot,
+                },
+                Provenance::from_utxos([*input], []),
+            )),
+        }
+    }
+    for (output, _) in created.iter().zip(&continued).filter(|(_, c)| !**c) {
+        let order_id = format!("{}#{}", tx_hash, output.tx_index);
+        live.insert(order_id.clone(), order_id.clone());
+        kinds.push((
+            EventKind::RobOrderPlace {
+                order_id: Some(order_id),
+                amount_lovelace: parse_lovelace(&output.value),
+                tx_hash: tx_hash.to_string(),
+                slot,
+            },
+            Provenance::from_utxos([], [*output]),
+        ));
+    }
+    kinds
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    #[default]
+    Open,
+    Filled,
+    Cancelled,
+}
+
+/// One ROB order from placement through its fills to a full fill or cancel.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderLifecycle {
+    pub order_id: String,
+    /// `None` when the order was placed before the fetched range.
+    pub placed_tx: Option<String>,
+    pub placed_lovelace: Option<u64>,
+    pub fill_txs: Vec<String>,
+    pub filled_lovelace: u64,
+    /// ADA returned by a cancel.
+    pub cancelled_lovelace: u64,
+    /// ADA still on the order; 0 once it is filled or cancelled.
+    pub open_lovelace: u64,
+    pub status: OrderStatus,
+    /// Tx of the full fill or cancel.
+    pub closed_tx: Option<String>,
+}
+
+/// Link ROB events carrying an `order_id` into one lifecycle per order, sorted by id. Events
+/// must be in chain order, as [`reconstruct_rob_events`] returns them.
+pub fn order_lifecycles<'a>(events: impl IntoIterator<Item = &'a Event>) -> Vec<OrderLifecycle> {
+    let mut orders: BTreeMap<String, OrderLifecycle> = BTreeMap::new();
+    for event in events {
+        match &event.kind {
+            EventKind::RobOrderPlace {
+                order_id: Some(id),
+                amount_lovelace,
+                tx_hash,
+                ..
+            } => {
+                let order = orders.entry(id.clone()).or_default();
+                order.placed_tx = Some(tx_hash.clone());
+                order.placed_lovelace = Some(*amount_lovelace);
+                order.open_lovelace = *amount_lovelace;
+            }
+            EventKind::RobOrderFill {
+                order_id: Some(id),
+                filled_lovelace,
+                remaining_lovelace,
+                tx_hash,
+                ..
+            } => {
+                let order = orders.entry(id.clone()).or_default();
+                order.fill_txs.push(tx_hash.clone());
+                order.filled_lovelace = order.filled_lovelace.saturating_add(*filled_lovelace);
+                order.open_lovelace = remaining_lovelace
+                    .unwrap_or_else(|| order.open_lovelace.saturating_sub(*filled_lovelace));
+                if order.open_lovelace == 0 {
+                    order.status = OrderStatus::Filled;
+                    order.closed_tx = Some(tx_hash.clone());
+                }
+            }
+            EventKind::RobOrderCancel {
+                order_id: Some(id),
+                returned_lovelace,
+                tx_hash,
+                ..
+            } => {
+                let order = orders.entry(id.clone()).or_default();
+                order.cancelled_lovelace = *returned_lovelace;
+                order.open_lovelace = 0;
+                order.status = OrderStatus::Cancelled;
+                order.closed_tx = Some(tx_hash.clone());
+            }
+            _ => {}
+        }
+    }
+    orders
+        .into_iter()
+        .map(|(order_id, order)| OrderLifecycle { order_id, ..order })
+        .collect()
+}
+
 fn parse_lovelace(s: &str) -> u64 {
     s.trim().parse::<u64>().unwrap_or(0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDER_BOOK: &str = "addr1wyrswpc8qurswpc8qurswpc8qurswpc8qurswpc8qurswpcqwuenc";
+    const WALLET: &str = "addr1vyrswpc8qurswpc8qurswpc8qurswpc8qurswpc8qurswpcfxqeyc";
+
+    fn utxo(tx_hash: &str, tx_index: u32, lovelace: u64, order: bool, iasset: bool) -> KoiosUtxo {
+        KoiosUtxo {
+            tx_hash: tx_hash.into(),
+            tx_index,
+            ..KoiosUtxo::lovelace(lovelace)
+                .with_datum_hash(order.then_some("0d"))
+                .with_asset("f0ff", "69555344", if iasset { 25 } else { 0 })
+        }
+    }
+
+    fn tx(tx_hash: &str, slot: u64) -> KoiosAccountTx {
+        KoiosAccountTx {
+            tx_hash: tx_hash.into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(slot),
+        }
+    }
+
+    #[test]
+    fn order_is_followed_from_place_to_cancel() {
+        let config = IndigoV2Config {
+            rob_datum_hashes: vec!["0d".into()],
+            ..Default::default()
+        };
+        let get = |hash: &str| {
+            let (inputs, outputs) = match hash {
+                "place" => (
+                    vec![utxo("w", 0, 101_000_000, false, false)],
+                    vec![utxo("place", 0, 100_000_000, true, false)],
+                ),
+                "fill" => (
+                    vec![utxo("place", 0, 100_000_000, true, false)],
+                    vec![
+                        utxo("fill", 0, 2_000_000, false, true).at(WALLET),
+                        utxo("fill", 1, 60_000_000, true, false).at(ORDER_BOOK),
+                    ],
+                ),
+                "cancel" => (
+                    vec![utxo("fill", 1, 60_000_000, true, false)],
+                    vec![utxo("cancel", 0, 59_800_000, false, false)],
+                ),
+                _ => return None,
+            };
+            Some(KoiosTxUtxos {
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                metadata: None,
+            })
+        };
+        // Out of chain order on purpose: ids link by slot, not by list position.
+        let txs = vec![tx("cancel", 3), tx("place", 1), tx("fill", 2)];
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_rob_events(&txs, get, now, &config);
+        assert_eq!(events.len(), 3);
+        // The fill is traced to the order it spent and continued, not to the wallet's output.
+        assert_eq!(
+            events[1].provenance,
+            Provenance {
+                inputs: vec!["place#0".into()],
+                outputs: vec!["fill#1".into()],
+                script_addresses: vec![ORDER_BOOK.into()],
+            }
+        );
+        assert!(matches!(
+            &events[2].kind,
+            EventKind::RobOrderCancel { order_id: Some(id), returned_lovelace: 60_000_000, .. }
+                if id == "place#0"
+        ));
+        assert_eq!(
+            order_lifecycles(&events),
+            vec![OrderLifecycle {
+                order_id: "place#0".into(),
+                placed_tx: Some("place".into()),
+                placed_lovelace: Some(100_000_000),
+                fill_txs: vec!["fill".into()],
+                filled_lovelace: 40_000_000,
+                cancelled_lovelace: 60_000_000,
+                open_lovelace: 0,
+                status: OrderStatus::Cancelled,
+                closed_tx: Some("cancel".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn order_spent_for_an_iasset_is_filled() {
+        let config = IndigoV2Config {
+            rob_datum_hashes: vec!["0d".into()],
+            ..Default::default()
+        };
+        let utxos = KoiosTxUtxos {
+            inputs: Some(vec![utxo("old", 3, 50_000_000, true, false)]),
+            outputs: Some(vec![utxo("take", 0, 2_000_000, false, true)]),
+            metadata: None,
+        };
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events =
+            reconstruct_rob_events(&[tx("take", 9)], |_| Some(utxos.clone()), now, &config);
+        let orders = order_lifecycles(&events);
+        // Placed before the range: known by the UTxO first seen, placement unknown.
+        assert_eq!(orders[0].order_id, "old#3");
+        assert_eq!(orders[0].placed_lovelace, None);
+        assert_eq!(orders[0].status, OrderStatus::Filled);
+        assert_eq!(orders[0].filled_lovelace, 50_000_000);
+    }
+
+    #[test]
+    fn fill_then_replace_is_a_cooldown() {
+        let get = |hash: &str| {
+            let (inputs, outputs) = match hash {
+                "fill" => (
+                    vec![utxo("o", 0, 10_000_000, false, false)],
+                    vec![utxo("fill", 0, 10_100_000, false, false)],
+                ),
+                _ => (vec![utxo("w", 0, 20_000_000, false, false)], vec![]),
+            };
+            Some(KoiosTxUtxos {
+                inputs: Some(inputs),
+                outputs: Some(outputs),
+                metadata: None,
+            })
+        };
+        let at = |hash: &str, slot, time| KoiosAccountTx {
+            block_time: Some(time),
+            ..tx(hash, slot)
+        };
+        let txs = vec![
+            at("place1", 1, 1_000),
+            at("fill", 2, 2_000),
+            at("place2", 3, 5_600),
+        ];
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events = reconstruct_rob_events(&txs, get, now, &IndigoV2Config::default());
+        let cooldowns: Vec<&EventKind> = events
+            .iter()
+            .map(|e| &e.kind)
+            .filter(|k| matches!(k, EventKind::RobCooldown { .. }))
+            .collect();
+        assert_eq!(
+            cooldowns,
+            vec![&EventKind::RobCooldown {
+                inferred_from_tx: true,
+                tx_hash: "place2".into(),
+                since_tx: Some("fill".into()),
+                duration_secs: Some(3_600),
+            }]
+        );
+    }
+
+    #[test]
+    fn batched_fill_premium_comes_from_each_order_datum() {
+        use crate::chain::fetch::KoiosInlineDatum;
+        let config = IndigoV2Config {
+            rob_datum_hashes: vec!["0d".into()],
+            ..Default::default()
+        };
+        // Premium 2%, reimbursement 1.5%.
+        let mut terms = utxo("a", 0, 50_000_000, true, false);
+        terms.inline_datum = Some(KoiosInlineDatum {
+            bytes: format!(
+                "d87984581c{}4469555344d87981194e20d87981193a98",
+                "ab".repeat(28)
+            ),
+            value: None,
+        });
+        let utxos = KoiosTxUtxos {
+            inputs: Some(vec![
+                terms,
+                utxo("b", 0, 30_000_000, true, false),
+                utxo("w", 0, 10_000_000, false, false),
+            ]),
+            outputs: Some(vec![
+                utxo("batch", 0, 2_000_000, false, true),
+                utxo("batch", 1, 100_000_000, false, false),
+            ]),
+            metadata: None,
+        };
+        let now = OffsetDateTime::from_unix_timestamp(0).unwrap();
+        let events =
+            reconstruct_rob_events(&[tx("batch", 5)], |_| Some(utxos.clone()), now, &config);
+        let rates: Vec<(Option<f64>, Option<f64>, bool)> = events
+            .iter()
+            .filter_map(|e| match &e.kind {
+                EventKind::RobOrderFill {
+                    premium_pct,
+                    reimbursement_pct,
+                    premium_estimated,
+                    ..
+                } => Some((*premium_pct, *reimbursement_pct, *premium_estimated)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0], (Some(2.0), Some(1.5), false));
+        // No decodable datum: the whole-tx delta, flagged.
+        assert!(rates[1].2);
+        // 12 / 90 ADA, to the micro-percent.
+        assert_eq!(rates[1].0, Some(13.333333));
+    }
+}
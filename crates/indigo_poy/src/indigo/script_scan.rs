@@ -0,0 +1,125 @@
+//! Script-address scanning: walk the Stability Pool / ROB script addresses' tx history and keep
+//! the txs that involve the user.
+//!
+//! Walking only the user's own tx list misses flows that stay at the script until the user
+//! claims them, e.g. liquidation distributions to SP accounts. A script tx involves the user
+//! when one of its UTxO datums embeds one of the user's key hashes (Indigo account and order
+//! datums carry the owner's credential). Script histories are long, so this is opt-in.
+
+use crate::chain::fetch::{FetchError, KoiosAccountTx, KoiosTxUtxos};
+use crate::chain::network::address_key_hashes;
+use crate::chain::Fetcher;
+use crate::indigo::IndigoV2Config;
+use std::collections::{BTreeSet, HashMap};
+use tracing::info;
+
+/// CBOR header of a 28-byte bytestring (`0x58 0x1c`), as key hashes appear in plutus data.
+const KEY_HASH_CBOR_PREFIX: &str = "581c";
+
+/// Script txs that involve the user, with the UTxOs fetched while filtering them.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptScan {
+    /// Matching txs, ordered by slot then hash.
+    pub txs: Vec<KoiosAccountTx>,
+    pub tx_utxos: HashMap<String, KoiosTxUtxos>,
+    /// Script txs examined, matching or not.
+    pub scanned: usize,
+}
+
+/// Hex key hashes (payment and stake) of the user's addresses, as they appear in datum CBOR.
+pub fn user_key_hash_patterns(addresses: &[String]) -> Vec<String> {
+    let hashes: BTreeSet<String> = addresses
+        .iter()
+        .flat_map(|a| address_key_hashes(a))
+        .map(|h| format!("{}{}", KEY_HASH_CBOR_PREFIX, hex::encode(h)))
+        .collect();
+    hashes.into_iter().collect()
+}
+
+/// True if any input or output datum of the tx embeds one of `patterns`.
+pub fn involves_user(utxos: &KoiosTxUtxos, patterns: &[String]) -> bool {
+    utxos
+        .inputs
+        .iter()
+        .chain(utxos.outputs.iter())
+        .flatten()
+        .filter_map(|u| u.datum())
+        .any(|d| {
+            let bytes = d.bytes.to_lowercase();
+            patterns.iter().any(|p| bytes.contains(p.as_str()))
+        })
+}
+
+/// Scan every script address in `config` for txs involving `user_addresses`. Empty when the
+/// config has no script hashes (heuristic mode has nothing to scan).
+pub async fn scan_script_addresses(
+    fetcher: &Fetcher,
+    config: &IndigoV2Config,
+    user_addresses: &[String],
+    from_slot_or_time: Option<&str>,
+    to_slot_or_time: Option<&str>,
+) -> Result<ScriptScan, FetchError> {
+    let scripts = config.script_addresses(fetcher.network());
+    let patterns = user_key_hash_patterns(user_addresses);
+    if scripts.is_empty() || patterns.is_empty() {
+        return Ok(ScriptScan::default());
+    }
+    let txs = fetcher
+        .account_txs_multi(&scripts, from_slot_or_time, to_slot_or_time)
+        .await?;
+    let hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
+    let mut fetched: HashMap<String, KoiosTxUtxos> = fetcher
+        .fetch_many(&hashes)
+        .await
+        .into_iter()
+        .filter_map(|(hash, r)| r.ok().map(|u| (hash, u)))
+        .collect();
+    fetcher.resolve_datums(fetched.values_mut()).await?;
+    let scanned = txs.len();
+    let mut scan = ScriptScan {
+        scanned,
+        ..Default::default()
+    };
+    for tx in txs {
+        if let Some(utxos) = fetched.remove(&tx.tx_hash) {
+            if involves_user(&utxos, &patterns) {
+                scan.tx_utxos.insert(tx.tx_hash.clone(), utxos);
+                scan.txs.push(tx);
+            }
+        }
+    }
+    info!(
+        scripts = scripts.len(),
+        scanned,
+        matched = scan.txs.len(),
+        "script address scan"
+    );
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::KoiosUtxo;
+
+    fn utxo_with_datum(bytes: &str) -> KoiosUtxo {
+        KoiosUtxo::lovelace(2_000_000).with_inline_datum(bytes)
+    }
+
+    #[test]
+    fn datum_embedding_user_key_hash_matches() {
+        // CIP-19 test vector: base address with payment and stake key hashes.
+        let address = "addr1qx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzer3n0d3vllmyqwsx5wktcd8cc3sq835lu7drv2xwl2wywfgse35a3x".to_string();
+        let patterns = user_key_hash_patterns(&[address]);
+        assert_eq!(patterns.len(), 2);
+        // Constr 0 [bytes(28) payment key hash]
+        let datum = format!("d8799f{}ff", patterns[0].to_uppercase());
+        let tx = |bytes: &str| KoiosTxUtxos {
+            inputs: Some(vec![]),
+            outputs: Some(vec![utxo_with_datum(bytes)]),
+            metadata: None,
+        };
+        assert!(involves_user(&tx(&datum), &patterns));
+        assert!(!involves_user(&tx("d87980"), &patterns));
+    }
+}
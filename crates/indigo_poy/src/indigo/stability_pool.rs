@@ -1,12 +1,27 @@
 //! Stability Pool: liquidation events, iAsset burnt, ADA received, realized premium, dilution.
+//!
+//! Deposits and withdrawals come from the owner's account datums when the tx carries them
+//! (see [`crate::indigo::datum`]); other SP txs fall back to reading ADA deltas.
+//!
+//! A liquidation is shared by every depositor in the pool. When the tx spends and recreates
+//! the pool UTxO and an earlier tx showed the wallet's account, the wallet gets its pro-rata
+//! share: its compounded deposit over the pool's total deposit, from the pool datum, times the
+//! iAsset burnt and the ADA the pool received. Its realized premium needs the iAsset's ADA
+//! price and is 0 here; [`crate::indigo::oracle`] fills it in.
 
-use crate::chain::fetch::{KoiosAccountTx, KoiosAsset, KoiosTxUtxos};
-use crate::indigo::events::{Event, EventKind};
+use crate::chain::assets::asset_unit;
+use crate::chain::fetch::{KoiosAccountTx, KoiosAsset, KoiosTxUtxos, KoiosUtxo};
+use crate::indigo::datum::{decode_sp_datum, SpAccount, SpDatum, SpPool, SpSnapshot};
+use crate::indigo::events::{Confidence, Event, EventKind, Provenance};
+use crate::indigo::position::SpPosition;
 use crate::indigo::protocol_config::IndigoV2Config;
+use std::collections::{BTreeMap, BTreeSet};
 use time::OffsetDateTime;
 
 /// Reconstruct Stability Pool events from account txs and per-tx UTxO data.
 /// When `config` has iasset_policy_ids or stability_pool_datum_hashes set, only UTxOs matching those are treated as SP.
+/// A tx spending or creating an SP account yields one deposit or withdrawal per account, from
+/// the change of its datum's deposit field.
 pub fn reconstruct_stability_pool_events(
     account_txs: &[KoiosAccountTx],
     get_tx_utxos: impl Fn(&str) -> Option<KoiosTxUtxos>,
@@ -14,7 +29,11 @@ pub fn reconstruct_stability_pool_events(
     config: &IndigoV2Config,
 ) -> Vec<Event> {
     let mut events = Vec::new();
-    for tx in account_txs {
+    let mut txs: Vec<&KoiosAccountTx> = account_txs.iter().collect();
+    txs.sort_by_key(|t| t.slot_no.unwrap_or(0));
+    // The wallet's open accounts as last seen, for pro-rata liquidation shares.
+    let mut held: BTreeMap<AccountKey, SpSnapshot> = BTreeMap::new();
+    for tx in txs {
         let slot = tx.slot_no;
         let block_time = tx
             .block_time
@@ -30,6 +49,108 @@ pub fn reconstruct_stability_pool_events(
         let inputs = utxos.inputs.as_deref().unwrap_or(&[]);
         let outputs = utxos.outputs.as_deref().unwrap_or(&[]);
 
+        let before = sp_accounts(inputs, config);
+        let after = sp_accounts(outputs, config);
+        for key in before.keys() {
+            held.remove(key);
+        }
+        for (_, account) in decoded_accounts(outputs, config) {
+            held.insert((account.owner, account.iasset), account.snapshot);
+        }
+        if !before.is_empty() || !after.is_empty() {
+            let keys: BTreeSet<&AccountKey> = before.keys().chain(after.keys()).collect();
+            for key in keys {
+                let (deposit_before, ada_before) = before.get(key).copied().unwrap_or_default();
+                let (deposit_after, ada_after) = after.get(key).copied().unwrap_or_default();
+                let delta = deposit_after - deposit_before;
+                if delta == 0 {
+                    continue;
+                }
+                let amount_lovelace = ada_after.abs_diff(ada_before);
+                let iasset_amount = Some(iasset_unit(&key.1, inputs, outputs, config));
+                let iasset_quantity = Some(delta.unsigned_abs());
+                let kind = if delta > 0 {
+                    EventKind::StabilityPoolDeposit {
+                        amount_lovelace,
+                        iasset_amount,
+                        iasset_quantity,
+                        tx_hash: tx_hash.clone(),
+                    }
+                } else {
+                    EventKind::StabilityPoolWithdraw {
+                        amount_lovelace,
+                        iasset_amount,
+                        iasset_quantity,
+                        tx_hash: tx_hash.clone(),
+                    }
+                };
+                let of_key = |utxos| {
+                    decoded_accounts(utxos, config)
+                        .filter(|(_, a)| (&a.owner, &a.iasset) == (&key.0, &key.1))
+                        .map(|(u, _)| u)
+                };
+                events.push(Event {
+                    kind,
+                    timestamp: ts,
+                    slot,
+                    tx_hash: tx_hash.clone(),
+                    extra: None,
+                    confidence: Confidence::High,
+                    provenance: Provenance::from_utxos(of_key(inputs), of_key(outputs)),
+                });
+            }
+            continue;
+        }
+
+        if let (Some((pool_in, pool)), Some((pool_out, pool_after))) =
+            (sp_pool(inputs, config), sp_pool(outputs, config))
+        {
+            let (ada_before, ada_after) = (
+                parse_lovelace(&pool_in.value),
+                parse_lovelace(&pool_out.value),
+            );
+            let pool_deposit = pool.snapshot.deposit;
+            let burnt = pool_deposit - pool_after.snapshot.deposit;
+            let user: i128 = held
+                .iter()
+                .filter(|((_, iasset), _)| *iasset == pool.iasset)
+                .filter_map(|(_, account)| compounded_deposit(account, &pool.snapshot))
+                .sum();
+            if burnt > 0 && ada_after > ada_before && pool_deposit > 0 && user > 0 {
+                let share = |x: u128| x * user as u128 / pool_deposit as u128;
+                let ada_received_lovelace =
+                    share(u128::from(ada_after - ada_before)).min(u64::MAX as u128) as u64;
+                events.push(Event {
+                    kind: EventKind::StabilityPoolLiquidation {
+                        iasset_burnt: iasset_unit(&pool.iasset, inputs, outputs, config),
+                        ada_received_lovelace,
+                        realized_premium_lovelace: 0,
+                        dilution_effect: None,
+                        pool_deposit_quantity: Some(pool_deposit as u128),
+                        user_deposit_quantity: Some(user as u128),
+                        iasset_lost_quantity: Some(share(burnt as u128)),
+                        oracle_price: None,
+                        tx_hash: tx_hash.clone(),
+                        slot,
+                    },
+                    timestamp: ts,
+                    slot,
+                    tx_hash: tx_hash.clone(),
+                    extra: None,
+                    confidence: Confidence::High,
+                    provenance: Provenance::from_utxos([pool_in], [pool_out]),
+                });
+                continue;
+            }
+        }
+
+        // Matched by configured datum or policy, sized by ADA deltas.
+        let shape_confidence =
+            if config.has_stability_pool_datum_hashes() || config.has_iasset_policy_ids() {
+                Confidence::Medium
+            } else {
+                Confidence::Heuristic
+            };
         let ada_in: u64 = inputs.iter().map(|u| parse_lovelace(&u.value)).sum();
         let _ada_out: u64 = outputs.iter().map(|u| parse_lovelace(&u.value)).sum();
 
@@ -45,7 +166,7 @@ pub fn reconstruct_stability_pool_events(
                 .asset_list
                 .as_ref()
                 .and_then(|a: &Vec<KoiosAsset>| a.first())
-                .map(|a| format!("{}${}", a.policy_id, a.asset_name));
+                .map(|a| asset_unit(&a.policy_id, &a.asset_name));
             if out_ada >= ada_in && ada_in > 0 {
                 events.push(Event {
                     kind: EventKind::StabilityPoolLiquidation {
@@ -53,6 +174,10 @@ pub fn reconstruct_stability_pool_events(
                         ada_received_lovelace: out_ada,
                         realized_premium_lovelace: out_ada.saturating_sub(ada_in).min(out_ada),
                         dilution_effect: None,
+                        pool_deposit_quantity: None,
+                        user_deposit_quantity: None,
+                        iasset_lost_quantity: None,
+                        oracle_price: None,
                         tx_hash: tx_hash.clone(),
                         slot,
                     },
@@ -60,18 +185,23 @@ pub fn reconstruct_stability_pool_events(
                     slot,
                     tx_hash: tx_hash.clone(),
                     extra: None,
+                    confidence: shape_confidence,
+                    provenance: Provenance::from_utxos([], [out]),
                 });
             } else if out_ada > 0 {
                 events.push(Event {
                     kind: EventKind::StabilityPoolWithdraw {
                         amount_lovelace: out_ada,
                         iasset_amount: iasset,
+                        iasset_quantity: None,
                         tx_hash: tx_hash.clone(),
                     },
                     timestamp: ts,
                     slot,
                     tx_hash: tx_hash.clone(),
                     extra: None,
+                    confidence: shape_confidence,
+                    provenance: Provenance::from_utxos([], [out]),
                 });
             }
         }
@@ -88,17 +218,20 @@ pub fn reconstruct_stability_pool_events(
                 .asset_list
                 .as_ref()
                 .and_then(|a: &Vec<KoiosAsset>| a.first())
-                .map(|a| format!("{}${}", a.policy_id, a.asset_name));
+                .map(|a| asset_unit(&a.policy_id, &a.asset_name));
             events.push(Event {
                 kind: EventKind::StabilityPoolDeposit {
                     amount_lovelace: in_ada,
                     iasset_amount: iasset,
+                    iasset_quantity: None,
                     tx_hash: tx_hash.clone(),
                 },
                 timestamp: ts,
                 slot,
                 tx_hash: tx_hash.clone(),
                 extra: None,
+                confidence: shape_confidence,
+                provenance: Provenance::from_utxos([inp], []),
             });
         }
     }
@@ -106,11 +239,143 @@ pub fn reconstruct_stability_pool_events(
     events
 }
 
-/// True if this UTxO should be treated as Stability Pool (datum and iAsset policy match config when set).
-fn is_sp_utxo(out: &crate::chain::fetch::KoiosUtxo, config: &IndigoV2Config) -> bool {
-    if !config.is_stability_pool_datum(out.datum_hash.as_deref()) {
+/// Account owner and iAsset token name (hex).
+type AccountKey = (String, String);
+
+/// SP account datums among `utxos`, with the UTxO carrying each.
+fn decoded_accounts<'a>(
+    utxos: &'a [KoiosUtxo],
+    config: &'a IndigoV2Config,
+) -> impl Iterator<Item = (&'a KoiosUtxo, SpAccount)> + 'a {
+    utxos.iter().filter_map(|utxo| {
+        if !config.is_stability_pool_datum(utxo.effective_datum_hash().as_deref()) {
+            return None;
+        }
+        match utxo.datum().map(|d| decode_sp_datum(&d.bytes)) {
+            Some(Ok(SpDatum::Account(account))) => Some((utxo, account)),
+            _ => None,
+        }
+    })
+}
+
+/// The first SP pool datum among `utxos`, with the UTxO carrying it.
+fn sp_pool<'a>(utxos: &'a [KoiosUtxo], config: &IndigoV2Config) -> Option<(&'a KoiosUtxo, SpPool)> {
+    utxos.iter().find_map(|utxo| {
+        if !config.is_stability_pool_datum(utxo.effective_datum_hash().as_deref()) {
+            return None;
+        }
+        match utxo.datum().map(|d| decode_sp_datum(&d.bytes)) {
+            Some(Ok(SpDatum::Pool(pool))) => Some((utxo, pool)),
+            _ => None,
+        }
+    })
+}
+
+/// An account's deposit after the liquidations since its snapshot: `D * P_pool / P_account`.
+/// `None` when the pool has moved to another epoch or scale, which this does not follow.
+fn compounded_deposit(account: &SpSnapshot, pool: &SpSnapshot) -> Option<i128> {
+    if account.epoch != pool.epoch || account.scale != pool.scale || account.product <= 0 {
+        return None;
+    }
+    account
+        .deposit
+        .checked_mul(pool.product)
+        .map(|d| d / account.product)
+}
+
+/// Liquidation ADA an account earned since its snapshot and has not claimed:
+/// `D * (S_pool - S_account) / P_account`. `None` across an epoch or scale change.
+fn pending_ada(account: &SpSnapshot, pool: &SpSnapshot) -> Option<i128> {
+    if account.epoch != pool.epoch || account.scale != pool.scale || account.product <= 0 {
+        return None;
+    }
+    account
+        .deposit
+        .checked_mul(pool.sum - account.sum)
+        .map(|g| (g / account.product).max(0))
+}
+
+/// Open accounts of `owners` (payment key hashes, hex) among `utxos`, the current UTxOs of an
+/// SP script address, compounded to the pool datum among them. An account whose pool is not
+/// there or has moved to another epoch or scale keeps its snapshot deposit and no pending ADA.
+pub(crate) fn open_sp_accounts(
+    utxos: &[KoiosUtxo],
+    owners: &BTreeSet<String>,
+    config: &IndigoV2Config,
+) -> Vec<SpPosition> {
+    let pools: BTreeMap<String, SpSnapshot> = utxos
+        .iter()
+        .filter(|u| config.is_stability_pool_datum(u.effective_datum_hash().as_deref()))
+        .filter_map(|u| match u.datum().map(|d| decode_sp_datum(&d.bytes)) {
+            Some(Ok(SpDatum::Pool(pool))) => Some((pool.iasset.to_lowercase(), pool.snapshot)),
+            _ => None,
+        })
+        .collect();
+    decoded_accounts(utxos, config)
+        .filter(|(_, account)| owners.contains(&account.owner.to_lowercase()))
+        .map(|(utxo, account)| {
+            let pool = pools.get(&account.iasset.to_lowercase());
+            let deposit = pool
+                .and_then(|p| compounded_deposit(&account.snapshot, p))
+                .unwrap_or(account.snapshot.deposit);
+            let pending = pool
+                .and_then(|p| pending_ada(&account.snapshot, p))
+                .unwrap_or(0);
+            SpPosition {
+                iasset: account.iasset.to_lowercase(),
+                deposit_quantity: deposit.max(0) as u128,
+                pending_ada_lovelace: pending.min(u64::MAX as i128) as u64,
+                utxo_lovelace: parse_lovelace(&utxo.value),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// SP accounts among `utxos`: (owner, iAsset) → (datum deposit, lovelace on the UTxO).
+fn sp_accounts(utxos: &[KoiosUtxo], config: &IndigoV2Config) -> BTreeMap<AccountKey, (i128, u64)> {
+    let mut accounts: BTreeMap<AccountKey, (i128, u64)> = BTreeMap::new();
+    for (utxo, account) in decoded_accounts(utxos, config) {
+        let entry = accounts.entry((account.owner, account.iasset)).or_default();
+        entry.0 += account.snapshot.deposit;
+        entry.1 = entry.1.saturating_add(parse_lovelace(&utxo.value));
+    }
+    accounts
+}
+
+/// `policy$name` of the iAsset named in an account datum, from a known-policy asset of that
+/// name in the tx; the bare name when none is present.
+fn iasset_unit(
+    name: &str,
+    inputs: &[KoiosUtxo],
+    outputs: &[KoiosUtxo],
+    config: &IndigoV2Config,
+) -> String {
+    inputs
+        .iter()
+        .chain(outputs)
+        .flat_map(|u| u.asset_list.iter().flatten())
+        .find(|a| {
+            a.asset_name.eq_ignore_ascii_case(name) && config.is_known_iasset_policy(&a.policy_id)
+        })
+        .map_or_else(
+            || name.to_string(),
+            |a| asset_unit(&a.policy_id, &a.asset_name),
+        )
+}
+
+/// True if this UTxO should be treated as Stability Pool (datum and iAsset policy match config
+/// when set), or its datum decodes as an SP pool or account datum.
+pub(crate) fn is_sp_utxo(out: &KoiosUtxo, config: &IndigoV2Config) -> bool {
+    if !config.is_stability_pool_datum(out.effective_datum_hash().as_deref()) {
         return false;
     }
+    if out
+        .datum()
+        .is_some_and(|d| decode_sp_datum(&d.bytes).is_ok())
+    {
+        return true;
+    }
     let Some(assets) = out.asset_list.as_ref() else {
         return false;
     };
@@ -151,4 +416,157 @@ mod tests {
         let out = reconstruct_stability_pool_events(&txs, get_none, ts(), &config);
         assert!(out.is_empty());
     }
+
+    #[test]
+    fn inline_datum_matches_configured_datum_hash() {
+        use crate::chain::fetch::{KoiosInlineDatum, KoiosUtxo};
+        let datum = KoiosInlineDatum {
+            bytes: "d87980".into(),
+            value: None,
+        };
+        let config = IndigoV2Config {
+            stability_pool_datum_hashes: vec![datum.hash().unwrap()],
+            ..Default::default()
+        };
+        let mut utxo = KoiosUtxo::lovelace(2_000_000).with_asset("f0ff", "69555344", 1);
+        assert!(!is_sp_utxo(&utxo, &config));
+        utxo.inline_datum = Some(datum);
+        assert!(is_sp_utxo(&utxo, &config));
+    }
+
+    #[test]
+    fn account_datums_give_deposits_and_withdrawals() {
+        /// SP account of owner `ab…ab` in iUSD holding `deposit`.
+        fn account(deposit: u32, lovelace: u64) -> KoiosUtxo {
+            let bytes = format!(
+                "d87a84581c{}4469555344d87985d879811a000f4240d879811a{:08x}d8798100d8798100d8798100d87a80",
+                "ab".repeat(28),
+                deposit
+            );
+            KoiosUtxo::lovelace(lovelace).with_inline_datum(&bytes)
+        }
+        let pool = KoiosUtxo::lovelace(5_000_000).with_asset("f0ff", "69555344", 10_000);
+        let config = IndigoV2Config {
+            iasset_policy_ids: vec!["f0ff".into()],
+            ..Default::default()
+        };
+        let tx = |hash: &str, slot| KoiosAccountTx {
+            tx_hash: hash.into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(slot),
+        };
+        let txs = vec![tx("adjust", 1), tx("close", 2)];
+        let get = |hash: &str| {
+            Some(match hash {
+                "adjust" => KoiosTxUtxos {
+                    inputs: Some(vec![account(500, 2_000_000), pool.clone()]),
+                    outputs: Some(vec![account(800, 2_000_000), pool.clone()]),
+                    metadata: None,
+                },
+                _ => KoiosTxUtxos {
+                    inputs: Some(vec![account(800, 2_000_000)]),
+                    outputs: Some(vec![]),
+                    metadata: None,
+                },
+            })
+        };
+        let kinds: Vec<EventKind> = reconstruct_stability_pool_events(&txs, get, ts(), &config)
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::StabilityPoolDeposit {
+                    amount_lovelace: 0,
+                    iasset_amount: Some("f0ff$69555344".into()),
+                    iasset_quantity: Some(300),
+                    tx_hash: "adjust".into(),
+                },
+                EventKind::StabilityPoolWithdraw {
+                    amount_lovelace: 2_000_000,
+                    iasset_amount: Some("69555344".into()),
+                    iasset_quantity: Some(800),
+                    tx_hash: "close".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn liquidation_is_shared_pro_rata() {
+        fn snapshot(product: u32, deposit: u32) -> String {
+            format!("d87985d879811a{product:08x}d879811a{deposit:08x}d8798100d8798100d8798100")
+        }
+        fn utxo(bytes: String, lovelace: u64, iusd: u32) -> KoiosUtxo {
+            KoiosUtxo::lovelace(lovelace)
+                .with_asset("f0ff", "69555344", iusd.into())
+                .with_inline_datum(&bytes)
+        }
+        let account = format!(
+            "d87a84581c{}4469555344{}d87a80",
+            "ab".repeat(28),
+            snapshot(1_000_000, 500)
+        );
+        let pool =
+            |product, deposit| format!("d879834469555344{}d87980", snapshot(product, deposit));
+        let config = IndigoV2Config {
+            iasset_policy_ids: vec!["f0ff".into()],
+            ..Default::default()
+        };
+        let tx = |hash: &str, slot| KoiosAccountTx {
+            tx_hash: hash.into(),
+            block_height: None,
+            block_time: Some(1_700_000_000),
+            epoch_no: None,
+            slot_no: Some(slot),
+        };
+        // Out of order: the account must be known before the liquidation is read.
+        let txs = vec![tx("liq", 2), tx("open", 1)];
+        let get = |hash: &str| {
+            Some(match hash {
+                "open" => KoiosTxUtxos {
+                    inputs: Some(vec![]),
+                    outputs: Some(vec![utxo(account.clone(), 2_000_000, 0)]),
+                    metadata: None,
+                },
+                _ => KoiosTxUtxos {
+                    inputs: Some(vec![utxo(pool(1_000_000, 2_000), 10_000_000, 2_000)]),
+                    outputs: Some(vec![utxo(pool(750_000, 1_500), 110_000_000, 1_500)]),
+                    metadata: None,
+                },
+            })
+        };
+        let events = reconstruct_stability_pool_events(&txs, get, ts(), &config);
+        assert_eq!(events.len(), 2);
+
+        // At the pool's current state, the account holds 750 000 / 1 000 000 of its deposit.
+        let owners = BTreeSet::from(["ab".repeat(28)]);
+        let now = [
+            utxo(account.clone(), 2_000_000, 0),
+            utxo(pool(750_000, 1_500), 110_000_000, 1_500),
+        ];
+        let open = open_sp_accounts(&now, &owners, &config);
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].deposit_quantity, 375);
+        assert_eq!(open[0].utxo_lovelace, 2_000_000);
+        assert!(open_sp_accounts(&now, &BTreeSet::new(), &config).is_empty());
+        assert_eq!(
+            events[1].kind,
+            EventKind::StabilityPoolLiquidation {
+                iasset_burnt: "f0ff$69555344".into(),
+                ada_received_lovelace: 25_000_000,
+                realized_premium_lovelace: 0,
+                dilution_effect: None,
+                pool_deposit_quantity: Some(2_000),
+                user_deposit_quantity: Some(500),
+                iasset_lost_quantity: Some(125),
+                oracle_price: None,
+                tx_hash: "liq".into(),
+                slot: Some(2),
+            }
+        );
+    }
 }
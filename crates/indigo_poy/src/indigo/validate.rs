@@ -0,0 +1,502 @@
+//! Config validation: catch a malformed or stale `IndigoV2Config` before it silently turns a
+//! parser heuristic.
+//!
+//! [`validate_config`] checks each identifier's shape (hex length, bech32) and flags duplicates,
+//! lists the fields left empty, and says which parsing mode each parser ends up in.
+//! [`cross_check_config`] then asks the chain whether each policy ID was ever minted under and
+//! each script and oracle address holds UTxOs.
+
+use crate::chain::fetch::FetchError;
+use crate::chain::network::payment_credential;
+use crate::chain::Fetcher;
+use crate::indigo::protocol_config::IndigoV2Config;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use tracing::warn;
+
+/// What an identifier field holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FieldKind {
+    /// 56-char validator hash, or the script's bech32 address.
+    Script,
+    /// 64-char datum hash.
+    Datum,
+    /// 56-char policy ID.
+    Policy,
+    /// Bech32 address.
+    Address,
+    /// Bech32 address, or a 56-char payment credential hash.
+    AddressOrHash,
+}
+
+impl FieldKind {
+    fn expected(&self) -> &'static str {
+        match self {
+            FieldKind::Script => "56-char hex script hash or bech32 address",
+            FieldKind::Datum => "64-char hex datum hash",
+            FieldKind::Policy => "56-char hex policy ID",
+            FieldKind::Address => "bech32 address",
+            FieldKind::AddressOrHash => "bech32 address or 56-char hex credential hash",
+        }
+    }
+
+    fn accepts(&self, entry: &str) -> bool {
+        let hex_of = |len: usize| {
+            let h = IndigoV2Config::norm_hex(entry);
+            h.len() == len && h.bytes().all(|b| b.is_ascii_hexdigit())
+        };
+        let address = || payment_credential(entry.trim()).is_some();
+        match self {
+            FieldKind::Script | FieldKind::AddressOrHash => hex_of(56) || address(),
+            FieldKind::Datum => hex_of(64),
+            FieldKind::Policy => hex_of(56),
+            FieldKind::Address => address(),
+        }
+    }
+}
+
+/// What is wrong with one config entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// Not the shape the field expects (described).
+    Malformed(&'static str),
+    /// Listed more than once in the field.
+    Duplicate,
+    /// Policy ID nothing was ever minted under.
+    NotMinted,
+    /// Script or oracle address holding no UTxOs.
+    NoUtxos,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Malformed(expected) => write!(f, "malformed (expected {})", expected),
+            Problem::Duplicate => f.write_str("duplicate"),
+            Problem::NotMinted => f.write_str("no asset minted under this policy"),
+            Problem::NoUtxos => f.write_str("address holds no UTxOs"),
+        }
+    }
+}
+
+/// One problem entry of one field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub field: &'static str,
+    pub value: String,
+    pub problem: Problem,
+}
+
+/// How a parser reads txs under a given config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParsingMode {
+    /// UTxOs matched by configured datum hashes.
+    Datum,
+    /// Assets matched by configured policy IDs.
+    Policy,
+    /// UTxOs matched by configured addresses.
+    Address,
+    /// Guessed from ADA deltas and tx shape.
+    Heuristic,
+    /// No events: the parser needs identifiers that are not configured.
+    Off,
+}
+
+impl ParsingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ParsingMode::Datum => "datum",
+            ParsingMode::Policy => "policy",
+            ParsingMode::Address => "address",
+            ParsingMode::Heuristic => "heuristic",
+            ParsingMode::Off => "off",
+        }
+    }
+}
+
+/// Result of [`validate_config`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConfigReport {
+    pub issues: Vec<ConfigIssue>,
+    /// Fields left empty.
+    pub missing: Vec<&'static str>,
+    /// Parser → the mode it runs in.
+    pub modes: BTreeMap<&'static str, ParsingMode>,
+}
+
+impl ConfigReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// The identifier fields with their kinds and entries.
+fn fields(config: &IndigoV2Config) -> Vec<(&'static str, FieldKind, Vec<&str>)> {
+    // Destructured so a new field cannot be left unchecked.
+    let IndigoV2Config {
+        stability_pool_script_hashes,
+        stability_pool_datum_hashes,
+        iasset_policy_ids,
+        rob_script_hashes,
+        rob_datum_hashes,
+        indy_policy_id,
+        cdp_script_hashes,
+        cdp_datum_hashes,
+        governance_staking_datum_hashes,
+        governance_poll_datum_hashes,
+        protocol_fee_addresses,
+        oracle_addresses,
+        dex_addresses,
+        exchange_addresses,
+        indy_airdrop_addresses,
+        indy_vesting_addresses,
+        registry_sha256: _,
+    } = config;
+    fn list(v: &[String]) -> Vec<&str> {
+        v.iter().map(String::as_str).collect()
+    }
+    vec![
+        (
+            "stability_pool_script_hashes",
+            FieldKind::Script,
+            list(stability_pool_script_hashes),
+        ),
+        (
+            "stability_pool_datum_hashes",
+            FieldKind::Datum,
+            list(stability_pool_datum_hashes),
+        ),
+        (
+            "iasset_policy_ids",
+            FieldKind::Policy,
+            list(iasset_policy_ids),
+        ),
+        (
+            "rob_script_hashes",
+            FieldKind::Script,
+            list(rob_script_hashes),
+        ),
+        ("rob_datum_hashes", FieldKind::Datum, list(rob_datum_hashes)),
+        (
+            "indy_policy_id",
+            FieldKind::Policy,
+            indy_policy_id.iter().map(String::as_str).collect(),
+        ),
+        (
+            "cdp_script_hashes",
+            FieldKind::Script,
+            list(cdp_script_hashes),
+        ),
+        ("cdp_datum_hashes", FieldKind::Datum, list(cdp_datum_hashes)),
+        (
+            "governance_staking_datum_hashes",
+            FieldKind::Datum,
+            list(governance_staking_datum_hashes),
+        ),
+        (
+            "governance_poll_datum_hashes",
+            FieldKind::Datum,
+            list(governance_poll_datum_hashes),
+        ),
+        (
+            "protocol_fee_addresses",
+            FieldKind::Address,
+            list(protocol_fee_addresses),
+        ),
+        (
+            "oracle_addresses",
+            FieldKind::Address,
+            oracle_addresses.values().map(String::as_str).collect(),
+        ),
+        (
+            "dex_addresses",
+            FieldKind::AddressOrHash,
+            list(dex_addresses),
+        ),
+        (
+            "exchange_addresses",
+            FieldKind::AddressOrHash,
+            list(exchange_addresses),
+        ),
+        (
+            "indy_airdrop_addresses",
+            FieldKind::AddressOrHash,
+            list(indy_airdrop_addresses),
+        ),
+        (
+            "indy_vesting_addresses",
+            FieldKind::AddressOrHash,
+            list(indy_vesting_addresses),
+        ),
+    ]
+}
+
+/// Check every identifier's shape and uniqueness, list the empty fields, and derive the
+/// parsing mode of each parser. Needs no network.
+pub fn validate_config(config: &IndigoV2Config) -> ConfigReport {
+    let mut report = ConfigReport::default();
+    for (field, kind, entries) in fields(config) {
+        if entries.is_empty() {
+            report.missing.push(field);
+        }
+        let mut seen = HashSet::new();
+        for entry in entries {
+            let problem = if !kind.accepts(entry) {
+                Problem::Malformed(kind.expected())
+            } else if !seen.insert(IndigoV2Config::norm_hex(entry)) {
+                Problem::Duplicate
+            } else {
+                continue;
+            };
+            report.issues.push(ConfigIssue {
+                field,
+                value: entry.to_string(),
+                problem,
+            });
+        }
+    }
+
+    let either = |configured: bool, mode: ParsingMode, otherwise: ParsingMode| {
+        if configured {
+            mode
+        } else {
+            otherwise
+        }
+    };
+    let sp = if config.has_stability_pool_datum_hashes() {
+        ParsingMode::Datum
+    } else {
+        either(
+            config.has_iasset_policy_ids(),
+            ParsingMode::Policy,
+            ParsingMode::Heuristic,
+        )
+    };
+    let governance = !config.governance_staking_datum_hashes.is_empty()
+        || !config.governance_poll_datum_hashes.is_empty();
+    let distributions =
+        !config.indy_airdrop_addresses.is_empty() || !config.indy_vesting_addresses.is_empty();
+    report.modes = BTreeMap::from([
+        ("stability_pool", sp),
+        (
+            "rob",
+            either(
+                config.has_rob_datum_hashes(),
+                ParsingMode::Datum,
+                ParsingMode::Heuristic,
+            ),
+        ),
+        (
+            "indy_staking",
+            either(
+                config.indy_policy_id.is_some(),
+                ParsingMode::Policy,
+                ParsingMode::Heuristic,
+            ),
+        ),
+        (
+            "indy_distribution",
+            either(distributions, ParsingMode::Address, ParsingMode::Off),
+        ),
+        (
+            "cdp",
+            either(
+                !config.cdp_datum_hashes.is_empty(),
+                ParsingMode::Datum,
+                ParsingMode::Off,
+            ),
+        ),
+        (
+            "governance",
+            either(governance, ParsingMode::Datum, ParsingMode::Off),
+        ),
+        (
+            "protocol_fees",
+            either(
+                !config.protocol_fee_addresses.is_empty(),
+                ParsingMode::Address,
+                ParsingMode::Off,
+            ),
+        ),
+        (
+            "oracle_pricing",
+            either(
+                !config.oracle_addresses.is_empty(),
+                ParsingMode::Address,
+                ParsingMode::Off,
+            ),
+        ),
+    ]);
+    report
+}
+
+/// Check the well-formed entries against chain data: each policy ID must have had an asset
+/// minted under it, and each SP, ROB, CDP script and oracle address must hold UTxOs. Policies
+/// are skipped with a warning when the provider cannot look them up.
+pub async fn cross_check_config(
+    config: &IndigoV2Config,
+    fetcher: &Fetcher,
+) -> Result<Vec<ConfigIssue>, FetchError> {
+    let mut issues = Vec::new();
+    let network = fetcher.network();
+    let mut warned = false;
+    for (field, kind, entries) in fields(config) {
+        let mut seen = HashSet::new();
+        for entry in entries {
+            if !kind.accepts(entry) || !seen.insert(IndigoV2Config::norm_hex(entry)) {
+                continue;
+            }
+            let problem = match (kind, field) {
+                (FieldKind::Policy, _) => {
+                    match fetcher
+                        .policy_minted(&IndigoV2Config::norm_hex(entry))
+                        .await?
+                    {
+                        Some(false) => Some(Problem::NotMinted),
+                        Some(true) => None,
+                        None => {
+                            if !warned {
+                                warn!(
+                                    provider = fetcher.provider_name(),
+                                    "provider cannot look up policies; policy IDs not checked"
+                                );
+                                warned = true;
+                            }
+                            None
+                        }
+                    }
+                }
+                (FieldKind::Script, _) | (FieldKind::Address, "oracle_addresses") => {
+                    let Some(address) = IndigoV2Config::script_entry_address(entry, network) else {
+                        continue;
+                    };
+                    let utxos = fetcher.address_utxos(&address).await?;
+                    utxos.is_empty().then_some(Problem::NoUtxos)
+                }
+                _ => None,
+            };
+            if let Some(problem) = problem {
+                issues.push(ConfigIssue {
+                    field,
+                    value: entry.to_string(),
+                    problem,
+                });
+            }
+        }
+    }
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+    use crate::chain::ChainProvider;
+    use crate::BoxFuture;
+
+    // Enterprise script address of hash 0707…07.
+    const SCRIPT: &str = "addr1wyrswpc8qurswpc8qurswpc8qurswpc8qurswpc8qurswpcqwuenc";
+
+    #[test]
+    fn shapes_duplicates_missing_fields_and_modes() {
+        let config = IndigoV2Config {
+            iasset_policy_ids: vec!["ab".repeat(28), "AB".repeat(28), "f0ff".into()],
+            rob_datum_hashes: vec!["cd".repeat(32)],
+            rob_script_hashes: vec![SCRIPT.into()],
+            protocol_fee_addresses: vec!["addr1nope".into()],
+            ..Default::default()
+        };
+        let report = validate_config(&config);
+        let problems: Vec<(&str, &str, &Problem)> = report
+            .issues
+            .iter()
+            .map(|i| (i.field, i.value.as_str(), &i.problem))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (
+                    "iasset_policy_ids",
+                    "AB".repeat(28).as_str(),
+                    &Problem::Duplicate
+                ),
+                (
+                    "iasset_policy_ids",
+                    "f0ff",
+                    &Problem::Malformed("56-char hex policy ID")
+                ),
+                (
+                    "protocol_fee_addresses",
+                    "addr1nope",
+                    &Problem::Malformed("bech32 address")
+                ),
+            ]
+        );
+        assert!(report.missing.contains(&"indy_policy_id"));
+        assert!(!report.missing.contains(&"rob_datum_hashes"));
+        assert_eq!(report.modes["stability_pool"], ParsingMode::Policy);
+        assert_eq!(report.modes["rob"], ParsingMode::Datum);
+        assert_eq!(report.modes["cdp"], ParsingMode::Off);
+        assert_eq!(report.modes["indy_staking"], ParsingMode::Heuristic);
+    }
+
+    /// Knows one minted policy; every address is empty.
+    struct EmptyChain;
+
+    impl ChainProvider for EmptyChain {
+        fn name(&self) -> &str {
+            "empty"
+        }
+
+        fn account_txs<'a>(
+            &'a self,
+            _address: &'a str,
+            _from_slot: Option<u64>,
+            _to_slot: Option<u64>,
+        ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn tx_utxos<'a>(
+            &'a self,
+            _tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+            Box::pin(async { Err(FetchError::OfflineMiss) })
+        }
+
+        fn address_utxos<'a>(
+            &'a self,
+            _address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+
+        fn policy_minted<'a>(
+            &'a self,
+            policy_id: &'a str,
+        ) -> BoxFuture<'a, Result<Option<bool>, FetchError>> {
+            Box::pin(async move { Ok(Some(policy_id == "ab".repeat(28))) })
+        }
+    }
+
+    #[test]
+    fn cross_check_flags_unminted_policies_and_empty_scripts() {
+        let config = IndigoV2Config {
+            iasset_policy_ids: vec!["ab".repeat(28), "cd".repeat(28)],
+            stability_pool_script_hashes: vec!["07".repeat(28)],
+            ..Default::default()
+        };
+        let fetcher = Fetcher::with_provider(Box::new(EmptyChain));
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let issues = rt.block_on(cross_check_config(&config, &fetcher)).unwrap();
+        let problems: Vec<(&str, &Problem)> =
+            issues.iter().map(|i| (i.field, &i.problem)).collect();
+        assert_eq!(
+            problems,
+            vec![
+                ("stability_pool_script_hashes", &Problem::NoUtxos),
+                ("iasset_policy_ids", &Problem::NotMinted),
+            ]
+        );
+    }
+}
@@ -6,12 +6,17 @@
 pub mod chain;
 pub mod compute;
 pub mod indigo;
+pub mod notify;
 pub mod report;
 pub mod verify;
+pub mod wallet;
 
-pub use chain::fetch::{KoiosAccountTx, KoiosTxUtxos, KoiosUtxo};
+pub use chain::fetch::{KoiosAccountTx, KoiosInlineDatum, KoiosTxUtxos, KoiosUtxo};
 pub use chain::{Cache, FetchConfig, Fetcher};
 pub use compute::{compute_metrics, ComputeInput, ComputedMetrics};
 pub use indigo::{Event, EventKind, IndigoEvents, IndigoV2Config};
 pub use report::ReportData;
 pub use verify::{reproducibility_hash, EvidenceBundle, VerificationResult};
+
+/// Boxed, sendable future used by object-safe async traits (notifiers, providers).
+pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
@@ -0,0 +1,189 @@
+//! SMTP email delivery of reports (email-safe HTML body + bundle / hash attachments).
+
+use crate::notify::{Notification, Notifier, NotifyError};
+use crate::BoxFuture;
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Env var consulted for the SMTP password when the config file omits it.
+pub const SMTP_PASSWORD_ENV: &str = "INDIGO_POY_SMTP_PASSWORD";
+
+/// Transport security for the SMTP connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Plain connection upgraded with STARTTLS (usually port 587).
+    #[default]
+    Starttls,
+    /// Implicit TLS (usually port 465).
+    Tls,
+    /// No encryption; only for local relays.
+    None,
+}
+
+/// SMTP settings, loaded from a JSON file. Keep the password in the env, not the file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+impl SmtpConfig {
+    /// Load from a JSON file; `password` falls back to `INDIGO_POY_SMTP_PASSWORD`.
+    pub fn load_from_path(path: &Path) -> Result<Self, NotifyError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| NotifyError::Config(format!("{}: {}", path.display(), e)))?;
+        let mut config: Self = serde_json::from_str(&content)
+            .map_err(|e| NotifyError::Config(format!("{}: {}", path.display(), e)))?;
+        if config.password.is_none() {
+            config.password = std::env::var(SMTP_PASSWORD_ENV).ok();
+        }
+        if config.to.is_empty() {
+            return Err(NotifyError::Config("smtp: no recipients".to_string()));
+        }
+        Ok(config)
+    }
+}
+
+/// One email: subject, text + HTML alternatives, and file attachments.
+#[derive(Clone, Debug, Default)]
+pub struct EmailReport {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    /// (file name, content type, bytes).
+    pub attachments: Vec<(String, String, Vec<u8>)>,
+}
+
+/// Sends report emails and plain-text notifications over SMTP.
+pub struct EmailNotifier {
+    config: SmtpConfig,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+fn email_err(e: impl std::fmt::Display) -> NotifyError {
+    NotifyError::Email(e.to_string())
+}
+
+impl EmailNotifier {
+    pub fn new(config: SmtpConfig) -> Result<Self, NotifyError> {
+        let mut builder = match config.security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                    .map_err(email_err)?
+            }
+            SmtpSecurity::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host).map_err(email_err)?
+            }
+            SmtpSecurity::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host)
+            }
+        };
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(user), Some(pass)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(user.clone(), pass.clone()));
+        }
+        Ok(Self {
+            transport: builder.build(),
+            config,
+        })
+    }
+
+    fn message_builder(
+        &self,
+        subject: &str,
+    ) -> Result<lettre::message::MessageBuilder, NotifyError> {
+        let from: Mailbox = self.config.from.parse().map_err(email_err)?;
+        let mut builder = Message::builder().from(from).subject(subject);
+        for to in &self.config.to {
+            let mailbox: Mailbox = to.parse().map_err(email_err)?;
+            builder = builder.to(mailbox);
+        }
+        Ok(builder)
+    }
+
+    /// Build the MIME message for a report (exposed for tests and dry runs).
+    pub fn build_report_message(&self, report: &EmailReport) -> Result<Message, NotifyError> {
+        let mut body = MultiPart::mixed().multipart(MultiPart::alternative_plain_html(
+            report.text.clone(),
+            report.html.clone(),
+        ));
+        for (name, content_type, bytes) in &report.attachments {
+            let ct = ContentType::parse(content_type).map_err(email_err)?;
+            body = body.singlepart(Attachment::new(name.clone()).body(bytes.clone(), ct));
+        }
+        self.message_builder(&report.subject)?
+            .multipart(body)
+            .map_err(email_err)
+    }
+
+    pub async fn send_report(&self, report: &EmailReport) -> Result<(), NotifyError> {
+        let message = self.build_report_message(report)?;
+        self.transport.send(message).await.map_err(email_err)?;
+        Ok(())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let message = self
+                .message_builder(&notification.title)?
+                .singlepart(SinglePart::plain(notification.to_text()))
+                .map_err(email_err)?;
+            self.transport.send(message).await.map_err(email_err)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_message_has_attachments() {
+        let config: SmtpConfig = serde_json::from_str(
+            r#"{"host":"localhost","port":2525,"security":"none","from":"poy@example.com","to":["me@example.com"]}"#,
+        )
+        .unwrap();
+        let notifier = EmailNotifier::new(config).unwrap();
+        let report = EmailReport {
+            subject: "Proof of yield".into(),
+            text: "hash abc".into(),
+            html: "<p>hash abc</p>".into(),
+            attachments: vec![(
+                "x.bundle.json".into(),
+                "application/json".into(),
+                b"{}".to_vec(),
+            )],
+        };
+        let raw =
+            String::from_utf8(notifier.build_report_message(&report).unwrap().formatted()).unwrap();
+        assert!(raw.contains("Subject: Proof of yield"));
+        assert!(raw.contains("x.bundle.json"));
+        assert!(raw.contains("text/html"));
+    }
+}
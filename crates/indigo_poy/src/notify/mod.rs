@@ -0,0 +1,101 @@
+//! Notification subsystem: deliver short run/verification summaries to external channels.
+
+pub mod alerts;
+mod email;
+mod slack;
+mod webhook;
+
+pub use email::{EmailNotifier, EmailReport, SmtpConfig, SmtpSecurity, SMTP_PASSWORD_ENV};
+pub use slack::{slack_payload, SlackNotifier};
+pub use webhook::WebhookNotifier;
+
+use crate::BoxFuture;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("notifier rejected message: status {0} body {1}")]
+    Rejected(u16, String),
+    #[error("config: {0}")]
+    Config(String),
+    #[error("email: {0}")]
+    Email(String),
+}
+
+/// Channel-agnostic notification payload. Each notifier renders it in its own format.
+#[derive(Clone, Debug, Default)]
+pub struct Notification {
+    pub title: String,
+    pub address: Option<String>,
+    pub bundle_hash: Option<String>,
+    /// Ordered key/value lines (e.g. "verdict" → "OK").
+    pub fields: Vec<(String, String)>,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.push((key.into(), value.into()));
+        self
+    }
+
+    /// Plain-text rendering used by text-based channels.
+    pub fn to_text(&self) -> String {
+        let mut out = self.title.clone();
+        if let Some(addr) = &self.address {
+            out.push_str(&format!("\naddress: {}", addr));
+        }
+        for (k, v) in &self.fields {
+            out.push_str(&format!("\n{}: {}", k, v));
+        }
+        if let Some(hash) = &self.bundle_hash {
+            out.push_str(&format!("\nsha256: {}", hash));
+        }
+        out
+    }
+}
+
+/// A delivery channel for notifications.
+pub trait Notifier: Send + Sync {
+    /// Short channel name for logs (e.g. "webhook").
+    fn name(&self) -> &str;
+
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>>;
+}
+
+/// Send to every notifier; failures are logged and do not stop delivery to the others.
+pub async fn notify_all(notifiers: &[Box<dyn Notifier>], notification: &Notification) {
+    for n in notifiers {
+        if let Err(e) = n.notify(notification).await {
+            tracing::warn!(notifier = n.name(), error = %e, "notification failed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_text_includes_fields_and_hash() {
+        let mut n = Notification::new("Bundle verified").field("verdict", "OK");
+        n.address = Some("addr1x".into());
+        n.bundle_hash = Some("ab".repeat(32));
+        let text = n.to_text();
+        assert!(text.starts_with("Bundle verified"));
+        assert!(text.contains("verdict: OK"));
+        assert!(text.contains("address: addr1x"));
+        assert!(text.ends_with(&"ab".repeat(32)));
+    }
+}
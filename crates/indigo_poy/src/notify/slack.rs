@@ -0,0 +1,107 @@
+//! Slack incoming-webhook notifier with a compact Block Kit layout.
+
+use crate::notify::{Notification, Notifier, NotifyError};
+use crate::BoxFuture;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Slack allows at most 10 fields per section block.
+const MAX_SECTION_FIELDS: usize = 10;
+
+/// Posts Block Kit messages to a Slack incoming webhook (`https://hooks.slack.com/...`).
+pub struct SlackNotifier {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl SlackNotifier {
+    pub fn new(url: impl Into<String>) -> Result<Self, NotifyError> {
+        let url = url.into();
+        if !url.starts_with("https://") {
+            return Err(NotifyError::Config(format!(
+                "invalid slack webhook url: {}",
+                url
+            )));
+        }
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .timeout(Duration::from_secs(15))
+            .build()?;
+        Ok(Self { url, client })
+    }
+}
+
+/// Block Kit payload: header, one section of fields (address first), and a context line with the hash.
+pub fn slack_payload(notification: &Notification) -> Value {
+    let mut fields: Vec<Value> = Vec::new();
+    if let Some(addr) = &notification.address {
+        fields.push(json!({ "type": "mrkdwn", "text": format!("*address*\n`{}`", addr) }));
+    }
+    for (k, v) in &notification.fields {
+        fields.push(json!({ "type": "mrkdwn", "text": format!("*{}*\n{}", k, v) }));
+    }
+    fields.truncate(MAX_SECTION_FIELDS);
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": notification.title },
+    })];
+    if !fields.is_empty() {
+        blocks.push(json!({ "type": "section", "fields": fields }));
+    }
+    if let Some(hash) = &notification.bundle_hash {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": format!("sha256 `{}`", hash) }],
+        }));
+    }
+    json!({ "text": notification.to_text(), "blocks": blocks })
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        "slack"
+    }
+
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let res = self
+                .client
+                .post(&self.url)
+                .json(&slack_payload(notification))
+                .send()
+                .await?;
+            let status = res.status();
+            if !status.is_success() {
+                let text = res.text().await.unwrap_or_default();
+                return Err(NotifyError::Rejected(status.as_u16(), text));
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_has_header_fields_and_hash_context() {
+        let mut n = Notification::new("Proof-of-yield run complete")
+            .field("new events", "3")
+            .field("APR Δ", "+0.42");
+        n.address = Some("addr1x".into());
+        n.bundle_hash = Some("cd".repeat(32));
+        let p = slack_payload(&n);
+        let blocks = p["blocks"].as_array().unwrap();
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[1]["fields"].as_array().unwrap().len(), 3);
+        assert!(blocks[2]["elements"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains(&"cd".repeat(32)));
+        assert!(p["text"].as_str().unwrap().contains("new events: 3"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Generic JSON webhook notifier (Discord-compatible `content` payload).
+
+use crate::notify::{Notification, Notifier, NotifyError};
+use crate::BoxFuture;
+use std::time::Duration;
+
+/// POSTs `{"content": "<text>", "username": "indigo-poy"}` to a webhook URL.
+pub struct WebhookNotifier {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>) -> Result<Self, NotifyError> {
+        let url = url.into();
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err(NotifyError::Config(format!("invalid webhook url: {}", url)));
+        }
+        let client = reqwest::Client::builder()
+            .use_rustls_tls()
+            .timeout(Duration::from_secs(15))
+            .build()?;
+        Ok(Self { url, client })
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, Result<(), NotifyError>> {
+        Box::pin(async move {
+            let body = serde_json::json!({
+                "content": notification.to_text(),
+                "username": "indigo-poy",
+            });
+            let res = self.client.post(&self.url).json(&body).send().await?;
+            let status = res.status();
+            if !status.is_success() {
+                let text = res.text().await.unwrap_or_default();
+                return Err(NotifyError::Rejected(status.as_u16(), text));
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,176 @@
+//! Anonymized aggregation over many bundles: APR distribution and premium capture by pool.
+//!
+//! Addresses never appear in the output. Each participant is identified by a short id derived
+//! from its bundle's reproducibility hash, so the publisher can match entries to bundles they
+//! hold while readers cannot recover addresses.
+
+use crate::indigo::EventKind;
+use crate::verify::{reproducibility_hash, EvidenceBundle, VerifyError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One anonymized row, ranked by APR (descending; entries without APR last).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub participant_id: String,
+    pub apr_pct: Option<f64>,
+    pub net_pnl_lovelace: i64,
+    pub liquidation_count: u64,
+    pub realized_premium_lovelace: u64,
+}
+
+/// Five-number summary plus mean.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: Option<f64>,
+    pub p25: Option<f64>,
+    pub median: Option<f64>,
+    pub p75: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+}
+
+/// Liquidation premium captured in one pool (keyed by iAsset), across all participants.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PoolCapture {
+    pub pool: String,
+    pub liquidation_count: u64,
+    pub realized_premium_lovelace: u64,
+    pub participants: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub participants: usize,
+    pub apr_distribution: Distribution,
+    pub entries: Vec<LeaderboardEntry>,
+    /// Sorted by pool key.
+    pub premium_by_pool: Vec<PoolCapture>,
+}
+
+fn participant_id(bundle_hash: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"indigo-poy-leaderboard:");
+    hasher.update(bundle_hash.as_bytes());
+    hex::encode(hasher.finalize())[..12].to_string()
+}
+
+/// Nearest-rank percentile over sorted values.
+fn percentile(sorted: &[f64], p: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let idx = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    sorted.get(idx).copied()
+}
+
+pub fn distribution(values: &[f64]) -> Distribution {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mean = (!sorted.is_empty()).then(|| sorted.iter().sum::<f64>() / sorted.len() as f64);
+    Distribution {
+        count: sorted.len(),
+        min: sorted.first().copied(),
+        p25: percentile(&sorted, 25.0),
+        median: percentile(&sorted, 50.0),
+        p75: percentile(&sorted, 75.0),
+        max: sorted.last().copied(),
+        mean,
+    }
+}
+
+/// Aggregate bundles into an anonymized leaderboard. Duplicate bundles (same hash) count once.
+pub fn build_leaderboard(bundles: &[EvidenceBundle]) -> Result<Leaderboard, VerifyError> {
+    let mut seen = BTreeSet::new();
+    let mut entries = Vec::new();
+    let mut pools: BTreeMap<String, (PoolCapture, BTreeSet<String>)> = BTreeMap::new();
+    for bundle in bundles {
+        let hash = reproducibility_hash(bundle)?;
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+        let id = participant_id(&hash);
+        let m = &bundle.metrics;
+        entries.push(LeaderboardEntry {
+            rank: 0,
+            participant_id: id.clone(),
+            apr_pct: m.combined.apr_pct,
+            net_pnl_lovelace: m.combined.net_pnl_lovelace,
+            liquidation_count: m.stability_pool.liquidation_count,
+            realized_premium_lovelace: m.stability_pool.total_realized_premium_lovelace,
+        });
+        for ev in &bundle.events.stability_pool {
+            if let EventKind::StabilityPoolLiquidation {
+                iasset_burnt,
+                realized_premium_lovelace,
+                ..
+            } = &ev.kind
+            {
+                let (cap, ids) = pools.entry(iasset_burnt.clone()).or_default();
+                cap.pool = iasset_burnt.clone();
+                cap.liquidation_count += 1;
+                cap.realized_premium_lovelace = cap
+                    .realized_premium_lovelace
+                    .saturating_add(*realized_premium_lovelace);
+                ids.insert(id.clone());
+            }
+        }
+    }
+    entries.sort_by(|a, b| match (a.apr_pct, b.apr_pct) {
+        (Some(x), Some(y)) => y
+            .total_cmp(&x)
+            .then_with(|| a.participant_id.cmp(&b.participant_id)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.participant_id.cmp(&b.participant_id),
+    });
+    for (i, e) in entries.iter_mut().enumerate() {
+        e.rank = i + 1;
+    }
+    let aprs: Vec<f64> = entries.iter().filter_map(|e| e.apr_pct).collect();
+    Ok(Leaderboard {
+        participants: entries.len(),
+        apr_distribution: distribution(&aprs),
+        entries,
+        premium_by_pool: pools
+            .into_values()
+            .map(|(mut cap, ids)| {
+                cap.participants = ids.len();
+                cap
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaderboard_is_anonymized_ranked_and_deduplicated() {
+        let mut a = EvidenceBundle::demo();
+        a.address = "addr1_alice".into();
+        let mut b = EvidenceBundle::demo();
+        b.address = "addr1_bob".into();
+        b.metrics.combined.apr_pct = Some(20.0);
+        let board = build_leaderboard(&[a.clone(), b, a]).unwrap();
+        assert_eq!(board.participants, 2);
+        assert_eq!(board.entries[0].apr_pct, Some(20.0));
+        assert_eq!(board.entries[1].rank, 2);
+        let json = serde_json::to_string(&board).unwrap();
+        assert!(!json.contains("alice") && !json.contains("bob"));
+        assert_eq!(board.apr_distribution.max, Some(20.0));
+    }
+
+    #[test]
+    fn distribution_quartiles() {
+        let d = distribution(&[5.0, 1.0, 3.0, 2.0, 4.0]);
+        assert_eq!(d.median, Some(3.0));
+        assert_eq!(d.p25, Some(2.0));
+        assert_eq!(d.mean, Some(3.0));
+        assert_eq!(distribution(&[]).median, None);
+    }
+}
@@ -1,5 +1,8 @@
 //! Report data structure (HTML is generated in indigo_poy_report crate).
 
+pub mod leaderboard;
+pub mod portfolio;
+
 use crate::verify::EvidenceBundle;
 use serde::{Deserialize, Serialize};
 
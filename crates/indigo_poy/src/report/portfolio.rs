@@ -0,0 +1,211 @@
+//! Consolidated view over several bundles, e.g. every address of a wallet: the figures of each
+//! address and their sum.
+//!
+//! Unlike `report --merge`, the bundles stay separate and each keeps its own hash. The
+//! portfolio is identified by [`portfolio_hash`] over those hashes, so it can be checked by
+//! verifying each bundle and hashing the list again.
+
+use crate::compute::annualize::Annualization;
+use crate::verify::{reproducibility_hash, EvidenceBundle, VerifyError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+/// One bundle's headline figures.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PortfolioEntry {
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub reproducibility_hash_sha256: String,
+    pub total_ada_in_lovelace: u64,
+    pub total_ada_out_lovelace: u64,
+    pub net_pnl_lovelace: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_position_lovelace: Option<u64>,
+    pub apr_pct: Option<f64>,
+    pub liquidation_count: u64,
+    pub realized_premium_lovelace: u64,
+    pub rob_premium_lovelace: u64,
+    pub indy_rewards_lovelace: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_start_ts: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_end_ts: Option<i64>,
+}
+
+/// The entries added up. Lovelace figures are sums; the period spans all of them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PortfolioSummary {
+    pub addresses: usize,
+    pub total_ada_in_lovelace: u64,
+    pub total_ada_out_lovelace: u64,
+    pub net_pnl_lovelace: i64,
+    /// Sum of the open positions; `None` unless every bundle valued its position.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_position_lovelace: Option<u64>,
+    /// Net PnL over the summed closing positions, annualized over the whole period as the
+    /// first bundle's APR was.
+    pub apr_pct: Option<f64>,
+    pub liquidation_count: u64,
+    pub realized_premium_lovelace: u64,
+    pub rob_premium_lovelace: u64,
+    pub indy_rewards_lovelace: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_start_ts: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_end_ts: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Portfolio {
+    /// Sorted by address, then hash.
+    pub entries: Vec<PortfolioEntry>,
+    pub summary: PortfolioSummary,
+    /// [`portfolio_hash`] over the entries' hashes.
+    pub portfolio_sha256: String,
+}
+
+/// SHA-256 over the sorted, deduplicated bundle hashes, one per line, after a domain prefix.
+pub fn portfolio_hash<S: AsRef<str>>(bundle_hashes: &[S]) -> String {
+    let sorted: BTreeSet<&str> = bundle_hashes.iter().map(AsRef::as_ref).collect();
+    let mut hasher = Sha256::new();
+    hasher.update(b"indigo-poy-portfolio:");
+    for hash in sorted {
+        hasher.update(hash.as_bytes());
+        hasher.update(b"\n");
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Combine bundles into a portfolio. Duplicate bundles (same hash) count once.
+pub fn build_portfolio(bundles: &[EvidenceBundle]) -> Result<Portfolio, VerifyError> {
+    let mut seen = BTreeSet::new();
+    let mut entries = Vec::new();
+    let mut annualization: Option<Annualization> = None;
+    for bundle in bundles {
+        let hash = reproducibility_hash(bundle)?;
+        if !seen.insert(hash.clone()) {
+            continue;
+        }
+        let m = &bundle.metrics;
+        annualization.get_or_insert(m.combined.annualization);
+        entries.push(PortfolioEntry {
+            address: bundle.address.clone(),
+            label: bundle.label.clone(),
+            reproducibility_hash_sha256: hash,
+            total_ada_in_lovelace: m.combined.total_ada_in_lovelace,
+            total_ada_out_lovelace: m.combined.total_ada_out_lovelace,
+            net_pnl_lovelace: m.combined.net_pnl_lovelace,
+            open_position_lovelace: m.combined.open_position_lovelace,
+            apr_pct: m.combined.apr_pct,
+            liquidation_count: m.stability_pool.liquidation_count,
+            realized_premium_lovelace: m.stability_pool.total_realized_premium_lovelace,
+            rob_premium_lovelace: m.rob.total_premium_received_lovelace,
+            indy_rewards_lovelace: m.indy_staking.total_rewards_lovelace,
+            period_start_ts: bundle.period_start_ts,
+            period_end_ts: bundle.period_end_ts,
+        });
+    }
+    entries.sort_by(|a, b| {
+        (&a.address, &a.reproducibility_hash_sha256)
+            .cmp(&(&b.address, &b.reproducibility_hash_sha256))
+    });
+    let summary = summarize(&entries, annualization.unwrap_or_default());
+    let hashes: Vec<&str> = entries
+        .iter()
+        .map(|e| e.reproducibility_hash_sha256.as_str())
+        .collect();
+    Ok(Portfolio {
+        portfolio_sha256: portfolio_hash(&hashes),
+        entries,
+        summary,
+    })
+}
+
+fn summarize(entries: &[PortfolioEntry], annualization: Annualization) -> PortfolioSummary {
+    let mut s = PortfolioSummary {
+        addresses: entries.len(),
+        open_position_lovelace: Some(0),
+        ..Default::default()
+    };
+    let mut net_pnl: i128 = 0;
+    let mut position: i128 = 0;
+    for e in entries {
+        s.total_ada_in_lovelace = s
+            .total_ada_in_lovelace
+            .saturating_add(e.total_ada_in_lovelace);
+        s.total_ada_out_lovelace = s
+            .total_ada_out_lovelace
+            .saturating_add(e.total_ada_out_lovelace);
+        net_pnl += e.net_pnl_lovelace as i128;
+        s.open_position_lovelace = s
+            .open_position_lovelace
+            .zip(e.open_position_lovelace)
+            .map(|(a, b)| a.saturating_add(b));
+        // As for one bundle: the position, or what is still in, is the capital.
+        position += e
+            .open_position_lovelace
+            .map(i128::from)
+            .unwrap_or(e.total_ada_in_lovelace as i128 - e.total_ada_out_lovelace as i128);
+        s.liquidation_count += e.liquidation_count;
+        s.realized_premium_lovelace = s
+            .realized_premium_lovelace
+            .saturating_add(e.realized_premium_lovelace);
+        s.rob_premium_lovelace = s
+            .rob_premium_lovelace
+            .saturating_add(e.rob_premium_lovelace);
+        s.indy_rewards_lovelace = s
+            .indy_rewards_lovelace
+            .saturating_add(e.indy_rewards_lovelace);
+        s.period_start_ts = match (s.period_start_ts, e.period_start_ts) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        s.period_end_ts = s.period_end_ts.max(e.period_end_ts);
+    }
+    if entries.is_empty() {
+        s.open_position_lovelace = None;
+    }
+    s.net_pnl_lovelace = net_pnl.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+    if let (Some(start), Some(end)) = (s.period_start_ts, s.period_end_ts) {
+        s.apr_pct = annualization.apr_pct(net_pnl.max(0), position.max(1), end - start);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portfolio_sums_the_bundles_and_hashes_their_hashes() {
+        let mut a = EvidenceBundle::demo();
+        a.address = "addr1_b".into();
+        a.period_start_ts = Some(0);
+        a.period_end_ts = Some(100);
+        let mut b = a.clone();
+        b.address = "addr1_a".into();
+        b.period_end_ts = Some(365 * 86_400);
+        let portfolio = build_portfolio(&[a.clone(), b.clone(), a.clone()]).unwrap();
+        assert_eq!(portfolio.summary.addresses, 2);
+        assert_eq!(portfolio.entries[0].address, "addr1_a");
+        let net = a.metrics.combined.net_pnl_lovelace;
+        assert_eq!(portfolio.summary.net_pnl_lovelace, 2 * net);
+        assert_eq!(
+            portfolio.summary.liquidation_count,
+            2 * a.metrics.stability_pool.liquidation_count
+        );
+        assert_eq!(portfolio.summary.period_end_ts, Some(365 * 86_400));
+        assert_eq!(portfolio.summary.open_position_lovelace, None);
+
+        let hashes = [
+            reproducibility_hash(&b).unwrap(),
+            reproducibility_hash(&a).unwrap(),
+        ];
+        assert_eq!(portfolio.portfolio_sha256, portfolio_hash(&hashes));
+        let reversed = [hashes[1].clone(), hashes[0].clone()];
+        assert_eq!(portfolio_hash(&hashes), portfolio_hash(&reversed));
+        assert_ne!(portfolio_hash(&hashes), portfolio_hash(&hashes[..1]));
+    }
+}
@@ -1,9 +1,17 @@
 //! Evidence bundle and SHA-256 reproducibility hash.
 
+use crate::chain::assets::AssetInfo;
+use crate::chain::fetch::{KoiosAccountTx, RewardWithdrawal};
+use crate::chain::Network;
+use crate::compute::currency::AccountCurrency;
+use crate::compute::pricing::PriceSeries;
 use crate::compute::ComputedMetrics;
-use crate::indigo::IndigoEvents;
+use crate::indigo::position::OpenPosition;
+use crate::indigo::{DataWarning, EventOverrides, IndigoEvents};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -12,6 +20,8 @@ pub enum VerifyError {
     Serialize(#[from] serde_json::Error),
     #[error("io: {0}")]
     Io(#[from] std::io::Error),
+    #[error("archive: {0}")]
+    Archive(String),
 }
 
 /// Evidence bundle: inputs + computed outputs for reproducibility.
@@ -19,15 +29,93 @@ pub enum VerifyError {
 pub struct EvidenceBundle {
     pub version: u32,
     pub address: String,
+    /// Wallet label for the address (e.g. from a wallet export), if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// All addresses merged into this bundle (`address` is the first), when more than one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addresses: Vec<String>,
+    /// Confirmation depth required of input txs, when gating was on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confirmations: Option<u32>,
+    /// Txs left out for being too close to the tip at fetch time (sorted); a later run will
+    /// include them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provisional_tx_hashes: Vec<String>,
+    /// Chain data provider the inputs were fetched from (e.g. "koios").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Network the inputs came from; absent in older bundles, which are mainnet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+    /// Lower / upper fetch bound as given (slot or RFC 3339), if any; `verify --deep` refetches
+    /// with the same bounds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    /// Txs found by scanning Indigo script addresses were included.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub scan_scripts: bool,
+    /// SHA-256 of the Indigo identifier registry merged into the protocol config, if one was
+    /// installed (see [`crate::indigo::registry`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_sha256: Option<String>,
+    /// User overrides applied to the input txs (see [`crate::indigo::EventOverrides`]).
+    #[serde(default, skip_serializing_if = "EventOverrides::is_empty")]
+    pub overrides: EventOverrides,
+    /// ADA/USD series the USD figures were valued with (see [`crate::compute::pricing`]), if
+    /// one was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ada_usd_prices: Option<PriceSeries>,
+    /// Currency of account `metrics.account` was computed in, with its prices, if one was
+    /// given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<AccountCurrency>,
+    /// The open position valued at report time (see [`crate::indigo::position`]), with
+    /// `--mark-to-market`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_position: Option<OpenPosition>,
+    /// With `--since-last-claim`: the claim tx the period starts after. Events up to it are
+    /// left out of `events` and the metrics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since_claim_tx: Option<String>,
+    /// Period the metrics cover (Unix time); absent in bundles from before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_start_ts: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period_end_ts: Option<i64>,
+    /// Reproducibility hash of the bundle this one extends (`compute --from-bundle`): its
+    /// txs were carried over, and only the txs after its last slot fetched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predecessor_sha256: Option<String>,
     pub created_utc_rfc3339: String,
     /// Tx hashes used as input (sorted).
     pub tx_hashes: Vec<String>,
     /// UTxO / datum / policy IDs referenced (sorted).
     pub input_refs: Vec<String>,
-    /// API responses included by content hash (sorted).
+    /// SHA-256 of every raw API response body the computation used (sorted). The bodies can
+    /// ship alongside as a `*.responses.jsonl.gz` sidecar.
     pub api_response_hashes: Vec<String>,
+    /// With a failover chain (`provider` like "koios,blockfrost"): which provider served which
+    /// of `api_response_hashes`, so a replay parses each response as it was parsed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub served_by: BTreeMap<String, Vec<String>>,
     pub events: IndigoEvents,
+    /// ADA staking reward withdrawals among the input txs, kept apart from Indigo yield.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reward_withdrawals: Vec<RewardWithdrawal>,
+    /// Ticker and decimals of the assets the events name, keyed by `policy$asset_name`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, AssetInfo>,
     pub metrics: ComputedMetrics,
+    /// Events that look misread (see [`crate::indigo::detect_anomalies`]), in time order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<DataWarning>,
+    /// Premium threshold of `warnings` in percent, when not
+    /// [`crate::indigo::DEFAULT_MAX_PREMIUM_PCT`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_premium_pct: Option<f64>,
     /// Optional: raw fetched payload hashes for offline verification.
     pub fetched_at_slots: Vec<u64>,
 }
@@ -35,6 +123,57 @@ pub struct EvidenceBundle {
 const BUNDLE_VERSION: u32 = 1;
 
 impl EvidenceBundle {
+    /// Recorded network; bundles from before network selection are mainnet.
+    pub fn network(&self) -> Network {
+        self.network.unwrap_or_default()
+    }
+
+    /// Latest slot among the input txs, after which an incremental run picks up.
+    pub fn last_slot(&self) -> Option<u64> {
+        self.fetched_at_slots.iter().max().copied()
+    }
+
+    /// `txs` less those this bundle already covers: at or before [`EvidenceBundle::last_slot`],
+    /// or among its `tx_hashes`. Providers that ignore the slot bounds return the whole history.
+    pub fn txs_after(&self, mut txs: Vec<KoiosAccountTx>) -> Vec<KoiosAccountTx> {
+        let last = self.last_slot();
+        let known: BTreeSet<&str> = self.tx_hashes.iter().map(String::as_str).collect();
+        txs.retain(|t| {
+            let newer = match (t.slot_no, last) {
+                (Some(slot), Some(last)) => slot > last,
+                _ => true,
+            };
+            newer && !known.contains(t.tx_hash.as_str())
+        });
+        txs
+    }
+
+    /// Carry the inputs of `prior` into this bundle, built from the txs after
+    /// [`EvidenceBundle::last_slot`], and reference it by hash. Events, reward withdrawals,
+    /// period, and metrics are the caller's to merge, as the metrics need all of them.
+    pub fn extend_from(&mut self, prior: &EvidenceBundle) -> Result<(), VerifyError> {
+        let merge = |into: &mut Vec<String>, from: &[String]| {
+            into.extend_from_slice(from);
+            into.sort();
+            into.dedup();
+        };
+        merge(&mut self.tx_hashes, &prior.tx_hashes);
+        merge(&mut self.input_refs, &prior.input_refs);
+        merge(&mut self.api_response_hashes, &prior.api_response_hashes);
+        for (provider, hashes) in &prior.served_by {
+            merge(self.served_by.entry(provider.clone()).or_default(), hashes);
+        }
+        for (unit, info) in &prior.assets {
+            self.assets
+                .entry(unit.clone())
+                .or_insert_with(|| info.clone());
+        }
+        self.fetched_at_slots = [&prior.fetched_at_slots[..], &self.fetched_at_slots].concat();
+        self.from = prior.from.clone();
+        self.predecessor_sha256 = Some(reproducibility_hash(prior)?);
+        Ok(())
+    }
+
     pub fn new(
         address: String,
         tx_hashes: Vec<String>,
@@ -50,12 +189,35 @@ impl EvidenceBundle {
         Self {
             version: BUNDLE_VERSION,
             address,
+            label: None,
+            addresses: vec![],
+            min_confirmations: None,
+            provisional_tx_hashes: vec![],
+            provider: None,
+            network: None,
+            from: None,
+            to: None,
+            scan_scripts: false,
+            registry_sha256: None,
+            overrides: EventOverrides::default(),
+            ada_usd_prices: None,
+            currency: None,
+            open_position: None,
+            since_claim_tx: None,
+            period_start_ts: None,
+            period_end_ts: None,
+            predecessor_sha256: None,
             created_utc_rfc3339,
             tx_hashes,
             input_refs,
             api_response_hashes,
+            served_by: BTreeMap::new(),
             events,
+            reward_withdrawals: vec![],
+            assets: BTreeMap::new(),
             metrics,
+            warnings: vec![],
+            max_premium_pct: None,
             fetched_at_slots,
         }
     }
@@ -70,6 +232,11 @@ impl EvidenceBundle {
             total_realized_premium_lovelace: 1_093_190,
             net_ada_from_liquidations_lovelace: -38_730_000,
             liquidation_count: 23,
+            withdrawals_cost_basis_lovelace: 0,
+            withdrawal_gain_lovelace: 0,
+            by_asset: Default::default(),
+            liquidation_pnl_lovelace: None,
+            liquidations: vec![],
         };
         let rob = RobMetrics {
             total_placed_lovelace: 20_000_000,
@@ -77,6 +244,9 @@ impl EvidenceBundle {
             total_premium_received_lovelace: 80_800,
             avg_premium_pct: Some(1.0),
             fill_count: 4,
+            total_cancelled_lovelace: None,
+            time_in_cooldown_secs: None,
+            orders: vec![],
         };
         let indy_staking = IndyStakingMetrics::default();
         let combined = CombinedMetrics {
@@ -84,23 +254,69 @@ impl EvidenceBundle {
             total_ada_out_lovelace: 11_270_000 + 8_080_000,
             net_pnl_lovelace: -50_730_000,
             apr_pct: Some(9.7),
+            apy_pct: None,
+            annualization: Default::default(),
+            ada_staking_rewards_lovelace: None,
+            protocol_fees_lovelace: None,
+            xirr_pct: None,
+            twr: None,
+            open_position_lovelace: None,
+            unrealized_pnl_lovelace: None,
+            trailing: vec![],
+            benchmark: None,
+            risk: None,
+            pending: None,
         };
         let metrics = ComputedMetrics {
             stability_pool,
             rob,
             indy_staking,
             combined,
+            attribution: None,
             dilution: None,
+            cdp: None,
+            governance: None,
+            confidence: None,
+            fiat: None,
+            account: None,
+            lots: None,
+            statements: None,
+            ledger: vec![],
+            warnings: vec![],
+            extra_metrics: Default::default(),
         };
         Self {
             version: BUNDLE_VERSION,
             address: "addr1_demo (Indigo Proof-of-Yield sample)".to_string(),
+            label: None,
+            addresses: vec![],
+            min_confirmations: None,
+            provisional_tx_hashes: vec![],
+            provider: None,
+            network: None,
+            from: None,
+            to: None,
+            scan_scripts: false,
+            registry_sha256: None,
+            overrides: EventOverrides::default(),
+            ada_usd_prices: None,
+            currency: None,
+            open_position: None,
+            since_claim_tx: None,
+            period_start_ts: None,
+            period_end_ts: None,
+            predecessor_sha256: None,
             created_utc_rfc3339: "2026-02-12T10:00:00Z".to_string(),
             tx_hashes: vec!["demo_tx_1".into(), "demo_tx_2".into()],
             input_refs: vec![],
             api_response_hashes: vec![],
+            served_by: BTreeMap::new(),
             events: IndigoEvents::default(),
+            reward_withdrawals: vec![],
+            assets: BTreeMap::new(),
             metrics,
+            warnings: vec![],
+            max_premium_pct: None,
             fetched_at_slots: vec![100_000, 100_100],
         }
     }
@@ -147,7 +363,6 @@ pub struct VerificationResult {
 }
 
 /// Verify a bundle file against an expected .sha256 file content.
-#[allow(dead_code)]
 pub fn verify_bundle_hash(
     bundle: &EvidenceBundle,
     expected_hex: &str,
@@ -162,10 +377,63 @@ pub fn verify_bundle_hash(
     })
 }
 
+/// Sidecar hash path for a bundle file: `x.bundle.json` → `x.sha256` in the same directory.
+pub fn sha256_path(bundle_path: &Path) -> PathBuf {
+    let stem = bundle_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let hash_stem = stem.strip_suffix(".bundle").unwrap_or(stem.as_ref());
+    bundle_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(format!("{}.sha256", hash_stem))
+}
+
+/// Top-level fields of `rebuilt` that differ from `expected` (sorted), e.g. after a deep
+/// verification re-ran the computation from the bundle's raw responses.
+pub fn bundle_differences(
+    expected: &EvidenceBundle,
+    rebuilt: &EvidenceBundle,
+) -> Result<Vec<String>, VerifyError> {
+    let expected = serde_json::to_value(expected)?;
+    let rebuilt = serde_json::to_value(rebuilt)?;
+    let (Some(a), Some(b)) = (expected.as_object(), rebuilt.as_object()) else {
+        return Ok(vec![]);
+    };
+    let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    Ok(keys
+        .into_iter()
+        .filter(|k| a.get(*k) != b.get(*k))
+        .cloned()
+        .collect())
+}
+
+/// Load a bundle file and verify it against its `.sha256` sidecar (missing sidecar = no match).
+pub fn verify_bundle_file(
+    bundle_path: &Path,
+) -> Result<(EvidenceBundle, VerificationResult), VerifyError> {
+    let bundle_json = std::fs::read_to_string(bundle_path)?;
+    let bundle: EvidenceBundle = serde_json::from_str(&bundle_json)?;
+    let expected = std::fs::read_to_string(sha256_path(bundle_path)).ok();
+    let result = match expected {
+        Some(exp) => verify_bundle_hash(&bundle, &exp)?,
+        None => VerificationResult {
+            bundle_hash: reproducibility_hash(&bundle)?,
+            expected_hash: None,
+            matches: false,
+        },
+    };
+    Ok((bundle, result))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chain::fetch::{FetchError, KoiosTxUtxos, KoiosUtxo};
+    use crate::chain::{ChainProvider, Fetcher};
     use crate::indigo::IndigoEvents;
+    use crate::BoxFuture;
 
     #[test]
     fn normalize_deterministic() {
@@ -192,4 +460,105 @@ mod tests {
         assert_eq!(h1, h2);
         assert_eq!(h1.len(), 64);
     }
+
+    #[test]
+    fn differences_name_changed_fields() {
+        let bundle = EvidenceBundle::demo();
+        assert!(bundle_differences(&bundle, &bundle).unwrap().is_empty());
+        let mut rebuilt = bundle.clone();
+        rebuilt.tx_hashes.pop();
+        rebuilt.from = Some("100".into());
+        assert_eq!(
+            bundle_differences(&bundle, &rebuilt).unwrap(),
+            vec!["from".to_string(), "tx_hashes".to_string()]
+        );
+    }
+
+    #[test]
+    fn extension_carries_the_predecessor_inputs() {
+        let prior = EvidenceBundle::demo();
+        assert_eq!(prior.last_slot(), Some(100_100));
+        let mut next = EvidenceBundle::new(
+            prior.address.clone(),
+            vec!["demo_tx_3".into(), "demo_tx_1".into()],
+            vec![],
+            vec!["h2".into()],
+            IndigoEvents::default(),
+            Default::default(),
+            vec![100_200],
+        );
+        next.from = Some("100101".into());
+        next.extend_from(&prior).unwrap();
+        assert_eq!(next.tx_hashes, ["demo_tx_1", "demo_tx_2", "demo_tx_3"]);
+        assert_eq!(next.fetched_at_slots, [100_000, 100_100, 100_200]);
+        assert_eq!(next.from, None);
+        assert_eq!(
+            next.predecessor_sha256,
+            Some(reproducibility_hash(&prior).unwrap())
+        );
+    }
+
+    /// Serves the whole history whatever the slot bounds, as Koios did before it filtered.
+    struct FullHistory;
+
+    impl ChainProvider for FullHistory {
+        fn name(&self) -> &str {
+            "full-history"
+        }
+
+        fn account_txs<'a>(
+            &'a self,
+            _address: &'a str,
+            _from_slot: Option<u64>,
+            _to_slot: Option<u64>,
+        ) -> BoxFuture<'a, Result<Vec<KoiosAccountTx>, FetchError>> {
+            let tx = |hash: &str, slot| KoiosAccountTx {
+                tx_hash: hash.to_string(),
+                block_height: None,
+                block_time: None,
+                epoch_no: None,
+                slot_no: Some(slot),
+            };
+            Box::pin(async move {
+                Ok(vec![
+                    tx("demo_tx_1", 99_000),
+                    tx("demo_tx_2", 100_150),
+                    tx("old_tx", 100_100),
+                    tx("demo_tx_3", 100_200),
+                ])
+            })
+        }
+
+        fn tx_utxos<'a>(
+            &'a self,
+            _tx_hash: &'a str,
+        ) -> BoxFuture<'a, Result<KoiosTxUtxos, FetchError>> {
+            Box::pin(async { Err(FetchError::OfflineMiss) })
+        }
+
+        fn address_utxos<'a>(
+            &'a self,
+            _address: &'a str,
+        ) -> BoxFuture<'a, Result<Vec<KoiosUtxo>, FetchError>> {
+            Box::pin(async { Ok(vec![]) })
+        }
+    }
+
+    #[test]
+    fn extension_skips_covered_txs_from_a_provider_ignoring_bounds() {
+        let prior = EvidenceBundle::demo();
+        let fetcher = Fetcher::with_provider(Box::new(FullHistory));
+        let from = (prior.last_slot().unwrap() + 1).to_string();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let txs = rt
+            .block_on(fetcher.account_txs("addr1x", Some(&from), None))
+            .unwrap();
+        assert_eq!(txs.len(), 4);
+        let new: Vec<String> = prior
+            .txs_after(txs)
+            .into_iter()
+            .map(|t| t.tx_hash)
+            .collect();
+        assert_eq!(new, ["demo_tx_3"]);
+    }
 }
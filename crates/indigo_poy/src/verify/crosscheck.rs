@@ -0,0 +1,171 @@
+//! Optional sanity cross-check of computed SP metrics against Indigo's public analytics.
+//!
+//! External values are **non-evidentiary**: they never enter the bundle or its hash. Large
+//! deviations are a parser-quality signal, not proof that either side is wrong.
+
+use crate::compute::ComputedMetrics;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Default analytics endpoint; override with `--analytics-url` / `INDIGO_ANALYTICS_URL`.
+pub const DEFAULT_ANALYTICS_URL: &str = "https://analytics.indigoprotocol.io/api";
+/// Relative deviation (percent) above which a metric is flagged.
+pub const DEFAULT_TOLERANCE_PCT: f64 = 10.0;
+
+#[derive(Error, Debug)]
+pub enum CrossCheckError {
+    #[error("request: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("analytics api: status {0} body {1}")]
+    Api(u16, String),
+    #[error("parse analytics response: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+/// Per-address / per-period figures reported by the analytics service. All fields optional.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AnalyticsSnapshot {
+    /// Total Stability Pool TVL at period end (lovelace-equivalent).
+    #[serde(default, alias = "tvl_lovelace")]
+    pub sp_tvl_lovelace: Option<u64>,
+    /// Address share of the pool at period end (percent).
+    #[serde(default, alias = "share_pct")]
+    pub sp_share_pct: Option<f64>,
+    /// Liquidations the address participated in during the period.
+    #[serde(default, alias = "liquidations")]
+    pub liquidation_count: Option<u64>,
+    /// Realized liquidation premium attributed to the address (lovelace).
+    #[serde(default, alias = "premium_lovelace")]
+    pub realized_premium_lovelace: Option<u64>,
+}
+
+/// One compared metric.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrossCheckItem {
+    pub metric: String,
+    pub computed: f64,
+    pub external: f64,
+    /// |computed − external| / max(|external|, 1) × 100.
+    pub deviation_pct: f64,
+    pub flagged: bool,
+}
+
+/// Result of a cross-check. Always marked non-evidentiary.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CrossCheckReport {
+    pub source: String,
+    pub non_evidentiary: bool,
+    pub tolerance_pct: f64,
+    pub items: Vec<CrossCheckItem>,
+}
+
+impl CrossCheckReport {
+    pub fn flagged(&self) -> impl Iterator<Item = &CrossCheckItem> {
+        self.items.iter().filter(|i| i.flagged)
+    }
+}
+
+fn item(metric: &str, computed: f64, external: f64, tolerance_pct: f64) -> CrossCheckItem {
+    let deviation_pct = (computed - external).abs() / external.abs().max(1.0) * 100.0;
+    CrossCheckItem {
+        metric: metric.to_string(),
+        computed,
+        external,
+        deviation_pct,
+        flagged: deviation_pct > tolerance_pct,
+    }
+}
+
+/// Compare computed metrics with the external snapshot; only metrics present on both sides are compared.
+pub fn compare_with_analytics(
+    metrics: &ComputedMetrics,
+    snapshot: &AnalyticsSnapshot,
+    source: &str,
+    tolerance_pct: f64,
+) -> CrossCheckReport {
+    let sp = &metrics.stability_pool;
+    let mut items = Vec::new();
+    if let Some(ext) = snapshot.liquidation_count {
+        items.push(item(
+            "sp_liquidation_count",
+            sp.liquidation_count as f64,
+            ext as f64,
+            tolerance_pct,
+        ));
+    }
+    if let Some(ext) = snapshot.realized_premium_lovelace {
+        items.push(item(
+            "sp_realized_premium_lovelace",
+            sp.total_realized_premium_lovelace as f64,
+            ext as f64,
+            tolerance_pct,
+        ));
+    }
+    if let (Some(ext_share), Some(tvl)) = (snapshot.sp_share_pct, snapshot.sp_tvl_lovelace) {
+        let position = sp
+            .total_deposits_lovelace
+            .saturating_sub(sp.total_withdrawals_lovelace);
+        let share = position as f64 / (tvl.max(1) as f64) * 100.0;
+        items.push(item("sp_tvl_share_pct", share, ext_share, tolerance_pct));
+    }
+    CrossCheckReport {
+        source: source.to_string(),
+        non_evidentiary: true,
+        tolerance_pct,
+        items,
+    }
+}
+
+/// Fetch the analytics snapshot for `address` over `[from_ts, to_ts]` (Unix seconds).
+pub async fn fetch_analytics_snapshot(
+    base_url: &str,
+    address: &str,
+    from_ts: Option<i64>,
+    to_ts: Option<i64>,
+) -> Result<AnalyticsSnapshot, CrossCheckError> {
+    let mut url = format!(
+        "{}/stability-pool/address-summary?address={}",
+        base_url.trim_end_matches('/'),
+        urlencoding::encode(address)
+    );
+    if let Some(from) = from_ts {
+        url.push_str(&format!("&from={}", from));
+    }
+    if let Some(to) = to_ts {
+        url.push_str(&format!("&to={}", to));
+    }
+    let client = reqwest::Client::builder()
+        .use_rustls_tls()
+        .timeout(Duration::from_secs(30))
+        .build()?;
+    let res = client.get(&url).send().await?;
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(CrossCheckError::Api(status.as_u16(), body));
+    }
+    Ok(serde_json::from_str(&body)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_large_deviation_only() {
+        let mut metrics = ComputedMetrics::default();
+        metrics.stability_pool.liquidation_count = 20;
+        metrics.stability_pool.total_realized_premium_lovelace = 1_000_000;
+        let snapshot = AnalyticsSnapshot {
+            liquidation_count: Some(21),
+            realized_premium_lovelace: Some(2_000_000),
+            ..Default::default()
+        };
+        let report = compare_with_analytics(&metrics, &snapshot, "test", DEFAULT_TOLERANCE_PCT);
+        assert!(report.non_evidentiary);
+        assert_eq!(report.items.len(), 2);
+        let flagged: Vec<_> = report.flagged().map(|i| i.metric.as_str()).collect();
+        assert_eq!(flagged, vec!["sp_realized_premium_lovelace"]);
+    }
+}
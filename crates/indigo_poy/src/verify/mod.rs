@@ -1,6 +1,12 @@
 //! Reproducibility hashing, manifest, and verification.
 
 mod bundle;
+pub mod crosscheck;
+pub mod responses;
+pub mod watch;
 
 pub use bundle::normalize_for_hash;
+pub use bundle::{
+    bundle_differences, sha256_path, verify_bundle_file, verify_bundle_hash, VerifyError,
+};
 pub use bundle::{reproducibility_hash, EvidenceBundle, VerificationResult};
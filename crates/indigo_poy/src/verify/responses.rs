@@ -0,0 +1,112 @@
+//! Sidecar archive of the raw API responses behind a bundle's `api_response_hashes`.
+//!
+//! Gzip'd JSON lines: a header, then one `{"sha256", "body"}` per response. With it, a third
+//! party can check that the bundle was computed from exactly these inputs.
+
+use crate::chain::RawResponse;
+use crate::verify::VerifyError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+const ARCHIVE_FORMAT: &str = "indigo-poy-responses";
+const ARCHIVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct ArchiveHeader {
+    format: String,
+    version: u32,
+}
+
+/// Sidecar path for a bundle file: `x.bundle.json` → `x.responses.jsonl.gz`.
+pub fn responses_path(bundle_path: &Path) -> PathBuf {
+    let stem = bundle_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let stem = stem.strip_suffix(".bundle").unwrap_or(stem.as_ref());
+    bundle_path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(format!("{}.responses.jsonl.gz", stem))
+}
+
+pub fn write_responses_archive(path: &Path, responses: &[RawResponse]) -> Result<(), VerifyError> {
+    let file = std::fs::File::create(path)?;
+    let mut out = BufWriter::new(GzEncoder::new(file, Compression::default()));
+    let header = ArchiveHeader {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+    };
+    serde_json::to_writer(&mut out, &header)?;
+    out.write_all(b"\n")?;
+    for response in responses {
+        serde_json::to_writer(&mut out, response)?;
+        out.write_all(b"\n")?;
+    }
+    out.into_inner()
+        .map_err(|e| e.into_error())?
+        .finish()?
+        .sync_all()?;
+    Ok(())
+}
+
+/// Read an archive, rejecting any body whose SHA-256 does not match its recorded hash.
+pub fn read_responses_archive(path: &Path) -> Result<Vec<RawResponse>, VerifyError> {
+    let reader = BufReader::new(GzDecoder::new(std::fs::File::open(path)?));
+    let mut lines = reader.lines();
+    let header: ArchiveHeader = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => return Err(VerifyError::Archive("empty responses archive".into())),
+    };
+    if header.format != ARCHIVE_FORMAT || header.version != ARCHIVE_VERSION {
+        return Err(VerifyError::Archive(format!(
+            "unsupported archive {} v{}",
+            header.format, header.version
+        )));
+    }
+    let mut out = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response: RawResponse = serde_json::from_str(&line)?;
+        if RawResponse::new(response.body.clone()).sha256 != response.sha256 {
+            return Err(VerifyError::Archive(format!(
+                "body does not match hash {}",
+                response.sha256
+            )));
+        }
+        out.push(response);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_roundtrip_and_tamper_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_path = dir.path().join("addr1x.bundle.json");
+        let path = responses_path(&bundle_path);
+        assert_eq!(path, dir.path().join("addr1x.responses.jsonl.gz"));
+
+        let responses = vec![RawResponse::new("[]".into()), RawResponse::new("{}".into())];
+        write_responses_archive(&path, &responses).unwrap();
+        assert_eq!(read_responses_archive(&path).unwrap(), responses);
+
+        let mut forged = responses.clone();
+        forged[1].body = r#"{"x":1}"#.into();
+        write_responses_archive(&path, &forged).unwrap();
+        assert!(matches!(
+            read_responses_archive(&path),
+            Err(VerifyError::Archive(_))
+        ));
+    }
+}
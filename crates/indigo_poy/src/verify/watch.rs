@@ -0,0 +1,126 @@
+//! Drop-folder verification: find new bundles, verify them, write verdict files alongside.
+
+use crate::verify::bundle::{verify_bundle_file, VerificationResult, VerifyError};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const BUNDLE_SUFFIX: &str = ".bundle.json";
+const VERDICT_SUFFIX: &str = ".verdict.json";
+
+/// Verdict written next to a verified bundle as `<stem>.verdict.json`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Verdict {
+    pub bundle_file: String,
+    pub address: Option<String>,
+    pub verified_utc_rfc3339: String,
+    pub result: Option<VerificationResult>,
+    /// Set when the bundle could not be read or parsed.
+    pub error: Option<String>,
+}
+
+impl Verdict {
+    pub fn ok(&self) -> bool {
+        self.result.as_ref().is_some_and(|r| r.matches)
+    }
+}
+
+/// Path of the verdict file for a bundle (`x.bundle.json` → `x.verdict.json`).
+pub fn verdict_path(bundle_path: &Path) -> PathBuf {
+    let name = bundle_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let stem = name.strip_suffix(BUNDLE_SUFFIX).unwrap_or(&name);
+    bundle_path.with_file_name(format!("{}{}", stem, VERDICT_SUFFIX))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Bundles in `dir` with no verdict yet, or whose bundle/.sha256 changed after the verdict. Sorted by path.
+pub fn pending_bundles(dir: &Path) -> Result<Vec<PathBuf>, VerifyError> {
+    let mut out = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_bundle = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().ends_with(BUNDLE_SUFFIX));
+        if !is_bundle || !path.is_file() {
+            continue;
+        }
+        let verdict_at = modified(&verdict_path(&path));
+        let newest_input = [Some(path.clone()), Some(crate::verify::sha256_path(&path))]
+            .into_iter()
+            .flatten()
+            .filter_map(|p| modified(&p))
+            .max();
+        if verdict_at.is_none() || newest_input > verdict_at {
+            out.push(path);
+        }
+    }
+    out.sort();
+    Ok(out)
+}
+
+/// Verify one bundle and write its verdict file. Parse failures produce a failing verdict rather than an error.
+pub fn verify_and_write_verdict(bundle_path: &Path) -> Result<Verdict, VerifyError> {
+    let verified_utc_rfc3339 = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default();
+    let bundle_file = bundle_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let verdict = match verify_bundle_file(bundle_path) {
+        Ok((bundle, result)) => Verdict {
+            bundle_file,
+            address: Some(bundle.address),
+            verified_utc_rfc3339,
+            result: Some(result),
+            error: None,
+        },
+        Err(e) => Verdict {
+            bundle_file,
+            address: None,
+            verified_utc_rfc3339,
+            result: None,
+            error: Some(e.to_string()),
+        },
+    };
+    std::fs::write(
+        verdict_path(bundle_path),
+        serde_json::to_string_pretty(&verdict)?,
+    )?;
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify::{reproducibility_hash, EvidenceBundle};
+
+    #[test]
+    fn drop_folder_verifies_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = EvidenceBundle::demo();
+        let bundle_path = dir.path().join("demo.bundle.json");
+        std::fs::write(&bundle_path, serde_json::to_string(&bundle).unwrap()).unwrap();
+        let hash = reproducibility_hash(&bundle).unwrap();
+        std::fs::write(dir.path().join("demo.sha256"), format!("{}\n", hash)).unwrap();
+        std::fs::write(dir.path().join("broken.bundle.json"), "{").unwrap();
+
+        let pending = pending_bundles(dir.path()).unwrap();
+        assert_eq!(pending.len(), 2);
+        let verdicts: Vec<Verdict> = pending
+            .iter()
+            .map(|p| verify_and_write_verdict(p).unwrap())
+            .collect();
+        assert!(!verdicts[0].ok() && verdicts[0].error.is_some());
+        assert!(verdicts[1].ok());
+        assert!(dir.path().join("demo.verdict.json").exists());
+        assert!(pending_bundles(dir.path()).unwrap().is_empty());
+    }
+}
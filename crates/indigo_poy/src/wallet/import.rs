@@ -0,0 +1,249 @@
+//! Address list import from wallet export files (Eternl CSV, Nami JSON, cardano-wallet dump).
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("no addresses found in export")]
+    Empty,
+}
+
+/// Wallet export formats understood by [`parse_wallet_export`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalletExportFormat {
+    /// CSV with an `address` column and optional `label`/`name`/`note` column (Eternl).
+    Csv,
+    /// JSON array of strings or `{address, name|label}` objects (Nami and similar).
+    Json,
+    /// `cardano-wallet address list` JSON (`[{"id": "addr1...", "state": ...}]`).
+    CardanoWallet,
+    /// One address per line, optional label after whitespace.
+    PlainText,
+}
+
+/// An address with an optional user-facing label from the wallet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LabeledAddress {
+    pub address: String,
+    pub label: Option<String>,
+}
+
+impl WalletExportFormat {
+    /// Guess the format from file content.
+    pub fn detect(content: &str) -> Self {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with('[') || trimmed.starts_with('{') {
+            if trimmed.contains("\"id\"") && trimmed.contains("\"state\"") {
+                return Self::CardanoWallet;
+            }
+            return Self::Json;
+        }
+        let first = trimmed.lines().next().unwrap_or("");
+        if first.contains(',') || first.contains(';') {
+            Self::Csv
+        } else {
+            Self::PlainText
+        }
+    }
+}
+
+/// Read and parse a wallet export file, auto-detecting the format.
+pub fn import_addresses(path: impl AsRef<Path>) -> Result<Vec<LabeledAddress>, ImportError> {
+    let content = std::fs::read_to_string(path)?;
+    parse_wallet_export(&content, None)
+}
+
+/// Parse wallet export content. Duplicates are removed (first label wins); order is preserved.
+pub fn parse_wallet_export(
+    content: &str,
+    format: Option<WalletExportFormat>,
+) -> Result<Vec<LabeledAddress>, ImportError> {
+    let format = format.unwrap_or_else(|| WalletExportFormat::detect(content));
+    let parsed = match format {
+        WalletExportFormat::Csv => parse_csv(content),
+        WalletExportFormat::Json | WalletExportFormat::CardanoWallet => {
+            parse_json(&serde_json::from_str(content)?)
+        }
+        WalletExportFormat::PlainText => parse_plain(content),
+    };
+    let mut seen = HashSet::new();
+    let out: Vec<LabeledAddress> = parsed
+        .into_iter()
+        .filter(|a| is_cardano_address(&a.address) && seen.insert(a.address.clone()))
+        .collect();
+    if out.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    Ok(out)
+}
+
+fn is_cardano_address(s: &str) -> bool {
+    ["addr1", "addr_test1", "stake1", "stake_test1"]
+        .iter()
+        .any(|p| s.starts_with(p))
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim().trim_matches('"').trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn parse_csv(content: &str) -> Vec<LabeledAddress> {
+    let delim = if content.lines().next().unwrap_or("").contains(';') {
+        ';'
+    } else {
+        ','
+    };
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty()).peekable();
+    let is_header = |line: &&str| {
+        !line
+            .split(delim)
+            .filter_map(non_empty)
+            .any(|c| is_cardano_address(&c))
+    };
+    let header: Vec<String> = match lines.peek() {
+        Some(first) if is_header(first) => lines
+            .next()
+            .unwrap_or("")
+            .split(delim)
+            .map(|h| h.trim().trim_matches('"').to_lowercase())
+            .collect(),
+        _ => vec![],
+    };
+    let addr_col = header.iter().position(|h| h.contains("address"));
+    let label_col = header
+        .iter()
+        .position(|h| h == "label" || h == "name" || h == "note" || h == "description");
+    lines
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split(delim).collect();
+            let address = match addr_col {
+                Some(i) => non_empty(cols.get(i)?).filter(|c| is_cardano_address(c))?,
+                None => cols.iter().find_map(|c| {
+                    let c = non_empty(c)?;
+                    is_cardano_address(&c).then_some(c)
+                })?,
+            };
+            let label = match (label_col, addr_col) {
+                (Some(i), _) => cols.get(i).and_then(|c| non_empty(c)),
+                (None, None) => cols
+                    .iter()
+                    .filter_map(|c| non_empty(c))
+                    .find(|c| *c != address),
+                (None, Some(_)) => None,
+            };
+            Some(LabeledAddress { address, label })
+        })
+        .collect()
+}
+
+fn parse_json(value: &Value) -> Vec<LabeledAddress> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(parse_json).collect(),
+        Value::String(s) => vec![LabeledAddress {
+            address: s.trim().to_string(),
+            label: None,
+        }],
+        Value::Object(map) => {
+            let address = ["address", "id", "addr", "payment_address"]
+                .iter()
+                .find_map(|k| map.get(*k).and_then(Value::as_str));
+            if let Some(address) = address {
+                let label = ["label", "name", "note"]
+                    .iter()
+                    .find_map(|k| map.get(*k).and_then(Value::as_str))
+                    .and_then(non_empty);
+                return vec![LabeledAddress {
+                    address: address.trim().to_string(),
+                    label,
+                }];
+            }
+            // Container objects, e.g. {"addresses": [...]} or {"accounts": [{"addresses": [...]}]}.
+            map.values()
+                .filter(|v| v.is_array() || v.is_object())
+                .flat_map(parse_json)
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+fn parse_plain(content: &str) -> Vec<LabeledAddress> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (address, label) = match line.split_once(char::is_whitespace) {
+                Some((a, l)) => (a, non_empty(l)),
+                None => (line, None),
+            };
+            Some(LabeledAddress {
+                address: address.to_string(),
+                label,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_with_header_and_labels() {
+        let csv = "Address,Label\naddr1qxa,Main\naddr1qxb,\naddr1qxa,Dup\n";
+        let out = parse_wallet_export(csv, None).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].label.as_deref(), Some("Main"));
+        assert_eq!(out[1].label, None);
+    }
+
+    #[test]
+    fn csv_header_is_a_first_row_without_an_address() {
+        let csv = "stake1uxa,Rewards\naddr1qxa,Main\n";
+        let out = parse_wallet_export(csv, None).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].address, "stake1uxa");
+
+        let csv = "Address,Label\ntotal,Sum\naddr1qxa,Main\n";
+        let out = parse_wallet_export(csv, Some(WalletExportFormat::Csv)).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].label.as_deref(), Some("Main"));
+    }
+
+    #[test]
+    fn cardano_wallet_and_nami_json() {
+        let cw = r#"[{"id":"addr1qxa","state":"used","derivation_path":["1852H"]}]"#;
+        assert_eq!(
+            WalletExportFormat::detect(cw),
+            WalletExportFormat::CardanoWallet
+        );
+        assert_eq!(
+            parse_wallet_export(cw, None).unwrap()[0].address,
+            "addr1qxa"
+        );
+
+        let nami = r#"{"accounts":[{"name":"Savings","address":"addr1qxz"}]}"#;
+        let out = parse_wallet_export(nami, None).unwrap();
+        assert_eq!(out[0].label.as_deref(), Some("Savings"));
+    }
+
+    #[test]
+    fn plain_text_skips_non_addresses() {
+        let txt = "# my wallets\naddr1qxa  Cold storage\nnot-an-address\n";
+        let out = parse_wallet_export(txt, None).unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].label.as_deref(), Some("Cold storage"));
+        assert!(parse_wallet_export("junk\n", None).is_err());
+    }
+}
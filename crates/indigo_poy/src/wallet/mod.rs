@@ -0,0 +1,7 @@
+//! Wallet-side inputs: address lists imported from wallet export files.
+
+mod import;
+
+pub use import::{
+    import_addresses, parse_wallet_export, ImportError, LabeledAddress, WalletExportFormat,
+};
@@ -66,8 +66,85 @@ fn integration_compute_from_events() {
         period_start_ts: Some(0),
         period_end_ts: Some(365 * 24 * 3600),
         current_ada_position: Some(1_000_000),
+        ..Default::default()
     };
     let m = compute_metrics(&input);
     assert_eq!(m.combined.total_ada_in_lovelace, 0);
     assert!(m.combined.apr_pct.is_some());
 }
+
+#[test]
+fn integration_replay_recorded_koios_fixtures() {
+    use indigo_poy::chain::{FetchConfig, Fetcher, ProviderKind};
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../testdata/replay/koios");
+    let config = FetchConfig {
+        provider: ProviderKind::Replay,
+        replay_dir: Some(dir),
+        ..Default::default()
+    };
+    let fetcher = Fetcher::new(config, None).unwrap();
+    assert_eq!(fetcher.provider_name(), "koios");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let txs = rt
+        .block_on(fetcher.account_txs("addr1q9replayfixture", None, None))
+        .unwrap();
+    assert_eq!(txs.len(), 2);
+    let hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
+    let utxos = rt.block_on(fetcher.fetch_many(&hashes));
+    assert!(utxos.iter().all(|(_, r)| r.is_ok()));
+    assert_eq!(fetcher.take_responses().len(), 3);
+    // Requests that were never recorded fail instead of reaching the network.
+    assert!(rt
+        .block_on(fetcher.account_txs("addr1q9other", None, None))
+        .is_err());
+}
+
+#[test]
+fn integration_replay_from_bundle_responses() {
+    use indigo_poy::chain::{FetchConfig, Fetcher, Network, ProviderKind};
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../testdata/replay/koios");
+    let config = FetchConfig {
+        provider: ProviderKind::Replay,
+        replay_dir: Some(dir),
+        ..Default::default()
+    };
+    let recorded = Fetcher::new(config, None).unwrap();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let run = |fetcher: &Fetcher| {
+        let txs = rt
+            .block_on(fetcher.account_txs("addr1q9replayfixture", None, None))
+            .unwrap();
+        let hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
+        let utxos = rt.block_on(fetcher.fetch_many(&hashes));
+        (txs.len(), utxos.iter().filter(|(_, r)| r.is_ok()).count())
+    };
+    let expected = run(&recorded);
+    let responses = recorded.take_responses();
+    assert!(responses.iter().all(|r| !r.requests.is_empty()));
+
+    let replay = Fetcher::from_responses(
+        FetchConfig::default(),
+        "koios",
+        Network::Mainnet,
+        &responses,
+    )
+    .unwrap();
+    assert_eq!(run(&replay), expected);
+    assert_eq!(replay.take_responses(), responses);
+    assert!(rt
+        .block_on(replay.account_txs("addr1q9other", None, None))
+        .is_err());
+}
+
+#[test]
+fn integration_reconstruct_dedups_txs_shared_by_addresses() {
+    let txs: Vec<indigo_poy::KoiosAccountTx> = load_fixture("account_txs.json");
+    let utxos_abc: indigo_poy::KoiosTxUtxos = load_fixture("tx_utxos_abc123.json");
+    let get = |h: &str| (h == "abc123def456").then(|| utxos_abc.clone());
+    let now = time::OffsetDateTime::from_unix_timestamp(1700000000).unwrap();
+    let single = reconstruct_all_events(&txs, get, now, None);
+    // The same txs seen from two addresses of one wallet.
+    let doubled: Vec<_> = txs.iter().chain(txs.iter()).cloned().collect();
+    let merged = reconstruct_all_events(&doubled, get, now, None);
+    assert_eq!(merged.all_events().count(), single.all_events().count());
+}
@@ -1,16 +1,55 @@
 //! indigo-poy CLI: fetch, compute, report, verify.
 
-use clap::{Parser, Subcommand};
-use indigo_poy::chain::{Cache, FetchConfig, Fetcher};
-use indigo_poy::compute::{compute_metrics, ComputeInput};
-use indigo_poy::indigo::{reconstruct_all_events, IndigoV2Config};
+use clap::{ArgGroup, Parser, Subcommand};
+use indigo_poy::chain::assets::AssetInfo;
+use indigo_poy::chain::xpub::DEFAULT_GAP_LIMIT;
+use indigo_poy::chain::{
+    served_by, AccountXpub, Cache, CacheTtl, EndpointPolicies, FetchConfig, FetchError, Fetcher,
+    Network, ProviderKind, RawResponse,
+};
+use indigo_poy::compute::annualize::{Annualization, DayCount, RateKind};
+use indigo_poy::compute::benchmark::StakingRate;
+use indigo_poy::compute::currency::{AccountCurrency, Currency};
+use indigo_poy::compute::lots::LotMethod;
+use indigo_poy::compute::pricing::PriceSeries;
+use indigo_poy::compute::{compute_metrics, principal_after, ComputeInput};
+use indigo_poy::indigo::oracle::{fetch_oracle_feed, price_liquidations, OracleFeed};
+use indigo_poy::indigo::position::fetch_open_position;
+use indigo_poy::indigo::registry::{registry_path, update_registry};
+use indigo_poy::indigo::script_scan::scan_script_addresses;
+use indigo_poy::indigo::validate::{cross_check_config, validate_config};
+use indigo_poy::indigo::{
+    detect_anomalies, reconstruct_all_events_with_overrides, EventOverrides, IndigoV2Config,
+    OwnAddresses, DEFAULT_MAX_PREMIUM_PCT,
+};
+use indigo_poy::notify::alerts::{evaluate_alerts, AlertConfig};
+use indigo_poy::notify::{
+    notify_all, EmailNotifier, EmailReport, Notification, Notifier, SlackNotifier, SmtpConfig,
+    WebhookNotifier,
+};
+use indigo_poy::report::leaderboard::build_leaderboard;
+use indigo_poy::report::portfolio::build_portfolio;
 use indigo_poy::report::ReportData;
-use indigo_poy::verify::{reproducibility_hash, EvidenceBundle, VerificationResult};
-use indigo_poy_report::render_report;
-use std::collections::HashMap;
+use indigo_poy::verify::crosscheck::{
+    compare_with_analytics, fetch_analytics_snapshot, DEFAULT_ANALYTICS_URL, DEFAULT_TOLERANCE_PCT,
+};
+use indigo_poy::verify::responses::{
+    read_responses_archive, responses_path, write_responses_archive,
+};
+use indigo_poy::verify::watch::{pending_bundles, verify_and_write_verdict};
+use indigo_poy::verify::{
+    bundle_differences, reproducibility_hash, verify_bundle_file, EvidenceBundle,
+};
+use indigo_poy::wallet::{import_addresses, LabeledAddress};
+use indigo_poy_report::{
+    build_email_html, build_email_text, render_leaderboard, render_portfolio, render_report_with,
+    write_csv_export, write_json_export, ReportOptions, Theme,
+};
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::Duration;
 use time::OffsetDateTime;
-use tracing::info;
+use tracing::{info, warn};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt()
@@ -22,6 +61,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         Command::Compute(args) => run_compute(args),
         Command::Report(args) => run_report(args),
         Command::Verify(args) => run_verify(args),
+        Command::Derive(args) => run_derive(args),
+        Command::Watch(args) => run_watch(args),
+        Command::Aggregate(args) => run_aggregate(args),
+        Command::Portfolio(args) => run_portfolio(args),
+        Command::Cache(args) => run_cache(args),
+        Command::Registry(args) => run_registry(args),
+        Command::Config(args) => run_config(args),
     }
 }
 
@@ -44,50 +90,241 @@ enum Command {
     Report(ReportArgs),
     /// Verify a bundle's reproducibility hash.
     Verify(VerifyArgs),
+    /// Derive payment/stake addresses from an account xpub (read-only).
+    Derive(DeriveArgs),
+    /// Periodically re-fetch, recompute, and re-render reports; deliver via webhook/email.
+    Watch(WatchArgs),
+    /// Aggregate many bundles into an anonymized leaderboard.
+    Aggregate(AggregateArgs),
+    /// Combine several addresses' bundles into one portfolio report with a combined hash.
+    Portfolio(PortfolioArgs),
+    /// Cache maintenance: prune old responses, export/import archives for offline hand-off.
+    Cache(CacheArgs),
+    /// Install the official Indigo identifiers from a registry file pinned by its SHA-256.
+    Registry(RegistryArgs),
+    /// Check the protocol config: identifier shapes, duplicates, and optionally the chain.
+    Config(ConfigArgs),
+}
+
+/// Where chain data comes from: provider, cache directory, offline mode.
+#[derive(clap::Args)]
+struct SourceArgs {
+    #[arg(long, default_value = "./data/cache")]
+    cache_dir: PathBuf,
+    #[arg(long)]
+    offline: bool,
+    /// Chain data provider: koios, blockfrost (needs BLOCKFROST_PROJECT_ID), kupo (self-hosted),
+    /// node (local cardano-node socket; build with `--features node`), or replay (--replay-dir).
+    #[arg(long, default_value_t = ProviderKind::Koios)]
+    provider: ProviderKind,
+    /// Provider to fail over to when the previous one errors or rate-limits; repeatable, tried
+    /// in order (e.g. `--provider koios --fallback-provider blockfrost`).
+    #[arg(long = "fallback-provider", value_name = "PROVIDER")]
+    fallback_providers: Vec<ProviderKind>,
+    /// Override the provider's API root (e.g. a local Kupo at http://localhost:1442).
+    #[arg(long)]
+    api_url: Option<String>,
+    /// Ogmios endpoint used by the kupo provider for chain tip queries.
+    #[arg(long)]
+    ogmios_url: Option<String>,
+    /// cardano-node socket for --provider node (default: CARDANO_NODE_SOCKET_PATH).
+    #[arg(long)]
+    node_socket: Option<PathBuf>,
+    /// Tx history source for --provider node (the node only serves current UTxOs), e.g. kupo.
+    #[arg(long)]
+    history_provider: Option<ProviderKind>,
+    /// Maximum parallel tx UTxO requests (the rate limit still applies).
+    #[arg(long, default_value_t = 4)]
+    max_concurrency: usize,
+    /// Bearer token for authenticated API tiers (e.g. Koios Pro). Prefer INDIGO_POY_API_TOKEN,
+    /// which keeps it out of shell history. Never cached or written to bundles.
+    #[arg(long)]
+    auth_token: Option<String>,
+    /// Cardano network: mainnet, preprod, or preview. Each network gets its own cache file.
+    #[arg(long, default_value_t = Network::Mainnet)]
+    network: Network,
+    /// Cache TTL override as `class=secs` (or `class=never`); repeatable. Classes: tx_history,
+    /// tx_utxos, address_utxos, account_addresses, tx_metadata, datum_info, asset_info, negative
+    /// (empty / not-found answers). Default: address_utxos expire after 600 s, account_addresses
+    /// after 3600 s, asset_info after 86400 s, negative after 300 s, the rest never.
+    #[arg(long = "cache-ttl", value_name = "CLASS=SECS")]
+    cache_ttl: Vec<String>,
+    /// Request timeout for one endpoint as `/endpoint=secs`; repeatable. Endpoints are labelled
+    /// as in --metrics-file (`/account_txs`, `/tx_utxos`, `/txs/:id/utxos`). Default: 30 s.
+    #[arg(long = "endpoint-timeout", value_name = "ENDPOINT=SECS")]
+    endpoint_timeouts: Vec<String>,
+    /// Retry count for one endpoint as `/endpoint=n`; repeatable. Default: 3.
+    #[arg(long = "endpoint-retries", value_name = "ENDPOINT=N")]
+    endpoint_retries: Vec<String>,
+    /// Also write the raw API responses behind each bundle to `<addr>.responses.jsonl.gz`, with
+    /// the requests they answered, so `verify --deep` can replay the run from them.
+    #[arg(long)]
+    save_responses: bool,
+    /// Record every response as a fixture file in this directory (for tests / bug reports).
+    #[arg(long)]
+    record_dir: Option<PathBuf>,
+    /// Fixture directory served by --provider replay (network comes from its manifest).
+    #[arg(long)]
+    replay_dir: Option<PathBuf>,
+    /// HTTP(S) proxy for provider requests (default: HTTPS_PROXY / NO_PROXY from the env).
+    #[arg(long)]
+    proxy_url: Option<String>,
+    /// Extra trusted root certificates (PEM), e.g. a corporate TLS-inspection CA; repeatable.
+    #[arg(long = "ca-cert", value_name = "PEM")]
+    ca_certs: Vec<PathBuf>,
+    /// Also scan the SP / ROB script addresses from the protocol config for txs whose datums
+    /// name the address's key hashes (compute, report, watch). Slow: walks the scripts' history.
+    #[arg(long)]
+    scan_scripts: bool,
+    /// JSON file reclassifying or annotating specific txs, as `{"<tx_hash>": {"category":
+    /// "other", "note": "..."}}` (compute, report, watch). Recorded in the bundle.
+    #[arg(long, value_name = "JSON")]
+    overrides: Option<PathBuf>,
+    /// Leave out txs with fewer confirmations than this (they may still roll back) and list
+    /// them in the bundle as provisional. 0 (default) skips the tip query.
+    #[arg(long, default_value_t = 0)]
+    min_confirmations: u32,
+    /// Write request metrics (per endpoint: requests by status, retries, cache hits/misses,
+    /// latency histogram) in Prometheus text format to this file at the end of each run, e.g.
+    /// for node_exporter's textfile collector.
+    #[arg(long)]
+    metrics_file: Option<PathBuf>,
+    /// ADA per INDY, to value INDY rewards in ADA (`indy_staking.total_indy_ada_equivalent_lovelace`).
+    /// Recorded in the bundle; not part of PnL.
+    #[arg(long, value_name = "ADA")]
+    indy_price: Option<f64>,
+    /// CSV of `timestamp,price` lines (USD per ADA; Unix seconds, RFC 3339, or YYYY-MM-DD), to
+    /// value each event in USD at its time (`metrics.fiat`). Recorded in the bundle.
+    #[arg(long, value_name = "FILE")]
+    ada_usd_prices: Option<PathBuf>,
+    /// Also add the flows up in this currency, ada, usd, or iusd, each event converted at its
+    /// price (`metrics.account`). Recorded in the bundle.
+    #[arg(long)]
+    currency: Option<Currency>,
+    /// CSV of `timestamp,price` lines, units of --currency per ADA, as for --ada-usd-prices.
+    /// Defaults to --ada-usd-prices for usd.
+    #[arg(long, value_name = "FILE")]
+    currency_prices: Option<PathBuf>,
+    /// Value the open position at report time (SP accounts at the oracle price plus unclaimed
+    /// ADA, and open ROB orders) from the SP script addresses' UTxOs, and report PnL with it.
+    /// Needs `stability_pool_script_hashes`; prices need `oracle_addresses`.
+    #[arg(long)]
+    mark_to_market: bool,
+    /// Match SP withdrawals and liquidations to deposit lots, fifo or lifo, for per-lot
+    /// realized gains (`metrics.lots`).
+    #[arg(long, value_name = "METHOD")]
+    cost_basis: Option<LotMethod>,
+    /// apr, or apy to also compound the APR once per epoch (`combined.apy_pct`).
+    #[arg(long, default_value_t = RateKind::Apr)]
+    annualization: RateKind,
+    /// Year used to annualize: 365, 365.25, or epochs (the period in epochs started, 73 a year).
+    /// Recorded in the bundle with --annualization.
+    #[arg(long, default_value_t = DayCount::Days365_25)]
+    day_count: DayCount,
+    /// Compare the yield with delegating the same ADA at this annual staking rate: a percent,
+    /// or a CSV of `timestamp,percent` lines as for --ada-usd-prices (`combined.benchmark`).
+    #[arg(long, value_name = "PCT|FILE")]
+    benchmark_staking: Option<String>,
+    /// Add monthly (UTC) and per-epoch statements to the bundle (`metrics.statements`): opening
+    /// balance, deposits, withdrawals, yield, and closing balance of each.
+    #[arg(long)]
+    statements: bool,
+    /// Flag SP liquidation and ROB fill premiums above this percent of the principal as
+    /// suspicious (bundle `warnings`). Default 20.
+    #[arg(long, value_name = "PCT")]
+    max_premium_pct: Option<f64>,
+}
+
+/// Which addresses to process: one address, a wallet export, or the addresses derived from an
+/// account xpub.
+#[derive(clap::Args)]
+#[command(group(ArgGroup::new("address_list").args(["addresses_file", "xpub"])))]
+struct AddressArgs {
+    /// Payment address, or a stake address (covers every payment address under it).
+    #[arg(long)]
+    address: Option<String>,
+    /// Wallet export (Eternl CSV, Nami JSON, cardano-wallet dump, or one address per line).
+    #[arg(long, conflicts_with = "address")]
+    addresses_file: Option<PathBuf>,
+    /// Account-level extended public key (acct_xvk1... or hex); covers all derived addresses,
+    /// each labelled by its path (`0/<i>` external, `1/<i>` internal).
+    #[arg(long, conflicts_with_all = ["address", "addresses_file"])]
+    xpub: Option<String>,
+    /// Addresses derived per role (external/internal) when using --xpub.
+    #[arg(long, default_value_t = DEFAULT_GAP_LIMIT)]
+    xpub_count: u32,
 }
 
 #[derive(Parser)]
 struct FetchArgs {
-    #[arg(long)]
-    address: String,
+    #[command(flatten)]
+    addresses: AddressArgs,
     #[arg(long)]
     from: Option<String>,
     #[arg(long)]
     to: Option<String>,
-    #[arg(long, default_value = "./data/cache")]
-    cache_dir: PathBuf,
-    #[arg(long)]
-    offline: bool,
+    #[command(flatten)]
+    source: SourceArgs,
 }
 
 #[derive(Parser)]
 struct ComputeArgs {
-    #[arg(long)]
-    address: String,
+    #[command(flatten)]
+    addresses: AddressArgs,
+    /// With --addresses-file or --xpub: build one bundle over all addresses instead of one per
+    /// address.
+    #[arg(long, requires = "address_list")]
+    merge: bool,
+    /// Compute only over what followed the latest claim in range (an INDY reward, SP premium,
+    /// or SP withdrawal): the period starts at that tx. Recorded in the bundle.
     #[arg(long)]
     since_last_claim: bool,
+    /// Extend this bundle instead of starting over: fetch only the txs after its last slot,
+    /// for its addresses, and merge them in. The new bundle records the old one's hash.
+    #[arg(long, value_name = "BUNDLE", conflicts_with_all = ["address", "addresses_file", "xpub", "since_last_claim", "from"])]
+    from_bundle: Option<PathBuf>,
     #[arg(long)]
     from: Option<String>,
     #[arg(long)]
     to: Option<String>,
-    #[arg(long, default_value = "./data/cache")]
-    cache_dir: PathBuf,
-    #[arg(long)]
-    offline: bool,
+    #[command(flatten)]
+    source: SourceArgs,
 }
 
 #[derive(Parser)]
 struct ReportArgs {
-    #[arg(long)]
-    address: String,
+    /// With --addresses-file or --xpub, one labelled report is written per address.
+    #[command(flatten)]
+    addresses: AddressArgs,
+    /// With --addresses-file or --xpub: write a single report over all addresses instead of one
+    /// per address.
+    #[arg(long, requires = "address_list")]
+    merge: bool,
+    /// With --addresses-file or --xpub: also write `portfolio.html` (and `.json`) over the
+    /// per-address bundles to the reports dir.
+    #[arg(long, requires = "address_list", conflicts_with = "merge")]
+    portfolio: bool,
+    /// HTML output path; with several addresses only together with --merge.
     #[arg(long)]
     out: Option<PathBuf>,
     #[arg(long, default_value = "./reports")]
     reports_dir: PathBuf,
-    #[arg(long, default_value = "./data/cache")]
-    cache_dir: PathBuf,
+    /// Also write `<addr>.metrics.json` (the metrics) and `<addr>.events.csv` (one row per
+    /// event) to the reports dir.
     #[arg(long)]
-    offline: bool,
+    export: bool,
+    /// Tera template to render the HTML with instead of the built-in one.
+    #[arg(long)]
+    template: Option<PathBuf>,
+    /// Explorer URL the event timeline appends tx hashes to (default: cardanoscan for the
+    /// network), e.g. https://cexplorer.io/tx/.
+    #[arg(long)]
+    explorer_url: Option<String>,
+    /// Colour scheme: auto (follow the browser), light, or dark. Printing is always light.
+    #[arg(long, default_value_t = Theme::Auto)]
+    theme: Theme,
+    #[command(flatten)]
+    source: SourceArgs,
     /// Generate a demo report with example metrics (for screenshots / Discord pitch).
     #[arg(long)]
     demo: bool,
@@ -95,169 +332,1308 @@ struct ReportArgs {
 
 #[derive(Parser)]
 struct VerifyArgs {
+    #[arg(long, required_unless_present = "watch")]
+    bundle: Option<PathBuf>,
+    /// Watch a drop folder: verify each new `*.bundle.json` and write `<stem>.verdict.json` next to it.
+    #[arg(long, conflicts_with = "bundle")]
+    watch: Option<PathBuf>,
+    /// Polling interval for --watch, in seconds.
+    #[arg(long, default_value_t = 5)]
+    poll_secs: u64,
+    /// With --watch: scan once and exit instead of polling forever.
+    #[arg(long)]
+    once: bool,
+    /// With --watch: POST each verdict to this webhook URL (Discord-compatible).
     #[arg(long)]
-    bundle: PathBuf,
+    notify_webhook: Option<String>,
+    /// With --watch: post each verdict to this Slack incoming webhook.
+    #[arg(long)]
+    notify_slack: Option<String>,
+    /// Compare metrics with Indigo's public analytics (non-evidentiary; never changes the verdict).
+    #[arg(long, requires = "bundle")]
+    crosscheck: bool,
+    /// Analytics API base URL for --crosscheck (default: INDIGO_ANALYTICS_URL or the public endpoint).
+    #[arg(long)]
+    analytics_url: Option<String>,
+    /// Relative deviation (%) above which --crosscheck flags a metric.
+    #[arg(long, default_value_t = DEFAULT_TOLERANCE_PCT)]
+    crosscheck_tolerance_pct: f64,
+    /// Fail unless the bundle was built for this network (bundles without one are mainnet).
+    #[arg(long, requires = "bundle")]
+    network: Option<Network>,
+    /// Also re-run reconstruction and compute from the bundle's responses archive (from
+    /// `--save-responses`), with no network and no cache, and require the same bundle.
+    #[arg(long, requires = "bundle")]
+    deep: bool,
 }
 
-fn cache_path(cache_dir: &std::path::Path) -> PathBuf {
-    cache_dir.join("cache.sqlite")
+#[derive(Parser)]
+struct WatchArgs {
+    #[command(flatten)]
+    addresses: AddressArgs,
+    #[arg(long, default_value = "./reports")]
+    reports_dir: PathBuf,
+    /// Tera template to render the HTML with instead of the built-in one.
+    #[arg(long)]
+    template: Option<PathBuf>,
+    /// Explorer URL the event timeline appends tx hashes to (default: cardanoscan for the
+    /// network), e.g. https://cexplorer.io/tx/.
+    #[arg(long)]
+    explorer_url: Option<String>,
+    /// Colour scheme: auto (follow the browser), light, or dark. Printing is always light.
+    #[arg(long, default_value_t = Theme::Auto)]
+    theme: Theme,
+    #[command(flatten)]
+    source: SourceArgs,
+    /// Seconds between runs (minimum 60).
+    #[arg(long, default_value_t = 86_400)]
+    interval_secs: u64,
+    /// Run once and exit (for cron).
+    #[arg(long)]
+    once: bool,
+    /// POST a run summary to this webhook URL (Discord-compatible).
+    #[arg(long)]
+    notify_webhook: Option<String>,
+    /// Post a run summary to this Slack incoming webhook.
+    #[arg(long)]
+    notify_slack: Option<String>,
+    /// Alert rules JSON (`{"rules": [...]}`); fired rules are sent through the notifiers.
+    #[arg(long)]
+    alerts: Option<PathBuf>,
+    /// SMTP config JSON; emails the report, hash, and bundle after each run.
+    /// Password from INDIGO_POY_SMTP_PASSWORD if not in the file.
+    #[arg(long)]
+    smtp_config: Option<PathBuf>,
 }
 
-fn run_fetch(args: FetchArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let cache = Cache::open(cache_path(&args.cache_dir))?;
+#[derive(Parser)]
+struct AggregateArgs {
+    /// Directory containing `*.bundle.json` files (redacted bundles are fine).
+    #[arg(long)]
+    bundles_dir: PathBuf,
+    /// Output HTML path; a `.json` with the same stem is written alongside.
+    #[arg(long, default_value = "./reports/leaderboard.html")]
+    out: PathBuf,
+}
+
+#[derive(Parser)]
+struct PortfolioArgs {
+    /// Bundle file to include; repeatable.
+    #[arg(long = "bundle", required_unless_present = "bundles_dir")]
+    bundles: Vec<PathBuf>,
+    /// Include every `*.bundle.json` in this directory.
+    #[arg(long)]
+    bundles_dir: Option<PathBuf>,
+    /// Output HTML path; a `.json` with the same stem is written alongside.
+    #[arg(long, default_value = "./reports/portfolio.html")]
+    out: PathBuf,
+}
+
+#[derive(Parser)]
+struct CacheArgs {
+    #[command(subcommand)]
+    action: CacheAction,
+}
+
+/// Which cache file a maintenance action works on.
+#[derive(clap::Args)]
+struct CacheLocation {
+    #[arg(long, default_value = "./data/cache")]
+    cache_dir: PathBuf,
+    #[arg(long, default_value_t = Network::Mainnet)]
+    network: Network,
+}
+
+impl CacheLocation {
+    fn open(&self) -> Result<(PathBuf, Cache), Box<dyn std::error::Error>> {
+        let path = cache_path(&self.cache_dir, self.network);
+        let cache = Cache::open(&path)?;
+        Ok((path, cache))
+    }
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Delete cached responses older than the given age.
+    Prune {
+        #[command(flatten)]
+        location: CacheLocation,
+        /// Remove rows written more than this many days ago.
+        #[arg(long)]
+        older_than_days: u64,
+    },
+    /// Write all cached responses to a compressed archive (for running `--offline` elsewhere).
+    Export {
+        #[command(flatten)]
+        location: CacheLocation,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Print row count, stored bytes, and age range.
+    Stats {
+        #[command(flatten)]
+        location: CacheLocation,
+    },
+    /// Merge an archive from `cache export` into the cache; newer local rows are kept.
+    Import {
+        #[command(flatten)]
+        location: CacheLocation,
+        #[arg(long)]
+        archive: PathBuf,
+    },
+}
+
+#[derive(Parser)]
+struct RegistryArgs {
+    #[command(subcommand)]
+    action: RegistryAction,
+}
+
+#[derive(Subcommand)]
+enum RegistryAction {
+    /// Download the registry, check its SHA-256, and install it for the network's config.
+    Update {
+        /// Registry file URL (JSON in the `indigo_v2.json` format).
+        #[arg(long)]
+        url: String,
+        /// SHA-256 (hex) the registry must have, as published by its maintainer.
+        #[arg(long)]
+        sha256: String,
+        #[arg(long, default_value_t = Network::Mainnet)]
+        network: Network,
+        /// Install here instead of `./config/indigo_v2.registry[.<network>].json`.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser)]
+struct ConfigArgs {
+    #[command(subcommand)]
+    action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Report malformed or duplicate identifiers, empty fields, and each parser's mode. Exits
+    /// non-zero when any identifier has a problem.
+    Validate {
+        /// Config file to check (default: the one compute would load for --network, with the
+        /// installed registry merged in).
+        #[arg(long)]
+        path: Option<PathBuf>,
+        /// Also check that each policy ID was minted under and each script and oracle address
+        /// holds UTxOs, through the provider.
+        #[arg(long)]
+        check_chain: bool,
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+}
+
+#[derive(Parser)]
+struct DeriveArgs {
+    /// Account-level extended public key (acct_xvk1... or hex).
+    #[arg(long)]
+    xpub: String,
+    /// Addresses derived per role (external/internal).
+    #[arg(long, default_value_t = DEFAULT_GAP_LIMIT)]
+    count: u32,
+    #[arg(long, default_value_t = Network::Mainnet)]
+    network: Network,
+}
+
+/// Mainnet keeps the original file name so existing caches stay valid.
+fn cache_path(cache_dir: &std::path::Path, network: Network) -> PathBuf {
+    match network {
+        Network::Mainnet => cache_dir.join("cache.sqlite"),
+        other => cache_dir.join(format!("cache.{}.sqlite", other)),
+    }
+}
+
+fn open_fetcher(source: &SourceArgs) -> Result<Fetcher, Box<dyn std::error::Error>> {
+    let cache = Cache::open(cache_path(&source.cache_dir, source.network))?;
+    let mut cache_ttl = CacheTtl::default();
+    for spec in &source.cache_ttl {
+        cache_ttl.apply_override(spec)?;
+    }
+    let mut endpoint_policies = EndpointPolicies::default();
+    for spec in &source.endpoint_timeouts {
+        endpoint_policies.apply_timeout(spec)?;
+    }
+    for spec in &source.endpoint_retries {
+        endpoint_policies.apply_retries(spec)?;
+    }
     let config = FetchConfig {
-        offline: args.offline,
+        network: source.network,
+        offline: source.offline,
+        provider: source.provider,
+        fallback_providers: source.fallback_providers.clone(),
+        base_url: source.api_url.clone(),
+        ogmios_url: source.ogmios_url.clone(),
+        node_socket: source.node_socket.clone(),
+        history_provider: source.history_provider,
+        max_concurrency: source.max_concurrency,
+        auth_token: source.auth_token.clone(),
+        cache_ttl,
+        endpoint_policies,
+        record_dir: source.record_dir.clone(),
+        replay_dir: source.replay_dir.clone(),
+        replayable: source.save_responses,
+        proxy_url: source.proxy_url.clone(),
+        extra_root_certs: source.ca_certs.clone(),
+        min_confirmations: source.min_confirmations,
         ..Default::default()
     };
-    let fetcher = Fetcher::new(config, Some(cache))?;
+    Ok(Fetcher::new(config, Some(cache))?)
+}
+
+fn run_cache(args: CacheArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        CacheAction::Prune {
+            location,
+            older_than_days,
+        } => {
+            let (path, cache) = location.open()?;
+            let older_than = Duration::from_secs(older_than_days.saturating_mul(86_400));
+            let removed = cache.prune(older_than)?;
+            info!(path = %path.display(), removed, "cache pruned");
+        }
+        CacheAction::Stats { location } => {
+            let (path, cache) = location.open()?;
+            let stats = cache.stats()?;
+            let date = |t: Option<i64>| {
+                t.and_then(|t| OffsetDateTime::from_unix_timestamp(t).ok())
+                    .map(|t| t.date().to_string())
+                    .unwrap_or_else(|| "—".to_string())
+            };
+            println!("{}", path.display());
+            println!("rows   {}", thousands(stats.rows));
+            println!("bytes  {}", thousands(stats.bytes));
+            println!(
+                "oldest {} / newest {}",
+                date(stats.oldest_utc),
+                date(stats.newest_utc)
+            );
+        }
+        CacheAction::Export { location, out } => {
+            let (path, cache) = location.open()?;
+            let rows = cache.export(&out)?;
+            info!(path = %path.display(), archive = %out.display(), rows, "cache exported");
+        }
+        CacheAction::Import { location, archive } => {
+            let (path, cache) = location.open()?;
+            let rows = cache.import(&archive)?;
+            info!(path = %path.display(), archive = %archive.display(), rows, "cache imported");
+        }
+    }
+    Ok(())
+}
+
+fn run_registry(args: RegistryArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        RegistryAction::Update {
+            url,
+            sha256,
+            network,
+            out,
+        } => {
+            let path = out.unwrap_or_else(|| registry_path(network));
+            let rt = tokio::runtime::Runtime::new()?;
+            let registry = rt.block_on(update_registry(&url, &sha256, &path))?;
+            info!(
+                path = %path.display(),
+                iasset_policies = registry.iasset_policy_ids.len(),
+                oracles = registry.oracle_addresses.len(),
+                indy_policy = registry.indy_policy_id.is_some(),
+                "registry installed"
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_config(args: ConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match args.action {
+        ConfigAction::Validate {
+            path,
+            check_chain,
+            source,
+        } => {
+            let config: IndigoV2Config = match &path {
+                // Unlike a run, a file that does not parse is the first thing to report.
+                Some(path) => serde_json::from_str(&std::fs::read_to_string(path)?)?,
+                None => IndigoV2Config::load_for(source.network),
+            };
+            let mut report = validate_config(&config);
+            if check_chain {
+                let fetcher = open_fetcher(&source)?;
+                let rt = tokio::runtime::Runtime::new()?;
+                report
+                    .issues
+                    .extend(rt.block_on(cross_check_config(&config, &fetcher))?);
+            }
+            for (parser, mode) in &report.modes {
+                println!("MODE\t{}\t{}", parser, mode.as_str());
+            }
+            for field in &report.missing {
+                println!("MISSING\t{}", field);
+            }
+            for issue in &report.issues {
+                println!("ISSUE\t{}\t{}\t{}", issue.field, issue.value, issue.problem);
+            }
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_fetch(args: FetchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let fetcher = open_fetcher(&args.source)?;
+    let addresses = resolve_addresses(&args.addresses, args.source.network)?;
     let rt = tokio::runtime::Runtime::new()?;
-    let txs = rt.block_on(async {
-        fetcher
-            .account_txs(&args.address, args.from.as_deref(), args.to.as_deref())
-            .await
-    })?;
-    info!(count = txs.len(), "fetched account_txs");
-    for tx in &txs {
-        let _ = rt.block_on(async { fetcher.tx_utxos(&tx.tx_hash).await });
-    }
-    info!(requests = fetcher.request_count(), "fetch complete");
+    for LabeledAddress { address, .. } in &addresses {
+        let txs = rt.block_on(async {
+            fetcher
+                .account_txs(address, args.from.as_deref(), args.to.as_deref())
+                .await
+        })?;
+        info!(%address, count = txs.len(), "fetched account_txs");
+        let hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
+        let _ = rt.block_on(fetcher.fetch_many(&hashes));
+    }
+    let stats = fetcher.stats();
+    info!(
+        requests = stats.requests,
+        throttled = stats.throttled,
+        coalesced = stats.coalesced,
+        rate_limit_ms = stats.rate_limit_ms,
+        "fetch complete"
+    );
+    print_cache_summary(&fetcher);
+    export_metrics(&fetcher, &args.source);
     Ok(())
 }
 
-fn run_compute(args: ComputeArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let cache = Cache::open(cache_path(&args.cache_dir))?;
-    let config = FetchConfig {
-        offline: args.offline,
-        ..Default::default()
+/// End-of-run cache summary on stderr (stdout stays machine-readable). Zero misses means the
+/// same command will work with `--offline`.
+fn print_cache_summary(fetcher: &Fetcher) {
+    let stats = fetcher.stats();
+    eprintln!(
+        "cache hits {} / misses {}",
+        thousands(stats.cache_hits),
+        thousands(stats.cache_misses)
+    );
+}
+
+/// Write the fetcher's request metrics to `--metrics-file`, if set. Failures are logged: a
+/// missing metrics file must not fail the run it describes.
+fn export_metrics(fetcher: &Fetcher, source: &SourceArgs) {
+    if let Some(path) = &source.metrics_file {
+        if let Err(e) = fetcher.metrics().write_textfile(path) {
+            warn!(path = %path.display(), error = %e, "failed to write metrics file");
+        }
+    }
+}
+
+/// `4812` -> `4,812`.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// The `*.bundle.json` files in `dir`, sorted.
+fn bundle_paths_in(dir: &std::path::Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| file_name(p).ends_with(".bundle.json"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+fn run_aggregate(args: AggregateArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let paths = bundle_paths_in(&args.bundles_dir)?;
+    let mut bundles = Vec::new();
+    for path in &paths {
+        match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| serde_json::from_str::<EvidenceBundle>(&s).map_err(|e| e.to_string()))
+        {
+            Ok(b) => bundles.push(b),
+            Err(e) => warn!(path = %path.display(), error = %e, "skipping unreadable bundle"),
+        }
+    }
+    let board = build_leaderboard(&bundles)?;
+    if let Some(parent) = args.out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    render_leaderboard(&board, &args.out)?;
+    let json_path = args.out.with_extension("json");
+    std::fs::write(&json_path, serde_json::to_string_pretty(&board)?)?;
+    info!(participants = board.participants, html = ?args.out, json = ?json_path, "leaderboard written");
+    Ok(())
+}
+
+fn run_portfolio(args: PortfolioArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let mut paths = args.bundles.clone();
+    if let Some(dir) = &args.bundles_dir {
+        paths.extend(bundle_paths_in(dir)?);
+    }
+    // Unlike the leaderboard, a bundle left out would make the totals wrong, so none is skipped.
+    let bundles = paths
+        .iter()
+        .map(
+            |path| -> Result<EvidenceBundle, Box<dyn std::error::Error>> {
+                let s = std::fs::read_to_string(path)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+                Ok(serde_json::from_str(&s).map_err(|e| format!("{}: {}", path.display(), e))?)
+            },
+        )
+        .collect::<Result<Vec<_>, _>>()?;
+    write_portfolio(&bundles, &args.out)
+}
+
+/// Build the portfolio over `bundles` and write it to `out`, with a `.json` alongside.
+fn write_portfolio(
+    bundles: &[EvidenceBundle],
+    out: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let portfolio = build_portfolio(bundles)?;
+    if let Some(parent) = out.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    render_portfolio(&portfolio, out)?;
+    let json_path = out.with_extension("json");
+    std::fs::write(&json_path, serde_json::to_string_pretty(&portfolio)?)?;
+    info!(addresses = portfolio.summary.addresses, hash = %portfolio.portfolio_sha256, html = ?out, json = ?json_path, "portfolio written");
+    Ok(())
+}
+
+fn run_derive(args: DeriveArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let derived = AccountXpub::parse(&args.xpub)?.derive_addresses(args.count, args.network)?;
+    println!("stake\t{}", derived.stake_address);
+    for (i, addr) in derived.external.iter().enumerate() {
+        println!("0/{}\t{}", i, addr);
+    }
+    for (i, addr) in derived.internal.iter().enumerate() {
+        println!("1/{}\t{}", i, addr);
+    }
+    Ok(())
+}
+
+/// Fetch txs and UTxOs for the addresses (one bundle, txs merged by hash), reconstruct events,
+/// compute metrics, and build the bundle. Also returns the raw responses used, whose hashes are
+/// recorded in the bundle. `now` stands in for missing block times.
+fn build_bundle(
+    fetcher: &Fetcher,
+    rt: &tokio::runtime::Runtime,
+    addresses: &[String],
+    from: Option<&str>,
+    to: Option<&str>,
+    options: &BundleOptions,
+    now: OffsetDateTime,
+) -> Result<(EvidenceBundle, Vec<RawResponse>), Box<dyn std::error::Error>> {
+    let scan_scripts = options.scan_scripts;
+    let network = fetcher.network();
+    let address = addresses.first().ok_or("no address given")?;
+    if let Some(other) = addresses.iter().find(|a| !network.matches_address(a)) {
+        return Err(format!("address {} is not a {} address", other, network).into());
+    }
+    let prior = options.prior.as_ref();
+    if let Some(prior) = prior.filter(|p| p.network() != network) {
+        return Err(format!(
+            "--from-bundle: bundle is for {}, not {}",
+            prior.network(),
+            network
+        )
+        .into());
+    }
+    let resume_slot = prior.map(|p| p.last_slot().map_or(0, |slot| slot + 1).to_string());
+    let from = resume_slot.as_deref().or(from);
+    let config = IndigoV2Config::load_for(network);
+    let _ = fetcher.take_responses();
+    let mut txs = rt.block_on(fetcher.account_txs_multi(addresses, from, to))?;
+    let mut tx_utxos = HashMap::new();
+    if scan_scripts {
+        if config.script_addresses(network).is_empty() {
+            warn!("--scan-scripts: no script hashes in the protocol config; nothing scanned");
+        }
+        let scan = rt.block_on(scan_script_addresses(fetcher, &config, addresses, from, to))?;
+        txs.extend(scan.txs);
+        txs.sort_by(|a, b| (a.slot_no, &a.tx_hash).cmp(&(b.slot_no, &b.tx_hash)));
+        txs.dedup_by(|a, b| a.tx_hash == b.tx_hash);
+        tx_utxos = scan.tx_utxos;
+    }
+    if let Some(prior) = prior {
+        txs = prior.txs_after(txs);
+    }
+    let (txs, provisional) = match rt.block_on(fetcher.split_provisional(txs.clone())) {
+        Ok((confirmed, provisional, tip)) => {
+            if let Some(tip) = tip {
+                info!(
+                    tip_slot = tip.slot,
+                    provisional = provisional.len(),
+                    "confirmation gating"
+                );
+            }
+            (confirmed, provisional)
+        }
+        Err(FetchError::OfflineMiss) => {
+            warn!("offline: chain tip unavailable; --min-confirmations not applied");
+            (txs, vec![])
+        }
+        Err(e) => return Err(e.into()),
     };
-    let fetcher = Fetcher::new(config, Some(cache))?;
-    let rt = tokio::runtime::Runtime::new()?;
-    let from = args.from.as_deref();
-    let to = args.to.as_deref();
-    let txs = rt.block_on(async { fetcher.account_txs(&args.address, from, to).await })?;
-    let tx_utxos: HashMap<String, _> = rt.block_on(async {
-        let mut map = HashMap::new();
-        for tx in &txs {
-            if let Ok(u) = fetcher.tx_utxos(&tx.tx_hash).await {
-                map.insert(tx.tx_hash.clone(), u);
+    let hashes: Vec<String> = txs
+        .iter()
+        .map(|t| t.tx_hash.clone())
+        .filter(|h| !tx_utxos.contains_key(h))
+        .collect();
+    for (hash, result) in rt.block_on(fetcher.fetch_many(&hashes)) {
+        match result {
+            Ok(utxos) => {
+                tx_utxos.insert(hash, utxos);
             }
+            // Includes responses that fail to parse, e.g. over an unreadable output value.
+            Err(e) => warn!(tx = %hash, error = %e, "tx UTxOs unavailable; tx skipped"),
         }
-        map
-    });
+    }
+    if let Err(e) = rt.block_on(fetcher.resolve_datums(tx_utxos.values_mut())) {
+        warn!(error = %e, "datum_info failed; datums known only by hash stay unresolved");
+    }
     let get_tx_utxos = |hash: &str| tx_utxos.get(hash).cloned();
-    let now = OffsetDateTime::now_utc();
-    let config = IndigoV2Config::load();
-    let events = reconstruct_all_events(&txs, get_tx_utxos, now, Some(&config));
-    let period_start = txs.iter().filter_map(|t| t.block_time).min();
-    let period_end = txs.iter().filter_map(|t| t.block_time).max();
+    let mut events = reconstruct_all_events_with_overrides(
+        &txs,
+        get_tx_utxos,
+        now,
+        Some(&config),
+        &options.overrides,
+        &OwnAddresses::new(addresses),
+    );
+    let feed = if config.oracle_addresses.is_empty() {
+        OracleFeed::default()
+    } else {
+        let feed = rt.block_on(fetch_oracle_feed(fetcher, &config, from, to));
+        let priced = price_liquidations(&mut events, &feed);
+        info!(priced, "liquidations priced at the oracle");
+        feed
+    };
+    let open_position = if options.mark_to_market {
+        if config.stability_pool_script_hashes.is_empty() {
+            warn!("--mark-to-market: no SP script hashes in the protocol config; SP accounts not valued");
+        }
+        let position = rt.block_on(fetch_open_position(
+            fetcher, &config, addresses, &feed, &events,
+        ))?;
+        info!(value_lovelace = position.value_lovelace, "open position");
+        Some(position)
+    } else {
+        None
+    };
+    let mut period_start = txs.iter().filter_map(|t| t.block_time).min();
+    let mut period_end = txs.iter().filter_map(|t| t.block_time).max();
+    if let Some(prior) = prior {
+        events.merge(prior.events.clone());
+        period_start = prior
+            .period_start_ts
+            .or_else(|| {
+                let first = prior
+                    .events
+                    .all_events()
+                    .map(|e| e.timestamp.unix_timestamp());
+                first.min()
+            })
+            .or(period_start);
+        period_end = period_end.or(prior.period_end_ts);
+        info!(
+            new_txs = txs.len(),
+            "--from-bundle: merged the txs after the bundle's last slot"
+        );
+    }
+    let mut in_range: HashSet<&str> = txs.iter().map(|t| t.tx_hash.as_str()).collect();
+    let mut opening_principal = None;
+    // With --from-bundle, the prior's withdrawals after the claim, by the time of their tx.
+    let mut prior_withdrawals = prior.map_or_else(Vec::new, |p| p.reward_withdrawals.clone());
+    let since_claim = if options.since_last_claim {
+        match events.last_claim().cloned() {
+            Some(claim) => {
+                // The principal still deposited at the claim keeps working in the new period.
+                let ledger = compute_metrics(&ComputeInput {
+                    events: events.clone(),
+                    ..Default::default()
+                })
+                .ledger;
+                opening_principal = Some(principal_after(&ledger, &claim.tx_hash));
+                let claimed_at = claim.timestamp.unix_timestamp();
+                let tx_times: HashMap<&str, i64> = events
+                    .all_events()
+                    .map(|e| (e.tx_hash.as_str(), e.timestamp.unix_timestamp()))
+                    .collect();
+                // A claim after the prior's period follows all of its withdrawals.
+                let prior_end = prior.and_then(|p| p.period_end_ts);
+                let mut untimed = 0;
+                prior_withdrawals.retain(|w| match tx_times.get(w.tx_hash.as_str()) {
+                    Some(&ts) => ts > claimed_at,
+                    None if prior_end.is_some_and(|end| end <= claimed_at) => false,
+                    None => {
+                        untimed += 1;
+                        false
+                    }
+                });
+                if untimed > 0 {
+                    warn!(
+                        untimed,
+                        "--since-last-claim: prior bundle withdrawals with no dated event left out"
+                    );
+                }
+                events.retain_after(&claim);
+                period_start = Some(claimed_at);
+                in_range = txs
+                    .iter()
+                    .filter(|t| t.block_time.is_some_and(|ts| ts > claimed_at))
+                    .map(|t| t.tx_hash.as_str())
+                    .collect();
+                info!(tx = %claim.tx_hash, "--since-last-claim: period starts after this claim");
+                Some(claim.tx_hash)
+            }
+            None => {
+                warn!("--since-last-claim: no claim in range; computing over the whole range");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let reward_withdrawals: Vec<_> = match rt.block_on(fetcher.reward_withdrawals(addresses)) {
+        Ok(all) => prior_withdrawals
+            .into_iter()
+            .chain(
+                all.into_iter()
+                    .filter(|w| in_range.contains(w.tx_hash.as_str())),
+            )
+            .collect(),
+        Err(e) => {
+            warn!(error = %e, "reward withdrawals unavailable; staking rewards may count as INDY rewards");
+            vec![]
+        }
+    };
     let input = ComputeInput {
         events: events.clone(),
         period_start_ts: period_start,
         period_end_ts: period_end,
-        current_ada_position: None,
+        current_ada_position: open_position.as_ref().map(|p| p.value_lovelace),
+        opening_principal_lovelace: opening_principal,
+        reward_withdrawals: reward_withdrawals.clone(),
+        ada_usd: options.ada_usd.clone(),
+        cost_basis: options.cost_basis,
+        annualization: options.annualization,
+        staking_benchmark: options.staking_benchmark.clone(),
+        statements: options.statements.then_some(network),
+        unclaimed_sp_lovelace: open_position.as_ref().map(|p| {
+            p.stability_pool
+                .iter()
+                .map(|a| a.pending_ada_lovelace)
+                .sum()
+        }),
+        currency: options.currency.clone(),
     };
     let metrics = compute_metrics(&input);
-    let tx_hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
-    let mut sorted_hashes = tx_hashes.clone();
+    for warning in &metrics.warnings {
+        warn!(%warning, "compute: inconsistent input");
+    }
+    // A predecessor's assets are carried over with the rest of its inputs.
+    let units: Vec<String> = events
+        .asset_units()
+        .into_iter()
+        .filter(|u| prior.is_none_or(|p| !p.assets.contains_key(u)))
+        .collect();
+    let assets = rt.block_on(fetcher.asset_info(&units)).unwrap_or_else(|e| {
+        warn!(error = %e, "asset_info failed; assets are labelled by name only");
+        units
+            .iter()
+            .filter_map(|u| Some((u.clone(), AssetInfo::from_unit(u)?)))
+            .collect()
+    });
+    let mut sorted_hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
     sorted_hashes.sort();
-    let bundle = EvidenceBundle::new(
-        args.address.clone(),
+    let responses = fetcher.take_responses();
+    let mut bundle = EvidenceBundle::new(
+        address.to_string(),
         sorted_hashes,
         vec![],
-        vec![],
+        responses.iter().map(|r| r.sha256.clone()).collect(),
         events,
         metrics,
         txs.iter().filter_map(|t| t.slot_no).collect(),
     );
-    let hash = reproducibility_hash(&bundle)?;
-    let reports_dir = PathBuf::from("./reports");
-    std::fs::create_dir_all(&reports_dir)?;
-    let addr_suffix = args
-        .address
+    bundle.provider = Some(fetcher.provider_name().to_string());
+    bundle.served_by = served_by(&responses);
+    bundle.network = Some(network);
+    bundle.from = from.map(str::to_string);
+    bundle.to = to.map(str::to_string);
+    bundle.scan_scripts = scan_scripts;
+    bundle.registry_sha256 = config.registry_sha256.clone();
+    bundle.overrides = options.overrides.clone();
+    bundle.ada_usd_prices = options.ada_usd.clone();
+    bundle.currency = options.currency.clone();
+    bundle.open_position = open_position;
+    bundle.since_claim_tx = since_claim.or_else(|| prior.and_then(|p| p.since_claim_tx.clone()));
+    bundle.period_start_ts = period_start;
+    bundle.period_end_ts = period_end;
+    bundle.assets = assets;
+    bundle.reward_withdrawals = reward_withdrawals;
+    bundle.warnings = detect_anomalies(
+        &bundle.events,
+        options.max_premium_pct.unwrap_or(DEFAULT_MAX_PREMIUM_PCT),
+    );
+    bundle.max_premium_pct = options.max_premium_pct;
+    for w in &bundle.warnings {
+        warn!(tx = %w.tx_hash, warning = %w.message, "events: suspicious");
+    }
+    if addresses.len() > 1 {
+        bundle.addresses = addresses.to_vec();
+    }
+    if fetcher.min_confirmations() > 0 {
+        bundle.min_confirmations = Some(fetcher.min_confirmations());
+        let mut hashes: Vec<String> = provisional.into_iter().map(|t| t.tx_hash).collect();
+        hashes.sort();
+        bundle.provisional_tx_hashes = hashes;
+    }
+    if let Some(prior) = prior {
+        bundle.extend_from(prior)?;
+    }
+    Ok((bundle, responses))
+}
+
+/// How `build_bundle` reads the txs beyond their range. Recorded in the bundle, so `verify
+/// --deep` rebuilds the same way.
+#[derive(Default)]
+struct BundleOptions {
+    scan_scripts: bool,
+    overrides: EventOverrides,
+    ada_usd: Option<PriceSeries>,
+    mark_to_market: bool,
+    cost_basis: Option<LotMethod>,
+    annualization: Annualization,
+    since_last_claim: bool,
+    staking_benchmark: Option<StakingRate>,
+    statements: bool,
+    /// Premium threshold of the data-quality warnings; `None` for the default.
+    max_premium_pct: Option<f64>,
+    currency: Option<AccountCurrency>,
+    /// Bundle to extend with the txs after its last slot.
+    prior: Option<EvidenceBundle>,
+}
+
+impl BundleOptions {
+    fn from_source(source: &SourceArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        let overrides = match &source.overrides {
+            Some(path) => EventOverrides::load(path)?,
+            None => EventOverrides::default(),
+        };
+        let ada_usd = match &source.ada_usd_prices {
+            Some(path) => Some(PriceSeries::load(path)?),
+            None => None,
+        };
+        let currency = match source.currency {
+            Some(currency) => {
+                let prices = match (&source.currency_prices, currency, &ada_usd) {
+                    (Some(path), _, _) => PriceSeries::load(path)?,
+                    (None, Currency::Usd, Some(series)) => series.clone(),
+                    (None, Currency::Ada, _) => PriceSeries::default(),
+                    _ => {
+                        return Err(
+                            format!("--currency {} needs --currency-prices", currency).into()
+                        )
+                    }
+                };
+                Some(AccountCurrency { currency, prices })
+            }
+            None => None,
+        };
+        let staking_benchmark = match &source.benchmark_staking {
+            Some(arg) => Some(match arg.parse::<f64>() {
+                Ok(pct) => StakingRate::Flat(pct),
+                Err(_) => StakingRate::Series(PriceSeries::load(std::path::Path::new(arg))?),
+            }),
+            None => None,
+        };
+        Ok(Self {
+            scan_scripts: source.scan_scripts,
+            overrides,
+            ada_usd,
+            mark_to_market: source.mark_to_market,
+            cost_basis: source.cost_basis,
+            annualization: Annualization {
+                rate: source.annualization,
+                day_count: source.day_count,
+            },
+            since_last_claim: false,
+            staking_benchmark,
+            statements: source.statements,
+            max_premium_pct: source.max_premium_pct,
+            currency,
+            prior: None,
+        })
+    }
+}
+
+/// Addresses to process: a single `--address`, every address in a wallet export file, or the
+/// payment addresses derived from an `--xpub`, external then internal.
+fn resolve_addresses(
+    args: &AddressArgs,
+    network: Network,
+) -> Result<Vec<LabeledAddress>, Box<dyn std::error::Error>> {
+    if let Some(path) = &args.addresses_file {
+        return Ok(import_addresses(path)?);
+    }
+    if let Some(xpub) = &args.xpub {
+        let derived = AccountXpub::parse(xpub)?.derive_addresses(args.xpub_count, network)?;
+        let roles = [(0, &derived.external), (1, &derived.internal)];
+        return Ok(roles
+            .into_iter()
+            .flat_map(|(role, addresses)| {
+                addresses
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, address)| LabeledAddress {
+                        address: address.clone(),
+                        label: Some(format!("{}/{}", role, i)),
+                    })
+            })
+            .collect());
+    }
+    match &args.address {
+        Some(address) => Ok(vec![LabeledAddress {
+            address: address.clone(),
+            label: None,
+        }]),
+        None => Err("one of --address, --addresses-file or --xpub is required".into()),
+    }
+}
+
+/// Address groups that each become one bundle: every address alone, or all of them together
+/// with `merge` (labelled by the first labelled entry).
+fn bundle_groups(addresses: &[LabeledAddress], merge: bool) -> Vec<(Vec<String>, Option<String>)> {
+    if merge {
+        let all = addresses.iter().map(|e| e.address.clone()).collect();
+        let label = addresses.iter().find_map(|e| e.label.clone());
+        vec![(all, label)]
+    } else {
+        addresses
+            .iter()
+            .map(|e| (vec![e.address.clone()], e.label.clone()))
+            .collect()
+    }
+}
+
+fn addr_suffix(address: &str) -> String {
+    address
         .chars()
         .take(20)
         .collect::<String>()
-        .replace([' ', ':'], "_");
-    let bundle_path = reports_dir.join(format!("{}.bundle.json", addr_suffix));
-    let hash_path = reports_dir.join(format!("{}.sha256", addr_suffix));
-    std::fs::write(&bundle_path, serde_json::to_string_pretty(&bundle)?)?;
-    std::fs::write(&hash_path, format!("{}\n", hash))?;
-    info!(?bundle_path, ?hash_path, "compute complete");
-    println!("{}", hash);
-    Ok(())
+        .replace([' ', ':'], "_")
 }
 
-fn run_report(args: ReportArgs) -> Result<(), Box<dyn std::error::Error>> {
-    if args.demo {
-        return run_report_demo(&args);
-    }
-    let cache = Cache::open(cache_path(&args.cache_dir))?;
-    let config = FetchConfig {
-        offline: args.offline,
-        ..Default::default()
-    };
-    let fetcher = Fetcher::new(config, Some(cache))?;
-    let rt = tokio::runtime::Runtime::new()?;
-    let txs = rt.block_on(async { fetcher.account_txs(&args.address, None, None).await })?;
-    let tx_utxos: HashMap<String, _> = rt.block_on(async {
-        let mut map = HashMap::new();
-        for tx in &txs {
-            if let Ok(u) = fetcher.tx_utxos(&tx.tx_hash).await {
-                map.insert(tx.tx_hash.clone(), u);
+fn run_compute(args: ComputeArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let prior = match &args.from_bundle {
+        Some(path) => {
+            let (prior, result) = verify_bundle_file(path)?;
+            if !result.matches {
+                return Err(format!(
+                    "--from-bundle: {} does not match its .sha256",
+                    path.display()
+                )
+                .into());
             }
+            Some(prior)
         }
-        map
-    });
-    let get_tx_utxos = |hash: &str| tx_utxos.get(hash).cloned();
-    let now = OffsetDateTime::now_utc();
-    let config = IndigoV2Config::load();
-    let events = reconstruct_all_events(&txs, get_tx_utxos, now, Some(&config));
-    let period_start = txs.iter().filter_map(|t| t.block_time).min();
-    let period_end = txs.iter().filter_map(|t| t.block_time).max();
-    let input = ComputeInput {
-        events: events.clone(),
-        period_start_ts: period_start,
-        period_end_ts: period_end,
-        current_ada_position: None,
+        None => None,
     };
-    let metrics = compute_metrics(&input);
-    let mut sorted_hashes: Vec<String> = txs.iter().map(|t| t.tx_hash.clone()).collect();
-    sorted_hashes.sort();
-    let bundle = EvidenceBundle::new(
-        args.address.clone(),
-        sorted_hashes,
-        vec![],
-        vec![],
-        events,
-        metrics,
-        txs.iter().filter_map(|t| t.slot_no).collect(),
-    );
+    let groups = match &prior {
+        Some(prior) if prior.addresses.is_empty() => {
+            vec![(vec![prior.address.clone()], prior.label.clone())]
+        }
+        Some(prior) => vec![(prior.addresses.clone(), prior.label.clone())],
+        None => {
+            let addresses = resolve_addresses(&args.addresses, args.source.network)?;
+            bundle_groups(&addresses, args.merge)
+        }
+    };
+    let fetcher = open_fetcher(&args.source)?;
+    let options = BundleOptions {
+        since_last_claim: args.since_last_claim,
+        prior,
+        ..BundleOptions::from_source(&args.source)?
+    };
+    let rt = tokio::runtime::Runtime::new()?;
+    let reports_dir = PathBuf::from("./reports");
+    std::fs::create_dir_all(&reports_dir)?;
+    for (group, label) in &groups {
+        let (mut bundle, responses) = build_bundle(
+            &fetcher,
+            &rt,
+            group,
+            args.from.as_deref(),
+            args.to.as_deref(),
+            &options,
+            OffsetDateTime::now_utc(),
+        )?;
+        bundle.label = label.clone();
+        if let Some(price) = args.source.indy_price {
+            bundle.metrics.indy_staking.price_indy(price);
+        }
+        let hash = reproducibility_hash(&bundle)?;
+        let suffix = addr_suffix(&bundle.address);
+        let bundle_path = reports_dir.join(format!("{}.bundle.json", suffix));
+        let hash_path = reports_dir.join(format!("{}.sha256", suffix));
+        std::fs::write(&bundle_path, serde_json::to_string_pretty(&bundle)?)?;
+        std::fs::write(&hash_path, format!("{}\n", hash))?;
+        if args.source.save_responses {
+            write_responses_archive(&responses_path(&bundle_path), &responses)?;
+        }
+        info!(?bundle_path, ?hash_path, "compute complete");
+        if groups.len() == 1 {
+            println!("{}", hash);
+        } else {
+            println!("{}\t{}", hash, bundle.address);
+        }
+    }
+    print_cache_summary(&fetcher);
+    export_metrics(&fetcher, &args.source);
+    Ok(())
+}
+
+/// Paths written for one address's report.
+struct ReportFiles {
+    data: ReportData,
+    html_path: PathBuf,
+    bundle_path: PathBuf,
+    hash_path: PathBuf,
+}
+
+/// Hash the bundle and write `<addr>.html`, `<addr>.bundle.json`, `<addr>.sha256` to `reports_dir`,
+/// plus `<addr>.responses.jsonl.gz` when `responses` is given.
+fn write_report_files(
+    reports_dir: &std::path::Path,
+    out: Option<PathBuf>,
+    bundle: EvidenceBundle,
+    responses: Option<&[RawResponse]>,
+    options: &ReportOptions,
+) -> Result<ReportFiles, Box<dyn std::error::Error>> {
     let reproducibility_hash_sha256 = reproducibility_hash(&bundle)?;
+    let suffix = addr_suffix(&bundle.address);
     let data = ReportData {
         bundle,
-        reproducibility_hash_sha256: reproducibility_hash_sha256.clone(),
+        reproducibility_hash_sha256,
     };
-    std::fs::create_dir_all(&args.reports_dir)?;
-    let addr_suffix = args
-        .address
-        .chars()
-        .take(20)
-        .collect::<String>()
-        .replace([' ', ':'], "_");
-    let html_path = args
-        .out
-        .unwrap_or_else(|| args.reports_dir.join(format!("{}.html", addr_suffix)));
-    let bundle_path = args
-        .reports_dir
-        .join(format!("{}.bundle.json", addr_suffix));
-    let hash_path = args.reports_dir.join(format!("{}.sha256", addr_suffix));
-    render_report(&data, &html_path)?;
+    std::fs::create_dir_all(reports_dir)?;
+    let html_path = out.unwrap_or_else(|| reports_dir.join(format!("{}.html", suffix)));
+    let bundle_path = reports_dir.join(format!("{}.bundle.json", suffix));
+    let hash_path = reports_dir.join(format!("{}.sha256", suffix));
+    render_report_with(&data, &html_path, options)?;
     std::fs::write(&bundle_path, serde_json::to_string_pretty(&data.bundle)?)?;
-    std::fs::write(&hash_path, format!("{}\n", reproducibility_hash_sha256))?;
-    info!(?html_path, ?bundle_path, ?hash_path, "report complete");
+    std::fs::write(
+        &hash_path,
+        format!("{}\n", data.reproducibility_hash_sha256),
+    )?;
+    if let Some(responses) = responses {
+        write_responses_archive(&responses_path(&bundle_path), responses)?;
+    }
+    Ok(ReportFiles {
+        data,
+        html_path,
+        bundle_path,
+        hash_path,
+    })
+}
+
+/// `--template` (read here), `--explorer-url` and `--theme`.
+fn report_options(
+    template: Option<&std::path::Path>,
+    explorer_url: Option<&str>,
+    theme: Theme,
+) -> Result<ReportOptions, Box<dyn std::error::Error>> {
+    let template = template
+        .map(|path| {
+            std::fs::read_to_string(path).map_err(|e| format!("template {}: {}", path.display(), e))
+        })
+        .transpose()?;
+    Ok(ReportOptions {
+        template,
+        explorer_tx_url: explorer_url.map(str::to_string),
+        theme,
+    })
+}
+
+/// `--export`: write `<stem>.metrics.json` and `<stem>.events.csv` to `reports_dir`.
+fn write_exports(
+    reports_dir: &std::path::Path,
+    stem: &str,
+    data: &ReportData,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let json_path = reports_dir.join(format!("{}.metrics.json", stem));
+    let csv_path = reports_dir.join(format!("{}.events.csv", stem));
+    write_json_export(data, &json_path)?;
+    write_csv_export(data, &csv_path)?;
+    info!(?json_path, ?csv_path, "exports written");
+    Ok(())
+}
+
+fn run_report(args: ReportArgs) -> Result<(), Box<dyn std::error::Error>> {
+    if args.demo {
+        return run_report_demo(&args);
+    }
+    let addresses = resolve_addresses(&args.addresses, args.source.network)?;
+    if args.out.is_some() && addresses.len() > 1 && !args.merge {
+        return Err("--out writes a single report; use it with --merge".into());
+    }
+    let report_options = report_options(
+        args.template.as_deref(),
+        args.explorer_url.as_deref(),
+        args.theme,
+    )?;
+    let fetcher = open_fetcher(&args.source)?;
+    let options = BundleOptions::from_source(&args.source)?;
+    let rt = tokio::runtime::Runtime::new()?;
+    let mut bundles = Vec::new();
+    for (group, label) in bundle_groups(&addresses, args.merge) {
+        let (mut bundle, responses) = build_bundle(
+            &fetcher,
+            &rt,
+            &group,
+            None,
+            None,
+            &options,
+            OffsetDateTime::now_utc(),
+        )?;
+        bundle.label = label;
+        if let Some(price) = args.source.indy_price {
+            bundle.metrics.indy_staking.price_indy(price);
+        }
+        let responses = args.source.save_responses.then_some(responses.as_slice());
+        let files = write_report_files(
+            &args.reports_dir,
+            args.out.clone(),
+            bundle,
+            responses,
+            &report_options,
+        )?;
+        info!(html_path = ?files.html_path, bundle_path = ?files.bundle_path, hash_path = ?files.hash_path, "report complete");
+        if args.export {
+            write_exports(
+                &args.reports_dir,
+                &addr_suffix(&files.data.bundle.address),
+                &files.data,
+            )?;
+        }
+        if args.portfolio {
+            bundles.push(files.data.bundle);
+        }
+    }
+    if args.portfolio {
+        write_portfolio(&bundles, &args.reports_dir.join("portfolio.html"))?;
+    }
+    print_cache_summary(&fetcher);
+    export_metrics(&fetcher, &args.source);
     Ok(())
 }
 
+/// The email for one watch run, with the bundle and its hash attached.
+fn email_report(
+    entry: &LabeledAddress,
+    files: &ReportFiles,
+) -> Result<EmailReport, Box<dyn std::error::Error>> {
+    Ok(EmailReport {
+        subject: format!(
+            "Proof of yield – {}",
+            entry.label.as_deref().unwrap_or(&entry.address)
+        ),
+        text: build_email_text(&files.data),
+        html: build_email_html(&files.data)?,
+        attachments: vec![
+            (
+                file_name(&files.bundle_path),
+                "application/json".to_string(),
+                std::fs::read(&files.bundle_path)?,
+            ),
+            (
+                file_name(&files.hash_path),
+                "text/plain".to_string(),
+                std::fs::read(&files.hash_path)?,
+            ),
+        ],
+    })
+}
+
+fn run_watch(args: WatchArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let addresses = resolve_addresses(&args.addresses, args.source.network)?;
+    let notifiers = build_notifiers(args.notify_webhook.as_deref(), args.notify_slack.as_deref())?;
+    let alert_rules = args
+        .alerts
+        .as_deref()
+        .map(AlertConfig::load_from_path)
+        .transpose()?
+        .unwrap_or_default()
+        .rules;
+    if !alert_rules.is_empty() && notifiers.is_empty() {
+        warn!("alert rules configured but no notifier (--notify-webhook / --notify-slack); alerts will only be logged");
+    }
+    let email = args
+        .smtp_config
+        .as_deref()
+        .map(|p| SmtpConfig::load_from_path(p).and_then(EmailNotifier::new))
+        .transpose()?;
+    let report_options = report_options(
+        args.template.as_deref(),
+        args.explorer_url.as_deref(),
+        args.theme,
+    )?;
+    let fetcher = open_fetcher(&args.source)?;
+    let options = BundleOptions::from_source(&args.source)?;
+    let rt = tokio::runtime::Runtime::new()?;
+    loop {
+        let mut failed = 0;
+        for entry in &addresses {
+            // One failed run (an API error, an open circuit breaker) waits for the next interval.
+            let (mut bundle, responses) = match build_bundle(
+                &fetcher,
+                &rt,
+                std::slice::from_ref(&entry.address),
+                None,
+                None,
+                &options,
+                OffsetDateTime::now_utc(),
+            ) {
+                Ok(built) => built,
+                Err(e) => {
+                    warn!(address = %entry.address, error = %e, "watch run failed; retrying next interval");
+                    failed += 1;
+                    continue;
+                }
+            };
+            bundle.label = entry.label.clone();
+            if let Some(price) = args.source.indy_price {
+                bundle.metrics.indy_staking.price_indy(price);
+            }
+            let previous = previous_bundle(&args.reports_dir, &entry.address);
+            let responses = args.source.save_responses.then_some(responses.as_slice());
+            let files = match write_report_files(
+                &args.reports_dir,
+                None,
+                bundle,
+                responses,
+                &report_options,
+            ) {
+                Ok(files) => files,
+                Err(e) => {
+                    warn!(address = %entry.address, error = %e, "writing the report failed; retrying next interval");
+                    failed += 1;
+                    continue;
+                }
+            };
+            let data = &files.data;
+            info!(address = %entry.address, hash = %data.reproducibility_hash_sha256, "watch run complete");
+            if !notifiers.is_empty() {
+                let comb = &data.bundle.metrics.combined;
+                let new_events = match &previous {
+                    Some(prev) => data
+                        .bundle
+                        .events
+                        .all_events()
+                        .filter(|e| prev.tx_hashes.binary_search(&e.tx_hash).is_err())
+                        .count(),
+                    None => data.bundle.events.all_events().count(),
+                };
+                let apr_delta = match (
+                    comb.apr_pct,
+                    previous.as_ref().and_then(|p| p.metrics.combined.apr_pct),
+                ) {
+                    (Some(now), Some(before)) => format!("{:+.2}", now - before),
+                    _ => "—".to_string(),
+                };
+                let mut n = Notification::new("Proof-of-yield run complete")
+                    .field("new events", new_events.to_string())
+                    .field("net PnL (lovelace)", comb.net_pnl_lovelace.to_string())
+                    .field(
+                        "APR %",
+                        comb.apr_pct
+                            .map(|x| format!("{:.2}", x))
+                            .unwrap_or_else(|| "—".to_string()),
+                    )
+                    .field("APR Δ", apr_delta);
+                n.address = Some(entry.address.clone());
+                n.bundle_hash = Some(data.reproducibility_hash_sha256.clone());
+                rt.block_on(notify_all(&notifiers, &n));
+            }
+            for alert in evaluate_alerts(&alert_rules, &data.bundle, OffsetDateTime::now_utc()) {
+                warn!(address = %entry.address, alert = %alert.message, "alert fired");
+                let n = alert.to_notification(&entry.address, &data.reproducibility_hash_sha256);
+                rt.block_on(notify_all(&notifiers, &n));
+            }
+            if let Some(email) = &email {
+                let sent = match email_report(entry, &files) {
+                    Ok(report) => rt
+                        .block_on(email.send_report(&report))
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                if let Err(e) = sent {
+                    warn!(error = %e, "email delivery failed");
+                }
+            }
+        }
+        print_cache_summary(&fetcher);
+        export_metrics(&fetcher, &args.source);
+        if args.once {
+            return match failed {
+                0 => Ok(()),
+                n => Err(format!("watch: {} of {} address(es) failed", n, addresses.len()).into()),
+            };
+        }
+        std::thread::sleep(Duration::from_secs(args.interval_secs.max(60)));
+    }
+}
+
+/// Notifiers selected on the command line.
+fn build_notifiers(
+    webhook: Option<&str>,
+    slack: Option<&str>,
+) -> Result<Vec<Box<dyn Notifier>>, Box<dyn std::error::Error>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if let Some(url) = webhook {
+        notifiers.push(Box::new(WebhookNotifier::new(url)?));
+    }
+    if let Some(url) = slack {
+        notifiers.push(Box::new(SlackNotifier::new(url)?));
+    }
+    Ok(notifiers)
+}
+
+/// Bundle from the previous run for `address`, if one was written to `reports_dir`.
+fn previous_bundle(reports_dir: &std::path::Path, address: &str) -> Option<EvidenceBundle> {
+    let path = reports_dir.join(format!("{}.bundle.json", addr_suffix(address)));
+    let json = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
 fn run_report_demo(args: &ReportArgs) -> Result<(), Box<dyn std::error::Error>> {
     let bundle = EvidenceBundle::demo();
     let reproducibility_hash_sha256 = reproducibility_hash(&bundle)?;
@@ -272,45 +1648,53 @@ fn run_report_demo(args: &ReportArgs) -> Result<(), Box<dyn std::error::Error>>
         .unwrap_or_else(|| args.reports_dir.join("demo.html"));
     let bundle_path = args.reports_dir.join("demo.bundle.json");
     let hash_path = args.reports_dir.join("demo.sha256");
-    render_report(&data, &html_path)?;
+    let options = report_options(
+        args.template.as_deref(),
+        args.explorer_url.as_deref(),
+        args.theme,
+    )?;
+    render_report_with(&data, &html_path, &options)?;
     std::fs::write(&bundle_path, serde_json::to_string_pretty(&data.bundle)?)?;
     std::fs::write(&hash_path, format!("{}\n", reproducibility_hash_sha256))?;
+    if args.export {
+        write_exports(&args.reports_dir, "demo", &data)?;
+    }
     info!(?html_path, ?bundle_path, ?hash_path, "demo report complete");
     println!("Demo report written to {}", html_path.display());
     Ok(())
 }
 
 fn run_verify(args: VerifyArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let bundle_json = std::fs::read_to_string(&args.bundle)?;
-    let bundle: EvidenceBundle = serde_json::from_str(&bundle_json)?;
-    let computed = reproducibility_hash(&bundle)?;
-    let stem = args
-        .bundle
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy();
-    let hash_stem = stem.strip_suffix(".bundle").unwrap_or(stem.as_ref());
-    let sha256_path = args
-        .bundle
-        .parent()
-        .unwrap_or(std::path::Path::new("."))
-        .join(format!("{}.sha256", hash_stem));
-    let expected = std::fs::read_to_string(sha256_path)
-        .ok()
-        .map(|s| s.trim().to_string());
-    let result = if let Some(ref exp) = expected {
-        VerificationResult {
-            bundle_hash: computed.clone(),
-            expected_hash: Some(exp.clone()),
-            matches: computed.to_lowercase() == exp.to_lowercase(),
-        }
-    } else {
-        VerificationResult {
-            bundle_hash: computed.clone(),
-            expected_hash: None,
-            matches: false,
-        }
+    if let Some(dir) = &args.watch {
+        return run_verify_watch(&args, dir);
+    }
+    let Some(bundle_path) = &args.bundle else {
+        return Err("either --bundle or --watch is required".into());
     };
+    let (bundle, result) = verify_bundle_file(bundle_path)?;
+    let network = bundle.network();
+    if !network.matches_address(&bundle.address) {
+        eprintln!("MISMATCH\taddress is not a {} address", network);
+        std::process::exit(1);
+    }
+    if let Some(expected) = args.network.filter(|n| *n != network) {
+        eprintln!("MISMATCH\tbundle network={} expected={}", network, expected);
+        std::process::exit(1);
+    }
+    if args.crosscheck {
+        run_crosscheck(&args, bundle_path, &bundle)?;
+    }
+    if args.deep {
+        let differences = verify_deep(bundle_path, &bundle)?;
+        if !differences.is_empty() {
+            eprintln!(
+                "MISMATCH\tdeep replay differs in {}",
+                differences.join(", ")
+            );
+            std::process::exit(1);
+        }
+        info!("deep verification: replayed bundle matches");
+    }
     if result.matches {
         println!("OK\t{}", result.bundle_hash);
     } else {
@@ -322,3 +1706,193 @@ fn run_verify(args: VerifyArgs) -> Result<(), Box<dyn std::error::Error>> {
     }
     Ok(())
 }
+
+/// Rebuild `bundle` from its responses archive alone (same provider, network, addresses, bounds,
+/// and confirmation depth) and return the top-level fields that come out different.
+fn verify_deep(
+    bundle_path: &std::path::Path,
+    bundle: &EvidenceBundle,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if let Some(predecessor) = &bundle.predecessor_sha256 {
+        return Err(format!(
+            "--deep: bundle extends {}; only bundles computed in full can be replayed",
+            predecessor
+        )
+        .into());
+    }
+    let archive = responses_path(bundle_path);
+    let responses = read_responses_archive(&archive)
+        .map_err(|e| format!("--deep: {}: {}", archive.display(), e))?;
+    let provider = bundle
+        .provider
+        .as_deref()
+        .ok_or("--deep: bundle does not record its provider")?;
+    let config = FetchConfig {
+        min_confirmations: bundle.min_confirmations.unwrap_or(0),
+        ..Default::default()
+    };
+    let fetcher = Fetcher::from_responses(config, provider, bundle.network(), &responses)?;
+    let created = OffsetDateTime::parse(
+        &bundle.created_utc_rfc3339,
+        &time::format_description::well_known::Rfc3339,
+    )?;
+    let addresses = if bundle.addresses.is_empty() {
+        vec![bundle.address.clone()]
+    } else {
+        bundle.addresses.clone()
+    };
+    let rt = tokio::runtime::Runtime::new()?;
+    let (mut rebuilt, _) = build_bundle(
+        &fetcher,
+        &rt,
+        &addresses,
+        bundle.from.as_deref(),
+        bundle.to.as_deref(),
+        &BundleOptions {
+            scan_scripts: bundle.scan_scripts,
+            overrides: bundle.overrides.clone(),
+            ada_usd: bundle.ada_usd_prices.clone(),
+            mark_to_market: bundle.open_position.is_some(),
+            cost_basis: bundle.metrics.lots.as_ref().map(|l| l.method),
+            annualization: bundle.metrics.combined.annualization,
+            since_last_claim: bundle.since_claim_tx.is_some(),
+            staking_benchmark: bundle
+                .metrics
+                .combined
+                .benchmark
+                .as_ref()
+                .map(|b| b.staking_rate.clone()),
+            statements: bundle.metrics.statements.is_some(),
+            max_premium_pct: bundle.max_premium_pct,
+            currency: bundle.currency.clone(),
+            prior: None,
+        },
+        created,
+    )?;
+    rebuilt.label = bundle.label.clone();
+    if let Some(price) = bundle.metrics.indy_staking.indy_price_ada {
+        rebuilt.metrics.indy_staking.price_indy(price);
+    }
+    rebuilt.created_utc_rfc3339 = bundle.created_utc_rfc3339.clone();
+    // Hooks are library code the CLI cannot run; their outputs are taken as recorded.
+    rebuilt.metrics.extra_metrics = bundle.metrics.extra_metrics.clone();
+    if bundle.period_start_ts.is_none() {
+        // Recorded since; these bundles only set the period implicitly.
+        rebuilt.period_start_ts = None;
+        rebuilt.period_end_ts = None;
+    }
+    Ok(bundle_differences(bundle, &rebuilt)?)
+}
+
+/// Compare bundle metrics with Indigo analytics; prints and writes `<stem>.crosscheck.json`. Never affects the verdict.
+fn run_crosscheck(
+    args: &VerifyArgs,
+    bundle_path: &std::path::Path,
+    bundle: &EvidenceBundle,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = args
+        .analytics_url
+        .clone()
+        .or_else(|| std::env::var("INDIGO_ANALYTICS_URL").ok())
+        .unwrap_or_else(|| DEFAULT_ANALYTICS_URL.to_string());
+    let from = bundle
+        .events
+        .all_events()
+        .map(|e| e.timestamp.unix_timestamp())
+        .min();
+    let to = bundle
+        .events
+        .all_events()
+        .map(|e| e.timestamp.unix_timestamp())
+        .max();
+    let rt = tokio::runtime::Runtime::new()?;
+    let snapshot = match rt.block_on(fetch_analytics_snapshot(
+        &base_url,
+        &bundle.address,
+        from,
+        to,
+    )) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!(error = %e, "cross-check skipped: analytics unavailable");
+            return Ok(());
+        }
+    };
+    let report = compare_with_analytics(
+        &bundle.metrics,
+        &snapshot,
+        &base_url,
+        args.crosscheck_tolerance_pct,
+    );
+    println!("CROSS-CHECK (non-evidentiary, source {})", report.source);
+    for item in &report.items {
+        println!(
+            "{}\t{}\tcomputed={:.2}\texternal={:.2}\tdeviation={:.1}%",
+            if item.flagged { "FLAG" } else { "ok" },
+            item.metric,
+            item.computed,
+            item.external,
+            item.deviation_pct
+        );
+    }
+    let out = crosscheck_path(bundle_path);
+    if out == bundle_path {
+        return Err(format!("crosscheck: {} would overwrite the bundle", out.display()).into());
+    }
+    std::fs::write(out, serde_json::to_string_pretty(&report)?)?;
+    Ok(())
+}
+
+/// `x.bundle.json` → `x.crosscheck.json`; any other name gets `.crosscheck.json` appended.
+fn crosscheck_path(bundle_path: &std::path::Path) -> PathBuf {
+    let name = file_name(bundle_path);
+    let stem = name.strip_suffix(".bundle.json").unwrap_or(&name);
+    bundle_path.with_file_name(format!("{}.crosscheck.json", stem))
+}
+
+fn run_verify_watch(
+    args: &VerifyArgs,
+    dir: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let notifiers = build_notifiers(args.notify_webhook.as_deref(), args.notify_slack.as_deref())?;
+    let rt = tokio::runtime::Runtime::new()?;
+    info!(dir = %dir.display(), poll_secs = args.poll_secs, "watching for bundles");
+    loop {
+        // A file that cannot be read yet (e.g. still being copied) is tried again next poll.
+        let mut failed = 0;
+        let pending = pending_bundles(dir).unwrap_or_else(|e| {
+            warn!(dir = %dir.display(), error = %e, "cannot list bundles; retrying next poll");
+            failed += 1;
+            vec![]
+        });
+        for bundle_path in pending {
+            let verdict = match verify_and_write_verdict(&bundle_path) {
+                Ok(verdict) => verdict,
+                Err(e) => {
+                    warn!(bundle = %bundle_path.display(), error = %e, "bundle skipped; retrying next poll");
+                    failed += 1;
+                    continue;
+                }
+            };
+            let status = if verdict.ok() { "OK" } else { "MISMATCH" };
+            println!("{}\t{}", status, bundle_path.display());
+            if !notifiers.is_empty() {
+                let mut n =
+                    Notification::new(format!("Bundle {}: {}", status, verdict.bundle_file));
+                n.address = verdict.address.clone();
+                n.bundle_hash = verdict.result.as_ref().map(|r| r.bundle_hash.clone());
+                if let Some(err) = &verdict.error {
+                    n = n.field("error", err.clone());
+                }
+                rt.block_on(notify_all(&notifiers, &n));
+            }
+        }
+        if args.once {
+            return match failed {
+                0 => Ok(()),
+                n => Err(format!("verify --watch: {} read error(s)", n).into()),
+            };
+        }
+        std::thread::sleep(Duration::from_secs(args.poll_secs.max(1)));
+    }
+}
@@ -0,0 +1,207 @@
+//! Inline SVG charts drawn from the per-epoch statements: the equity curve and the yield of
+//! each epoch. Plain SVG markup with `<title>` tooltips, so the report needs no script or
+//! network to show them. Colours other than the bars' come from the theme's `.chart` rules.
+
+use indigo_poy::compute::statement::Statement;
+use indigo_poy::ReportData;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 180.0;
+/// Room for the axis labels on the left and below.
+const LEFT: f64 = 64.0;
+const BOTTOM: f64 = 20.0;
+const TOP: f64 = 8.0;
+
+/// Chart card over `metrics.statements.epochs`; empty without statements or with a single
+/// epoch, where a chart shows nothing the summary does not.
+pub(crate) fn charts_card(data: &ReportData) -> String {
+    let Some(statements) = &data.bundle.metrics.statements else {
+        return String::new();
+    };
+    let epochs = &statements.epochs;
+    if epochs.len() < 2 {
+        return String::new();
+    }
+    format!(
+        "\n<h2>Charts</h2>\n<div class=\"card\">\n  <p class=\"label\">Equity: principal at work plus yield to date, at each epoch end (ADA)</p>\n{}  <p class=\"label\">Yield per epoch, after protocol fees (ADA)</p>\n{}</div>\n",
+        equity_curve(epochs),
+        yield_bars(epochs)
+    )
+}
+
+/// Principal at work plus the yield earned so far, at the end of each epoch, in lovelace.
+fn equity(epochs: &[Statement]) -> Vec<i64> {
+    let mut earned = 0i64;
+    epochs
+        .iter()
+        .map(|s| {
+            earned = earned.saturating_add(s.yield_lovelace);
+            s.closing_balance_lovelace.saturating_add(earned)
+        })
+        .collect()
+}
+
+fn equity_curve(epochs: &[Statement]) -> String {
+    let values = equity(epochs);
+    let scale = Scale::new(&values);
+    let step = (WIDTH - LEFT) / (values.len() - 1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| format!("{:.1},{:.1}", LEFT + i as f64 * step, scale.y(v)))
+        .collect();
+    let dots: String = values
+        .iter()
+        .zip(epochs)
+        .enumerate()
+        .map(|(i, (&v, s))| {
+            format!(
+                "    <circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2\" class=\"dot\"><title>Epoch {}: {} ADA</title></circle>\n",
+                LEFT + i as f64 * step,
+                scale.y(v),
+                crate::escape_html(&s.period),
+                ada(v)
+            )
+        })
+        .collect();
+    svg(
+        "Equity curve",
+        &scale,
+        epochs,
+        &format!(
+            "    <polyline class=\"line\" fill=\"none\" stroke-width=\"2\" points=\"{}\"/>\n{}",
+            points.join(" "),
+            dots
+        ),
+    )
+}
+
+fn yield_bars(epochs: &[Statement]) -> String {
+    let values: Vec<i64> = epochs.iter().map(|s| s.yield_lovelace).collect();
+    let scale = Scale::new(&values);
+    let slot = (WIDTH - LEFT) / values.len() as f64;
+    let zero = scale.y(0);
+    let bars: String = values
+        .iter()
+        .zip(epochs)
+        .enumerate()
+        .map(|(i, (&v, s))| {
+            let y = scale.y(v);
+            format!(
+                "    <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>Epoch {}: {} ADA</title></rect>\n",
+                LEFT + i as f64 * slot + slot * 0.1,
+                y.min(zero),
+                slot * 0.8,
+                (zero - y).abs().max(0.5),
+                if v < 0 { "#f85149" } else { "#3fb950" },
+                crate::escape_html(&s.period),
+                ada(v)
+            )
+        })
+        .collect();
+    svg("Yield per epoch", &scale, epochs, &bars)
+}
+
+/// The frame both charts share: the value axis with its bounds and zero line, the first and
+/// last epoch under it, and `body`.
+fn svg(title: &str, scale: &Scale, epochs: &[Statement], body: &str) -> String {
+    let mut axis = format!(
+        "    <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n    <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>\n",
+        LEFT - 6.0,
+        TOP + 4.0,
+        ada(scale.max),
+        LEFT - 6.0,
+        HEIGHT - BOTTOM,
+        ada(scale.min)
+    );
+    if scale.min < 0 && scale.max > 0 {
+        axis.push_str(&format!(
+            "    <line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" class=\"axis\"/>\n",
+            LEFT,
+            WIDTH,
+            y = scale.y(0)
+        ));
+    }
+    let first = epochs.first().map(|s| s.period.as_str()).unwrap_or("");
+    let last = epochs.last().map(|s| s.period.as_str()).unwrap_or("");
+    format!(
+        "  <svg viewBox=\"0 0 {w} {h}\" width=\"100%\" role=\"img\" aria-label=\"{title}\" font-size=\"11\" class=\"chart\">\n    <line x1=\"{left}\" y1=\"{base}\" x2=\"{w}\" y2=\"{base}\" class=\"axis\"/>\n{axis}    <text x=\"{left}\" y=\"{h}\">Epoch {first}</text>\n    <text x=\"{w}\" y=\"{h}\" text-anchor=\"end\">Epoch {last}</text>\n{body}  </svg>\n",
+        w = WIDTH,
+        h = HEIGHT,
+        left = LEFT,
+        base = HEIGHT - BOTTOM,
+        title = title,
+        axis = axis,
+        first = crate::escape_html(first),
+        last = crate::escape_html(last),
+        body = body
+    )
+}
+
+/// Lovelace to the chart's y coordinate, the range always taking in zero.
+struct Scale {
+    min: i64,
+    max: i64,
+}
+
+impl Scale {
+    fn new(values: &[i64]) -> Self {
+        let min = values.iter().copied().min().unwrap_or(0).min(0);
+        let max = values.iter().copied().max().unwrap_or(0).max(0);
+        Self { min, max }
+    }
+
+    fn y(&self, v: i64) -> f64 {
+        let span = (self.max as f64 - self.min as f64).max(1.0);
+        let plot = HEIGHT - BOTTOM - TOP;
+        TOP + (self.max as f64 - v as f64) / span * plot
+    }
+}
+
+/// Lovelace as ADA with two decimals.
+fn ada(lovelace: i64) -> String {
+    format!("{:.2}", lovelace as f64 / 1_000_000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indigo_poy::compute::statement::Statements;
+    use indigo_poy::EvidenceBundle;
+
+    fn epoch(period: &str, closing: i64, yield_lovelace: i64) -> Statement {
+        Statement {
+            period: period.into(),
+            closing_balance_lovelace: closing,
+            yield_lovelace,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn charts_draw_one_mark_per_epoch() {
+        let mut bundle = EvidenceBundle::demo();
+        let data = |bundle: &EvidenceBundle| ReportData {
+            bundle: bundle.clone(),
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        assert_eq!(charts_card(&data(&bundle)), "");
+
+        let epochs = vec![
+            epoch("500", 100_000_000, 2_000_000),
+            epoch("501", 100_000_000, -500_000),
+            epoch("502", 50_000_000, 3_000_000),
+        ];
+        assert_eq!(equity(&epochs), [102_000_000, 101_500_000, 54_500_000]);
+        bundle.metrics.statements = Some(Statements {
+            monthly: vec![],
+            epochs,
+        });
+        let html = charts_card(&data(&bundle));
+        assert_eq!(html.matches("<circle").count(), 3);
+        assert_eq!(html.matches("<rect").count(), 3);
+        assert!(html.contains("<title>Epoch 501: -0.50 ADA</title>"));
+        assert!(html.contains("fill=\"#f85149\""));
+        assert!(!html.contains("<script"));
+    }
+}
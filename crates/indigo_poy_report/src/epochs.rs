@@ -0,0 +1,188 @@
+//! Epoch-by-epoch breakdown: what went in and what the wallet earned in each epoch it was
+//! active, with the totals under it.
+
+use indigo_poy::chain::assets::format_decimal;
+use indigo_poy::compute::fixed::percent_of;
+use indigo_poy::indigo::EventKind;
+use indigo_poy::ReportData;
+use std::collections::BTreeMap;
+
+/// Longer histories start collapsed.
+const OPEN_UP_TO: usize = 12;
+
+/// One epoch's figures, in lovelace.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct EpochRow {
+    /// SP deposits and ROB orders placed.
+    deposits_lovelace: u64,
+    liquidation_count: u64,
+    /// Realized SP premium and ROB fill premium.
+    premium_lovelace: u64,
+    /// INDY staking rewards and the SP premium paid through INDY staking.
+    rewards_lovelace: u64,
+}
+
+impl EpochRow {
+    fn add(&mut self, other: &EpochRow) {
+        self.deposits_lovelace = self
+            .deposits_lovelace
+            .saturating_add(other.deposits_lovelace);
+        self.liquidation_count += other.liquidation_count;
+        self.premium_lovelace = self.premium_lovelace.saturating_add(other.premium_lovelace);
+        self.rewards_lovelace = self.rewards_lovelace.saturating_add(other.rewards_lovelace);
+    }
+}
+
+/// The epochs (on the bundle's network) that saw an event, oldest first.
+fn epoch_rows(data: &ReportData) -> BTreeMap<u64, EpochRow> {
+    let network = data.bundle.network();
+    let mut rows: BTreeMap<u64, EpochRow> = BTreeMap::new();
+    for ev in data.bundle.events.all_events() {
+        let row = rows
+            .entry(network.epoch_at(ev.timestamp.unix_timestamp()))
+            .or_default();
+        match &ev.kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace, ..
+            }
+            | EventKind::RobOrderPlace {
+                amount_lovelace, ..
+            } => row.deposits_lovelace = row.deposits_lovelace.saturating_add(*amount_lovelace),
+            EventKind::StabilityPoolLiquidation {
+                realized_premium_lovelace,
+                ..
+            } => {
+                row.liquidation_count += 1;
+                row.premium_lovelace = row
+                    .premium_lovelace
+                    .saturating_add(*realized_premium_lovelace);
+            }
+            EventKind::RobOrderFill {
+                filled_lovelace,
+                premium_pct,
+                ..
+            } => {
+                let premium = percent_of(*filled_lovelace, premium_pct.unwrap_or(0.0));
+                row.premium_lovelace = row.premium_lovelace.saturating_add(premium);
+            }
+            EventKind::IndyStakingReward {
+                amount_lovelace, ..
+            }
+            | EventKind::IndySpPremium {
+                amount_lovelace, ..
+            } => row.rewards_lovelace = row.rewards_lovelace.saturating_add(*amount_lovelace),
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// The breakdown card, in a `<details>` that starts open for short histories; empty without
+/// events.
+pub(crate) fn epochs_card(data: &ReportData) -> String {
+    let rows = epoch_rows(data);
+    if rows.is_empty() {
+        return String::new();
+    }
+    let mut total = EpochRow::default();
+    let mut table = String::new();
+    for (epoch, row) in &rows {
+        total.add(row);
+        table.push_str(&table_row(&epoch.to_string(), row, "td"));
+    }
+    table.push_str(&table_row("Total", &total, "th"));
+    format!(
+        "\n<h2>Per epoch</h2>\n<div class=\"card\">\n  <details{}>\n  <summary>{} epoch(s) with activity</summary>\n  <table>\n    <tr><th>Epoch</th><th>Deposits (ADA)</th><th>Liquidations</th><th>Premium (ADA)</th><th>Rewards (ADA)</th></tr>\n{}  </table>\n  </details>\n</div>\n",
+        if rows.len() <= OPEN_UP_TO { " open" } else { "" },
+        rows.len(),
+        table
+    )
+}
+
+fn table_row(label: &str, row: &EpochRow, cell: &str) -> String {
+    let ada = |l: u64| format_decimal(u128::from(l), 6);
+    format!(
+        "    <tr><{c}>{}</{c}><{c} class=\"mono\">{}</{c}><{c}>{}</{c}><{c} class=\"mono\">{}</{c}><{c} class=\"mono\">{}</{c}></tr>\n",
+        label,
+        ada(row.deposits_lovelace),
+        row.liquidation_count,
+        ada(row.premium_lovelace),
+        ada(row.rewards_lovelace),
+        c = cell
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indigo_poy::EvidenceBundle;
+
+    fn event(ts: &str, kind: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "kind": kind,
+            "timestamp": ts,
+            "slot": null,
+            "tx_hash": "t",
+            "extra": null
+        })
+    }
+
+    #[test]
+    fn epochs_add_up_to_the_totals() {
+        let mut bundle = EvidenceBundle::demo();
+        // Mainnet epochs 471 (from 2024-03-05 21:44:51 UTC) and 472 (from 2024-03-10).
+        bundle.events = serde_json::from_value(serde_json::json!({
+            "stability_pool": [
+                event("2024-03-07T00:00:00Z", serde_json::json!({
+                    "kind": "stability_pool_deposit",
+                    "amount_lovelace": 100_000_000,
+                    "iasset_amount": null,
+                    "tx_hash": "t"
+                })),
+                event("2024-03-12T00:00:00Z", serde_json::json!({
+                    "kind": "stability_pool_liquidation",
+                    "iasset_burnt": "unknown",
+                    "ada_received_lovelace": 11_000_000,
+                    "realized_premium_lovelace": 1_000_000,
+                    "dilution_effect": null,
+                    "tx_hash": "t",
+                    "slot": null
+                })),
+            ],
+            "rob": [
+                event("2024-03-07T01:00:00Z", serde_json::json!({
+                    "kind": "rob_order_fill",
+                    "order_id": null,
+                    "filled_lovelace": 10_000_000,
+                    "premium_pct": 5.0,
+                    "reimbursement_pct": null,
+                    "tx_hash": "t",
+                    "slot": null
+                })),
+            ],
+            "indy_staking": [
+                event("2024-03-12T01:00:00Z", serde_json::json!({
+                    "kind": "indy_staking_reward",
+                    "amount_lovelace": 2_000_000,
+                    "epoch": null,
+                    "tx_hash": "t"
+                })),
+            ],
+            "other": []
+        }))
+        .unwrap();
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let rows = epoch_rows(&data);
+        assert_eq!(rows.keys().copied().collect::<Vec<_>>(), [471, 472]);
+        assert_eq!(rows[&471].premium_lovelace, 500_000);
+        assert_eq!(rows[&472].liquidation_count, 1);
+        let html = epochs_card(&data);
+        assert!(html.contains("<details open>"));
+        assert!(html.contains(
+            "<tr><th>Total</th><th class=\"mono\">100</th><th>1</th><th class=\"mono\">1.5</th><th class=\"mono\">2</th></tr>"
+        ));
+    }
+}
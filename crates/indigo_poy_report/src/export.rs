@@ -0,0 +1,302 @@
+//! Machine-readable exports next to the HTML: the metrics as JSON and the events as a flat CSV,
+//! for spreadsheets and scripts that should not parse the bundle.
+
+use crate::ReportError;
+use indigo_poy::indigo::{Event, EventKind};
+use indigo_poy::ReportData;
+use std::path::Path;
+
+/// Header of [`export_csv`].
+pub const CSV_HEADER: &str =
+    "timestamp,slot,tx_hash,kind,ada_lovelace,premium_lovelace,asset,quantity,confidence";
+
+/// The metrics with the address, period, and reproducibility hash they belong to, as pretty
+/// JSON.
+pub fn export_json(data: &ReportData) -> Result<String, ReportError> {
+    let bundle = &data.bundle;
+    let value = serde_json::json!({
+        "address": bundle.address,
+        "label": bundle.label,
+        "created_utc_rfc3339": bundle.created_utc_rfc3339,
+        "period_start_ts": bundle.period_start_ts,
+        "period_end_ts": bundle.period_end_ts,
+        "reproducibility_hash_sha256": data.reproducibility_hash_sha256,
+        "metrics": bundle.metrics,
+    });
+    serde_json::to_string_pretty(&value).map_err(ReportError::Json)
+}
+
+/// One row per event, in time order (slot, then tx hash), under [`CSV_HEADER`]. `ada_lovelace`
+/// is the event's ADA amount (deposited, received, placed, paid, ...); `asset` and `quantity`
+/// are the iAsset or INDY it moved. Cells an event has no figure for are empty.
+pub fn export_csv(data: &ReportData) -> String {
+    let mut out = format!("{}\n", CSV_HEADER);
+    for ev in events_in_time_order(data) {
+        let row = EventRow::of(&ev.kind);
+        let cells = [
+            ev.timestamp.unix_timestamp().to_string(),
+            opt(ev.slot),
+            ev.tx_hash.clone(),
+            kind_name(&ev.kind),
+            opt(row.ada_lovelace),
+            opt(row.premium_lovelace),
+            row.asset.unwrap_or_default().to_string(),
+            opt(row.quantity),
+            serde_json::to_value(ev.confidence)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        ];
+        let cells: Vec<String> = cells.iter().map(|c| escape_csv(c)).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Write [`export_json`] to `out_path`.
+pub fn write_json_export(data: &ReportData, out_path: impl AsRef<Path>) -> Result<(), ReportError> {
+    std::fs::write(out_path.as_ref(), export_json(data)?).map_err(ReportError::Io)
+}
+
+/// Write [`export_csv`] to `out_path`.
+pub fn write_csv_export(data: &ReportData, out_path: impl AsRef<Path>) -> Result<(), ReportError> {
+    std::fs::write(out_path.as_ref(), export_csv(data)).map_err(ReportError::Io)
+}
+
+/// The bundle's events, sorted by time, slot, then tx hash.
+pub(crate) fn events_in_time_order(data: &ReportData) -> Vec<&Event> {
+    let mut events: Vec<&Event> = data.bundle.events.all_events().collect();
+    events.sort_by(|a, b| {
+        let key = |e: &Event| (e.timestamp, e.slot, e.tx_hash.clone());
+        key(a).cmp(&key(b))
+    });
+    events
+}
+
+/// The figures of one event that fit the flat columns.
+#[derive(Default)]
+pub(crate) struct EventRow<'a> {
+    pub ada_lovelace: Option<u64>,
+    pub premium_lovelace: Option<u64>,
+    /// iAsset unit (`policy$asset_name`), or `INDY`.
+    pub asset: Option<&'a str>,
+    pub quantity: Option<u128>,
+}
+
+impl<'a> EventRow<'a> {
+    pub fn of(kind: &'a EventKind) -> Self {
+        let ada = |l: u64| Self {
+            ada_lovelace: Some(l),
+            ..Default::default()
+        };
+        match kind {
+            EventKind::StabilityPoolDeposit {
+                amount_lovelace,
+                iasset_amount,
+                iasset_quantity,
+                ..
+            }
+            | EventKind::StabilityPoolWithdraw {
+                amount_lovelace,
+                iasset_amount,
+                iasset_quantity,
+                ..
+            } => Self {
+                ada_lovelace: Some(*amount_lovelace),
+                asset: iasset_amount.as_deref(),
+                quantity: *iasset_quantity,
+                ..Default::default()
+            },
+            EventKind::StabilityPoolLiquidation {
+                iasset_burnt,
+                ada_received_lovelace,
+                realized_premium_lovelace,
+                iasset_lost_quantity,
+                ..
+            } => Self {
+                ada_lovelace: Some(*ada_received_lovelace),
+                premium_lovelace: Some(*realized_premium_lovelace),
+                asset: Some(iasset_burnt),
+                quantity: *iasset_lost_quantity,
+            },
+            EventKind::RobOrderPlace {
+                amount_lovelace, ..
+            } => ada(*amount_lovelace),
+            EventKind::RobOrderFill {
+                filled_lovelace, ..
+            } => ada(*filled_lovelace),
+            EventKind::RobOrderCancel {
+                returned_lovelace, ..
+            } => ada(*returned_lovelace),
+            EventKind::IndyStakingReward {
+                amount_lovelace,
+                indy_quantity,
+                ..
+            }
+            | EventKind::IndyAirdrop {
+                amount_lovelace,
+                indy_quantity,
+                ..
+            }
+            | EventKind::IndyVestingUnlock {
+                amount_lovelace,
+                indy_quantity,
+                ..
+            } => Self {
+                ada_lovelace: Some(*amount_lovelace),
+                asset: indy_quantity.map(|_| "INDY"),
+                quantity: indy_quantity.map(u128::from),
+                ..Default::default()
+            },
+            EventKind::IndySpPremium {
+                amount_lovelace, ..
+            }
+            | EventKind::CdpDeposit {
+                amount_lovelace, ..
+            }
+            | EventKind::CdpWithdraw {
+                amount_lovelace, ..
+            }
+            | EventKind::CdpFee {
+                amount_lovelace, ..
+            }
+            | EventKind::ProtocolFeePaid {
+                amount_lovelace, ..
+            } => ada(*amount_lovelace),
+            EventKind::CdpOpen {
+                collateral_lovelace,
+                ..
+            } => ada(*collateral_lovelace),
+            EventKind::CdpClose {
+                collateral_returned_lovelace,
+                ..
+            } => ada(*collateral_returned_lovelace),
+            EventKind::CdpMint {
+                iasset, quantity, ..
+            }
+            | EventKind::CdpBurn {
+                iasset, quantity, ..
+            } => Self {
+                asset: Some(iasset),
+                quantity: Some(*quantity as u128),
+                ..Default::default()
+            },
+            EventKind::CdpRedeemedAgainst {
+                iasset,
+                redeemed_quantity,
+                ada_returned_lovelace,
+                ..
+            } => Self {
+                ada_lovelace: Some(*ada_returned_lovelace),
+                asset: Some(iasset),
+                quantity: Some(*redeemed_quantity as u128),
+                ..Default::default()
+            },
+            EventKind::GovStakeLock { indy_quantity, .. }
+            | EventKind::GovStakeUnlock { indy_quantity, .. }
+            | EventKind::GovProposalDeposit { indy_quantity, .. } => Self {
+                asset: Some("INDY"),
+                quantity: Some(*indy_quantity as u128),
+                ..Default::default()
+            },
+            EventKind::OtherFlow {
+                amount_lovelace, ..
+            } => Self {
+                ada_lovelace: *amount_lovelace,
+                ..Default::default()
+            },
+            EventKind::RobCooldown { .. } | EventKind::GovVote { .. } => Self::default(),
+        }
+    }
+}
+
+/// The `kind` tag the event serializes with (e.g. `stability_pool_deposit`).
+pub(crate) fn kind_name(kind: &EventKind) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|v| Some(v.get("kind")?.as_str()?.to_string()))
+        .unwrap_or_default()
+}
+
+fn opt<T: ToString>(x: Option<T>) -> String {
+    x.map(|x| x.to_string()).unwrap_or_default()
+}
+
+/// Quote a cell holding a comma, quote, or line break (RFC 4180).
+fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indigo_poy::EvidenceBundle;
+
+    #[test]
+    fn csv_has_one_row_per_event_in_time_order() {
+        let mut bundle = EvidenceBundle::demo();
+        // The SP list comes first, so the ROB fill at t=1 has to be sorted ahead of it.
+        bundle.events = serde_json::from_value(serde_json::json!({
+            "stability_pool": [{
+                "kind": {
+                    "kind": "stability_pool_liquidation",
+                    "iasset_burnt": "pid$69555344",
+                    "ada_received_lovelace": 11_000_000,
+                    "realized_premium_lovelace": 1_000_000,
+                    "dilution_effect": null,
+                    "tx_hash": "liq",
+                    "slot": 2
+                },
+                "timestamp": "1970-01-01T00:00:02Z",
+                "slot": 2,
+                "tx_hash": "liq",
+                "extra": null,
+                "confidence": "high"
+            }],
+            "rob": [{
+                "kind": {
+                    "kind": "rob_order_fill",
+                    "order_id": null,
+                    "filled_lovelace": 5_000_000,
+                    "premium_pct": 1.0,
+                    "reimbursement_pct": null,
+                    "tx_hash": "fill",
+                    "slot": 1
+                },
+                "timestamp": "1970-01-01T00:00:01Z",
+                "slot": 1,
+                "tx_hash": "fill",
+                "extra": null
+            }],
+            "indy_staking": [],
+            "other": []
+        }))
+        .unwrap();
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let csv = export_csv(&data);
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            [
+                CSV_HEADER,
+                "1,1,fill,rob_order_fill,5000000,,,,heuristic",
+                "2,2,liq,stability_pool_liquidation,11000000,1000000,pid$69555344,,high",
+            ]
+        );
+        assert_eq!(escape_csv("a,\"b\""), "\"a,\"\"b\"\"\"");
+
+        let json: serde_json::Value = serde_json::from_str(&export_json(&data).unwrap()).unwrap();
+        assert_eq!(json["reproducibility_hash_sha256"], "a".repeat(64));
+        assert_eq!(
+            json["metrics"]["combined"]["net_pnl_lovelace"],
+            data.bundle.metrics.combined.net_pnl_lovelace
+        );
+    }
+}
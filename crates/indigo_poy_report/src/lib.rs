@@ -1,12 +1,116 @@
-//! Static HTML report generation from proof-of-yield evidence bundle.
+//! Static HTML report generation from proof-of-yield evidence bundle, with JSON and CSV
+//! exports of the same figures.
 
+use indigo_poy::chain::assets::format_decimal;
+use indigo_poy::chain::network::Network;
+use indigo_poy::compute::annualize::DayCount;
+use indigo_poy::report::leaderboard::Leaderboard;
+use indigo_poy::report::portfolio::Portfolio;
 use indigo_poy::ReportData;
 use std::io::Write;
 use std::path::Path;
 
+mod charts;
+mod epochs;
+mod export;
+mod timeline;
+pub use export::{export_csv, export_json, write_csv_export, write_json_export, CSV_HEADER};
+
+/// The built-in report template ([Tera](https://keats.github.io/tera/) syntax); a custom
+/// template gets the same variables.
+pub const DEFAULT_TEMPLATE: &str = include_str!("../templates/report.html");
+
+/// Stylesheet shared by every HTML page: dark and light colours as CSS variables, picked by
+/// `prefers-color-scheme` unless `<html data-theme>` says otherwise, and black on white in print.
+pub const THEME_CSS: &str = include_str!("../templates/theme.css");
+
+/// Script behind the report's "Verify now" button: hashes the embedded bundle as
+/// [`indigo_poy::verify::reproducibility_hash`] does, with its own SHA-256, so it works offline
+/// and from `file://`.
+pub const VERIFY_JS: &str = include_str!("../templates/verify.js");
+
+/// Colour scheme of the report page. Printing always uses the light print stylesheet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Follow the viewer's `prefers-color-scheme`.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Auto => "auto",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+}
+
+impl std::fmt::Display for Theme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(Theme::Auto),
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            other => Err(format!(
+                "unknown theme: {} (expected auto, light, or dark)",
+                other
+            )),
+        }
+    }
+}
+
+/// How the HTML is rendered. None of it is part of the bundle or its hash.
+#[derive(Clone, Debug, Default)]
+pub struct ReportOptions {
+    /// Template source; [`DEFAULT_TEMPLATE`] when `None`.
+    pub template: Option<String>,
+    /// Prefix the event timeline appends tx hashes to (e.g.
+    /// `https://cexplorer.io/tx/`); cardanoscan for the bundle's network when `None`.
+    pub explorer_tx_url: Option<String>,
+    pub theme: Theme,
+}
+
+impl ReportOptions {
+    fn template(&self) -> &str {
+        self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE)
+    }
+
+    fn explorer_tx_url(&self, network: Network) -> String {
+        self.explorer_tx_url.clone().unwrap_or_else(|| {
+            match network {
+                Network::Mainnet => "https://cardanoscan.io/transaction/",
+                Network::Preprod => "https://preprod.cardanoscan.io/transaction/",
+                Network::Preview => "https://preview.cardanoscan.io/transaction/",
+            }
+            .to_string()
+        })
+    }
+}
+
 /// Render a static HTML report to `out_path`. Embeds the full report JSON for verification.
 pub fn render_report(data: &ReportData, out_path: impl AsRef<Path>) -> Result<(), ReportError> {
-    let html = build_html(data)?;
+    render_report_with(data, out_path, &ReportOptions::default())
+}
+
+/// [`render_report`] with a custom template or explorer.
+pub fn render_report_with(
+    data: &ReportData,
+    out_path: impl AsRef<Path>,
+    options: &ReportOptions,
+) -> Result<(), ReportError> {
+    let html = build_html_with(data, options)?;
     let mut f = std::fs::File::create(out_path.as_ref()).map_err(ReportError::Io)?;
     f.write_all(html.as_bytes()).map_err(ReportError::Io)?;
     Ok(())
@@ -14,10 +118,42 @@ pub fn render_report(data: &ReportData, out_path: impl AsRef<Path>) -> Result<()
 
 /// Build HTML string from report data (for testing or in-memory use).
 pub fn build_html(data: &ReportData) -> Result<String, ReportError> {
+    build_html_with(data, &ReportOptions::default())
+}
+
+/// [`build_html`] with `options`. A template's text values are HTML-escaped; the rendered
+/// rows and cards are passed as HTML, for `| safe`.
+pub fn build_html_with(data: &ReportData, options: &ReportOptions) -> Result<String, ReportError> {
     let json_embed = serde_json::to_string(&data).map_err(ReportError::Json)?;
     let json_escaped = escape_json_in_html(&json_embed);
-    let addr_escaped = escape_html(&data.bundle.address);
-    let hash_escaped = escape_html(&data.reproducibility_hash_sha256);
+    let label_html = data
+        .bundle
+        .label
+        .as_deref()
+        .map(|l| format!("<p><strong>{}</strong></p>\n", escape_html(l)))
+        .unwrap_or_default();
+    let cdp_html = cdp_card(data);
+    let governance_html = governance_card(data);
+    let assets_html = assets_card(data);
+    let warnings_html = warnings_card(data);
+    let data_warnings_html = data_warnings_box(data);
+    let account_html = account_card(data);
+    let fiat_html = fiat_card(data);
+    let charts_html = charts::charts_card(data);
+    let epochs_html = epochs::epochs_card(data);
+    let timeline_html =
+        timeline::timeline_card(data, &options.explorer_tx_url(data.bundle.network()));
+    let return_rows = metrics_return_rows(data);
+    let staking_row = metrics_staking_row(data);
+    let fee_row = metrics_fee_row(data);
+    let confidence_row = metrics_confidence_row(data);
+    let attribution_rows = metrics_attribution_rows(data);
+    let rob_idle_rows = rob_idle_rows(data);
+    let indy_token_rows = indy_token_rows(data);
+    let sp_dilution_rows = sp_dilution_rows(data);
+    let sp_asset_table = sp_asset_table(data);
+    let sp_liquidation_pnl_rows = sp_liquidation_pnl_rows(data);
+    let indy_distribution_rows = indy_distribution_rows(data);
 
     let metrics = &data.bundle.metrics;
     let sp = &metrics.stability_pool;
@@ -39,128 +175,896 @@ pub fn build_html(data: &ReportData) -> Result<String, ReportError> {
         .map(|x| format!("{:.1}", x))
         .unwrap_or_else(|| "—".to_string());
 
-    let html = format!(
+    let opt_pct = |x: Option<f64>| {
+        x.map(|x| format!("{:.2}%", x))
+            .unwrap_or_else(|| "—".to_string())
+    };
+
+    let mut ctx = tera::Context::new();
+    ctx.insert("bundle", &data.bundle);
+    ctx.insert(
+        "reproducibility_hash_sha256",
+        &data.reproducibility_hash_sha256,
+    );
+    ctx.insert("addr", &data.bundle.address);
+    ctx.insert("label_html", &label_html);
+    ctx.insert("cdp_html", &cdp_html);
+    ctx.insert("governance_html", &governance_html);
+    ctx.insert("assets_html", &assets_html);
+    ctx.insert("warnings_html", &warnings_html);
+    ctx.insert("data_warnings_html", &data_warnings_html);
+    ctx.insert("account_html", &account_html);
+    ctx.insert("fiat_html", &fiat_html);
+    ctx.insert("charts_html", &charts_html);
+    ctx.insert("epochs_html", &epochs_html);
+    ctx.insert("timeline_html", &timeline_html);
+    ctx.insert("return_rows", &return_rows);
+    ctx.insert("staking_row", &staking_row);
+    ctx.insert("fee_row", &fee_row);
+    ctx.insert("confidence_row", &confidence_row);
+    ctx.insert("attribution_rows", &attribution_rows);
+    ctx.insert("rob_idle_rows", &rob_idle_rows);
+    ctx.insert("indy_token_rows", &indy_token_rows);
+    ctx.insert("sp_dilution_rows", &sp_dilution_rows);
+    ctx.insert("sp_asset_table", &sp_asset_table);
+    ctx.insert("sp_liquidation_pnl_rows", &sp_liquidation_pnl_rows);
+    ctx.insert("indy_distribution_rows", &indy_distribution_rows);
+    ctx.insert("created", &data.bundle.created_utc_rfc3339);
+    ctx.insert("hash", &data.reproducibility_hash_sha256);
+    ctx.insert("avg_liq_price", &avg_liq_price);
+    ctx.insert("apr_snippet", &apr_snippet);
+    ctx.insert("rob_avg_pct_snippet", &rob_avg_pct_snippet);
+    ctx.insert("net_pnl", &comb.net_pnl_lovelace);
+    ctx.insert("total_in", &comb.total_ada_in_lovelace);
+    ctx.insert("total_out", &comb.total_ada_out_lovelace);
+    ctx.insert("apr", &opt_pct(comb.apr_pct));
+    ctx.insert("sp_deposits", &sp.total_deposits_lovelace);
+    ctx.insert("sp_withdrawals", &sp.total_withdrawals_lovelace);
+    ctx.insert("sp_liq", &sp.total_liquidations_ada_received_lovelace);
+    ctx.insert("sp_premium", &sp.total_realized_premium_lovelace);
+    ctx.insert("sp_count", &sp.liquidation_count);
+    ctx.insert("rob_placed", &rob.total_placed_lovelace);
+    ctx.insert("rob_filled", &rob.total_filled_lovelace);
+    ctx.insert("rob_premium", &rob.total_premium_received_lovelace);
+    ctx.insert("rob_avg_pct", &opt_pct(rob.avg_premium_pct));
+    ctx.insert("rob_fill_count", &rob.fill_count);
+    ctx.insert("indy_rewards", &indy.total_rewards_lovelace);
+    ctx.insert("indy_sp", &indy.total_sp_premium_lovelace);
+    ctx.insert("indy_count", &indy.reward_tx_count);
+    ctx.insert("json_embed", &json_escaped);
+    ctx.insert("theme_css", THEME_CSS);
+    ctx.insert("verify_js", VERIFY_JS);
+    ctx.insert("theme", options.theme.as_str());
+    render_template(options.template(), &ctx)
+}
+
+/// Render `template` under an `.html` name, so Tera escapes every value not marked `| safe`
+/// whatever the file was called.
+fn render_template(template: &str, ctx: &tera::Context) -> Result<String, ReportError> {
+    let mut tera = tera::Tera::default();
+    tera.add_raw_template("report.html", template)
+        .map_err(ReportError::Template)?;
+    tera.render("report.html", ctx)
+        .map_err(ReportError::Template)
+}
+
+/// ROB rows for cancelled orders and time in cooldown, when there were any.
+fn rob_idle_rows(data: &ReportData) -> String {
+    let rob = &data.bundle.metrics.rob;
+    let mut rows = String::new();
+    if let Some(l) = rob.total_cancelled_lovelace {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Cancelled (lovelace)</span><span>{}</span>\n",
+            l
+        ));
+    }
+    if let Some(secs) = rob.time_in_cooldown_secs {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Time in cooldown</span><span>{:.1} days</span>\n",
+            secs as f64 / 86_400.0
+        ));
+    }
+    rows
+}
+
+/// SP rows for the wallet's latest pool share and the premium other depositors diluted away,
+/// when the period has pro-rata liquidations.
+fn sp_dilution_rows(data: &ReportData) -> String {
+    let Some(dilution) = &data.bundle.metrics.dilution else {
+        return String::new();
+    };
+    let mut rows = String::new();
+    if let Some(pct) = dilution.user_share_pct {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Pool share (last liquidation)</span><span>{:.2}%</span>\n",
+            pct
+        ));
+    }
+    if let Some(lovelace) = dilution.dilution_effect_lovelace {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Premium lost to dilution (lovelace)</span><span>{}</span>\n",
+            lovelace
+        ));
+    }
+    rows
+}
+
+/// SP rows for the P&L of the oracle-priced liquidations, when there were any.
+fn sp_liquidation_pnl_rows(data: &ReportData) -> String {
+    let sp = &data.bundle.metrics.stability_pool;
+    let Some(pnl) = sp.liquidation_pnl_lovelace else {
+        return String::new();
+    };
+    let losing = sp
+        .liquidations
+        .iter()
+        .filter(|l| l.pnl_lovelace < 0)
+        .count();
+    format!(
+        "    <span class=\"label\">Liquidation P&amp;L at oracle (lovelace)</span><span>{}</span>\n    <span class=\"label\">Liquidations at a loss</span><span>{} of {}</span>\n",
+        pnl,
+        losing,
+        sp.liquidations.len()
+    )
+}
+
+/// Per-iAsset table of the SP metrics, when the events name more than one pool.
+fn sp_asset_table(data: &ReportData) -> String {
+    let by_asset = &data.bundle.metrics.stability_pool.by_asset;
+    if by_asset.len() < 2 {
+        return String::new();
+    }
+    let mut rows = String::new();
+    for (unit, pool) in by_asset {
+        let name = data
+            .bundle
+            .assets
+            .get(unit)
+            .map_or_else(|| unit.clone(), |info| info.display_name());
+        rows.push_str(&format!(
+            "      <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&name),
+            pool.liquidation_count,
+            pool.realized_premium_lovelace,
+            pool.net_pnl_lovelace
+        ));
+    }
+    format!(
+        "  <table>\n      <tr><th>iAsset</th><th>Liquidations</th><th>Realized premium</th><th>Net PnL (lovelace)</th></tr>\n{}  </table>\n",
+        rows
+    )
+}
+
+/// INDY rows: token amount, and its ADA equivalent when a price was given.
+fn indy_token_rows(data: &ReportData) -> String {
+    let indy = &data.bundle.metrics.indy_staking;
+    let Some(quantity) = indy.total_indy_quantity else {
+        return String::new();
+    };
+    let mut rows = format!(
+        "    <span class=\"label\">INDY received</span><span>{}</span>\n",
+        format_decimal(u128::from(quantity), 6)
+    );
+    if let (Some(lovelace), Some(price)) =
+        (indy.total_indy_ada_equivalent_lovelace, indy.indy_price_ada)
+    {
+        rows.push_str(&format!(
+            "    <span class=\"label\">INDY ADA equivalent (lovelace, at {} ADA)</span><span>{}</span>\n",
+            price, lovelace
+        ));
+    }
+    rows
+}
+
+/// INDY rows for airdrops and vesting unlocks, which are not rewards, when any were seen.
+fn indy_distribution_rows(data: &ReportData) -> String {
+    let indy = &data.bundle.metrics.indy_staking;
+    [
+        ("INDY airdropped (not yield)", indy.airdrop_indy_quantity),
+        ("INDY vested (not yield)", indy.vesting_indy_quantity),
+    ]
+    .into_iter()
+    .filter_map(|(label, quantity)| {
+        quantity.map(|q| {
+            format!(
+                "    <span class=\"label\">{}</span><span>{}</span>\n",
+                label,
+                format_decimal(u128::from(q), 6)
+            )
+        })
+    })
+    .collect()
+}
+
+/// Summary rows for APY, trailing-window APRs, the money-weighted and time-weighted returns,
+/// the open position, the passive benchmarks, and the lots' realized gain, when they could be
+/// computed, and a day count other than 365.25.
+fn metrics_return_rows(data: &ReportData) -> String {
+    let combined = &data.bundle.metrics.combined;
+    let mut rows = String::new();
+    if combined.annualization.day_count != DayCount::default() {
+        rows.push_str(&format!(
+            "    <span class=\"label\">APR day count</span><span class=\"mono\">{}</span>\n",
+            combined.annualization.day_count
+        ));
+    }
+    if let (Some(value), Some(pnl)) = (
+        combined.open_position_lovelace,
+        combined.unrealized_pnl_lovelace,
+    ) {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Open position (lovelace)</span><span class=\"mono\">{}</span>\n    <span class=\"label\">Net PnL with open position</span><span class=\"mono\">{}</span>\n",
+            value, pnl
+        ));
+    }
+    if let Some(pending) = &combined.pending {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Pending rewards (estimated, not in PnL)</span><span class=\"mono\">{}</span>\n",
+            pending.total_lovelace
+        ));
+    }
+    if let Some(b) = &combined.benchmark {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Staking benchmark (lovelace)</span><span class=\"mono\">{}</span>\n    <span class=\"label\">Yield vs. staking</span><span class=\"mono\">{}</span>\n    <span class=\"label\">Yield vs. holding</span><span class=\"mono\">{}</span>\n",
+            b.staking_earned_lovelace, b.vs_staking_lovelace, b.vs_hold_lovelace
+        ));
+    }
+    if let Some(lots) = &data.bundle.metrics.lots {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Realized gain on lots ({})</span><span class=\"mono\">{}</span>\n",
+            lots.method.as_str().to_uppercase(),
+            lots.realized_gain_lovelace
+        ));
+    }
+    let mut row = |label: &str, x: f64| {
+        rows.push_str(&format!(
+            "    <span class=\"label\">{}</span><span class=\"mono\">{:.2}%</span>\n",
+            label, x
+        ))
+    };
+    if let Some(x) = combined.apy_pct {
+        row("APY % (compounded per epoch)", x);
+    }
+    for window in &combined.trailing {
+        if let Some(x) = window.apr_pct {
+            row(&format!("APR % (last {} days)", window.days), x);
+        }
+    }
+    if let Some(x) = combined.xirr_pct {
+        row("XIRR % (money-weighted)", x);
+    }
+    if let Some(risk) = &combined.risk {
+        row("Max drawdown %", risk.max_drawdown_pct);
+        row("Volatility % (annualized)", risk.volatility_pct);
+    }
+    if let Some(twr) = &combined.twr {
+        row("TWR % (time-weighted)", twr.cumulative_pct);
+        if let Some(x) = twr.annualized_pct {
+            row("TWR % (annualized)", x);
+        }
+    }
+    rows
+}
+
+/// Summary row for ADA staking rewards withdrawn (not part of PnL), when they were fetched.
+fn metrics_staking_row(data: &ReportData) -> String {
+    data.bundle
+        .metrics
+        .combined
+        .ada_staking_rewards_lovelace
+        .map(|l| {
+            format!(
+                "    <span class=\"label\">ADA staking rewards (not in PnL)</span><span class=\"mono\">{}</span>\n",
+                l
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Summary row for protocol fees (already taken out of net PnL), when any were paid.
+fn metrics_fee_row(data: &ReportData) -> String {
+    data.bundle
+        .metrics
+        .combined
+        .protocol_fees_lovelace
+        .map(|l| {
+            format!(
+                "    <span class=\"label\">Protocol fees (in PnL)</span><span class=\"mono\">{}</span>\n",
+                l
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Summary row for how much of the premium and ADA out is heuristic, when there were events.
+fn metrics_confidence_row(data: &ReportData) -> String {
+    let Some(confidence) = &data.bundle.metrics.confidence else {
+        return String::new();
+    };
+    let pct = |x: Option<f64>| x.map_or_else(|| "—".to_string(), |x| format!("{:.1}%", x));
+    format!(
+        "    <span class=\"label\">Heuristic share (premium / ADA out)</span><span class=\"mono\">{} / {}</span>\n",
+        pct(confidence.premium.heuristic_pct()),
+        pct(confidence.ada_out.heuristic_pct())
+    )
+}
+
+/// Summary rows splitting net PnL by source, when there were events; they sum to the net PnL.
+fn metrics_attribution_rows(data: &ReportData) -> String {
+    let Some(a) = &data.bundle.metrics.attribution else {
+        return String::new();
+    };
+    [
+        ("SP liquidation premium", a.liquidation_premium_lovelace),
+        ("dilution", a.dilution_lovelace),
+        ("ROB premium", a.rob_premium_lovelace),
+        ("INDY rewards", a.indy_rewards_lovelace),
+        ("SP withdrawals above cost", a.withdrawal_gain_lovelace),
+        ("protocol fees", a.fees_lovelace),
+        ("principal not returned", a.principal_lovelace),
+    ]
+    .iter()
+    .map(|(label, lovelace)| {
+        format!(
+            "    <span class=\"label\">PnL from {}</span><span class=\"mono\">{}</span>\n",
+            label, lovelace
+        )
+    })
+    .collect()
+}
+
+/// Collateral, iAssets minted / burnt, and fees of the owner's CDPs; empty when there are none.
+fn cdp_card(data: &ReportData) -> String {
+    let Some(cdp) = &data.bundle.metrics.cdp else {
+        return String::new();
+    };
+    let mut rows = format!(
+        "    <span class=\"label\">Opened / closed</span><span>{} / {}</span>\n    <span class=\"label\">Collateral posted (lovelace)</span><span>{}</span>\n    <span class=\"label\">Collateral withdrawn (lovelace)</span><span>{}</span>\n    <span class=\"label\">Fees paid (lovelace)</span><span>{}</span>\n",
+        cdp.open_count,
+        cdp.close_count,
+        cdp.collateral_posted_lovelace,
+        cdp.collateral_withdrawn_lovelace,
+        cdp.fees_paid_lovelace
+    );
+    if cdp.redemption_count > 0 {
+        rows.push_str(&format!(
+            "    <span class=\"label\">Redeemed against (collateral lovelace)</span><span>{} ({})</span>\n",
+            cdp.redemption_count, cdp.collateral_redeemed_lovelace
+        ));
+    }
+    let name = |unit: &String| {
+        data.bundle
+            .assets
+            .get(unit)
+            .map_or_else(|| unit.clone(), |info| info.display_name())
+    };
+    for (verb, quantities) in [
+        ("minted", &cdp.iasset_minted),
+        ("burnt", &cdp.iasset_burnt),
+        ("redeemed", &cdp.iasset_redeemed),
+    ] {
+        for (unit, quantity) in quantities {
+            rows.push_str(&format!(
+                "    <span class=\"label\">{} {}</span><span>{}</span>\n",
+                escape_html(&name(unit)),
+                verb,
+                quantity
+            ));
+        }
+    }
+    for (unit, debt) in &cdp.iasset_debt {
+        rows.push_str(&format!(
+            "    <span class=\"label\">{} debt</span><span>{}</span>\n",
+            escape_html(&name(unit)),
+            debt
+        ));
+    }
+    format!(
+        "\n<h2>CDPs</h2>\n<div class=\"card\">\n  <div class=\"grid\">\n{}  </div>\n</div>\n",
+        rows
+    )
+}
+
+/// Locked / unlocked INDY, votes, and proposals; empty without governance events.
+fn governance_card(data: &ReportData) -> String {
+    let Some(gov) = &data.bundle.metrics.governance else {
+        return String::new();
+    };
+    format!(
+        "\n<h2>Governance</h2>\n<div class=\"card\">\n  <div class=\"grid\">\n    <span class=\"label\">INDY locked / unlocked</span><span>{} / {}</span>\n    <span class=\"label\">Votes</span><span>{}</span>\n    <span class=\"label\">Proposals (INDY deposited)</span><span>{} ({})</span>\n  </div>\n</div>\n",
+        format_decimal(u128::from(gov.indy_locked), 6),
+        format_decimal(u128::from(gov.indy_unlocked), 6),
+        gov.vote_count,
+        gov.proposal_count,
+        format_decimal(u128::from(gov.proposal_deposits_indy), 6)
+    )
+}
+
+/// Ticker, decimals, and unit of each asset the events name; empty when there are none.
+fn assets_card(data: &ReportData) -> String {
+    if data.bundle.assets.is_empty() {
+        return String::new();
+    }
+    let rows: String = data
+        .bundle
+        .assets
+        .iter()
+        .map(|(unit, info)| {
+            format!(
+                "    <span class=\"label\">{}</span><span class=\"mono\">{} decimals · {}</span>\n",
+                escape_html(&info.display_name()),
+                info.decimals,
+                escape_html(unit)
+            )
+        })
+        .collect();
+    format!(
+        "\n<h2>Assets</h2>\n<div class=\"card\">\n  <div class=\"grid\">\n{}  </div>\n</div>\n",
+        rows
+    )
+}
+
+/// Inconsistencies the computation met (`metrics.warnings`); empty when there were none.
+fn warnings_card(data: &ReportData) -> String {
+    let warnings = &data.bundle.metrics.warnings;
+    if warnings.is_empty() {
+        return String::new();
+    }
+    let items: String = warnings
+        .iter()
+        .map(|w| format!("    <li class=\"mono\">{}</li>\n", escape_html(w)))
+        .collect();
+    format!(
+        "\n<h2>Compute warnings</h2>\n<div class=\"card\">\n  <ul>\n{}  </ul>\n</div>\n",
+        items
+    )
+}
+
+/// The combined figures in the currency of account (`metrics.account`), each event converted
+/// at its time; empty without one.
+fn account_card(data: &ReportData) -> String {
+    let Some(account) = &data.bundle.metrics.account else {
+        return String::new();
+    };
+    let ticker = account.currency.ticker();
+    let mut rows = String::new();
+    let mut row = |label: &str, x: f64| {
+        rows.push_str(&format!(
+            "    <span class=\"label\">{}</span><span class=\"mono\">{:.2}</span>\n",
+            label, x
+        ))
+    };
+    row("Net PnL", account.net_pnl);
+    row("Total in", account.total_in);
+    row("Total out", account.total_out);
+    row("Premium and rewards", account.yield_earned);
+    if account.protocol_fees != 0.0 {
+        row("Protocol fees (in PnL)", account.protocol_fees);
+    }
+    if let (Some(position), Some(pnl)) = (account.open_position, account.unrealized_pnl) {
+        row("Open position", position);
+        row("Net PnL with open position", pnl);
+    }
+    if let Some(apr) = account.apr_pct {
+        row("APR %", apr);
+    }
+    format!(
+        "\n<h2>Summary in {}</h2>\n<div class=\"card\">\n  <div class=\"grid\">\n{}  </div>\n  <p class=\"footer\">Each event converted at the {} price of its time, then added up.</p>\n</div>\n",
+        ticker, rows, ticker
+    )
+}
+
+/// The combined flows in ADA and, beside them, in USD at each event's price (`metrics.fiat`);
+/// empty without an ADA/USD series.
+fn fiat_card(data: &ReportData) -> String {
+    let Some(fiat) = &data.bundle.metrics.fiat else {
+        return String::new();
+    };
+    let comb = &data.bundle.metrics.combined;
+    let mut rows = String::new();
+    let mut row = |label: &str, ada: String, usd: f64| {
+        rows.push_str(&format!(
+            "    <tr><td>{}</td><td class=\"mono\">{}</td><td class=\"mono\">{:.2}</td></tr>\n",
+            label, ada, usd
+        ))
+    };
+    let ada = |l: u64| format_decimal(u128::from(l), 6);
+    row(
+        "Total in",
+        ada(comb.total_ada_in_lovelace),
+        fiat.total_in_usd,
+    );
+    row(
+        "Total out",
+        ada(comb.total_ada_out_lovelace),
+        fiat.total_out_usd,
+    );
+    if fiat.protocol_fees_usd != 0.0 {
+        row(
+            "Protocol fees",
+            ada(comb.protocol_fees_lovelace.unwrap_or(0)),
+            fiat.protocol_fees_usd,
+        );
+    }
+    let net = comb.net_pnl_lovelace;
+    let sign = if net < 0 { "-" } else { "" };
+    row(
+        "Net PnL",
+        format!("{}{}", sign, ada(net.unsigned_abs())),
+        fiat.net_pnl_usd,
+    );
+    format!(
+        "\n<h2>Value in USD</h2>\n<div class=\"card\">\n  <table>\n    <tr><th></th><th>ADA</th><th>USD</th></tr>\n{}  </table>\n  <p class=\"footer\">Each event valued at the ADA/USD price of its time. ADA/USD at the period end: {:.4}. Of the USD net PnL, {:.2} came from ADA's price moving rather than from yield.</p>\n</div>\n",
+        rows, fiat.end_price_usd, fiat.currency_effect_usd
+    )
+}
+
+/// Events that look misread (bundle `warnings`), boxed above the figures they may skew; empty
+/// when there were none.
+fn data_warnings_box(data: &ReportData) -> String {
+    let warnings = &data.bundle.warnings;
+    if warnings.is_empty() {
+        return String::new();
+    }
+    let items: String = warnings
+        .iter()
+        .map(|w| {
+            format!(
+                "    <li><span class=\"mono\">{}</span>: {}</li>\n",
+                escape_html(&w.tx_hash),
+                escape_html(&w.message)
+            )
+        })
+        .collect();
+    format!(
+        "<div class=\"card warn\">\n  <p><strong>Data-quality warnings</strong> — {} event(s) look misread; check them before relying on the figures.</p>\n  <ul>\n{}  </ul>\n</div>\n",
+        warnings.len(),
+        items
+    )
+}
+
+/// Email-safe variant: table layout with inline styles, no `<style>`/`<script>`, no embedded bundle
+/// (the bundle is sent as an attachment instead).
+pub fn build_email_html(data: &ReportData) -> Result<String, ReportError> {
+    let metrics = &data.bundle.metrics;
+    let sp = &metrics.stability_pool;
+    let rob = &metrics.rob;
+    let indy = &metrics.indy_staking;
+    let comb = &metrics.combined;
+    let pct = |x: Option<f64>| {
+        x.map(|x| format!("{:.2}%", x))
+            .unwrap_or_else(|| "—".to_string())
+    };
+    let mut rows: Vec<(&str, String)> = vec![
+        ("Net PnL (lovelace)", comb.net_pnl_lovelace.to_string()),
+        ("Total ADA in", comb.total_ada_in_lovelace.to_string()),
+        ("Total ADA out", comb.total_ada_out_lovelace.to_string()),
+        ("APR %", pct(comb.apr_pct)),
+        ("SP liquidations", sp.liquidation_count.to_string()),
+        (
+            "SP realized premium",
+            sp.total_realized_premium_lovelace.to_string(),
+        ),
+        ("ROB fills", rob.fill_count.to_string()),
+        (
+            "ROB premium received",
+            rob.total_premium_received_lovelace.to_string(),
+        ),
+        ("INDY rewards", indy.total_rewards_lovelace.to_string()),
+    ];
+    if comb.xirr_pct.is_some() {
+        rows.insert(4, ("XIRR %", pct(comb.xirr_pct)));
+    }
+    if let Some(twr) = &comb.twr {
+        rows.push(("TWR % (annualized)", pct(twr.annualized_pct)));
+    }
+    if let Some(confidence) = &metrics.confidence {
+        rows.push((
+            "Heuristic share of premium",
+            pct(confidence.premium.heuristic_pct()),
+        ));
+    }
+    let td = "padding:4px 12px;border-bottom:1px solid #e1e4e8;";
+    let table_rows: String = rows
+        .iter()
+        .map(|(k, v)| {
+            format!(
+                "<tr><td style=\"{td}color:#57606a;\">{}</td><td style=\"{td}font-family:monospace;\">{}</td></tr>\n",
+                escape_html(k),
+                escape_html(v),
+                td = td
+            )
+        })
+        .collect();
+    let label_html = data
+        .bundle
+        .label
+        .as_deref()
+        .map(|l| {
+            format!(
+                "<p style=\"margin:0;\"><strong>{}</strong></p>\n",
+                escape_html(l)
+            )
+        })
+        .unwrap_or_default();
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head><meta charset="UTF-8"/><title>Proof of Yield – {addr}</title></head>
+<body style="margin:0;padding:16px;background:#ffffff;color:#24292f;font-family:Arial,Helvetica,sans-serif;">
+<h1 style="font-size:20px;margin:0 0 8px 0;">Proof of Yield Report</h1>
+{label_html}<p style="font-family:monospace;font-size:12px;word-break:break-all;margin:4px 0;">{addr}</p>
+<p style="font-size:13px;color:#57606a;margin:4px 0 16px 0;">Generated: {created}</p>
+<table cellpadding="0" cellspacing="0" style="border-collapse:collapse;font-size:14px;">
+{table_rows}</table>
+<p style="font-size:13px;margin-top:16px;">Reproducibility hash (SHA-256):<br/><span style="font-family:monospace;font-size:12px;word-break:break-all;">{hash}</span></p>
+<p style="font-size:12px;color:#57606a;">The evidence bundle is attached. Verify with <code>indigo-poy verify --bundle &lt;file&gt;</code>.</p>
+</body>
+</html>"#,
+        addr = escape_html(&data.bundle.address),
+        label_html = label_html,
+        created = escape_html(&data.bundle.created_utc_rfc3339),
+        table_rows = table_rows,
+        hash = escape_html(&data.reproducibility_hash_sha256),
+    ))
+}
+
+/// Plain-text summary used as the text alternative of the email.
+pub fn build_email_text(data: &ReportData) -> String {
+    let comb = &data.bundle.metrics.combined;
+    let mut out = format!("Proof of Yield Report\n{}\n", data.bundle.address);
+    if let Some(label) = &data.bundle.label {
+        out.push_str(&format!("{}\n", label));
+    }
+    out.push_str(&format!(
+        "Generated: {}\nNet PnL (lovelace): {}\nAPR %: {}\nSHA-256: {}\n",
+        data.bundle.created_utc_rfc3339,
+        comb.net_pnl_lovelace,
+        comb.apr_pct
+            .map(|x| format!("{:.2}", x))
+            .unwrap_or_else(|| "—".to_string()),
+        data.reproducibility_hash_sha256,
+    ));
+    out
+}
+
+/// Render the anonymized multi-bundle leaderboard to `out_path`.
+pub fn render_leaderboard(
+    board: &Leaderboard,
+    out_path: impl AsRef<Path>,
+) -> Result<(), ReportError> {
+    let html = build_leaderboard_html(board)?;
+    std::fs::write(out_path.as_ref(), html).map_err(ReportError::Io)
+}
+
+/// Build the leaderboard HTML (same look as the per-address report; no addresses are shown).
+pub fn build_leaderboard_html(board: &Leaderboard) -> Result<String, ReportError> {
+    let json_embed = escape_json_in_html(&serde_json::to_string(board).map_err(ReportError::Json)?);
+    let opt = |x: Option<f64>| {
+        x.map(|x| format!("{:.2}", x))
+            .unwrap_or_else(|| "—".to_string())
+    };
+    let d = &board.apr_distribution;
+    let entry_rows: String = board
+        .entries
+        .iter()
+        .map(|e| {
+            format!(
+                "<tr><td>{}</td><td class=\"mono\">{}</td><td>{}</td><td class=\"mono\">{}</td><td>{}</td><td class=\"mono\">{}</td></tr>\n",
+                e.rank,
+                escape_html(&e.participant_id),
+                opt(e.apr_pct),
+                e.net_pnl_lovelace,
+                e.liquidation_count,
+                e.realized_premium_lovelace
+            )
+        })
+        .collect();
+    let pool_rows: String = board
+        .premium_by_pool
+        .iter()
+        .map(|p| {
+            format!(
+                "<tr><td class=\"mono\">{}</td><td>{}</td><td class=\"mono\">{}</td><td>{}</td></tr>\n",
+                escape_html(&p.pool),
+                p.liquidation_count,
+                p.realized_premium_lovelace,
+                p.participants
+            )
+        })
+        .collect();
+    Ok(format!(
         r#"<!DOCTYPE html>
 <html lang="en">
 <head>
 <meta charset="UTF-8"/>
 <meta name="viewport" content="width=device-width,initial-scale=1"/>
-<title>Proof of Yield – {addr}</title>
+<title>Proof of Yield – Leaderboard</title>
 <style>
-:root {{ font-family: system-ui, sans-serif; background: #0f1419; color: #e6edf3; }}
-body {{ max-width: 720px; margin: 0 auto; padding: 1.5rem; }}
-h1 {{ font-size: 1.4rem; margin-bottom: 0.5rem; }}
-h2 {{ font-size: 1.1rem; margin-top: 1.5rem; color: #8b949e; }}
-.mono {{ font-family: ui-monospace, monospace; font-size: 0.9em; word-break: break-all; }}
-.card {{ background: #161b22; border: 1px solid #30363d; border-radius: 6px; padding: 1rem; margin: 0.5rem 0; }}
-.grid {{ display: grid; grid-template-columns: auto 1fr; gap: 0.25rem 1rem; }}
-.label {{ color: #8b949e; }}
-.hash {{ font-size: 0.85em; }}
-.footer {{ margin-top: 2rem; font-size: 0.85rem; color: #8b949e; }}
-.snippet {{ font-size: 0.95rem; line-height: 1.5; }}
+{theme_css}
+body {{ max-width: 880px; }}
+table {{ margin-top: 0; }}
 </style>
 </head>
 <body>
-<h1>Proof of Yield Report</h1>
-<p class="mono">{addr}</p>
-<p>Generated: {created}</p>
-
-<h2>At a glance</h2>
-<div class="card snippet">
-  <p><strong>SP:</strong> {sp_count} liquidations, realized premium {apr_snippet}% (annualized), avg ADA liquidation price {avg_liq_price}, dilution-adjusted.</p>
-  <p><strong>ROB:</strong> {rob_fill_count} partial fills, reimbursement premium captured {rob_avg_pct_snippet}.</p>
+<h1>Proof of Yield Leaderboard</h1>
+<p>{participants} participants. Participant ids are derived from bundle hashes; no addresses are included.</p>
+
+<h2>APR distribution (%)</h2>
+<div class="card">
+  <table>
+    <tr><th>min</th><th>p25</th><th>median</th><th>p75</th><th>max</th><th>mean</th></tr>
+    <tr><td>{min}</td><td>{p25}</td><td>{median}</td><td>{p75}</td><td>{max}</td><td>{mean}</td></tr>
+  </table>
 </div>
 
-<h2>Reproducibility</h2>
+<h2>Ranking</h2>
 <div class="card">
-  <div class="mono hash">SHA-256: {hash}</div>
-  <p class="footer">Anyone can verify this report by re-running <code>indigo-poy verify --bundle &lt;file&gt;</code> and comparing the hash.</p>
+  <table>
+    <tr><th>#</th><th>Participant</th><th>APR %</th><th>Net PnL (lovelace)</th><th>Liquidations</th><th>Realized premium</th></tr>
+{entry_rows}  </table>
 </div>
 
-<h2>Summary</h2>
+<h2>Premium capture by pool</h2>
 <div class="card">
-  <div class="grid">
-    <span class="label">Net PnL (lovelace)</span><span class="mono">{net_pnl}</span>
-    <span class="label">Total ADA in</span><span class="mono">{total_in}</span>
-    <span class="label">Total ADA out</span><span class="mono">{total_out}</span>
-    <span class="label">APR %</span><span class="mono">{apr}</span>
-  </div>
+  <table>
+    <tr><th>Pool (iAsset)</th><th>Liquidations</th><th>Realized premium</th><th>Participants</th></tr>
+{pool_rows}  </table>
 </div>
 
-<h2>Stability Pool</h2>
 <div class="card">
-  <div class="grid">
-    <span class="label">Deposits (lovelace)</span><span>{sp_deposits}</span>
-    <span class="label">Withdrawals (lovelace)</span><span>{sp_withdrawals}</span>
-    <span class="label">Liquidations (ADA received)</span><span>{sp_liq}</span>
-    <span class="label">Realized premium</span><span>{sp_premium}</span>
-    <span class="label">Liquidation count</span><span>{sp_count}</span>
-  </div>
+  <script type="application/json" id="leaderboard">{json_embed}</script>
 </div>
 
-<h2>ROB (Redemption Order Book)</h2>
+<div class="footer">
+  <p>Generated by <a href="https://github.com/gorusys/indigo-proof-of-yield">indigo-proof-of-yield</a>.</p>
+</div>
+</body>
+</html>"#,
+        theme_css = THEME_CSS,
+        participants = board.participants,
+        min = opt(d.min),
+        p25 = opt(d.p25),
+        median = opt(d.median),
+        p75 = opt(d.p75),
+        max = opt(d.max),
+        mean = opt(d.mean),
+        entry_rows = entry_rows,
+        pool_rows = pool_rows,
+        json_embed = json_embed,
+    ))
+}
+
+/// Render the consolidated multi-address report to `out_path`.
+pub fn render_portfolio(
+    portfolio: &Portfolio,
+    out_path: impl AsRef<Path>,
+) -> Result<(), ReportError> {
+    let html = build_portfolio_html(portfolio)?;
+    std::fs::write(out_path.as_ref(), html).map_err(ReportError::Io)
+}
+
+/// Build the portfolio HTML: the combined summary and hash, then one card per address.
+pub fn build_portfolio_html(portfolio: &Portfolio) -> Result<String, ReportError> {
+    let json_embed =
+        escape_json_in_html(&serde_json::to_string(portfolio).map_err(ReportError::Json)?);
+    let opt = |x: Option<f64>| {
+        x.map(|x| format!("{:.2}%", x))
+            .unwrap_or_else(|| "—".to_string())
+    };
+    let ada = |l: u64| format_decimal(u128::from(l), 6);
+    let signed_ada = |l: i64| {
+        let sign = if l < 0 { "-" } else { "" };
+        format!("{}{}", sign, ada(l.unsigned_abs()))
+    };
+    let grid = |rows: &[(&str, String)]| -> String {
+        rows.iter()
+            .map(|(k, v)| {
+                format!(
+                    "    <span class=\"label\">{}</span><span class=\"mono\">{}</span>\n",
+                    k,
+                    escape_html(v)
+                )
+            })
+            .collect()
+    };
+    let s = &portfolio.summary;
+    let mut summary_rows = vec![
+        ("Addresses", s.addresses.to_string()),
+        ("Net PnL (ADA)", signed_ada(s.net_pnl_lovelace)),
+        ("Total ADA in", ada(s.total_ada_in_lovelace)),
+        ("Total ADA out", ada(s.total_ada_out_lovelace)),
+        ("APR %", opt(s.apr_pct)),
+        ("SP liquidations", s.liquidation_count.to_string()),
+        (
+            "SP realized premium (ADA)",
+            ada(s.realized_premium_lovelace),
+        ),
+        ("ROB premium (ADA)", ada(s.rob_premium_lovelace)),
+        ("INDY rewards (ADA)", ada(s.indy_rewards_lovelace)),
+    ];
+    if let Some(position) = s.open_position_lovelace {
+        summary_rows.insert(4, ("Open position (ADA)", ada(position)));
+    }
+    let entry_cards: String = portfolio
+        .entries
+        .iter()
+        .map(|e| {
+            let title = match &e.label {
+                Some(label) => format!(
+                    "<p><strong>{}</strong></p>\n  <p class=\"mono\">{}</p>",
+                    escape_html(label),
+                    escape_html(&e.address)
+                ),
+                None => format!("<p class=\"mono\">{}</p>", escape_html(&e.address)),
+            };
+            let mut rows = vec![
+                ("Net PnL (ADA)", signed_ada(e.net_pnl_lovelace)),
+                ("Total ADA in", ada(e.total_ada_in_lovelace)),
+                ("Total ADA out", ada(e.total_ada_out_lovelace)),
+                ("APR %", opt(e.apr_pct)),
+                ("SP liquidations", e.liquidation_count.to_string()),
+                ("SP realized premium (ADA)", ada(e.realized_premium_lovelace)),
+                ("ROB premium (ADA)", ada(e.rob_premium_lovelace)),
+                ("INDY rewards (ADA)", ada(e.indy_rewards_lovelace)),
+            ];
+            if let Some(position) = e.open_position_lovelace {
+                rows.insert(3, ("Open position (ADA)", ada(position)));
+            }
+            format!(
+                "<div class=\"card\">\n  {}\n  <div class=\"grid\">\n{}  </div>\n  <div class=\"mono hash\">SHA-256: {}</div>\n</div>\n",
+                title,
+                grid(&rows),
+                escape_html(&e.reproducibility_hash_sha256)
+            )
+        })
+        .collect();
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="UTF-8"/>
+<meta name="viewport" content="width=device-width,initial-scale=1"/>
+<title>Proof of Yield – Portfolio</title>
+<style>
+{theme_css}
+.hash {{ margin-top: 0.5rem; }}
+</style>
+</head>
+<body>
+<h1>Proof of Yield Portfolio</h1>
+<p>{addresses} addresses, each with its own evidence bundle.</p>
+
+<h2>Reproducibility</h2>
 <div class="card">
-  <div class="grid">
-    <span class="label">Total placed (lovelace)</span><span>{rob_placed}</span>
-    <span class="label">Total filled (lovelace)</span><span>{rob_filled}</span>
-    <span class="label">Premium received</span><span>{rob_premium}</span>
-    <span class="label">Avg premium %</span><span>{rob_avg_pct}</span>
-    <span class="label">Fill count</span><span>{rob_fill_count}</span>
-  </div>
+  <div class="mono hash">Portfolio SHA-256: {hash}</div>
+  <p class="footer">The SHA-256 of the bundle hashes below, sorted. Verify each bundle with <code>indigo-poy verify --bundle &lt;file&gt;</code>, then rebuild the portfolio from them with <code>indigo-poy portfolio</code> and compare.</p>
 </div>
 
-<h2>INDY Staking</h2>
+<h2>Combined</h2>
 <div class="card">
   <div class="grid">
-    <span class="label">Total rewards (lovelace)</span><span>{indy_rewards}</span>
-    <span class="label">SP premium (lovelace)</span><span>{indy_sp}</span>
-    <span class="label">Reward tx count</span><span>{indy_count}</span>
-  </div>
+{summary_rows}  </div>
 </div>
 
-<h2>Evidence bundle (embedded)</h2>
+<h2>Per address</h2>
+{entry_cards}
 <div class="card">
-  <p class="footer">The full evidence bundle is embedded below for verification. Do not edit.</p>
-  <script type="application/json" id="evidence-bundle">{json_embed}</script>
+  <script type="application/json" id="portfolio">{json_embed}</script>
 </div>
 
 <div class="footer">
-  <p>Generated by <a href="https://github.com/gorusys/indigo-proof-of-yield" style="color:#58a6ff">indigo-proof-of-yield</a>. Read-only tool; no seeds; no signing.</p>
+  <p>Generated by <a href="https://github.com/gorusys/indigo-proof-of-yield">indigo-proof-of-yield</a>. Read-only tool; no seeds; no signing.</p>
 </div>
 </body>
 </html>"#,
-        addr = addr_escaped,
-        created = escape_html(&data.bundle.created_utc_rfc3339),
-        hash = hash_escaped,
-        avg_liq_price = avg_liq_price,
-        apr_snippet = apr_snippet,
-        rob_avg_pct_snippet = rob_avg_pct_snippet,
-        net_pnl = comb.net_pnl_lovelace,
-        total_in = comb.total_ada_in_lovelace,
-        total_out = comb.total_ada_out_lovelace,
-        apr = comb
-            .apr_pct
-            .map(|x| format!("{:.2}%", x))
-            .unwrap_or_else(|| "—".to_string()),
-        sp_deposits = sp.total_deposits_lovelace,
-        sp_withdrawals = sp.total_withdrawals_lovelace,
-        sp_liq = sp.total_liquidations_ada_received_lovelace,
-        sp_premium = sp.total_realized_premium_lovelace,
-        sp_count = sp.liquidation_count,
-        rob_placed = rob.total_placed_lovelace,
-        rob_filled = rob.total_filled_lovelace,
-        rob_premium = rob.total_premium_received_lovelace,
-        rob_avg_pct = rob
-            .avg_premium_pct
-            .map(|x| format!("{:.2}%", x))
-            .unwrap_or_else(|| "—".to_string()),
-        rob_fill_count = rob.fill_count,
-        indy_rewards = indy.total_rewards_lovelace,
-        indy_sp = indy.total_sp_premium_lovelace,
-        indy_count = indy.reward_tx_count,
-        json_embed = json_escaped,
-    );
-    Ok(html)
+        theme_css = THEME_CSS,
+        addresses = s.addresses,
+        hash = escape_html(&portfolio.portfolio_sha256),
+        summary_rows = grid(&summary_rows),
+        entry_cards = entry_cards,
+        json_embed = json_embed,
+    ))
 }
 
 fn escape_html(s: &str) -> String {
@@ -186,6 +1090,8 @@ fn escape_json_in_html(s: &str) -> String {
 pub enum ReportError {
     Io(std::io::Error),
     Json(serde_json::Error),
+    /// The template did not parse or render.
+    Template(tera::Error),
 }
 
 impl std::fmt::Display for ReportError {
@@ -193,6 +1099,16 @@ impl std::fmt::Display for ReportError {
         match self {
             ReportError::Io(e) => write!(f, "io: {}", e),
             ReportError::Json(e) => write!(f, "json: {}", e),
+            ReportError::Template(e) => {
+                // Tera keeps the cause (e.g. the unknown variable) in the source chain.
+                write!(f, "template: {}", e)?;
+                let mut source = std::error::Error::source(e);
+                while let Some(s) = source {
+                    write!(f, ": {}", s)?;
+                    source = s.source();
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -223,5 +1139,430 @@ mod tests {
         assert!(html.contains("Proof of Yield"));
         assert!(html.contains("addr1_test"));
         assert!(html.contains("evidence-bundle"));
+        assert!(!html.contains("<h2>Assets</h2>"));
+        assert!(!html.contains("<h2>CDPs</h2>"));
+        assert!(!html.contains("<h2>Governance</h2>"));
+        assert!(!html.contains("Time in cooldown"));
+    }
+
+    #[test]
+    fn indy_card_shows_token_and_ada_equivalent() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.indy_staking.total_indy_quantity = Some(1_500_000_000);
+        bundle.metrics.indy_staking.price_indy(0.5);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("INDY received</span><span>1,500"));
+        assert!(html.contains("(lovelace, at 0.5 ADA)</span><span>750000000"));
+        assert!(!html.contains("INDY airdropped"));
+    }
+
+    #[test]
+    fn sp_card_shows_dilution() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.dilution = Some(indigo_poy::compute::DilutionModel {
+            user_share_pct: Some(18.75),
+            dilution_effect_lovelace: Some(1_000_000),
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("Pool share (last liquidation)</span><span>18.75%"));
+        assert!(html.contains("Premium lost to dilution (lovelace)</span><span>1000000"));
+    }
+
+    #[test]
+    fn summary_shows_xirr_and_twr() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.combined.xirr_pct = Some(4.919);
+        bundle.metrics.combined.twr = Some(indigo_poy::compute::TimeWeightedReturn {
+            cumulative_pct: 10.25,
+            annualized_pct: Some(5.0),
+            periods: vec![],
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("XIRR % (money-weighted)</span><span class=\"mono\">4.92%"));
+        assert!(html.contains("TWR % (time-weighted)</span><span class=\"mono\">10.25%"));
+        assert!(html.contains("TWR % (annualized)</span><span class=\"mono\">5.00%"));
+    }
+
+    #[test]
+    fn sp_card_shows_liquidation_pnl() {
+        let mut bundle = EvidenceBundle::demo();
+        let sp = &mut bundle.metrics.stability_pool;
+        sp.liquidations = vec![indigo_poy::compute::LiquidationPnl {
+            pnl_lovelace: -5_000_000,
+            ..Default::default()
+        }];
+        sp.liquidation_pnl_lovelace = Some(-5_000_000);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("Liquidation P&amp;L at oracle (lovelace)</span><span>-5000000"));
+        assert!(html.contains("Liquidations at a loss</span><span>1 of 1"));
+    }
+
+    #[test]
+    fn sp_card_breaks_out_iassets() {
+        let mut bundle = EvidenceBundle::demo();
+        let pool = |liquidation_count| indigo_poy::compute::SpAssetMetrics {
+            liquidation_count,
+            ..Default::default()
+        };
+        bundle.metrics.stability_pool.by_asset = [
+            ("f0ff$69425443".to_string(), pool(3)),
+            ("f0ff$69555344".to_string(), pool(20)),
+        ]
+        .into();
+        bundle.assets.insert(
+            "f0ff$69425443".into(),
+            indigo_poy::chain::assets::AssetInfo::from_unit("f0ff$69425443").unwrap(),
+        );
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<tr><td>iBTC</td><td>3</td>"));
+        assert!(html.contains("<tr><td>f0ff$69555344</td><td>20</td>"));
+    }
+
+    #[test]
+    fn indy_card_shows_airdrops_apart() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.indy_staking.airdrop_indy_quantity = Some(2_000_000);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("INDY airdropped (not yield)</span><span>2"));
+        assert!(!html.contains("INDY vested"));
+    }
+
+    #[test]
+    fn summary_shows_heuristic_share() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.confidence = Some(indigo_poy::compute::ConfidenceMetrics {
+            premium: indigo_poy::compute::ConfidenceSplit {
+                high_lovelace: 300,
+                heuristic_lovelace: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("(premium / ADA out)</span><span class=\"mono\">25.0% / —"));
+        assert!(build_email_html(&data).unwrap().contains("25.00%"));
+    }
+
+    #[test]
+    fn summary_shows_protocol_fees() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.combined.protocol_fees_lovelace = Some(500_000);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("Protocol fees (in PnL)</span><span class=\"mono\">500000"));
+    }
+
+    #[test]
+    fn summary_splits_pnl_by_source() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.attribution = Some(indigo_poy::compute::pnl_attribution(
+            -1_000_000, 2_000_000, 0, 0, 0, 0, 500_000,
+        ));
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("PnL from protocol fees</span><span class=\"mono\">-500000"));
+        assert!(
+            html.contains("PnL from principal not returned</span><span class=\"mono\">-2500000")
+        );
+    }
+
+    #[test]
+    fn governance_card_counts_votes() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.governance = Some(indigo_poy::compute::GovernanceMetrics {
+            indy_locked: 2_000_000_000,
+            vote_count: 3,
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("INDY locked / unlocked</span><span>2,000 / 0"));
+        assert!(html.contains("Votes</span><span>3"));
+    }
+
+    #[test]
+    fn rob_card_shows_cooldown() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.rob.time_in_cooldown_secs = Some(3 * 86_400);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("Time in cooldown</span><span>3.0 days"));
+        assert!(!html.contains("Cancelled (lovelace)"));
+    }
+
+    #[test]
+    fn cdp_card_names_minted_iassets() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.cdp = Some(indigo_poy::compute::CdpMetrics {
+            collateral_posted_lovelace: 100_000_000,
+            iasset_minted: [("f66d$69555344".to_string(), 40)].into(),
+            iasset_debt: [("f66d$69555344".to_string(), 40)].into(),
+            open_count: 1,
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<h2>CDPs</h2>"));
+        assert!(html.contains("Collateral posted (lovelace)</span><span>100000000"));
+        assert!(html.contains("f66d$69555344 minted</span><span>40"));
+        assert!(html.contains("f66d$69555344 debt</span><span>40"));
+        assert!(!html.contains("Redeemed against"));
+    }
+
+    #[test]
+    fn cdp_card_shows_redemptions() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.cdp = Some(indigo_poy::compute::CdpMetrics {
+            redemption_count: 2,
+            collateral_redeemed_lovelace: 25_000_000,
+            iasset_redeemed: [("f66d$69555344".to_string(), 10)].into(),
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("Redeemed against (collateral lovelace)</span><span>2 (25000000)"));
+        assert!(html.contains("f66d$69555344 redeemed</span><span>10"));
+    }
+
+    #[test]
+    fn assets_card_lists_tickers() {
+        let mut bundle = EvidenceBundle::demo();
+        let info = indigo_poy::chain::assets::AssetInfo {
+            decimals: 6,
+            ..indigo_poy::chain::assets::AssetInfo::from_name("f66d", "69555344")
+        };
+        bundle.assets.insert(info.unit(), info);
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<h2>Assets</h2>"));
+        assert!(html.contains("iUSD</span><span class=\"mono\">6 decimals · f66d$69555344"));
+    }
+
+    #[test]
+    fn warnings_card_lists_inconsistencies() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle
+            .metrics
+            .warnings
+            .push("combined.total_ada_in_lovelace overflowed at tx <t>; clamped".into());
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<h2>Compute warnings</h2>"));
+        assert!(html.contains("at tx &lt;t&gt;; clamped"));
+    }
+
+    #[test]
+    fn account_card_shows_the_currency_of_account() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.metrics.account = Some(indigo_poy::compute::currency::AccountMetrics {
+            currency: indigo_poy::compute::currency::Currency::Iusd,
+            net_pnl: 12.5,
+            ..Default::default()
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<h2>Summary in iUSD</h2>"));
+        assert!(html.contains("Net PnL</span><span class=\"mono\">12.50"));
+    }
+
+    #[test]
+    fn data_warnings_are_boxed_above_the_figures() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.warnings.push(indigo_poy::indigo::DataWarning {
+            kind: indigo_poy::indigo::DataWarningKind::HighPremium,
+            tx_hash: "t1".into(),
+            timestamp: 0,
+            message: "ROB fill premium 45.00%, over 20%".into(),
+        });
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        let boxed = html.find("Data-quality warnings").unwrap();
+        assert!(boxed < html.find("<h2>At a glance</h2>").unwrap());
+        assert!(html.contains("<span class=\"mono\">t1</span>: ROB fill premium 45.00%"));
+    }
+
+    #[test]
+    fn custom_template_sees_the_bundle_and_escapes_text() {
+        let mut bundle = EvidenceBundle::demo();
+        bundle.label = Some("<b>Guild</b>".into());
+        let data = ReportData {
+            bundle,
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let template = "{{ bundle.label }}|{{ label_html | safe }}|{{ bundle.metrics.stability_pool.liquidation_count }}";
+        let options = |template: &str| ReportOptions {
+            template: Some(template.into()),
+            ..Default::default()
+        };
+        let html = build_html_with(&data, &options(template)).unwrap();
+        assert_eq!(
+            html,
+            "&lt;b&gt;Guild&lt;&#x2F;b&gt;|<p><strong>&lt;b&gt;Guild&lt;/b&gt;</strong></p>\n|23"
+        );
+        let err = build_html_with(&data, &options("{{ no_such_value }}")).unwrap_err();
+        assert!(err.to_string().contains("no_such_value"));
+    }
+
+    #[test]
+    fn fiat_card_puts_usd_beside_ada() {
+        let mut bundle = EvidenceBundle::demo();
+        let data = |bundle: &EvidenceBundle| ReportData {
+            bundle: bundle.clone(),
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        assert_eq!(fiat_card(&data(&bundle)), "");
+        bundle.metrics.combined.total_ada_in_lovelace = 100_000_000;
+        bundle.metrics.combined.total_ada_out_lovelace = 90_000_000;
+        bundle.metrics.combined.net_pnl_lovelace = -10_000_000;
+        bundle.metrics.fiat = Some(indigo_poy::compute::FiatMetrics {
+            total_in_usd: 50.0,
+            total_out_usd: 63.0,
+            net_pnl_usd: 13.0,
+            end_price_usd: 0.7,
+            currency_effect_usd: 20.0,
+            ..Default::default()
+        });
+        let html = build_html(&data(&bundle)).unwrap();
+        assert!(html.contains("<h2>Value in USD</h2>"));
+        assert!(html.contains(
+            "<tr><td>Net PnL</td><td class=\"mono\">-10</td><td class=\"mono\">13.00</td></tr>"
+        ));
+        assert!(!html.contains("<td>Protocol fees</td>"));
+    }
+
+    #[test]
+    fn report_carries_the_in_browser_verifier() {
+        let data = ReportData {
+            bundle: EvidenceBundle::demo(),
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let html = build_html(&data).unwrap();
+        assert!(html.contains("<button type=\"button\" id=\"verify-now\">Verify now</button>"));
+        assert!(html.contains(VERIFY_JS));
+        // Inline, a `</` would close the script early.
+        assert!(!VERIFY_JS.contains("</"));
+        let embed = html.find("id=\"evidence-bundle\"").unwrap();
+        assert!(embed < html.find("getElementById(\"verify-now\")").unwrap());
+    }
+
+    #[test]
+    fn theme_sets_data_theme_and_print_styles_apply_to_all() {
+        let data = ReportData {
+            bundle: EvidenceBundle::demo(),
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        let auto = build_html(&data).unwrap();
+        assert!(auto.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+        assert!(auto.contains("@media print"));
+        assert!(auto.contains("<div class=\"card no-print\">"));
+        let light = ReportOptions {
+            theme: "Light".parse().unwrap(),
+            ..Default::default()
+        };
+        let html = build_html_with(&data, &light).unwrap();
+        assert!(html.contains("<html lang=\"en\" data-theme=\"light\">"));
+        assert!("sepia".parse::<Theme>().is_err());
+    }
+
+    #[test]
+    fn leaderboard_html_omits_addresses() {
+        let board =
+            indigo_poy::report::leaderboard::build_leaderboard(&[EvidenceBundle::demo()]).unwrap();
+        let html = build_leaderboard_html(&board).unwrap();
+        assert!(html.contains("Leaderboard"));
+        assert!(!html.contains("addr1_demo"));
+    }
+
+    #[test]
+    fn portfolio_html_has_a_card_per_address_and_the_combined_hash() {
+        let mut a = EvidenceBundle::demo();
+        a.address = "addr1_alice".into();
+        let mut b = EvidenceBundle::demo();
+        b.address = "addr1_bob".into();
+        b.label = Some("Savings".into());
+        let portfolio = indigo_poy::report::portfolio::build_portfolio(&[a, b]).unwrap();
+        let html = build_portfolio_html(&portfolio).unwrap();
+        assert!(html.contains(&format!(
+            "Portfolio SHA-256: {}",
+            portfolio.portfolio_sha256
+        )));
+        assert!(html.contains("<p class=\"mono\">addr1_alice</p>"));
+        assert!(html.contains("<p><strong>Savings</strong></p>"));
+        assert!(
+            html.contains("<span class=\"label\">Addresses</span><span class=\"mono\">2</span>")
+        );
+        assert_eq!(html.matches("SHA-256: ").count(), 3);
+    }
+
+    #[test]
+    fn email_html_has_no_scripts() {
+        let data = ReportData {
+            bundle: EvidenceBundle::demo(),
+            reproducibility_hash_sha256: "b".repeat(64),
+        };
+        let html = build_email_html(&data).unwrap();
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("<style"));
+        assert!(html.contains(&"b".repeat(64)));
+        assert!(build_email_text(&data).contains(&"b".repeat(64)));
     }
 }
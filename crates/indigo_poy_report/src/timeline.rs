@@ -0,0 +1,177 @@
+//! The events as a table a person can read: one row per event in time order, each tx linked to
+//! a block explorer.
+
+use crate::escape_html;
+use crate::export::{events_in_time_order, kind_name, EventRow};
+use indigo_poy::chain::assets::format_decimal;
+use indigo_poy::compute::FiatMetrics;
+use indigo_poy::indigo::{Event, EventKind};
+use indigo_poy::ReportData;
+
+/// Event table with tx hashes linked under `explorer_tx_url`, and a USD column beside the
+/// amounts when the bundle has ADA/USD prices; empty without events.
+pub(crate) fn timeline_card(data: &ReportData, explorer_tx_url: &str) -> String {
+    let events = events_in_time_order(data);
+    if events.is_empty() {
+        return String::new();
+    }
+    let fiat = data.bundle.metrics.fiat.as_ref();
+    let rows: String = events
+        .iter()
+        .map(|ev| {
+            let usd = match fiat {
+                Some(fiat) => format!("<td class=\"mono\">{}</td>", usd(fiat, ev)),
+                None => String::new(),
+            };
+            format!(
+                "    <tr><td class=\"mono\">{}</td><td>{}</td><td class=\"mono\">{}</td>{}<td class=\"mono\">{}</td><td class=\"mono\"><a href=\"{}{}\">{}</a></td></tr>\n",
+                date(ev),
+                escape_html(&kind_name(&ev.kind).replace('_', " ")),
+                escape_html(&amount(data, ev)),
+                usd,
+                escape_html(&premium(ev)),
+                escape_html(explorer_tx_url),
+                escape_html(&ev.tx_hash),
+                escape_html(short_hash(&ev.tx_hash))
+            )
+        })
+        .collect();
+    format!(
+        "\n<h2>Event timeline</h2>\n<div class=\"card\">\n  <table>\n    <tr><th>Date (UTC)</th><th>Kind</th><th>Amount</th>{}<th>Premium</th><th>Tx</th></tr>\n{}  </table>\n</div>\n",
+        if fiat.is_some() { "<th>USD</th>" } else { "" },
+        rows
+    )
+}
+
+/// `YYYY-MM-DD HH:MM`.
+fn date(ev: &Event) -> String {
+    let ts = ev.timestamp;
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        ts.year(),
+        u8::from(ts.month()),
+        ts.day(),
+        ts.hour(),
+        ts.minute()
+    )
+}
+
+/// The event's ADA amount, else the iAsset or INDY it moved; `—` for neither.
+fn amount(data: &ReportData, ev: &Event) -> String {
+    let row = EventRow::of(&ev.kind);
+    if let Some(lovelace) = row.ada_lovelace {
+        return format!("{} ADA", format_decimal(u128::from(lovelace), 6));
+    }
+    match (row.asset, row.quantity) {
+        (Some("INDY"), Some(q)) => format!("{} INDY", format_decimal(q, 6)),
+        (Some(unit), Some(q)) => match data.bundle.assets.get(unit) {
+            Some(info) => format!(
+                "{} {}",
+                format_decimal(q, info.decimals),
+                info.display_name()
+            ),
+            None => format!("{} {}", q, unit),
+        },
+        _ => "—".to_string(),
+    }
+}
+
+/// The event's ADA amount at the ADA/USD price of its tx; `—` without either.
+fn usd(fiat: &FiatMetrics, ev: &Event) -> String {
+    match (
+        EventRow::of(&ev.kind).ada_lovelace,
+        fiat.event_prices.get(&ev.tx_hash),
+    ) {
+        (Some(lovelace), Some(price)) => format!("{:.2}", lovelace as f64 / 1_000_000.0 * price),
+        _ => "—".to_string(),
+    }
+}
+
+/// Realized SP premium in ADA, or a ROB fill's premium rate.
+fn premium(ev: &Event) -> String {
+    match &ev.kind {
+        EventKind::RobOrderFill {
+            premium_pct: Some(pct),
+            ..
+        } => format!("{:.2}%", pct),
+        kind => match EventRow::of(kind).premium_lovelace {
+            Some(lovelace) => format!("{} ADA", format_decimal(u128::from(lovelace), 6)),
+            None => String::new(),
+        },
+    }
+}
+
+/// First 8 characters of a tx hash; the link carries the full one.
+fn short_hash(hash: &str) -> &str {
+    hash.get(..8).unwrap_or(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indigo_poy::EvidenceBundle;
+
+    #[test]
+    fn timeline_lists_events_in_order_with_explorer_links() {
+        let mut bundle = EvidenceBundle::demo();
+        let data = |bundle: &EvidenceBundle| ReportData {
+            bundle: bundle.clone(),
+            reproducibility_hash_sha256: "a".repeat(64),
+        };
+        assert_eq!(timeline_card(&data(&bundle), "https://x/tx/"), "");
+
+        bundle.events = serde_json::from_value(serde_json::json!({
+            "stability_pool": [{
+                "kind": {
+                    "kind": "stability_pool_liquidation",
+                    "iasset_burnt": "unknown",
+                    "ada_received_lovelace": 11_500_000,
+                    "realized_premium_lovelace": 1_000_000,
+                    "dilution_effect": null,
+                    "tx_hash": "bbbbbbbbbbbb",
+                    "slot": 2
+                },
+                "timestamp": "2024-03-05T10:02:00Z",
+                "slot": 2,
+                "tx_hash": "bbbbbbbbbbbb",
+                "extra": null
+            }],
+            "rob": [{
+                "kind": {
+                    "kind": "rob_order_fill",
+                    "order_id": null,
+                    "filled_lovelace": 5_000_000,
+                    "premium_pct": 1.5,
+                    "reimbursement_pct": null,
+                    "tx_hash": "aaaaaaaaaaaa",
+                    "slot": 1
+                },
+                "timestamp": "2024-03-04T09:01:00Z",
+                "slot": 1,
+                "tx_hash": "aaaaaaaaaaaa",
+                "extra": null
+            }],
+            "indy_staking": [],
+            "other": []
+        }))
+        .unwrap();
+        let html = timeline_card(&data(&bundle), "https://x/tx/");
+        let fill = html.find("rob order fill").unwrap();
+        let liquidation = html.find("stability pool liquidation").unwrap();
+        assert!(fill < liquidation);
+        assert!(html.contains("<td class=\"mono\">2024-03-04 09:01</td>"));
+        assert!(html.contains("<td class=\"mono\">5 ADA</td><td class=\"mono\">1.50%</td>"));
+        assert!(html.contains("<td class=\"mono\">11.5 ADA</td><td class=\"mono\">1 ADA</td>"));
+        assert!(html.contains("<a href=\"https://x/tx/bbbbbbbbbbbb\">bbbbbbbb</a>"));
+        assert!(!html.contains("<th>USD</th>"));
+
+        bundle.metrics.fiat = Some(FiatMetrics {
+            event_prices: [("aaaaaaaaaaaa".to_string(), 0.5)].into(),
+            ..Default::default()
+        });
+        let html = timeline_card(&data(&bundle), "https://x/tx/");
+        assert!(html.contains("<th>Amount</th><th>USD</th><th>Premium</th>"));
+        assert!(html.contains("<td class=\"mono\">5 ADA</td><td class=\"mono\">2.50</td>"));
+        assert!(html.contains("<td class=\"mono\">11.5 ADA</td><td class=\"mono\">—</td>"));
+    }
+}
@@ -0,0 +1,159 @@
+// "Verify now": recompute the reproducibility hash of the embedded bundle in the browser, the way
+// `indigo_poy::verify::reproducibility_hash` does. That is SHA-256 over the bundle JSON with
+// object keys sorted by code point and no whitespace. Numbers and strings are kept as written:
+// serde_json wrote them, and JS numbers would lose large integers and `1.0`.
+(function () {
+  "use strict";
+
+  var K = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+  ];
+
+  function sha256Hex(bytes) {
+    var h = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+    var len = bytes.length;
+    var padded = new Uint8Array(((len + 9 + 63) >> 6) << 6);
+    padded.set(bytes);
+    padded[len] = 0x80;
+    var view = new DataView(padded.buffer);
+    view.setUint32(padded.length - 8, Math.floor(len / 0x20000000));
+    view.setUint32(padded.length - 4, (len << 3) >>> 0);
+    var w = new Array(64);
+    for (var off = 0; off < padded.length; off += 64) {
+      for (var i = 0; i < 16; i++) w[i] = view.getUint32(off + i * 4);
+      for (i = 16; i < 64; i++) {
+        var a = w[i - 15], b = w[i - 2];
+        var s0 = ror(a, 7) ^ ror(a, 18) ^ (a >>> 3);
+        var s1 = ror(b, 17) ^ ror(b, 19) ^ (b >>> 10);
+        w[i] = (w[i - 16] + s0 + w[i - 7] + s1) | 0;
+      }
+      var v = h.slice();
+      for (i = 0; i < 64; i++) {
+        var t1 = v[7] + (ror(v[4], 6) ^ ror(v[4], 11) ^ ror(v[4], 25)) + ((v[4] & v[5]) ^ (~v[4] & v[6])) + K[i] + w[i];
+        var t2 = (ror(v[0], 2) ^ ror(v[0], 13) ^ ror(v[0], 22)) + ((v[0] & v[1]) ^ (v[0] & v[2]) ^ (v[1] & v[2]));
+        v = [(t1 + t2) | 0, v[0], v[1], v[2], (v[3] + t1) | 0, v[4], v[5], v[6]];
+      }
+      for (i = 0; i < 8; i++) h[i] = (h[i] + v[i]) | 0;
+    }
+    return h.map(function (x) { return ("0000000" + (x >>> 0).toString(16)).slice(-8); }).join("");
+  }
+
+  function ror(x, n) {
+    return (x >>> n) | (x << (32 - n));
+  }
+
+  // Parses JSON text into a tree whose leaves are their source text.
+  function parse(text) {
+    var pos = 0;
+    function ws() {
+      while (pos < text.length && " \t\n\r".indexOf(text[pos]) >= 0) pos++;
+    }
+    function expect(c) {
+      ws();
+      if (text[pos] !== c) throw new Error("expected " + c + " at " + pos);
+      pos++;
+    }
+    function string() {
+      var start = pos++;
+      while (text[pos] !== '"') {
+        if (pos >= text.length) throw new Error("unterminated string");
+        pos += text[pos] === "\\" ? 2 : 1;
+      }
+      pos++;
+      return text.slice(start, pos);
+    }
+    function value() {
+      ws();
+      var c = text[pos];
+      if (c === "{") {
+        pos++;
+        var members = [];
+        ws();
+        if (text[pos] === "}") { pos++; return { members: members }; }
+        do {
+          ws();
+          var key = string();
+          expect(":");
+          members.push([key, value()]);
+          ws();
+        } while (text[pos++] === ",");
+        if (text[pos - 1] !== "}") throw new Error("expected } at " + (pos - 1));
+        return { members: members };
+      }
+      if (c === "[") {
+        pos++;
+        var items = [];
+        ws();
+        if (text[pos] === "]") { pos++; return { items: items }; }
+        do {
+          items.push(value());
+          ws();
+        } while (text[pos++] === ",");
+        if (text[pos - 1] !== "]") throw new Error("expected ] at " + (pos - 1));
+        return { items: items };
+      }
+      if (c === '"') return string();
+      var start = pos;
+      while (pos < text.length && ",]} \t\n\r".indexOf(text[pos]) < 0) pos++;
+      if (start === pos) throw new Error("unexpected input at " + pos);
+      return text.slice(start, pos);
+    }
+    var tree = value();
+    ws();
+    if (pos !== text.length) throw new Error("trailing input at " + pos);
+    return tree;
+  }
+
+  function compareCodePoints(a, b) {
+    var x = Array.from(JSON.parse(a)), y = Array.from(JSON.parse(b));
+    for (var i = 0; i < x.length && i < y.length; i++) {
+      var d = x[i].codePointAt(0) - y[i].codePointAt(0);
+      if (d !== 0) return d;
+    }
+    return x.length - y.length;
+  }
+
+  function canonical(node) {
+    if (typeof node === "string") return node;
+    if (node.items) return "[" + node.items.map(canonical).join(",") + "]";
+    var members = node.members.slice().sort(function (a, b) { return compareCodePoints(a[0], b[0]); });
+    return "{" + members.map(function (m) { return m[0] + ":" + canonical(m[1]); }).join(",") + "}";
+  }
+
+  function member(node, name) {
+    for (var i = 0; i < node.members.length; i++) {
+      if (JSON.parse(node.members[i][0]) === name) return node.members[i][1];
+    }
+    throw new Error("no " + name + " in the embedded data");
+  }
+
+  // The embed is HTML-escaped, and script content is not unescaped by the browser.
+  function unescapeHtml(s) {
+    var entities = { "&amp;": "&", "&lt;": "<", "&gt;": ">", "&quot;": '"', "&#39;": "'" };
+    return s.replace(/&(amp|lt|gt|quot|#39);/g, function (e) { return entities[e]; });
+  }
+
+  function verify() {
+    var result = document.getElementById("verify-result");
+    try {
+      var embedded = parse(unescapeHtml(document.getElementById("evidence-bundle").textContent));
+      var expected = JSON.parse(member(embedded, "reproducibility_hash_sha256"));
+      var bytes = new TextEncoder().encode(canonical(member(embedded, "bundle")));
+      var actual = sha256Hex(bytes);
+      result.textContent = actual === expected.toLowerCase()
+        ? "✓ The embedded bundle hashes to " + actual + ", the hash above."
+        : "✗ The embedded bundle hashes to " + actual + ", not the hash above.";
+    } catch (e) {
+      result.textContent = "✗ Could not verify: " + e.message;
+    }
+  }
+
+  document.getElementById("verify-now").addEventListener("click", verify);
+})();